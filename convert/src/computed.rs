@@ -0,0 +1,97 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use bdat::legacy::float::BdatReal;
+use bdat::{Cell, ColumnDef, Label, Row, Table, TableAccessor, TableBuilder, Value, ValueType};
+
+use crate::expr::{Expr, ExprError};
+
+/// A column that is not part of the table, but is calculated from other columns
+/// when extracting.
+///
+/// Syntax: `Name=Expression`, e.g. `TotalAtk=Atk*Level`
+#[derive(Clone, Debug)]
+pub struct ComputedColumn {
+    pub name: String,
+    expr: Expr,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ComputedColumnError {
+    #[error("Expected 'Name=Expression'")]
+    MissingName,
+    #[error(transparent)]
+    Expr(#[from] ExprError),
+}
+
+impl ComputedColumn {
+    /// Returns whether the table has every column the expression needs, and doesn't
+    /// already have a column with the same name.
+    pub fn applies_to(&self, table: &Table) -> bool {
+        let label = Label::parse(self.name.as_str(), false);
+        table.columns().all(|c| *c.label() != label)
+            && self
+                .expr
+                .columns()
+                .all(|l| table.columns().any(|c| c.label() == l))
+    }
+
+    /// Evaluates the column for every row, then adds it at the end of the table.
+    ///
+    /// Values are stored as floats.
+    pub fn apply<'b>(&self, table: Table<'b>) -> anyhow::Result<Table<'b>> {
+        let values = table
+            .rows()
+            .map(|row| {
+                self.expr.eval(&row).with_context(|| {
+                    format!("Could not compute {} for row {}", self.name, row.id())
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let modern = table.is_modern();
+        let name = table.name().clone();
+        let columns = table
+            .columns()
+            .cloned()
+            .chain(std::iter::once(ColumnDef::new(
+                ValueType::Float,
+                Label::parse(self.name.clone(), false),
+            )))
+            .collect();
+        let rows = table
+            .into_rows()
+            .zip(values)
+            .map(|(row, value)| {
+                let id = row.id();
+                let cell = Cell::Single(Value::Float(BdatReal::Unknown(value as f32)));
+                Row::new(id, row.into_cells().chain(std::iter::once(cell)).collect())
+            })
+            .collect();
+
+        let builder = TableBuilder::with_name(name)
+            .set_columns(columns)
+            .set_rows(rows);
+        Ok(if modern {
+            builder.build_modern().into()
+        } else {
+            builder.build_legacy().into()
+        })
+    }
+}
+
+impl FromStr for ComputedColumn {
+    type Err = ComputedColumnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, expr) = s.split_once('=').ok_or(ComputedColumnError::MissingName)?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ComputedColumnError::MissingName);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            expr: expr.parse()?,
+        })
+    }
+}
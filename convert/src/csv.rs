@@ -0,0 +1,438 @@
+use anyhow::{Context, Result};
+use bdat::fmt::json::{column_keys, ColumnSchema, JsonTable, TableRow};
+use bdat::fmt::{CsvOptions as ExportOptions, MultiValueEncoding};
+use bdat::{BdatVersion, FlagDef, Label, RowRef, Table, ValueType};
+use csv::StringRecord;
+use std::io::{Read, Write};
+
+use crate::{
+    error::FormatError,
+    json::JsonConverter,
+    parallel::map_row_chunks,
+    schema::{ColumnLayout, FileSchema},
+    BdatDeserialize, BdatSerialize, FormatOptions,
+};
+
+/// Converts tables to and from CSV files.
+pub struct CsvConverter {
+    opts: ExportOptions,
+    separator: u8,
+    lists: MultiValueEncoding,
+    flags: MultiValueEncoding,
+    row_id_name: String,
+    /// Tables are read into their JSON representation, then converted like JSON tables
+    json: JsonConverter,
+}
+
+impl CsvConverter {
+    pub fn new(opts: &FormatOptions) -> Self {
+        Self {
+            opts: ExportOptions::new()
+                .separator(opts.csv_separator)
+                .lists(opts.csv_lists)
+                .flags(opts.csv_flags)
+                .untyped(opts.untyped)
+                .names_with_hashes(opts.names_with_hashes)
+                .row_id(opts.csv_row_id.then_some(opts.csv_row_id_name.as_str())),
+            separator: opts.csv_separator as u8,
+            lists: opts.csv_lists,
+            flags: opts.csv_flags,
+            row_id_name: opts.csv_row_id_name.clone(),
+            json: JsonConverter::new(opts),
+        }
+    }
+
+    /// Reads a CSV table into its JSON representation.
+    ///
+    /// Every header must include the column's value type, e.g. `Param {3}`. List and flag
+    /// cells are rebuilt from the column layouts recorded in the schema, as written with
+    /// the list and flag encodings. Without a row ID column, row IDs start at 1.
+    fn read_json(
+        &self,
+        name: &Label,
+        file_schema: &FileSchema,
+        reader: &mut dyn Read,
+    ) -> Result<JsonTable> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(self.separator)
+            .from_reader(reader);
+        let headers = reader.headers()?.clone();
+        let id_index = headers.iter().position(|h| h.trim() == self.row_id_name);
+
+        let mut columns: Vec<CsvColumn> = Vec::new();
+        for (i, header) in headers.iter().enumerate() {
+            if Some(i) == id_index {
+                continue;
+            }
+            let (column_name, ty) = parse_header(header)
+                .ok_or_else(|| FormatError::MissingTypeInfo.with_context(name.clone()))?;
+            let (base, part) = split_header(column_name);
+            match (columns.last_mut(), part) {
+                (Some(column), Some(part)) if column.name == base && !column.parts.is_empty() => {
+                    column.fields.push(i);
+                    column.parts.push(part);
+                }
+                _ => columns.push(CsvColumn {
+                    header,
+                    name: if part.is_some() { base } else { column_name },
+                    ty,
+                    fields: vec![i],
+                    parts: part.into_iter().collect(),
+                    kind: CellKind::Value,
+                }),
+            }
+        }
+
+        let keys = column_keys(columns.iter().map(|c| c.name));
+        let layouts = file_schema.column_layouts(name);
+        let mut schema = Vec::with_capacity(columns.len());
+        for (column, key) in columns.iter_mut().zip(&keys) {
+            let layout = layouts.and_then(|l| l.get(key));
+            column.kind = self.cell_kind(column, layout).ok_or_else(|| {
+                FormatError::UnknownHeader(column.header.to_string()).with_context(name.clone())
+            })?;
+            schema.push(ColumnSchema {
+                name: column.name.to_string(),
+                ty: column.ty,
+                hashed: file_schema.version.are_labels_hashed(),
+                flags: layout.map(|l| l.flags.clone()).unwrap_or_default(),
+                count: layout.map_or(1, |l| l.count),
+                text: None,
+                raw: None,
+                description: None,
+            });
+        }
+
+        let mut rows = Vec::new();
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            let id = match id_index {
+                Some(index) => record[index]
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid row ID in row {}", i + 1))?,
+                None => i + 1,
+            };
+            let mut cells = serde_json::Map::with_capacity(columns.len());
+            for (column, key) in columns.iter().zip(&keys) {
+                let value = self
+                    .cell_value(column, &record)
+                    .with_context(|| format!("Invalid value in row {id}"))?
+                    .ok_or_else(|| FormatError::IncompleteRow(id).with_context(name.clone()))?;
+                cells.insert(key.clone(), value);
+            }
+            rows.push(TableRow { id, cells });
+        }
+
+        Ok(JsonTable {
+            format_version: None,
+            name: None,
+            version: Some(file_schema.version),
+            base_id: None,
+            description: None,
+            schema: Some(schema),
+            rows,
+        })
+    }
+
+    /// Matches a CSV column to its layout in the schema. Returns `None` if the headers
+    /// don't match, e.g. flag headers for a column without flags.
+    fn cell_kind<'s>(
+        &self,
+        column: &CsvColumn,
+        layout: Option<&'s ColumnLayout>,
+    ) -> Option<CellKind<'s>> {
+        let Some(layout) = layout else {
+            return column.parts.is_empty().then_some(CellKind::Value);
+        };
+        if !layout.flags.is_empty() {
+            let matches = match column.parts.is_empty() {
+                true => self.flags != MultiValueEncoding::Columns,
+                false => column
+                    .parts
+                    .iter()
+                    .map(|p| match p {
+                        HeaderPart::Flag(flag) => Some(*flag),
+                        HeaderPart::Element(_) => None,
+                    })
+                    .eq(layout.flags.iter().map(|f| Some(f.label()))),
+            };
+            matches.then_some(CellKind::Flags(&layout.flags))
+        } else {
+            let matches = match column.parts.is_empty() {
+                true => self.lists != MultiValueEncoding::Columns,
+                false => column
+                    .parts
+                    .iter()
+                    .map(|p| match p {
+                        HeaderPart::Element(i) => Some(*i),
+                        HeaderPart::Flag(_) => None,
+                    })
+                    .eq((0..layout.count).map(Some)),
+            };
+            matches.then_some(CellKind::List(layout.count))
+        }
+    }
+
+    /// Reads a cell as a JSON value: lists as arrays, and flags as a map of flag name to
+    /// value. Returns `None` if a list or flags cell doesn't have a value for each element.
+    fn cell_value(
+        &self,
+        column: &CsvColumn,
+        record: &StringRecord,
+    ) -> Result<Option<serde_json::Value>> {
+        let split = |encoding: MultiValueEncoding| -> Result<Vec<String>> {
+            match column.parts.is_empty() {
+                true => Ok(encoding.split(&record[column.fields[0]])?),
+                false => Ok(column
+                    .fields
+                    .iter()
+                    .map(|&i| record[i].to_string())
+                    .collect()),
+            }
+        };
+        let value = match column.kind {
+            CellKind::Value => field_value(column.ty, &record[column.fields[0]]),
+            CellKind::List(count) => {
+                let values = split(self.lists)?;
+                if values.len() != count {
+                    return Ok(None);
+                }
+                values.iter().map(|v| field_value(column.ty, v)).collect()
+            }
+            CellKind::Flags(flags) => {
+                let values = split(self.flags)?;
+                if values.len() != flags.len() {
+                    return Ok(None);
+                }
+                let map = flags
+                    .iter()
+                    .zip(values)
+                    .map(|(flag, v)| (flag.label().to_string(), field_value(column.ty, &v)))
+                    .collect::<serde_json::Map<_, _>>();
+                map.into()
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+/// A table column in a CSV file. List and flag columns can span several fields.
+struct CsvColumn<'h> {
+    /// The first header of the column
+    header: &'h str,
+    name: &'h str,
+    ty: ValueType,
+    /// Indices of the fields with the column's values
+    fields: Vec<usize>,
+    /// What each field holds, for lists and flags in separate fields
+    parts: Vec<HeaderPart<'h>>,
+    kind: CellKind<'h>,
+}
+
+/// A field of a list or flags column, see [`split_header`].
+#[derive(Clone, Copy)]
+enum HeaderPart<'h> {
+    /// `Name[i]`
+    Element(usize),
+    /// `Name [Flag]`
+    Flag(&'h str),
+}
+
+#[derive(Clone, Copy)]
+enum CellKind<'s> {
+    Value,
+    /// A list with the given number of values
+    List(usize),
+    Flags(&'s [FlagDef]),
+}
+
+impl BdatDeserialize for CsvConverter {
+    fn read_table(
+        &self,
+        name: Label,
+        file_schema: &FileSchema,
+        reader: &mut dyn Read,
+    ) -> Result<Table<'static>> {
+        let table = self.read_json(&name, file_schema, reader)?;
+        self.json.convert_table(name, file_schema, table)
+    }
+
+    fn read_standalone(
+        &self,
+        file_name: &str,
+        version: Option<BdatVersion>,
+        reader: &mut dyn Read,
+    ) -> Result<(BdatVersion, Table<'static>)> {
+        let version = version.ok_or_else(|| {
+            FormatError::MissingMetadata("version").with_context(Label::from(file_name))
+        })?;
+        let name = Label::parse_for_version(file_name, version);
+        let schema = FileSchema::new(file_name.to_string(), version);
+        Ok((version, self.read_table(name, &schema, reader)?))
+    }
+
+    fn get_table_extension(&self) -> &'static str {
+        "csv"
+    }
+}
+
+/// Splits a typed header, e.g. `Param {3}`, into the column name and value type.
+fn parse_header(header: &str) -> Option<(&str, ValueType)> {
+    let (name, ty) = header.trim().strip_suffix('}')?.rsplit_once(" {")?;
+    Some((name, ValueType::try_from(ty.parse::<u8>().ok()?).ok()?))
+}
+
+/// Splits a column name from a header of a list or flags column, e.g. `Param[1]` or
+/// `Param [Flag]`.
+fn split_header(name: &str) -> (&str, Option<HeaderPart<'_>>) {
+    let Some(inner) = name.strip_suffix(']') else {
+        return (name, None);
+    };
+    if let Some((base, flag)) = inner.rsplit_once(" [") {
+        return (base, Some(HeaderPart::Flag(flag)));
+    }
+    match inner.rsplit_once('[') {
+        Some((base, i)) => match i.parse() {
+            Ok(i) => (base, Some(HeaderPart::Element(i))),
+            Err(_) => (name, None),
+        },
+        None => (name, None),
+    }
+}
+
+/// Reads a field as a JSON value. Numbers and lists (as JSON arrays) are parsed, other
+/// text (e.g. hashes and enum names) is kept as a string.
+fn field_value(ty: ValueType, field: &str) -> serde_json::Value {
+    match ty {
+        ValueType::String | ValueType::DebugString | ValueType::Raw => field.into(),
+        _ => serde_json::from_str(field).unwrap_or_else(|_| field.into()),
+    }
+}
+
+impl BdatSerialize for CsvConverter {
+    fn write_table(
+        &self,
+        table: &Table,
+        rows: Vec<RowRef>,
+        _: &FileSchema,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let chunks = map_row_chunks(&rows, |i, chunk| {
+            let mut buf = Vec::new();
+            let opts = self.opts.clone().header(i == 0);
+            bdat::fmt::to_csv_rows(table, chunk.iter().copied(), &mut buf, &opts)?;
+            Ok(buf)
+        })
+        .context("Failed to write CSV")?;
+        for chunk in chunks {
+            writer.write_all(&chunk)?;
+        }
+        Ok(())
+    }
+
+    fn get_file_name(&self, table_name: &str) -> String {
+        format!("{table_name}.csv")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatFile, SwitchEndian, TableAccessor};
+
+    use super::*;
+
+    fn read_legacy(bytes: &[u8]) -> Table<'_> {
+        bdat::legacy::from_bytes_copy::<SwitchEndian>(bytes, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .remove(0)
+            .into()
+    }
+
+    fn legacy_table() -> Table<'static> {
+        read_legacy(include_bytes!("../../tests/res/test_legacy_1.bdat"))
+    }
+
+    /// Extracts a table to CSV and packs it again, returning the CSV text and the packed file.
+    fn round_trip(table: &Table, opts: &FormatOptions) -> (String, Vec<u8>) {
+        let mut schema = FileSchema::new("test".to_string(), BdatVersion::LegacySwitch);
+        schema.feed_table(table);
+        let converter = CsvConverter::new(opts);
+        let mut csv = Vec::new();
+        converter
+            .write_table(table, table.rows().collect(), &schema, &mut csv)
+            .unwrap();
+        let read = converter
+            .read_table(table.name().clone(), &schema, &mut csv.as_slice())
+            .unwrap();
+        let bytes =
+            bdat::legacy::to_vec::<SwitchEndian>([read.into_legacy()], BdatVersion::LegacySwitch)
+                .unwrap();
+        (String::from_utf8(csv).unwrap(), bytes)
+    }
+
+    fn assert_same_table(expected: &Table, table: &Table) {
+        assert!(expected.columns().eq(table.columns()));
+        assert!(expected
+            .rows()
+            .map(|r| r.cells().cloned().collect::<Vec<_>>())
+            .eq(table.rows().map(|r| r.cells().cloned().collect::<Vec<_>>())));
+    }
+
+    #[test]
+    fn legacy_round_trip() {
+        let table = legacy_table();
+        let (csv, bytes) = round_trip(&table, &FormatOptions::default());
+        // Single-bit flags are booleans
+        assert!(csv.lines().nth(1).unwrap().ends_with(",true,3,true"));
+        let packed = read_legacy(&bytes);
+        assert_eq!(4, packed.column_count());
+        assert_same_table(&table, &packed);
+    }
+
+    #[test]
+    fn multi_value_encodings() {
+        let table = legacy_table();
+        let encodings = [
+            MultiValueEncoding::Columns,
+            MultiValueEncoding::Joined(';'),
+            MultiValueEncoding::Json,
+        ];
+        for lists in encodings {
+            for flags in encodings {
+                let opts = FormatOptions {
+                    csv_lists: lists,
+                    csv_flags: flags,
+                    ..Default::default()
+                };
+                let (csv, bytes) = round_trip(&table, &opts);
+                let header = csv.lines().next().unwrap();
+                assert_eq!(
+                    lists == MultiValueEncoding::Columns,
+                    header.contains("value_str_arr[2] {7}")
+                );
+                assert_eq!(
+                    flags == MultiValueEncoding::Columns,
+                    header.contains("value_flags [Bit4] {1}")
+                );
+                assert_same_table(&table, &read_legacy(&bytes));
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_headers() {
+        let table = legacy_table();
+        let converter = CsvConverter::new(&FormatOptions::default());
+        let mut schema = FileSchema::new("test".to_string(), BdatVersion::LegacySwitch);
+        schema.feed_table(&table);
+        let read =
+            |csv: &str| converter.read_table(table.name().clone(), &schema, &mut csv.as_bytes());
+        // Flag that isn't in the schema
+        assert!(read("value_flags [Unknown] {3}\n1").is_err());
+        // Column without a list layout
+        assert!(read("value_u32[0] {3}\n1").is_err());
+    }
+}
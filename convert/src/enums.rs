@@ -0,0 +1,136 @@
+//! Symbolic names for enum-like numeric columns, as declared in file schemas.
+
+use std::collections::BTreeMap;
+
+use bdat::{Cell, Table, Value};
+use serde::{Deserialize, Serialize};
+
+/// Enum annotations for a table, keyed by column name.
+pub type TableEnums = BTreeMap<String, EnumDef>;
+
+/// Maps the values of a numeric column to names.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct EnumDef(BTreeMap<i64, String>);
+
+impl EnumDef {
+    /// Returns the name for a value, if there is one.
+    pub fn name(&self, value: i64) -> Option<&str> {
+        self.0.get(&value).map(String::as_str)
+    }
+
+    /// Returns the value for a name, if there is one.
+    pub fn value(&self, name: &str) -> Option<i64> {
+        self.0.iter().find(|(_, n)| *n == name).map(|(v, _)| *v)
+    }
+
+    /// Replaces symbolic names in a JSON cell with their numeric values.
+    ///
+    /// Numbers are left as-is. If the name is not defined, it is returned as the error.
+    pub fn resolve_json(&self, value: serde_json::Value) -> Result<serde_json::Value, String> {
+        match value {
+            serde_json::Value::String(name) => match self.value(&name) {
+                Some(v) => Ok(v.into()),
+                None => Err(name),
+            },
+            serde_json::Value::Array(values) => values
+                .into_iter()
+                .map(|v| self.resolve_json(v))
+                .collect::<Result<Vec<_>, _>>()
+                .map(serde_json::Value::Array),
+            v => Ok(v),
+        }
+    }
+
+    fn name_value(&self, value: &mut Value) {
+        if let Some(name) = integer_value(value).and_then(|v| self.name(v)) {
+            *value = Value::String(name.to_string().into());
+        }
+    }
+}
+
+/// Replaces the values of annotated columns with their names, when the value
+/// has a name.
+///
+/// Note that the resulting table is only meant to be serialized to text formats,
+/// as cell values no longer match their column types.
+pub fn apply_names(table: &mut Table, enums: &TableEnums) {
+    let columns = table
+        .columns()
+        .map(|c| enums.get(&c.label().to_string()).cloned())
+        .collect::<Vec<_>>();
+    if columns.iter().all(Option::is_none) {
+        return;
+    }
+    for mut row in table.rows_mut() {
+        for (cell, def) in row.cells_mut().zip(&columns) {
+            let Some(def) = def else { continue };
+            match cell {
+                Cell::Single(v) => def.name_value(v),
+                Cell::List(values) => values.iter_mut().for_each(|v| def.name_value(v)),
+                Cell::Flags(_) => {}
+            }
+        }
+    }
+}
+
+fn integer_value(value: &Value) -> Option<i64> {
+    Some(match value {
+        Value::SignedByte(v) => *v as i64,
+        Value::SignedShort(v) => *v as i64,
+        Value::SignedInt(v) => *v as i64,
+        Value::UnsignedByte(v) | Value::Percent(v) => *v as i64,
+        Value::UnsignedShort(v) => *v as i64,
+        Value::UnsignedInt(v) => *v as i64,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, ColumnDef, Label, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    fn element() -> EnumDef {
+        serde_json::from_str(r#"{"0": "None", "1": "Fire", "-1": "Any"}"#).unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let mut table = TableBuilder::with_name(Label::String("Table".into()))
+            .add_column(ColumnDef::new(ValueType::SignedByte, "Element".into()))
+            .add_column(ColumnDef::new(ValueType::SignedByte, "Level".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::SignedByte(-1)),
+                    Cell::Single(Value::SignedByte(1)),
+                ],
+            ))
+            .add_row(Row::new(
+                2,
+                vec![
+                    Cell::Single(Value::SignedByte(5)),
+                    Cell::Single(Value::SignedByte(1)),
+                ],
+            ))
+            .build(BdatVersion::Modern);
+        let enums = TableEnums::from([("Element".to_string(), element())]);
+        apply_names(&mut table, &enums);
+
+        let cells = table
+            .rows()
+            .map(|r| r.cells().cloned().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(Cell::Single(Value::String("Any".into())), cells[0][0]);
+        assert_eq!(Cell::Single(Value::SignedByte(1)), cells[0][1]);
+        // No name for this value
+        assert_eq!(Cell::Single(Value::SignedByte(5)), cells[1][0]);
+
+        let def = element();
+        assert_eq!(Ok((-1).into()), def.resolve_json("Any".into()));
+        assert_eq!(Ok(5.into()), def.resolve_json(5.into()));
+        assert_eq!(Err("Water".to_string()), def.resolve_json("Water".into()));
+    }
+}
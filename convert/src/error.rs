@@ -0,0 +1,134 @@
+use std::fmt::Display;
+
+use bdat::Label;
+
+#[derive(Debug)]
+pub struct OptLabel(Option<Label>);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Missing required argument: {0}")]
+    MissingRequiredArgument(&'static str),
+    #[error("Unsupported file type '{0}'")]
+    UnknownFileType(String),
+    #[error("Option '--{0}' is not supported for this file type")]
+    UnsupportedOption(&'static str),
+    #[error("Expected a BDAT file and one or more JSON files with rows")]
+    AppendFiles,
+    #[error("Expected a single BDAT file to split")]
+    SplitFiles,
+    #[error("Expected a single old and a single new BDAT file to make a patch from")]
+    EmitPatchFiles,
+    #[error("Expected a single BDAT file to patch")]
+    PatchFiles,
+    #[error("Expected a directory for each language, with the reference language first")]
+    LanguageFiles,
+    #[error("Expected a patch directory for each mod, and at least two mods")]
+    ModDirectories,
+    #[error("Table '{0}' not found")]
+    TableNotFound(String),
+    #[error("Table '{0}' already exists")]
+    TableExists(String),
+    #[error("Not a legacy BDAT file")]
+    NotLegacy,
+    #[error("Not a modern BDAT file")]
+    NotModern,
+    #[error(
+        "Found {} problem(s) before packing, no files were written:\n{}",
+        _0.len(),
+        _0.iter().map(|p| format!("  - {p}")).collect::<Vec<_>>().join("\n")
+    )]
+    PackCheck(Vec<String>),
+    #[error(
+        "Found {} table file(s) that are not listed in any schema:\n{}",
+        _0.len(),
+        _0.iter().map(|p| format!("  - {p}")).collect::<Vec<_>>().join("\n")
+    )]
+    OrphanedFiles(Vec<String>),
+    #[error(
+        "Found {} conflict(s) between patches, nothing was patched:\n{}",
+        _0.len(),
+        _0.iter().map(|p| format!("  - {p}")).collect::<Vec<_>>().join("\n")
+    )]
+    PatchConflicts(Vec<String>),
+    #[error("Table name '{0}' can't be used as a file name, see '--sanitize-names'")]
+    InvalidFileName(String),
+    #[error("Interrupted before all files were converted")]
+    Cancelled,
+    #[error("Schema error: {0}")]
+    Schema(#[from] SchemaError),
+    #[error("Table format error ({table}): {error}")]
+    Format { table: OptLabel, error: FormatError },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("No schema files found, please run 'extract' without '--no-schema'")]
+    MissingSchema,
+    #[error(
+        "Outdated schema for file '{}', found version {}, expected version {}, \
+        please run 'schema upgrade', or 'extract' again without '--no-schema'", _0.0, _0.1, _0.2
+    )]
+    OutdatedSchema(Box<(String, usize, usize)>),
+    #[error(
+        "Schema for file '{}' has version {}, but only versions up to {} are supported, \
+        please update bdat-toolset", _0.0, _0.1, _0.2
+    )]
+    UnsupportedSchema(Box<(String, usize, usize)>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FormatError {
+    #[error("Missing type information, please run 'extract' without '-u', or add types manually")]
+    MissingTypeInfo,
+    #[error("Row {0} does not have entries for all columns")]
+    IncompleteRow(usize),
+    #[error("Row {} has a value for column {}, which is not in the schema", _0.0, _0.1)]
+    UnknownColumn(Box<(usize, String)>),
+    #[error("Column header '{0}' doesn't match the list and flag columns in the schema")]
+    UnknownHeader(String),
+    #[error("Row ID {0} doesn't follow the previous row, expected {1}")]
+    NonConsecutiveRow(usize, usize),
+    #[error("Entry for row {0} is missing, was a row deleted without updating the IDs?")]
+    MissingRow(usize),
+    #[error("Unknown name '{}' for enum column {}, was it added to the schema?", _0.0, _0.1)]
+    UnknownEnumName(Box<(String, OptLabel)>),
+    #[error("Referenced table {0} not found in the same file")]
+    MissingRefTable(String),
+    #[error("Row {} references a missing row ({}: {}) in table {}", _0.0, _0.1, _0.2, _0.3)]
+    BrokenReference(Box<(usize, OptLabel, String, String)>),
+    #[error(
+        "Table has no {0} metadata, and there is no schema file to read it from, \
+        please set it with '--game'"
+    )]
+    MissingMetadata(&'static str),
+    #[error(transparent)]
+    Import(#[from] bdat::fmt::ImportError),
+}
+
+impl FormatError {
+    pub fn with_context(self, table_name: impl Into<OptLabel>) -> Error {
+        Error::Format {
+            table: table_name.into(),
+            error: self,
+        }
+    }
+}
+
+impl Display for OptLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(l) => l.fmt(f),
+            None => write!(f, "<Unnamed>"),
+        }
+    }
+}
+
+impl<L> From<L> for OptLabel
+where
+    L: Into<Option<Label>>,
+{
+    fn from(label: L) -> Self {
+        Self(label.into())
+    }
+}
@@ -0,0 +1,660 @@
+//! Simple expressions over the cells of a row, e.g. `Atk * Level` or `Lv > 50 AND Name != ''`.
+//!
+//! Supported syntax: decimal numbers, strings (`'text'` or `"text"`), column names (`Name`,
+//! `<1234ABCD>` for hashed names, or `Table.Name` in queries), `+`, `-`, `*`, `/`,
+//! comparisons (`=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`), `AND`, `OR`, `NOT` and parentheses.
+//!
+//! Comparisons and logic operators evaluate to 1 (true) or 0 (false).
+
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use bdat::{label::LabelParseError, Cell, Label, RowRef, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Text(String),
+    Column(Label),
+    /// A column of a specific table, e.g. `Table.Column`
+    Field(String, Label),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+/// The result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Number(f64),
+    Text(String),
+}
+
+/// Provides the values of the columns used in an expression.
+pub trait Scope {
+    /// Returns the value of a column. `table` is set for columns of a specific table
+    /// (`Table.Column`).
+    fn value(&self, table: Option<&str>, column: &Label) -> Result<Scalar, ExprError>;
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ExprError {
+    #[error("Unexpected character '{1}' at position {0}")]
+    UnexpectedChar(usize, char),
+    #[error("Expected '{1}' at position {0}")]
+    Expected(usize, &'static str),
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+    #[error(transparent)]
+    InvalidLabel(#[from] LabelParseError),
+    #[error("Column {0} not found")]
+    MissingColumn(String),
+    #[error("{0} is not numeric")]
+    NotNumeric(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Op(BinOp),
+    Open,
+    Close,
+    Comma,
+    Not,
+}
+
+/// Parses expressions from a list of tokens. Parsing stops at the first token that
+/// can't continue the expression, so the parser can be used for larger languages that
+/// contain expressions.
+pub struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Expr {
+    /// Evaluates a numeric expression for a row.
+    pub fn eval(&self, row: &RowRef) -> Result<f64, ExprError> {
+        self.eval_number(row)
+    }
+
+    /// Evaluates the expression with column values from a scope.
+    pub fn eval_in(&self, scope: &impl Scope) -> Result<Scalar, ExprError> {
+        Ok(match self {
+            Expr::Number(n) => Scalar::Number(*n),
+            Expr::Text(s) => Scalar::Text(s.clone()),
+            Expr::Column(label) => scope.value(None, label)?,
+            Expr::Field(table, label) => scope.value(Some(table), label)?,
+            Expr::Neg(e) => Scalar::Number(-e.eval_number(scope)?),
+            Expr::Not(e) => Scalar::from(!e.eval_in(scope)?.is_true()),
+            Expr::Binary(a, op, b) => match op {
+                // Short-circuit, so conditions can guard the other side
+                BinOp::And => {
+                    Scalar::from(a.eval_in(scope)?.is_true() && b.eval_in(scope)?.is_true())
+                }
+                BinOp::Or => {
+                    Scalar::from(a.eval_in(scope)?.is_true() || b.eval_in(scope)?.is_true())
+                }
+                op if op.is_comparison() => {
+                    Scalar::from(op.matches(a.eval_in(scope)?.compare(&b.eval_in(scope)?)))
+                }
+                op => {
+                    let (a, b) = (a.eval_number(scope)?, b.eval_number(scope)?);
+                    Scalar::Number(match op {
+                        BinOp::Add => a + b,
+                        BinOp::Sub => a - b,
+                        BinOp::Mul => a * b,
+                        _ => a / b,
+                    })
+                }
+            },
+        })
+    }
+
+    fn eval_number(&self, scope: &impl Scope) -> Result<f64, ExprError> {
+        match self.eval_in(scope)? {
+            Scalar::Number(n) => Ok(n),
+            Scalar::Text(_) => Err(ExprError::NotNumeric(self.to_string())),
+        }
+    }
+
+    /// Returns an iterator over the columns used in the expression.
+    pub fn columns(&self) -> Box<dyn Iterator<Item = &Label> + '_> {
+        Box::new(self.fields().map(|(_, l)| l))
+    }
+
+    /// Returns an iterator over the columns used in the expression, along with their
+    /// table if they are written as `Table.Column`.
+    pub fn fields(&self) -> Box<dyn Iterator<Item = (Option<&str>, &Label)> + '_> {
+        match self {
+            Expr::Number(_) | Expr::Text(_) => Box::new(std::iter::empty()),
+            Expr::Column(l) => Box::new(std::iter::once((None, l))),
+            Expr::Field(t, l) => Box::new(std::iter::once((Some(t.as_str()), l))),
+            Expr::Neg(e) | Expr::Not(e) => e.fields(),
+            Expr::Binary(a, _, b) => Box::new(a.fields().chain(b.fields())),
+        }
+    }
+}
+
+impl Scalar {
+    pub fn is_true(&self) -> bool {
+        match self {
+            Scalar::Number(n) => *n != 0.0,
+            Scalar::Text(s) => !s.is_empty(),
+        }
+    }
+
+    /// Compares two values. Numbers are compared numerically, and are compared to
+    /// text as text.
+    pub fn compare(&self, other: &Scalar) -> Ordering {
+        match (self, other) {
+            (Scalar::Number(a), Scalar::Number(b)) => a.total_cmp(b),
+            (Scalar::Text(a), Scalar::Text(b)) => a.cmp(b),
+            (a, b) => a.to_string().cmp(&b.to_string()),
+        }
+    }
+}
+
+impl From<bool> for Scalar {
+    fn from(b: bool) -> Self {
+        Scalar::Number(if b { 1.0 } else { 0.0 })
+    }
+}
+
+impl From<&Cell<'_>> for Scalar {
+    fn from(cell: &Cell) -> Self {
+        match cell {
+            Cell::Single(Value::String(s) | Value::DebugString(s)) => Scalar::Text(s.to_string()),
+            // Go through the shortest representation of the float, so that e.g. 0.1
+            // doesn't become 0.10000000149011612
+            Cell::Single(Value::Float(f)) => {
+                Scalar::Number(f32::from(*f).to_string().parse().unwrap())
+            }
+            Cell::Single(v) => match numeric_value(v) {
+                Some(n) => Scalar::Number(n),
+                None => Scalar::Text(v.to_string()),
+            },
+            c => Scalar::Text(c.to_string()),
+        }
+    }
+}
+
+impl Scope for RowRef<'_, '_> {
+    fn value(&self, table: Option<&str>, column: &Label) -> Result<Scalar, ExprError> {
+        table
+            .is_none()
+            .then(|| self.get_if_present(column))
+            .flatten()
+            .map(Scalar::from)
+            .ok_or_else(|| ExprError::MissingColumn(field_name(table, column)))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s)?;
+        let expr = parser.expr()?;
+        parser.end()?;
+        Ok(expr)
+    }
+}
+
+impl<'s> Parser<'s> {
+    pub fn new(source: &'s str) -> Result<Self, ExprError> {
+        Ok(Self {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+        })
+    }
+
+    pub fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    pub fn next_token(&mut self) -> Result<(usize, Token), ExprError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or(ExprError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is the given keyword. Keywords are not case-sensitive.
+    pub fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let is_keyword =
+            matches!(self.peek(), Some(Token::Ident(name)) if name.eq_ignore_ascii_case(keyword));
+        if is_keyword {
+            self.pos += 1;
+        }
+        is_keyword
+    }
+
+    pub fn expect_keyword(&mut self, keyword: &'static str) -> Result<(), ExprError> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(ExprError::Expected(self.position(), keyword)),
+        }
+    }
+
+    /// Consumes the next token if it is equal to `token`.
+    pub fn eat(&mut self, token: &Token) -> bool {
+        let is_token = self.peek() == Some(token);
+        if is_token {
+            self.pos += 1;
+        }
+        is_token
+    }
+
+    /// Returns an error if there are tokens left.
+    pub fn end(&self) -> Result<(), ExprError> {
+        match self.tokens.get(self.pos) {
+            Some((idx, _)) => Err(ExprError::UnexpectedChar(
+                *idx,
+                self.source[*idx..].chars().next().unwrap(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the position of the next token in the source, or the length of the
+    /// source if there are no tokens left.
+    pub fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.source.len(), |(idx, _)| *idx)
+    }
+
+    pub fn expr(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.and()?;
+        while self.eat_keyword("OR") || self.eat(&Token::Op(BinOp::Or)) {
+            lhs = Expr::Binary(Box::new(lhs), BinOp::Or, Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.not()?;
+        while self.eat_keyword("AND") || self.eat(&Token::Op(BinOp::And)) {
+            lhs = Expr::Binary(Box::new(lhs), BinOp::And, Box::new(self.not()?));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, ExprError> {
+        if self.eat_keyword("NOT") || self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let lhs = self.sum()?;
+        match self.peek() {
+            Some(Token::Op(op)) if op.is_comparison() => {
+                let op = *op;
+                self.pos += 1;
+                Ok(Expr::Binary(Box::new(lhs), op, Box::new(self.sum()?)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ (BinOp::Add | BinOp::Sub))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.factor()?;
+        while let Some(Token::Op(op @ (BinOp::Mul | BinOp::Div))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.factor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ExprError> {
+        let (idx, token) = self.next_token()?;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Text(s) => Ok(Expr::Text(s)),
+            Token::Ident(name) => match name.split_once('.') {
+                Some((table, column)) => Ok(Expr::Field(table.to_string(), column.parse()?)),
+                None => Ok(Expr::Column(name.parse()?)),
+            },
+            Token::Op(BinOp::Sub) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Token::Open => {
+                let expr = self.expr()?;
+                match self.next_token() {
+                    Ok((_, Token::Close)) => Ok(expr),
+                    Ok((idx, _)) => Err(ExprError::Expected(idx, ")")),
+                    Err(_) => Err(ExprError::UnexpectedEnd),
+                }
+            }
+            Token::Op(_) | Token::Close | Token::Comma | Token::Not => Err(
+                ExprError::UnexpectedChar(idx, self.source[idx..].chars().next().unwrap()),
+            ),
+        }
+    }
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Eq => "=",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "AND",
+            BinOp::Or => "OR",
+        }
+    }
+
+    fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+        )
+    }
+
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            BinOp::Eq => ordering.is_eq(),
+            BinOp::Ne => ordering.is_ne(),
+            BinOp::Lt => ordering.is_lt(),
+            BinOp::Le => ordering.is_le(),
+            BinOp::Gt => ordering.is_gt(),
+            BinOp::Ge => ordering.is_ge(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '+' => Token::Op(BinOp::Add),
+            '-' => Token::Op(BinOp::Sub),
+            '*' => Token::Op(BinOp::Mul),
+            '/' => Token::Op(BinOp::Div),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '=' => {
+                // Also accept "==" as in C-like languages
+                chars.next_if(|(_, c)| *c == '=');
+                Token::Op(BinOp::Eq)
+            }
+            '!' => match chars.next_if(|(_, c)| *c == '=') {
+                Some(_) => Token::Op(BinOp::Ne),
+                None => Token::Not,
+            },
+            '&' | '|' => match chars.next_if(|(_, next)| *next == c) {
+                Some(_) if c == '&' => Token::Op(BinOp::And),
+                Some(_) => Token::Op(BinOp::Or),
+                None => return Err(ExprError::UnexpectedChar(idx, c)),
+            },
+            '<' if !is_hash_start(&s[idx..]) => {
+                match chars.next_if(|(_, c)| *c == '=' || *c == '>') {
+                    Some((_, '=')) => Token::Op(BinOp::Le),
+                    Some(_) => Token::Op(BinOp::Ne),
+                    None => Token::Op(BinOp::Lt),
+                }
+            }
+            '>' => match chars.next_if(|(_, c)| *c == '=') {
+                Some(_) => Token::Op(BinOp::Ge),
+                None => Token::Op(BinOp::Gt),
+            },
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        // Quotes are escaped by doubling them
+                        Some((_, q)) if q == c && chars.next_if(|(_, n)| *n == c).is_none() => {
+                            break
+                        }
+                        Some((_, ch)) => text.push(ch),
+                        None => return Err(ExprError::UnexpectedEnd),
+                    }
+                }
+                Token::Text(text)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = idx + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.') {
+                    end = i + c.len_utf8();
+                }
+                Token::Number(
+                    s[idx..end]
+                        .parse()
+                        .map_err(|_| ExprError::UnexpectedChar(idx, c))?,
+                )
+            }
+            c if c == '<' || c == '$' || c.is_alphabetic() || c == '_' => {
+                // Names can be qualified with a table name, e.g. Table.Column
+                let mut end = ident_end(s, idx, &mut chars)?;
+                let is_qualified = s[end..].starts_with('.')
+                    && s[end + 1..].starts_with(|c: char| {
+                        c == '<' || c == '$' || c == '_' || c.is_alphabetic()
+                    });
+                if let Some((dot, _)) = chars.next_if(|_| is_qualified) {
+                    end = ident_end(s, dot + 1, &mut chars)?;
+                }
+                Token::Ident(s[idx..end].to_string())
+            }
+            c => return Err(ExprError::UnexpectedChar(idx, c)),
+        };
+        tokens.push((idx, token));
+    }
+    Ok(tokens)
+}
+
+/// Consumes a name starting at `start`, returning the index after its end. Names are
+/// either hashed (`<1234ABCD>`) or made of letters, digits, `_` and `$`.
+fn ident_end(
+    s: &str,
+    start: usize,
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+) -> Result<usize, ExprError> {
+    // The first character has already been consumed for the first part of the name
+    if chars.peek().is_some_and(|(i, _)| *i == start) {
+        chars.next();
+    }
+    if s[start..].starts_with('<') {
+        // Hashed name, e.g. <1234ABCD>
+        let (end, _) = chars
+            .find(|(_, c)| *c == '>')
+            .ok_or(ExprError::UnexpectedEnd)?;
+        return Ok(end + 1);
+    }
+    let mut end = start + s[start..].chars().next().map_or(0, char::len_utf8);
+    while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$') {
+        end = i + c.len_utf8();
+    }
+    Ok(end)
+}
+
+/// Returns whether a `<` starts a hashed name (`<1234ABCD>`) rather than a comparison.
+fn is_hash_start(s: &str) -> bool {
+    let rest = &s[1..];
+    let name_len = rest
+        .find(|c: char| !c.is_alphanumeric())
+        .unwrap_or(rest.len());
+    name_len > 0 && rest[name_len..].starts_with('>')
+}
+
+fn field_name(table: Option<&str>, column: &Label) -> String {
+    match table {
+        Some(table) => format!("{table}.{column}"),
+        None => column.to_string(),
+    }
+}
+
+/// Returns the numeric value of a cell value, or [`None`] if it isn't a number.
+pub fn numeric_value(value: &Value) -> Option<f64> {
+    Some(match value {
+        Value::SignedByte(v) => *v as f64,
+        Value::SignedShort(v) => *v as f64,
+        Value::SignedInt(v) => *v as f64,
+        Value::UnsignedByte(v) | Value::Percent(v) => *v as f64,
+        Value::UnsignedShort(v) => *v as f64,
+        Value::UnsignedInt(v) => *v as f64,
+        Value::Float(f) => f32::from(*f) as f64,
+        _ => return None,
+    })
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{n}"),
+            Expr::Text(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expr::Column(l) => write!(f, "{l}"),
+            Expr::Field(t, l) => write!(f, "{t}.{l}"),
+            Expr::Neg(e) => write!(f, "-{e}"),
+            Expr::Not(e) => write!(f, "NOT {e}"),
+            Expr::Binary(a, op, b) => write!(f, "({a} {} {b})", op.symbol()),
+        }
+    }
+}
+
+impl Display for Scalar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scalar::Number(n) => write!(f, "{n}"),
+            Scalar::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_precedence() {
+        let expr: Expr = "Atk * Level + -2 / (Def - 1)".parse().unwrap();
+        assert_eq!("((Atk * Level) + (-2 / (Def - 1)))", expr.to_string());
+        assert_eq!(
+            vec!["Atk", "Level", "Def"],
+            expr.columns().map(|l| l.to_string()).collect::<Vec<_>>()
+        );
+
+        let hashed: Expr = "<0000BEEF> * 2".parse().unwrap();
+        assert_eq!(Some(&Label::Hash(0xBEEF)), hashed.columns().next());
+    }
+
+    #[test]
+    fn parse_conditions() {
+        let expr: Expr = "Lv >= 90 AND NOT Name = 'it''s' or Chr.Atk<5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            "(((Lv >= 90) AND NOT (Name = 'it''s')) OR (Chr.Atk < 5))",
+            expr.to_string()
+        );
+        assert_eq!(
+            vec![
+                (None, "Lv".to_string()),
+                (None, "Name".to_string()),
+                (Some("Chr"), "Atk".to_string())
+            ],
+            expr.fields()
+                .map(|(t, l)| (t, l.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        let hashed: Expr = "<0000BEEF> <> Tbl.<0000CAFE>".parse().unwrap();
+        assert_eq!("(<0000BEEF> != Tbl.<0000CAFE>)", hashed.to_string());
+    }
+
+    #[test]
+    fn eval_conditions() {
+        struct Values;
+        impl Scope for Values {
+            fn value(&self, _: Option<&str>, column: &Label) -> Result<Scalar, ExprError> {
+                match column.to_string().as_str() {
+                    "Lv" => Ok(Scalar::Number(95.0)),
+                    "Name" => Ok(Scalar::Text("Rex".to_string())),
+                    c => Err(ExprError::MissingColumn(c.to_string())),
+                }
+            }
+        }
+        let eval = |s: &str| s.parse::<Expr>().unwrap().eval_in(&Values);
+
+        assert_eq!(Ok(Scalar::from(true)), eval("Lv >= 90 AND Name != ''"));
+        assert_eq!(
+            Ok(Scalar::from(false)),
+            eval("Name < 'Pyra' OR Lv * 2 < 100")
+        );
+        assert_eq!(Ok(Scalar::from(true)), eval("Lv > 100 AND Missing OR 1"));
+        assert_eq!(Ok(Scalar::from(true)), eval("Lv >= 90 && Name != \"\""));
+        assert_eq!(
+            Ok(Scalar::from(false)),
+            eval("!(Lv == 95) || Name == 'Pyra'")
+        );
+        assert_eq!(
+            Err(ExprError::NotNumeric("Name".to_string())),
+            eval("Name + 1")
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(ExprError::UnexpectedEnd), "Atk *".parse::<Expr>());
+        assert_eq!(
+            Err(ExprError::UnexpectedChar(4, '%')),
+            "Atk % 2".parse::<Expr>()
+        );
+        assert_eq!(
+            Err(ExprError::UnexpectedChar(4, ')')),
+            "Atk ) 2".parse::<Expr>()
+        );
+        assert!(matches!(
+            "<XYZ> + 1".parse::<Expr>(),
+            Err(ExprError::InvalidLabel(_))
+        ));
+        assert_eq!(Err(ExprError::UnexpectedEnd), "Name = 'a".parse::<Expr>());
+        assert_eq!(
+            Err(ExprError::UnexpectedChar(3, '&')),
+            "Lv & 1".parse::<Expr>()
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Turns table names into file names that can be written, and back.
+
+use std::borrow::Cow;
+
+use crate::{error::Error, FileNames, SanitizeNames};
+
+/// Characters that are not allowed in file names on Windows
+const WINDOWS_RESERVED: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names that can't be used as file names on Windows, with or without an extension
+const WINDOWS_DEVICES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Escapes the characters of a table name that can't be used in a file name, as `%XX`
+/// (the UTF-8 bytes in hex). `%` is escaped as well, so [`unescape`] gets the name back.
+///
+/// Other policies replace the characters with `_`, or fail with [`Error::InvalidFileName`].
+pub fn escape(name: &str, mode: FileNames, policy: SanitizeNames) -> Result<Cow<'_, str>, Error> {
+    let portable = match mode {
+        FileNames::Portable => true,
+        FileNames::Native => cfg!(windows),
+    };
+    let stem = name.split('.').next().unwrap_or_default();
+    let is_device = portable && WINDOWS_DEVICES.iter().any(|d| d.eq_ignore_ascii_case(stem));
+    let last = name.chars().count().saturating_sub(1);
+
+    let needs_escape = |i: usize, c: char| {
+        (c == '%' && policy == SanitizeNames::PercentEncode)
+            || c == '/'
+            || c == '\0'
+            || (portable
+                && (c.is_ascii_control()
+                    || WINDOWS_RESERVED.contains(&c)
+                    // Windows drops trailing dots and spaces
+                    || (i == last && (c == '.' || c == ' '))
+                    || (i == 0 && is_device)))
+            // "." and ".." are directories
+            || (c == '.' && name.chars().all(|c| c == '.'))
+    };
+    if !name.chars().enumerate().any(|(i, c)| needs_escape(i, c)) {
+        return Ok(Cow::Borrowed(name));
+    }
+
+    let mut escaped = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if !needs_escape(i, c) {
+            escaped.push(c);
+            continue;
+        }
+        match policy {
+            SanitizeNames::PercentEncode => {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    escaped.push_str(&format!("%{b:02X}"));
+                }
+            }
+            SanitizeNames::Replace => escaped.push('_'),
+            SanitizeNames::Error => return Err(Error::InvalidFileName(name.to_string())),
+        }
+    }
+    Ok(Cow::Owned(escaped))
+}
+
+/// Reverts [`escape`]. Sequences that aren't valid escapes are kept as they are.
+pub fn unescape(file_name: &str) -> Cow<'_, str> {
+    if !file_name.contains('%') {
+        return Cow::Borrowed(file_name);
+    }
+    let bytes = file_name.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = (bytes[i] == b'%')
+            .then(|| file_name.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    match String::from_utf8(out) {
+        Ok(name) => Cow::Owned(name),
+        Err(_) => Cow::Borrowed(file_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for (name, file_name) in [
+            ("BTL_Enemy", "BTL_Enemy"),
+            ("a:b*c?", "a%3Ab%2Ac%3F"),
+            ("50%", "50%25"),
+            ("dir/name", "dir%2Fname"),
+            ("trailing.", "trailing%2E"),
+            ("con", "%63on"),
+            ("Com1.txt", "%43om1.txt"),
+            ("..", "%2E%2E"),
+            ("ラベル", "ラベル"),
+        ] {
+            let escaped = escape(name, FileNames::Portable, SanitizeNames::PercentEncode).unwrap();
+            assert_eq!(file_name, escaped);
+            assert_eq!(name, unescape(&escaped));
+        }
+        if cfg!(not(windows)) {
+            let escaped = escape("a:b", FileNames::Native, SanitizeNames::PercentEncode);
+            assert_eq!("a:b", escaped.unwrap());
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let escape = |name, policy| escape(name, FileNames::Portable, policy);
+        assert_eq!("a_b_c_", escape("a:b*c?", SanitizeNames::Replace).unwrap());
+        assert_eq!("50%", escape("50%", SanitizeNames::Replace).unwrap());
+        assert_eq!("_on", escape("con", SanitizeNames::Replace).unwrap());
+        assert_eq!("50%", escape("50%", SanitizeNames::Error).unwrap());
+        assert!(matches!(
+            escape("a:b", SanitizeNames::Error),
+            Err(Error::InvalidFileName(name)) if name == "a:b"
+        ));
+    }
+}
@@ -27,12 +27,13 @@ impl Filter {
 impl FromIterator<FilterArg> for Filter {
     fn from_iter<T: IntoIterator<Item = FilterArg>>(iter: T) -> Self {
         Self::from_iter(iter.into_iter().flat_map(|s| {
-            match u32::from_str_radix(&s.0, 16) {
-                Ok(n) => [Some(Label::Hash(n)), Some(Label::String(s.0))]
-                    .into_iter()
-                    .flatten(),
-                Err(_) => [Some(Label::String(s.0)), None].into_iter().flatten(),
-            }
+            // Bare hex digits could be either a hash or a name, so match both
+            let labels = match (s.0.parse(), u32::from_str_radix(&s.0, 16)) {
+                (Ok(l @ Label::Hash(_)), _) => [Some(l), None],
+                (_, Ok(n)) => [Some(Label::Hash(n)), Some(Label::String(s.0))],
+                _ => [Some(Label::String(s.0)), None],
+            };
+            labels.into_iter().flatten()
         }))
     }
 }
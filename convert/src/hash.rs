@@ -0,0 +1,558 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::OpenOptions,
+    hash::{BuildHasher, Hasher},
+    io::{BufReader, Read, Seek, Write},
+};
+
+use bdat::{
+    hash::{murmur3_with_seed, IdentityHasher, PreHashedMap},
+    Cell, Label, Table, TableAccessor, Value,
+};
+
+#[derive(Clone, Copy, Default)]
+pub struct MurmurHasher(u32);
+
+pub type MurmurHashMap<K, V> = std::collections::HashMap<K, V, MurmurHasher>;
+pub type MurmurHashSet<K> = std::collections::HashSet<K, MurmurHasher>;
+
+impl BuildHasher for MurmurHasher {
+    type Hasher = Self;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        *self
+    }
+}
+
+impl Hasher for MurmurHasher {
+    fn finish(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = murmur3_with_seed(bytes, self.0);
+    }
+}
+
+/// Incremented when the way hash lists are parsed changes, to invalidate cached tables.
+const CACHE_VERSION: u64 = 2;
+
+/// An entry of a hash list that could not be read.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum HashLineError {
+    #[error("invalid hash '{0}', expected e.g. <1A2B3C4D> or 0x1A2B3C4D")]
+    InvalidHash(String),
+    #[error("missing name after the hash")]
+    MissingName,
+    #[error("hash <{0:08X}> does not match name '{1}' (<{2:08X}>)")]
+    HashMismatch(u32, String, u32),
+}
+
+pub struct HashNameTable {
+    file_name_hash: u64,
+    inner: NameStore,
+    /// Friendly names for hashes without a known name, see [`HashNameTable::load_aliases`].
+    aliases: PreHashedMap<u32, String>,
+}
+
+/// Backing storage for hash -> name mappings.
+enum NameStore {
+    /// One allocation per name, fastest lookups.
+    Map(PreHashedMap<u32, String>),
+    /// All names share a single buffer, see [`CompactNames`].
+    Compact(CompactNames),
+}
+
+/// A memory-efficient name store, used with `--compact-hashes`.
+///
+/// Names are concatenated into one string, and lookups binary-search a list of
+/// `(hash, start, end)` entries sorted by hash. This avoids the per-name allocation
+/// and hash map overhead, at the cost of slower lookups.
+#[derive(Default)]
+struct CompactNames {
+    names: String,
+    entries: Vec<(u32, u32, u32)>,
+}
+
+impl HashNameTable {
+    pub fn empty() -> Self {
+        Self::with_store(false)
+    }
+
+    fn with_store(compact: bool) -> Self {
+        Self {
+            inner: match compact {
+                true => NameStore::Compact(CompactNames::default()),
+                false => NameStore::Map(HashMap::with_hasher(IdentityHasher::default())),
+            },
+            file_name_hash: 0,
+            aliases: HashMap::with_hasher(IdentityHasher::default()),
+        }
+    }
+
+    pub fn read(mut reader: impl Read, hash: u64, compact: bool) -> std::io::Result<Self> {
+        let mut res = Self::with_store(compact);
+        res.file_name_hash = hash;
+
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf)?;
+
+        let mut string = Vec::new();
+        for _ in 0..usize::from_le_bytes(buf) {
+            reader.read_exact(&mut buf[0..4])?;
+            reader.read_exact(&mut buf[4..6])?;
+
+            let hash = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+            let len = u16::from_le_bytes(buf[4..6].try_into().unwrap());
+
+            string.resize(len as usize, 0);
+            reader.read_exact(&mut string)?;
+
+            res.inner
+                .insert(hash, std::str::from_utf8(&string).unwrap());
+        }
+        res.inner.finish();
+
+        Ok(res)
+    }
+
+    /// Loads names from a list, one in each line.
+    ///
+    /// Lines can also be `hash,name` pairs, as in CSV files with a `hash,name` header.
+    /// Blank lines and lines starting with `#` are ignored. Malformed lines are skipped, and
+    /// passed to `skipped` with their line number, starting from 1. When the list was
+    /// already loaded before, the names are read from a cache, and lines are not checked
+    /// again.
+    ///
+    /// If `compact` is set, names are stored in a [`CompactNames`] table instead of a
+    /// hash map.
+    pub fn load_from_names(
+        mut reader: impl Read,
+        compact: bool,
+        skipped: &mut dyn FnMut(usize, HashLineError),
+    ) -> std::io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        // The upper half holds the cache version, so caches from older parsers are rebuilt
+        let hash = bdat::hash::murmur3(text.as_bytes()) as u64 | (CACHE_VERSION << 32);
+
+        let mut cached = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(std::env::temp_dir().join("bdat-hashes.dat"))?;
+
+        let mut saved_hash = [0u8; 8];
+        if cached.read(&mut saved_hash)? == saved_hash.len() && hash.to_le_bytes() == saved_hash {
+            return Self::read(BufReader::new(cached), hash, compact);
+        }
+
+        let mut res = Self::with_store(compact);
+        res.file_name_hash = hash;
+        for (i, line) in text.lines().enumerate() {
+            match parse_line(line) {
+                Ok(Some((hash, name))) => res.inner.insert(hash, name),
+                Ok(None) => {}
+                Err(e) => skipped(i + 1, e),
+            }
+        }
+        res.inner.finish();
+        drop(text);
+
+        cached.rewind()?;
+        res.write(&mut cached)?;
+        let len = cached.stream_position()?;
+        cached.set_len(len)?;
+
+        Ok(res)
+    }
+
+    /// Loads aliases from a list of `hash,alias` pairs, one in each line. Blank lines and
+    /// lines starting with `#` are ignored. Malformed lines are skipped, and passed to
+    /// `skipped` with their line number, starting from 1.
+    ///
+    /// Unlike names, aliases don't have to match their hash. They are never written to
+    /// tables, and are only used for file names and when printing names, so that hashed
+    /// tables and columns are easier to find until their real names are known.
+    pub fn load_aliases(
+        &mut self,
+        mut reader: impl Read,
+        skipped: &mut dyn FnMut(usize, HashLineError),
+    ) -> std::io::Result<()> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        for (i, line) in text.lines().enumerate() {
+            match parse_alias_line(line) {
+                Ok(Some((hash, alias))) => {
+                    self.aliases.insert(hash, alias.to_string());
+                }
+                Ok(None) => {}
+                Err(e) => skipped(i + 1, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the alias of a hashed name, if it has one and the name is not known.
+    pub fn alias(&self, label: &Label) -> Option<&str> {
+        match label {
+            Label::Hash(hash) if self.unhash(*hash).is_none() => {
+                self.aliases.get(hash).map(String::as_str)
+            }
+            _ => None,
+        }
+    }
+
+    /// Replaces the table's name and column names with known names.
+    ///
+    /// Commands that only print names can resolve them as needed with
+    /// [`HashNameTable::convert_label_cow`] instead.
+    pub fn convert_all(&self, table: &mut Table) {
+        if self.inner.len() == 0 {
+            return;
+        }
+        let mut name = table.name().clone();
+        self.convert_label(&mut name);
+        table.set_name(name);
+        for col in table.columns_mut() {
+            self.convert_label(col.label_mut());
+        }
+    }
+
+    /// Replaces hash values that have a known name with text made by `format`,
+    /// e.g. `Name (<1A2B3C4D>)`.
+    ///
+    /// Like enum names, the resulting table is only meant to be serialized to text formats.
+    pub fn name_values(&self, table: &mut Table, format: impl Fn(&str, u32) -> String) {
+        if self.inner.len() == 0 {
+            return;
+        }
+        let name_value = |value: &mut Value| {
+            if let Value::HashRef(hash) = value {
+                if let Some(name) = self.unhash(*hash) {
+                    *value = Value::String(format(name, *hash).into());
+                }
+            }
+        };
+        for mut row in table.rows_mut() {
+            for cell in row.cells_mut() {
+                match cell {
+                    Cell::Single(v) => name_value(v),
+                    Cell::List(values) => values.iter_mut().for_each(name_value),
+                    Cell::Flags(_) => {}
+                }
+            }
+        }
+    }
+
+    pub fn get_label(&self, hash: u32) -> Label {
+        self.unhash(hash)
+            .map(|s| Label::Unhashed(s.to_string()))
+            .unwrap_or_else(|| Label::Hash(hash))
+    }
+
+    pub fn unhash(&self, hash: u32) -> Option<&str> {
+        self.inner.get(hash)
+    }
+
+    pub fn convert_label(&self, label: &mut Label) {
+        if let Label::Hash(hash) = label {
+            *label = self.get_label(*hash);
+        }
+    }
+
+    //   _____
+    // < label >
+    //   -----
+    //          \   ^__^
+    //           \  (oo)\_______
+    //              (__)\       )\/\
+    //                  ||----w |
+    //                  ||     ||
+    pub fn convert_label_cow<'moo>(&self, label: &'moo Label) -> Cow<'moo, Label> {
+        match label {
+            Label::Hash(h) => Cow::Owned(self.get_label(*h)),
+            l => Cow::Borrowed(l),
+        }
+    }
+
+    /// Writes the table into a format that can be deserialized
+    /// with [`HashNameTable::read`].
+    pub fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.file_name_hash.to_le_bytes())?;
+        writer.write_all(&self.inner.len().to_le_bytes())?;
+        self.inner.try_for_each(|k, v| {
+            writer.write_all(&k.to_le_bytes())?;
+
+            let bytes = v.as_bytes();
+            writer.write_all(&(bytes.len() as u16).to_le_bytes())?;
+            writer.write_all(bytes)
+        })
+    }
+}
+
+/// Parses a line of a hash list, returning [`None`] for lines without names.
+///
+/// Lines are `hash,name` pairs if the text before the first comma is a hash, and plain
+/// names otherwise, so names can have commas. Names are kept as written, except that a
+/// pair's name may be trimmed if that is what matches its hash.
+pub fn parse_line(line: &str) -> Result<Option<(u32, &str)>, HashLineError> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    if line.trim().is_empty() || line.starts_with('#') || line.eq_ignore_ascii_case("hash,name") {
+        return Ok(None);
+    }
+    let Some((hash, name)) = line
+        .split_once(',')
+        .and_then(|(hash, name)| Some((parse_hash(hash.trim())?, name)))
+    else {
+        return Ok(Some((bdat::hash::murmur3_str(line), line)));
+    };
+    if name.trim().is_empty() {
+        return Err(HashLineError::MissingName);
+    }
+    // Spaces after the comma are usually only there for readability
+    [name, name.trim()]
+        .into_iter()
+        .find(|name| bdat::hash::murmur3_str(name) == hash)
+        .map(|name| Some((hash, name)))
+        .ok_or_else(|| {
+            let name = name.trim();
+            HashLineError::HashMismatch(hash, name.to_string(), bdat::hash::murmur3_str(name))
+        })
+}
+
+/// Parses a line of an alias list, returning [`None`] for lines without aliases.
+fn parse_alias_line(line: &str) -> Result<Option<(u32, &str)>, HashLineError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.eq_ignore_ascii_case("hash,alias") {
+        return Ok(None);
+    }
+    let (hash, alias) = line.split_once(',').unwrap_or((line, ""));
+    let hash = parse_hash(hash.trim())
+        .ok_or_else(|| HashLineError::InvalidHash(hash.trim().to_string()))?;
+    match alias.trim() {
+        "" => Err(HashLineError::MissingName),
+        alias => Ok(Some((hash, alias))),
+    }
+}
+
+/// Parses `<1A2B3C4D>`, `0x1A2B3C4D` and `1A2B3C4D` hashes.
+fn parse_hash(text: &str) -> Option<u32> {
+    let hex = match text.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+        Some(hex) => hex,
+        None => text
+            .strip_prefix("0x")
+            .or_else(|| text.strip_prefix("0X"))
+            .unwrap_or(text),
+    };
+    if hex.is_empty() || hex.len() > 8 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+impl NameStore {
+    /// Adds a name. If the hash already has a name, the first one is kept, in both stores.
+    fn insert(&mut self, hash: u32, name: &str) {
+        match self {
+            Self::Map(map) => {
+                map.entry(hash).or_insert_with(|| name.to_string());
+            }
+            Self::Compact(names) => {
+                let start = names.names.len() as u32;
+                names.names.push_str(name);
+                names.entries.push((hash, start, names.names.len() as u32));
+            }
+        }
+    }
+
+    /// Prepares the store for lookups, must be called after all names have been inserted.
+    fn finish(&mut self) {
+        if let Self::Compact(names) = self {
+            // Stable sort, so duplicate hashes keep their first name
+            names.entries.sort_by_key(|(hash, _, _)| *hash);
+            names.entries.dedup_by_key(|(hash, _, _)| *hash);
+            names.entries.shrink_to_fit();
+            names.names.shrink_to_fit();
+        }
+    }
+
+    fn get(&self, hash: u32) -> Option<&str> {
+        match self {
+            Self::Map(map) => map.get(&hash).map(|s| s.as_str()),
+            Self::Compact(names) => names
+                .entries
+                .binary_search_by_key(&hash, |(hash, _, _)| *hash)
+                .ok()
+                .map(|idx| {
+                    let (_, start, end) = names.entries[idx];
+                    &names.names[start as usize..end as usize]
+                }),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Map(map) => map.len(),
+            Self::Compact(names) => names.entries.len(),
+        }
+    }
+
+    fn try_for_each<E>(&self, mut f: impl FnMut(u32, &str) -> Result<(), E>) -> Result<(), E> {
+        match self {
+            Self::Map(map) => map.iter().try_for_each(|(k, v)| f(*k, v)),
+            Self::Compact(names) => names.entries.iter().try_for_each(|&(hash, start, end)| {
+                f(hash, &names.names[start as usize..end as usize])
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_alias_line, parse_line, HashLineError, HashNameTable};
+    use bdat::hash::murmur3_str;
+    use bdat::{Cell, ColumnDef, Label, Row, Table, TableAccessor, TableBuilder, Value, ValueType};
+
+    #[test]
+    fn compact_round_trip() {
+        let names = ["ITM_PcEquip", "Name", "DebugName", "Name"];
+        let mut map = HashNameTable::with_store(false);
+        let mut compact = HashNameTable::with_store(true);
+        for name in names {
+            map.inner.insert(murmur3_str(name), name);
+            compact.inner.insert(murmur3_str(name), name);
+        }
+        compact.inner.finish();
+
+        assert_eq!(3, compact.inner.len());
+        for name in names {
+            assert_eq!(Some(name), compact.unhash(murmur3_str(name)));
+            assert_eq!(
+                map.unhash(murmur3_str(name)),
+                compact.unhash(murmur3_str(name))
+            );
+        }
+        assert_eq!(None, compact.unhash(murmur3_str("Missing")));
+
+        let mut buf = Vec::new();
+        compact.write(&mut buf).unwrap();
+        let read = HashNameTable::read(&buf[8..], 0, true).unwrap();
+        assert_eq!(Some("DebugName"), read.unhash(murmur3_str("DebugName")));
+    }
+
+    #[test]
+    fn list_lines() {
+        let name = |s| Ok(Some((murmur3_str(s), s)));
+        assert_eq!(name("Name"), parse_line("Name"));
+        assert_eq!(name("Name"), parse_line("Name\r"));
+        assert_eq!(Ok(None), parse_line("  "));
+        assert_eq!(Ok(None), parse_line("# XC3 names"));
+        assert_eq!(Ok(None), parse_line("hash,name"));
+
+        let hash = murmur3_str("DebugName");
+        assert_eq!(
+            name("DebugName"),
+            parse_line(&format!("<{hash:08X}>,DebugName"))
+        );
+        assert_eq!(
+            name("DebugName"),
+            parse_line(&format!("0x{hash:x}, DebugName"))
+        );
+        assert_eq!(
+            name("DebugName"),
+            parse_line(&format!("{hash:08X},DebugName"))
+        );
+
+        // Names with commas, and names with meaningful spaces
+        assert_eq!(name("Name,DebugName"), parse_line("Name,DebugName"));
+        assert_eq!(name(" Name "), parse_line(" Name "));
+        let spaced = murmur3_str(" Name");
+        assert_eq!(
+            Ok(Some((spaced, " Name"))),
+            parse_line(&format!("{spaced:08X}, Name"))
+        );
+        assert_eq!(
+            Err(HashLineError::MissingName),
+            parse_line(&format!("{hash:08X},"))
+        );
+        assert_eq!(
+            Err(HashLineError::HashMismatch(
+                1,
+                "Name".to_string(),
+                murmur3_str("Name")
+            )),
+            parse_line("<00000001>,Name")
+        );
+    }
+
+    #[test]
+    fn duplicate_hashes() {
+        for compact in [false, true] {
+            let mut hashes = HashNameTable::with_store(compact);
+            hashes.inner.insert(1, "First");
+            hashes.inner.insert(2, "Other");
+            hashes.inner.insert(1, "Second");
+            hashes.inner.finish();
+            assert_eq!(Some("First"), hashes.unhash(1), "compact: {compact}");
+            assert_eq!(2, hashes.inner.len());
+        }
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(
+            Ok(Some((0x1A2B3C4D, "Enemy stats"))),
+            parse_alias_line("<1A2B3C4D>, Enemy stats")
+        );
+        assert_eq!(Ok(None), parse_alias_line("hash,alias"));
+        assert_eq!(
+            Err(HashLineError::MissingName),
+            parse_alias_line("0x1A2B3C4D")
+        );
+
+        let mut hashes = HashNameTable::with_store(false);
+        hashes.inner.insert(murmur3_str("Name"), "Name");
+        let list = format!(
+            "<1A2B3C4D>,Enemy stats\n{:08X},Known\n0x1234",
+            murmur3_str("Name")
+        );
+        let mut skipped = Vec::new();
+        hashes
+            .load_aliases(list.as_bytes(), &mut |line, e| skipped.push((line, e)))
+            .unwrap();
+        assert_eq!(vec![(3, HashLineError::MissingName)], skipped);
+        assert_eq!(Some("Enemy stats"), hashes.alias(&Label::Hash(0x1A2B3C4D)));
+        // Known names take priority
+        assert_eq!(None, hashes.alias(&Label::Hash(murmur3_str("Name"))));
+        assert_eq!(None, hashes.alias(&Label::String("Enemy".to_string())));
+    }
+
+    #[test]
+    fn name_values() {
+        let mut hashes = HashNameTable::with_store(false);
+        hashes.inner.insert(murmur3_str("ITM_Potion"), "ITM_Potion");
+        let mut table: Table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+            .add_column(ColumnDef::new(ValueType::HashRef, Label::Hash(0xDEADBEEF)))
+            .add_row(Row::new(
+                1,
+                vec![Cell::Single(Value::HashRef(murmur3_str("ITM_Potion")))],
+            ))
+            .add_row(Row::new(2, vec![Cell::Single(Value::HashRef(1))]))
+            .build_modern()
+            .into();
+
+        hashes.name_values(&mut table, |name, hash| format!("{name} {hash:X}"));
+        let expected = format!("ITM_Potion {:X}", murmur3_str("ITM_Potion"));
+        assert_eq!(
+            &Cell::Single(Value::String(expected.into())),
+            table.row(1).cell(Label::Hash(0xDEADBEEF)).unwrap()
+        );
+        assert_eq!(
+            &Cell::Single(Value::HashRef(1)),
+            table.row(2).cell(Label::Hash(0xDEADBEEF)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use anyhow::{Context, Result};
+use bdat::fmt::json::{column_keys, ColumnSchema, JsonTable};
+use bdat::fmt::HashFormat;
+use bdat::{BdatVersion, Label, RowRef, Table, TableAccessor};
+
+use crate::{
+    error::FormatError,
+    parallel::{map_row_chunks, ROW_CHUNK},
+    schema::FileSchema,
+    BdatDeserialize, BdatSerialize, FormatOptions,
+};
+
+/// Converts tables to and from JSON files.
+pub struct JsonConverter {
+    opts: bdat::fmt::JsonOptions,
+    hashes: HashFormat,
+    pretty: bool,
+}
+
+impl JsonConverter {
+    pub fn new(opts: &FormatOptions) -> Self {
+        Self {
+            opts: bdat::fmt::JsonOptions::new()
+                .untyped(opts.untyped)
+                .pretty(opts.pretty)
+                .hashes(opts.hash_format)
+                .names_with_hashes(opts.names_with_hashes),
+            hashes: opts.hash_format,
+            pretty: opts.pretty,
+        }
+    }
+}
+
+impl BdatSerialize for JsonConverter {
+    fn write_table(
+        &self,
+        table: &Table,
+        rows: Vec<RowRef>,
+        schema: &FileSchema,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let opts = self.opts.version(schema.version);
+        let mut header = JsonTable::from_rows(table, [], &opts);
+        if let Some(description) = schema.table_description(table.name()) {
+            header.description = description.table.clone();
+            for (col, def) in header.schema.iter_mut().flatten().zip(table.columns()) {
+                col.description = schema
+                    .column_description(table.name(), def.label())
+                    .map(str::to_string);
+            }
+        }
+        if rows.len() <= ROW_CHUNK {
+            return bdat::fmt::to_json_with_header(header, table, rows, writer, &opts)
+                .context("Failed to write JSON");
+        }
+        // Converting cells is the slow part, serializing the converted rows is not
+        header.rows = map_row_chunks(&rows, |_, chunk| {
+            Ok(JsonTable::from_rows(table, chunk.iter().copied(), &opts).rows)
+        })?
+        .into_iter()
+        .flatten()
+        .collect();
+        match self.pretty {
+            true => serde_json::to_writer_pretty(writer, &header),
+            false => serde_json::to_writer(writer, &header),
+        }
+        .context("Failed to write JSON")
+    }
+
+    fn get_file_name(&self, table_name: &str) -> String {
+        format!("{table_name}.json")
+    }
+
+    fn format_named_hash(&self, name: &str, hash: u32) -> String {
+        self.hashes.format_named(name, hash)
+    }
+}
+
+impl BdatDeserialize for JsonConverter {
+    fn read_table(
+        &self,
+        name: Label,
+        file_schema: &FileSchema,
+        reader: &mut dyn Read,
+    ) -> Result<Table<'static>> {
+        let table: JsonTable =
+            serde_json::from_reader(reader).context("failed to read JSON table")?;
+        self.convert_table(name, file_schema, table)
+    }
+
+    fn read_standalone(
+        &self,
+        file_name: &str,
+        version: Option<BdatVersion>,
+        reader: &mut dyn Read,
+    ) -> Result<(BdatVersion, Table<'static>)> {
+        let table: JsonTable =
+            serde_json::from_reader(reader).context("failed to read JSON table")?;
+        let name = table.name.as_deref().unwrap_or(file_name);
+        let version = table.version.or(version).ok_or_else(|| {
+            FormatError::MissingMetadata("version").with_context(Label::from(name))
+        })?;
+        let name = Label::parse_for_version(name, version);
+        let schema = FileSchema::new(name.to_string(), version);
+        Ok((version, self.convert_table(name, &schema, table)?))
+    }
+
+    fn get_table_extension(&self) -> &'static str {
+        "json"
+    }
+}
+
+impl JsonConverter {
+    /// Converts a table in its JSON representation, which other formats can also
+    /// be read into.
+    ///
+    /// Rows are read by [`JsonTable::into_builder`], after dropping computed columns and
+    /// replacing enum names with their values.
+    pub(crate) fn convert_table(
+        &self,
+        name: Label,
+        file_schema: &FileSchema,
+        mut table: JsonTable,
+    ) -> Result<Table<'static>> {
+        let schema = table
+            .schema
+            .as_mut()
+            .ok_or_else(|| FormatError::MissingTypeInfo.with_context(name.clone()))?;
+        let label = |col: &ColumnSchema| {
+            file_schema
+                .recorded_hash(&col.name)
+                .filter(|_| col.hashed)
+                .unwrap_or_else(|| col.label())
+        };
+
+        let computed = file_schema.computed_columns(&name);
+        if !computed.is_empty() {
+            schema.retain(|col| !computed.contains(&col.name));
+            for row in &mut table.rows {
+                row.cells.retain(|k, _| !computed.contains(k));
+            }
+        }
+
+        if let Some(enums) = file_schema.table_enums(&name) {
+            let keys = column_keys(schema.iter().map(|c| c.name.as_str()));
+            // Enums are keyed by plain names
+            let defs = schema
+                .iter()
+                .zip(keys)
+                .filter_map(|(col, key)| {
+                    let plain = Label::split_named_hash(&col.name).map_or(&*col.name, |(n, _)| n);
+                    Some((key, (enums.get(plain)?, label(col))))
+                })
+                .collect::<HashMap<_, _>>();
+            for row in &mut table.rows {
+                for (k, v) in row.cells.iter_mut() {
+                    let Some((def, column)) = defs.get(k) else {
+                        continue;
+                    };
+                    *v = def.resolve_json(std::mem::take(v)).map_err(|n| {
+                        FormatError::UnknownEnumName(Box::new((n, column.clone().into())))
+                            .with_context(name.clone())
+                    })?;
+                }
+            }
+        }
+
+        let unique = file_schema.unique_columns(&name);
+        let builder = table
+            .into_builder(name.clone(), label)
+            .map_err(|e| FormatError::Import(e).with_context(name.clone()))?;
+        for column in unique {
+            let Some(column) = builder
+                .columns()
+                .find(|c| file_schema.is_label(column, c.label()))
+            else {
+                continue;
+            };
+            builder
+                .check_unique(column.label())
+                .with_context(|| format!("Table {name} has duplicate values"))?;
+        }
+        Ok(builder.try_build(file_schema.version)?)
+    }
+}
@@ -0,0 +1,1511 @@
+//! Conversion between BDAT files and text formats (JSON, CSV, XLSX and SQL), as used by
+//! `bdat-toolset`.
+//!
+//! [`extract`] writes the tables of BDAT files to a directory, along with a
+//! [schema](schema::FileSchema) for each file. [`pack`] reads them back into BDAT files.
+//!
+//! ```no_run
+//! use bdat_convert::{ExtractOptions, FileType, PackOptions};
+//! use std::path::PathBuf;
+//!
+//! let files = [PathBuf::from("bdat/fld.bdat")];
+//! bdat_convert::extract(&files, "out".as_ref(), &ExtractOptions::new(FileType::Json), &())?;
+//!
+//! let files = [PathBuf::from("out/fld.bschema")];
+//! bdat_convert::pack(&files, "packed".as_ref(), &PackOptions::new(FileType::Json), &())?;
+//! # Ok::<_, anyhow::Error>(())
+//! ```
+
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fs::File,
+    io::{BufReader, Read, Seek, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use bdat::fmt::{HashFormat, MultiValueEncoding};
+use bdat::{BdatFile, BdatVersion, Diagnostic, Label, RowRef, Table, TableAccessor};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, SchemaError},
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+    manifest::ExtractManifest,
+    memory::MemoryBudget,
+    parallel::Pools,
+    schema::FileSchema,
+    summary::{Stage, SummaryCounter},
+    unresolved::UnresolvedHashes,
+    util::{zip::UnpackedArchive, BdatGame},
+};
+
+pub use crate::computed::ComputedColumn;
+pub use crate::csv::CsvConverter;
+pub use crate::json::JsonConverter;
+pub use crate::parallel::Jobs;
+pub use crate::row_filter::RowFilter;
+pub use crate::summary::Summary;
+
+mod computed;
+mod csv;
+mod enums;
+pub mod error;
+pub mod expr;
+pub mod file_name;
+pub mod filter;
+pub mod hash;
+mod json;
+mod manifest;
+mod memory;
+mod parallel;
+pub mod patch;
+pub mod refs;
+mod row_filter;
+pub mod schema;
+mod sql;
+pub mod summary;
+mod unresolved;
+pub mod util;
+mod xlsx;
+
+/// The format of extracted tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    /// A JSON file per table
+    Json,
+    /// A CSV file per table
+    Csv,
+    /// A single workbook per BDAT file, with a sheet per table. Workbooks can't be packed.
+    Xlsx,
+    /// A single SQL script per BDAT file. Scripts can't be packed.
+    Sql,
+}
+
+/// How table names are turned into file names when extracting.
+///
+/// Characters that can't be used are written as `%XX`, the character's UTF-8 bytes in hex
+/// (`%` itself is escaped too). When the file name is not the table name, the `.bschema`
+/// file records it, so packing restores the original name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileNames {
+    /// File names are valid on every platform: characters that Windows doesn't allow
+    /// (e.g. `:`, `*` and `?`), control characters, trailing dots and spaces, and device
+    /// names like `CON` are escaped.
+    #[default]
+    Portable,
+    /// Only characters that are invalid on the current platform are escaped.
+    Native,
+}
+
+/// What [`extract`] does with table names that can't be used as file names as they are.
+/// [`FileNames`] decides which characters can't be used. The policy is recorded in the
+/// `.bschema` file, along with the file name of every table whose name was changed, so
+/// packing finds the tables and restores their names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SanitizeNames {
+    /// Characters are written as `%XX`. Table files packed without a schema get their
+    /// names back from the file name.
+    #[default]
+    PercentEncode,
+    /// Characters are replaced with `_`, which is easier to read and type. Table files
+    /// packed without a schema keep the changed name.
+    Replace,
+    /// Extracting fails with [`Error::InvalidFileName`].
+    Error,
+}
+
+impl SanitizeNames {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Where table files are written when extracting to JSON or CSV. The layout is recorded in
+/// the `.bschema` file, so packing finds the tables either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// A directory per BDAT file, e.g. `fld/FLD_Map.json` next to `fld.bschema`.
+    #[default]
+    Nested,
+    /// Tables next to the schema, with the BDAT file name as a prefix, e.g.
+    /// `fld_FLD_Map.json`.
+    Flat,
+}
+
+impl Layout {
+    fn is_nested(&self) -> bool {
+        *self == Self::Nested
+    }
+}
+
+/// How tables are written to and read from JSON and CSV files.
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// Leaves value types out of extracted tables. Untyped tables can't be packed.
+    pub untyped: bool,
+    /// Writes names that were resolved from hashes together with the hash, e.g.
+    /// `Name (<1A2B3C4D>)`, for table names, column names and hash values.
+    pub names_with_hashes: bool,
+    /// Adds spaces and newlines to JSON output.
+    pub pretty: bool,
+    /// How hashed names and hash values are written in JSON output. Packing accepts all formats.
+    pub hash_format: HashFormat,
+    /// The CSV field separator. Must be an ASCII character.
+    pub csv_separator: char,
+    /// How legacy-BDAT lists are written to CSV files.
+    pub csv_lists: MultiValueEncoding,
+    /// How flags are written to CSV files.
+    pub csv_flags: MultiValueEncoding,
+    /// Adds a first column with row IDs to CSV files.
+    pub csv_row_id: bool,
+    /// The header of the CSV row ID column. When packing, a column with this header is
+    /// always read as row IDs.
+    pub csv_row_id_name: String,
+}
+
+/// Settings for [`extract`].
+pub struct ExtractOptions {
+    pub file_type: FileType,
+    pub format: FormatOptions,
+    /// The game to read files for. If absent, it is detected from each file. Files in a
+    /// different format than the game are read with the detected game instead, see
+    /// [`BdatGame::for_file`].
+    pub game: Option<BdatGame>,
+    /// Names to resolve hashed names with. Tables that are still hashed are written to
+    /// files named after their alias, if they have one.
+    pub hashes: HashNameTable,
+    /// Doesn't write `.bschema` files. Extracted tables can't be packed without a schema.
+    /// Schemas left in the output directory by a previous extraction are not read either.
+    pub no_schema: bool,
+    /// Only extracts these tables. If empty, extracts all tables.
+    pub tables: Vec<String>,
+    /// Columns calculated from other columns, see [`ComputedColumn`].
+    pub computed: Vec<ComputedColumn>,
+    /// Only extracts rows that match the condition. Not supported for XLSX and SQL output.
+    pub row_filter: Option<RowFilter>,
+    /// Writes a CSV report of hashes that could not be resolved to this file.
+    pub unresolved_report: Option<PathBuf>,
+    /// Skips files that were fully extracted into `out_dir` by a previous extraction, as
+    /// recorded in its `.extract-manifest` file. Files whose size changed are extracted
+    /// again. The unresolved hash report only covers the files extracted this time.
+    pub resume: bool,
+    /// Limits how many bytes of BDAT data are converted at the same time. Files are read
+    /// one table at a time instead of all at once, and fewer files are converted in
+    /// parallel, so memory use depends on the size of the largest table rather than
+    /// the largest file. Not supported for XLSX and SQL output.
+    pub max_memory: Option<u64>,
+    /// How table names are turned into file names.
+    pub file_names: FileNames,
+    /// What to do with table names that can't be used as file names.
+    pub sanitize_names: SanitizeNames,
+    /// Where table files are written.
+    pub layout: Layout,
+    /// Only reports what would be extracted, in [`Summary::planned`], without creating
+    /// or changing any file. BDAT files are still read, as are schema annotations and
+    /// the manifest in `out_dir`.
+    pub dry_run: bool,
+    /// How many files and tables are converted at the same time.
+    pub jobs: Jobs,
+}
+
+/// Settings for [`pack`].
+pub struct PackOptions {
+    /// The format of the tables to read, either [`FileType::Json`] or [`FileType::Csv`].
+    pub file_type: FileType,
+    pub format: FormatOptions,
+    /// The game to write files for. If absent, it is chosen from the BDAT version of each
+    /// file. Tables without a schema or metadata need it to know their version.
+    pub game: Option<BdatGame>,
+    /// Keeps tables in the order they are listed in the schema, instead of sorting them
+    /// by name hash. Only affects modern (XC3) files.
+    pub preserve_order: bool,
+    /// Scrambles the name and string sections of legacy tables, like in the games'
+    /// files. Only affects legacy files.
+    pub scramble: bool,
+    /// Doesn't read every table before packing. By default, all tables are read and
+    /// checked first (missing table files, values that don't match their column types,
+    /// row IDs and references), and [`pack`] fails with [`Error::PackCheck`], listing every
+    /// problem, before any BDAT file is written. Without the check, packing stops at the
+    /// first problem, and files packed before it are kept.
+    pub no_check: bool,
+    /// Fails with [`Error::OrphanedFiles`] if there are table files that no schema lists,
+    /// e.g. because of a typo or a renamed table. By default, they are reported as
+    /// warnings, and left out.
+    pub strict: bool,
+    /// Only reports what would be packed, in [`Summary::planned`], without writing any
+    /// BDAT file. Tables are still read and checked. `.zip` archives are still unpacked
+    /// to a temporary directory.
+    pub dry_run: bool,
+    /// How many files and tables are packed at the same time.
+    pub jobs: Jobs,
+}
+
+/// Settings for [`convert`].
+pub struct ConvertOptions {
+    /// The format of the extracted tables, either [`FileType::Json`] or [`FileType::Csv`].
+    pub from: FileType,
+    /// The format to convert the tables to.
+    pub to: FileType,
+    pub format: FormatOptions,
+    /// Only reports what would be converted, in [`Summary::planned`], without creating
+    /// any file. Tables are still read.
+    pub dry_run: bool,
+    /// How many files and tables are converted at the same time.
+    pub jobs: Jobs,
+}
+
+/// Receives progress updates from [`extract`] and [`pack`], e.g. to display progress bars.
+/// Every method does nothing by default, so `&()` can be passed to ignore updates.
+///
+/// Files are converted in parallel, so calls for different files can be interleaved.
+pub trait Progress: Sync {
+    /// Called before converting, with the number of files to convert.
+    fn start(&self, _files: usize) {}
+
+    /// Called when a file starts being converted, with the number of tables that will be
+    /// converted.
+    fn start_file(&self, _file: &Path, _tables: usize) {}
+
+    /// Called after each table of a file is converted.
+    fn table_done(&self, _file: &Path) {}
+
+    /// Called after a file is converted.
+    fn file_done(&self, _file: &Path) {}
+
+    /// Called instead of [`Progress::file_done`] for files that are not extracted again
+    /// because they were extracted before, see [`ExtractOptions::resume`].
+    fn file_skipped(&self, _file: &Path) {}
+
+    /// Reports a problem that doesn't stop the conversion.
+    fn warn(&self, _message: &str) {}
+
+    /// Returns whether the conversion should stop, e.g. because the user pressed Ctrl-C.
+    /// Files that are being converted are finished, but no new files are started, and the
+    /// conversion fails with [`Error::Cancelled`].
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl Progress for () {}
+
+pub trait BdatSerialize {
+    /// Writes the given rows of a converted BDAT table to a [`Write`] implementation.
+    /// `schema` is the schema of the file the table was read from.
+    fn write_table(
+        &self,
+        table: &Table,
+        rows: Vec<RowRef>,
+        schema: &FileSchema,
+        writer: &mut dyn Write,
+    ) -> Result<()>;
+
+    /// Formats the file name for a converted BDAT table.
+    fn get_file_name(&self, table_name: &str) -> String;
+
+    /// Formats a hash value together with its name, for `--names-with-hashes`.
+    fn format_named_hash(&self, name: &str, hash: u32) -> String {
+        format!("{name} ({})", Label::Hash(hash))
+    }
+}
+
+pub trait BdatDeserialize {
+    /// Reads a BDAT table from a file.
+    fn read_table(
+        &self,
+        name: Label,
+        schema: &FileSchema,
+        reader: &mut dyn Read,
+    ) -> Result<Table<'static>>;
+
+    /// Reads a BDAT table from a file, without a file schema. The name and version come
+    /// from the table's own metadata if it has any, otherwise from the file name and
+    /// `version`.
+    ///
+    /// Returns the table, along with the BDAT version of the file it belongs to.
+    fn read_standalone(
+        &self,
+        file_name: &str,
+        version: Option<BdatVersion>,
+        reader: &mut dyn Read,
+    ) -> Result<(BdatVersion, Table<'static>)>;
+
+    /// Returns the file extension used in converted table files
+    fn get_table_extension(&self) -> &'static str;
+}
+
+/// How extracted tables are written.
+enum Output {
+    /// A file per table
+    Tables(Box<dyn BdatSerialize + Send + Sync>),
+    /// A single file per BDAT file
+    File(FileOutput),
+}
+
+#[derive(Clone, Copy)]
+enum FileOutput {
+    /// An XLSX workbook, with a sheet per table
+    Workbook,
+    /// An SQL script that creates and fills a table per BDAT table
+    Sql,
+}
+
+impl Output {
+    fn new(file_type: FileType, format: &FormatOptions) -> Self {
+        match file_type {
+            FileType::Csv => Self::Tables(Box::new(CsvConverter::new(format))),
+            FileType::Json => Self::Tables(Box::new(JsonConverter::new(format))),
+            FileType::Xlsx => Self::File(FileOutput::Workbook),
+            FileType::Sql => Self::File(FileOutput::Sql),
+        }
+    }
+}
+
+/// Returns the reader for extracted tables of `file_type`, which must be JSON or CSV.
+fn deserializer(
+    file_type: FileType,
+    format: &FormatOptions,
+) -> Result<Box<dyn BdatDeserialize + Send + Sync>> {
+    match file_type {
+        FileType::Json => Ok(Box::new(JsonConverter::new(format))),
+        FileType::Csv => Ok(Box::new(CsvConverter::new(format))),
+        t => Err(Error::UnknownFileType(t.to_string()).into()),
+    }
+}
+
+/// Extracts the tables of BDAT files into `out_dir`. The directory structure of the files
+/// is kept, and each file's tables are written to a directory named after the file.
+///
+/// Returns what was extracted, see [`Summary`].
+pub fn extract<'a>(
+    files: &'a [PathBuf],
+    out_dir: &Path,
+    opts: &ExtractOptions,
+    progress: &dyn Progress,
+) -> Result<Summary> {
+    if !opts.dry_run {
+        std::fs::create_dir_all(out_dir).context("Could not create output directory")?;
+    }
+
+    let output = Output::new(opts.file_type, &opts.format);
+    if opts.row_filter.is_some() && matches!(output, Output::File(_)) {
+        return Err(Error::UnsupportedOption("where").into());
+    }
+    if opts.format.names_with_hashes && matches!(output, Output::File(_)) {
+        return Err(Error::UnsupportedOption("names-with-hashes").into());
+    }
+    if opts.max_memory.is_some() && matches!(output, Output::File(_)) {
+        return Err(Error::UnsupportedOption("max-memory").into());
+    }
+
+    let ctx = ExtractContext {
+        opts,
+        table_filter: opts.tables.iter().cloned().map(FilterArg).collect(),
+        unresolved: UnresolvedHashes::default(),
+        summary: SummaryCounter::new(opts.dry_run),
+        progress,
+        pools: Pools::new(opts.jobs)?,
+    };
+    let budget = opts.max_memory.map(MemoryBudget::new);
+
+    let manifest = match opts.dry_run {
+        true => ExtractManifest::read_only(out_dir, opts.resume)?,
+        false => ExtractManifest::open(out_dir, opts.resume)?,
+    };
+
+    let base_path = crate::util::get_common_denominator(files);
+    progress.start(files.len());
+
+    // Returns where a file goes in the manifest and its size, or `None` if it was already
+    // extracted
+    let check = |path: &'a PathBuf| -> Result<Option<(&'a Path, u64)>> {
+        let relative_path = path.strip_prefix(&base_path).unwrap();
+        // With a single file, the common directory is the file itself
+        let manifest_path = match relative_path.as_os_str().is_empty() {
+            true => Path::new(path.file_name().unwrap()),
+            false => relative_path,
+        };
+        let size = std::fs::metadata(path)?.len();
+        if manifest.is_done(manifest_path, size) {
+            ctx.summary.file_skipped();
+            progress.file_skipped(path);
+            return Ok(None);
+        }
+        Ok(Some((manifest_path, size)))
+    };
+    let extract_file = |path: &Path, manifest_path: &Path, size: u64| -> Result<()> {
+        let file_name = path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .map(ToString::to_string)
+            .unwrap();
+        let relative_path = path.strip_prefix(&base_path).unwrap();
+        let out_dir = out_dir.join(relative_path.parent().unwrap_or_else(|| Path::new("")));
+
+        let schema = match (&output, &budget) {
+            (Output::Tables(serializer), Some(_)) => {
+                ctx.extract_streaming(path, file_name, &out_dir, &**serializer)?
+            }
+            (output, _) => ctx.extract_file(path, file_name, &out_dir, output)?,
+        };
+        if !opts.no_schema {
+            ctx.summary.write(path, None, &schema.path(&out_dir), || {
+                schema.write(&out_dir)
+            })?;
+        }
+
+        manifest.add(manifest_path, size)?;
+        ctx.summary.file_done();
+        progress.file_done(path);
+        Ok(())
+    };
+
+    let res = match &budget {
+        Some(budget) => {
+            // Memory is reserved on this thread, before a file is sent to the pool. A pool
+            // thread that waits for its rows to be encoded can start another file, so it must
+            // never wait for the budget itself, or it could wait for its own reservation.
+            let error = Mutex::new(None);
+            ctx.pools.files_scope(|scope| {
+                for path in files {
+                    if progress.is_cancelled() || error.lock().unwrap().is_some() {
+                        break;
+                    }
+                    let (manifest_path, size) = match check(path) {
+                        Ok(Some(file)) => file,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            error.lock().unwrap().get_or_insert(Err(e));
+                            break;
+                        }
+                    };
+                    // A table is never larger than its file
+                    let reservation = budget.reserve(size);
+                    let (extract_file, error) = (&extract_file, &error);
+                    scope.spawn(move |_| {
+                        let _reservation = reservation;
+                        if let Err(e) = extract_file(path, manifest_path, size) {
+                            error.lock().unwrap().get_or_insert(Err(e));
+                        }
+                    });
+                }
+            });
+            error.into_inner().unwrap()
+        }
+        None => ctx.pools.files(|| {
+            files
+                .par_iter()
+                .panic_fuse()
+                .map(|path| {
+                    if progress.is_cancelled() {
+                        return Ok(());
+                    }
+                    match check(path)? {
+                        Some((manifest_path, size)) => extract_file(path, manifest_path, size),
+                        None => Ok(()),
+                    }
+                })
+                .find_any(|r: &anyhow::Result<()>| r.is_err())
+        }),
+    };
+
+    if let Some(r) = res {
+        r?;
+    }
+    if progress.is_cancelled() {
+        return Err(Error::Cancelled.into());
+    }
+
+    if let Some(path) = &opts.unresolved_report {
+        ctx.summary.write(&base_path, None, path, || {
+            util::write_atomic(path, |writer| ctx.unresolved.write(writer))
+                .context("Could not write unresolved hash report")
+        })?;
+    }
+
+    Ok(ctx.summary.finish(ctx.unresolved.len()))
+}
+
+/// A table that is ready to be written, see [`ExtractContext::prepare_table`].
+struct PreparedTable<'b> {
+    table: Table<'b>,
+    /// IDs of the rows to write, or `None` for every row
+    rows: Option<Vec<usize>>,
+    /// The name of the table's file, without extension
+    file_name: String,
+}
+
+/// Settings and state shared by every file of an extraction.
+struct ExtractContext<'a> {
+    opts: &'a ExtractOptions,
+    table_filter: Filter,
+    unresolved: UnresolvedHashes,
+    summary: SummaryCounter,
+    progress: &'a dyn Progress,
+    pools: Pools,
+}
+
+impl<'a> ExtractContext<'a> {
+    /// Reads every table of a file at once, and parses and writes the tables in parallel.
+    fn extract_file(
+        &self,
+        path: &Path,
+        file_name: String,
+        out_dir: &Path,
+        output: &Output,
+    ) -> Result<FileSchema> {
+        let mut file = self.summary.time(Stage::Read, || std::fs::read(path))?;
+        let game = BdatGame::for_file(self.opts.game, bdat::detect_bytes_version(&file))
+            .with_context(|| format!("Could not detect BDAT version ({})", path.display()))?;
+        let mut diagnostics = Vec::new();
+        let tables = self
+            .summary
+            .time(Stage::Read, || {
+                self.pools.tables(|| {
+                    game.parse_bytes(&mut file, self.pools.min_tables(), &mut |d| {
+                        diagnostics.push(d)
+                    })
+                })
+            })
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
+        for diagnostic in diagnostics {
+            self.warn_diagnostic(path, diagnostic);
+        }
+
+        let mut schema = self.new_schema(file_name, game.into(), out_dir)?;
+        let tables_dir = schema.tables_dir(out_dir);
+
+        // The schema needs to see every table in file order, so do that before
+        // handing the tables over to the thread pool.
+        let tables = tables
+            .into_iter()
+            .filter_map(|table| self.prepare_table(table, &mut schema).transpose())
+            .collect::<Result<Vec<_>>>()?;
+
+        let serializer = match output {
+            Output::Tables(serializer) => serializer,
+            Output::File(output) => {
+                let tables = tables.into_iter().map(|t| t.table).collect::<Vec<_>>();
+                let out_path = file_output_path(*output, &schema, out_dir);
+                self.summary.write(path, None, &out_path, || {
+                    write_file_output(*output, &tables, &schema, &out_path)
+                })?;
+                for table in &tables {
+                    self.summary.table_done(table.row_count());
+                }
+                return Ok(schema);
+            }
+        };
+        if !self.summary.is_dry_run() {
+            std::fs::create_dir_all(&tables_dir)?;
+        }
+
+        self.progress.start_file(path, tables.len());
+
+        self.pools.tables(|| {
+            tables
+                .into_par_iter()
+                .with_min_len(self.pools.min_tables())
+                .panic_fuse()
+                .try_for_each(|table| {
+                    self.write_table(path, table, &**serializer, &schema, &tables_dir)?;
+                    self.progress.table_done(path);
+                    Ok::<_, anyhow::Error>(())
+                })
+        })?;
+
+        Ok(schema)
+    }
+
+    /// Reads and writes one table at a time, for [`ExtractOptions::max_memory`]. Only the
+    /// table that is being converted is kept in memory.
+    fn extract_streaming(
+        &self,
+        path: &Path,
+        file_name: String,
+        out_dir: &Path,
+        serializer: &dyn BdatSerialize,
+    ) -> Result<FileSchema> {
+        let context = || format!("Could not parse BDAT tables ({})", path.to_string_lossy());
+        let (game, mut file) = self.summary.time(Stage::Read, || {
+            let mut reader = BufReader::new(File::open(path)?);
+            let detected = bdat::detect_file_version(&mut reader);
+            let game = BdatGame::for_file(self.opts.game, detected)
+                .with_context(|| format!("Could not detect BDAT version ({})", path.display()))?;
+            reader.rewind()?;
+            let file = game.from_reader(reader).with_context(context)?;
+            Ok::<_, anyhow::Error>((game, file))
+        })?;
+
+        let mut schema = self.new_schema(file_name, game.into(), out_dir)?;
+        let tables_dir = schema.tables_dir(out_dir);
+        if !self.summary.is_dry_run() {
+            std::fs::create_dir_all(&tables_dir)?;
+        }
+
+        // Tables are only filtered by name after being read, so every table is counted
+        let count = file.table_count();
+        self.progress.start_file(path, count);
+        for i in 0..count {
+            let table = self
+                .summary
+                .time(Stage::Read, || file.get_table(i))
+                .with_context(context)?;
+            for diagnostic in file.take_diagnostics() {
+                self.warn_diagnostic(path, diagnostic);
+            }
+            if let Some(table) = self.prepare_table(table, &mut schema)? {
+                self.write_table(path, table, serializer, &schema, &tables_dir)?;
+            }
+            self.progress.table_done(path);
+        }
+
+        Ok(schema)
+    }
+
+    /// Creates the schema of a file, with the annotations of the schema previously written to
+    /// `out_dir`. With [`ExtractOptions::no_schema`], no schema is read or written, so the
+    /// previous schema is left alone.
+    fn new_schema(
+        &self,
+        file_name: String,
+        version: BdatVersion,
+        out_dir: &Path,
+    ) -> Result<FileSchema> {
+        let mut schema = FileSchema::new(file_name, version);
+        schema.layout = self.opts.layout;
+        if !self.opts.no_schema {
+            schema.load_annotations(out_dir)?;
+        }
+        Ok(schema)
+    }
+
+    fn warn_diagnostic(&self, path: &Path, diagnostic: Diagnostic) {
+        self.summary
+            .warn(self.progress, format!("{}: {diagnostic}", path.display()));
+    }
+
+    /// Resolves the table's names, records it in the schema and adds computed columns.
+    ///
+    /// Returns `None` if the table is filtered out.
+    fn prepare_table<'b>(
+        &self,
+        table: Table<'b>,
+        schema: &mut FileSchema,
+    ) -> Result<Option<PreparedTable<'b>>> {
+        self.summary
+            .time(Stage::Convert, || self.convert_table(table, schema))
+    }
+
+    fn convert_table<'b>(
+        &self,
+        mut table: Table<'b>,
+        schema: &mut FileSchema,
+    ) -> Result<Option<PreparedTable<'b>>> {
+        self.opts.hashes.convert_all(&mut table);
+        schema.resolve_labels(&mut table);
+        schema.feed_table(&table);
+        if !self.table_filter.contains(table.name()) {
+            self.summary.table_skipped();
+            return Ok(None);
+        }
+        self.unresolved
+            .add_table(&schema.file_name, &table, &self.opts.hashes);
+        let mut computed = Vec::new();
+        for column in &self.opts.computed {
+            if column.applies_to(&table) {
+                table = column.apply(table)?;
+                computed.push(column.name.clone());
+            }
+        }
+        schema.add_computed(table.name(), computed);
+        let rows = match &self.opts.row_filter {
+            Some(filter) if filter.applies_to(&table) => Some(filter.matching_rows(&table)?),
+            _ => None,
+        };
+        let file_name = match self.opts.file_type {
+            FileType::Json | FileType::Csv => {
+                let alias = self.opts.hashes.alias(table.name());
+                schema.assign_file_name(
+                    table.name(),
+                    alias,
+                    self.opts.file_names,
+                    self.opts.sanitize_names,
+                )?
+            }
+            FileType::Xlsx | FileType::Sql => String::new(),
+        };
+        Ok(Some(PreparedTable {
+            table,
+            rows,
+            file_name,
+        }))
+    }
+
+    fn write_table(
+        &self,
+        source: &Path,
+        prepared: PreparedTable,
+        serializer: &dyn BdatSerialize,
+        schema: &FileSchema,
+        tables_dir: &Path,
+    ) -> Result<()> {
+        let PreparedTable {
+            mut table,
+            rows,
+            file_name,
+        } = prepared;
+        if let Some(enums) = schema.table_enums(table.name()) {
+            enums::apply_names(&mut table, enums);
+        }
+        if self.opts.format.names_with_hashes {
+            self.opts.hashes.name_values(&mut table, |name, hash| {
+                serializer.format_named_hash(name, hash)
+            });
+        }
+        let out_path =
+            tables_dir.join(serializer.get_file_name(&schema.table_file_stem(&file_name)));
+        // Rows were filtered before applying enum names, so conditions
+        // compare the original values
+        let rows: Vec<_> = match rows {
+            Some(ids) => ids.into_iter().map(|id| table.row(id)).collect(),
+            None => table.rows().collect(),
+        };
+        self.write_rows(source, &table, rows, serializer, schema, &out_path)
+    }
+
+    /// Writes rows of a table read from `source`.
+    fn write_rows(
+        &self,
+        source: &Path,
+        table: &Table,
+        rows: Vec<RowRef>,
+        serializer: &dyn BdatSerialize,
+        schema: &FileSchema,
+        out_path: &Path,
+    ) -> Result<()> {
+        let row_count = rows.len();
+        self.summary
+            .write(source, Some(table.name()), out_path, || {
+                util::write_atomic(out_path, |writer| {
+                    serializer
+                        .write_table(table, rows, schema, writer)
+                        .context("Could not write table")
+                })
+                .context("Could not save table")
+            })?;
+        self.summary.table_done(row_count);
+        Ok(())
+    }
+}
+
+/// Packs extracted tables into BDAT files in `out_dir`.
+///
+/// `files` are the `.bschema` files to pack, and the tables are read from a directory
+/// next to each schema, named after the file. If there are no schemas, every table file
+/// in `files` is packed on its own, using the metadata embedded in the table.
+///
+/// `.zip` archives in `files` are packed as if their contents had been given as a
+/// directory, e.g. an archive of extracted tables and their schemas. Archives are unpacked
+/// to a temporary directory first.
+///
+/// Returns what was packed, see [`Summary`].
+pub fn pack(
+    files: &[PathBuf],
+    out_dir: &Path,
+    opts: &PackOptions,
+    progress: &dyn Progress,
+) -> Result<Summary> {
+    if !opts.dry_run {
+        std::fs::create_dir_all(out_dir).context("Could not create output directory")?;
+    }
+
+    let deserializer = deserializer(opts.file_type, &opts.format)?;
+    let ctx = PackContext {
+        opts,
+        deserializer: deserializer.as_ref(),
+        out_dir,
+        progress,
+        summary: SummaryCounter::new(opts.dry_run),
+        pools: Pools::new(opts.jobs)?,
+    };
+
+    let (archives, files): (Vec<_>, Vec<_>) = files
+        .iter()
+        .cloned()
+        .partition(|path| path.extension().is_some_and(|e| e == "zip"));
+    let archives = archives
+        .iter()
+        .map(|path| {
+            UnpackedArchive::new(path)
+                .with_context(|| format!("Could not unpack {}", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut orphans = Vec::new();
+    let inputs = std::iter::once(Ok(files))
+        .chain(archives.iter().map(UnpackedArchive::files))
+        .map(|files| {
+            let files = files?;
+            orphans.extend(ctx.orphaned_files(&files));
+            Ok(ctx.select_files(files))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if opts.strict && !orphans.is_empty() {
+        let orphans = orphans.iter().map(|p| p.display().to_string()).collect();
+        return Err(Error::OrphanedFiles(orphans).into());
+    }
+    for path in &orphans {
+        ctx.summary.warn(
+            progress,
+            format!(
+                "Table file {} is not listed in any schema, and was not packed",
+                path.display()
+            ),
+        );
+    }
+
+    let total = inputs.iter().map(PackInput::len).sum();
+    if total == 0 {
+        return Err(Error::from(SchemaError::MissingSchema).into());
+    }
+    if !opts.no_check {
+        let problems = inputs
+            .iter()
+            .flat_map(|input| match input {
+                PackInput::Schemas(files) => ctx.check_schemas(files),
+                PackInput::Tables(_) => Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        if !problems.is_empty() {
+            return Err(Error::PackCheck(problems).into());
+        }
+    }
+    progress.start(total);
+    for input in inputs {
+        match input {
+            PackInput::Schemas(files) => ctx.pack_schemas(&files)?,
+            PackInput::Tables(files) => ctx.pack_standalone(&files)?,
+        }
+    }
+    if progress.is_cancelled() {
+        return Err(Error::Cancelled.into());
+    }
+
+    Ok(ctx.summary.finish(0))
+}
+
+/// The files to pack from one input directory or archive.
+enum PackInput {
+    /// Schema files, with tables in a directory next to each schema
+    Schemas(Vec<PathBuf>),
+    /// Table files without schemas, each packed on its own
+    Tables(Vec<PathBuf>),
+}
+
+impl PackInput {
+    fn len(&self) -> usize {
+        match self {
+            Self::Schemas(files) | Self::Tables(files) => files.len(),
+        }
+    }
+}
+
+/// Settings and state shared by every file that is packed.
+struct PackContext<'a> {
+    opts: &'a PackOptions,
+    deserializer: &'a (dyn BdatDeserialize + Send + Sync),
+    out_dir: &'a Path,
+    progress: &'a dyn Progress,
+    summary: SummaryCounter,
+    pools: Pools,
+}
+
+impl<'a> PackContext<'a> {
+    /// Picks the schemas from `files`, or the table files if there are no schemas.
+    fn select_files(&self, files: Vec<PathBuf>) -> PackInput {
+        let has_extension = |path: &PathBuf, ext: &str| path.extension().is_some_and(|e| e == ext);
+        let schemas = files
+            .iter()
+            .filter(|path| has_extension(path, "bschema"))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !schemas.is_empty() {
+            return PackInput::Schemas(schemas);
+        }
+        let extension = self.deserializer.get_table_extension();
+        PackInput::Tables(
+            files
+                .into_iter()
+                .filter(|path| has_extension(path, extension))
+                .collect(),
+        )
+    }
+
+    /// Returns the table files in `files` that are not listed in any of the schemas in
+    /// `files`. If there are no schemas, every table is packed on its own, so none are
+    /// left out.
+    fn orphaned_files(&self, files: &[PathBuf]) -> Vec<PathBuf> {
+        let extension = self.deserializer.get_table_extension();
+        let mut listed = HashSet::new();
+        let mut has_schemas = false;
+        for path in files
+            .iter()
+            .filter(|path| path.extension().is_some_and(|e| e == "bschema"))
+        {
+            has_schemas = true;
+            // Schemas that can't be read fail later on
+            let Ok(schema) = File::open(path)
+                .map_err(anyhow::Error::from)
+                .and_then(FileSchema::read)
+            else {
+                continue;
+            };
+            listed.extend(
+                schema
+                    .find_table_files(path.parent().unwrap(), extension)
+                    .into_iter()
+                    .map(|(_, path)| path),
+            );
+        }
+        if !has_schemas {
+            return Vec::new();
+        }
+        files
+            .iter()
+            .filter(|path| path.extension().is_some_and(|e| e == extension))
+            .filter(|path| !listed.contains(*path))
+            .cloned()
+            .collect()
+    }
+
+    fn pack_schemas(&self, schema_files: &[PathBuf]) -> Result<()> {
+        let PackContext {
+            opts,
+            deserializer,
+            out_dir,
+            progress,
+            ref summary,
+            ref pools,
+        } = *self;
+        let base_path = crate::util::get_common_denominator(schema_files);
+
+        let res = pools.files(|| {
+            schema_files
+                .into_par_iter()
+                .panic_fuse()
+                .map(|schema_path| {
+                    if progress.is_cancelled() {
+                        return Ok(());
+                    }
+                    let schema_file = FileSchema::read(File::open(schema_path)?)?;
+
+                    // The relative path to the tables (we mimic the original file structure in the output)
+                    let relative_path = schema_path
+                        .strip_prefix(&base_path)
+                        .unwrap()
+                        .parent()
+                        .unwrap_or_else(|| Path::new(""));
+
+                    progress.start_file(schema_path, schema_file.table_count());
+
+                    let table_files = schema_file.find_table_files(
+                        schema_path.parent().unwrap(),
+                        deserializer.get_table_extension(),
+                    );
+                    let tables = pools.tables(|| {
+                        table_files
+                            .into_par_iter()
+                            .with_min_len(pools.min_tables())
+                            .panic_fuse()
+                            .map(|(label, table)| {
+                                progress.table_done(schema_path);
+                                summary.time(Stage::Read, || {
+                                    self.read_table(&schema_file, label, &table)
+                                })
+                            })
+                            .collect::<Result<Vec<_>>>()
+                    })?;
+
+                    summary.time(Stage::Convert, || schema_file.check_references(&tables))?;
+
+                    if tables.is_empty() {
+                        summary.warn(
+                            progress,
+                            format!("File {} has no tables", schema_path.display()),
+                        );
+                    }
+                    for table in &tables {
+                        summary.table_done(table.row_count());
+                    }
+
+                    let out_dir = out_dir.join(relative_path);
+                    if !summary.is_dry_run() {
+                        std::fs::create_dir_all(&out_dir)?;
+                    }
+                    let out_path = out_dir.join(format!("{}.bdat", schema_file.file_name));
+                    let game = opts
+                        .game
+                        .unwrap_or_else(|| BdatGame::version_default(schema_file.version));
+                    summary.write(schema_path, None, &out_path, || {
+                        util::write_atomic(&out_path, |writer| {
+                            Ok(game.to_writer(
+                                writer,
+                                tables,
+                                !opts.preserve_order,
+                                opts.scramble,
+                            )?)
+                        })
+                    })?;
+                    summary.file_done();
+                    progress.file_done(schema_path);
+                    Ok(())
+                })
+                .find_any(|r: &anyhow::Result<()>| r.is_err())
+        });
+
+        res.unwrap_or(Ok(()))
+    }
+
+    /// Reads every table of the schema files without writing anything, and returns the
+    /// problems that would stop packing, for [`PackOptions::no_check`].
+    fn check_schemas(&self, schema_files: &[PathBuf]) -> Vec<String> {
+        let extension = self.deserializer.get_table_extension();
+        let pools = &self.pools;
+        pools.files(|| {
+            schema_files
+                .par_iter()
+                .flat_map(|schema_path| {
+                    let schema_file = match File::open(schema_path)
+                        .map_err(anyhow::Error::from)
+                        .and_then(FileSchema::read)
+                    {
+                        Ok(schema) => schema,
+                        Err(e) => return vec![format!("{}: {e:#}", schema_path.display())],
+                    };
+                    let schema_dir = schema_path.parent().unwrap();
+                    let mut problems = schema_file
+                        .missing_table_files(schema_dir, extension)
+                        .into_iter()
+                        .map(|name| {
+                            format!("{}: no table file for table {name}", schema_path.display())
+                        })
+                        .collect::<Vec<_>>();
+
+                    let table_files = schema_file.find_table_files(schema_dir, extension);
+                    let (tables, errors): (Vec<_>, Vec<_>) = pools.tables(|| {
+                        table_files
+                            .into_par_iter()
+                            .with_min_len(pools.min_tables())
+                            .map(|(label, path)| {
+                                self.read_table(&schema_file, label, &path)
+                                    .map_err(|e| format!("{}: {e:#}", path.display()))
+                            })
+                            .partition_map(|res| match res {
+                                Ok(table) => rayon::iter::Either::Left(table),
+                                Err(e) => rayon::iter::Either::Right(e),
+                            })
+                    });
+                    problems.extend(errors);
+                    if let Err(e) = schema_file.check_references(&tables) {
+                        problems.push(format!("{}: {e}", schema_path.display()));
+                    }
+                    problems
+                })
+                .collect()
+        })
+    }
+
+    /// Reads a table file listed in a schema.
+    fn read_table(
+        &self,
+        schema_file: &FileSchema,
+        label: Label,
+        path: &Path,
+    ) -> Result<Table<'static>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        self.deserializer.read_table(
+            label.into_hash(schema_file.version),
+            schema_file,
+            &mut reader,
+        )
+    }
+
+    /// Packs tables that don't have a schema file, using the metadata embedded in each
+    /// table. Every table is written to its own BDAT file, named after the table file.
+    fn pack_standalone(&self, table_files: &[PathBuf]) -> Result<()> {
+        let PackContext {
+            opts,
+            deserializer,
+            out_dir,
+            progress,
+            ref summary,
+            ref pools,
+        } = *self;
+        let base_path = crate::util::get_common_denominator(table_files);
+
+        pools.files(|| {
+            table_files
+                .into_par_iter()
+                .panic_fuse()
+                .try_for_each(|path| {
+                    if progress.is_cancelled() {
+                        return Ok(());
+                    }
+                    let mut reader = BufReader::new(File::open(path)?);
+                    let file_name = path.file_stem().and_then(OsStr::to_str).unwrap();
+                    // Tables without metadata are named after the file
+                    let table_name = file_name::unescape(file_name);
+                    let (version, table) = summary
+                        .time(Stage::Read, || {
+                            deserializer.read_standalone(
+                                &table_name,
+                                opts.game.map(Into::into),
+                                &mut reader,
+                            )
+                        })
+                        .with_context(|| format!("Could not read {}", path.display()))?;
+                    summary.table_done(table.row_count());
+
+                    let out_dir = out_dir.join(
+                        path.strip_prefix(&base_path)
+                            .unwrap()
+                            .parent()
+                            .unwrap_or_else(|| Path::new("")),
+                    );
+                    if !summary.is_dry_run() {
+                        std::fs::create_dir_all(&out_dir)?;
+                    }
+                    let game = opts
+                        .game
+                        .unwrap_or_else(|| BdatGame::version_default(version));
+                    let out_path = out_dir.join(format!("{file_name}.bdat"));
+                    let name = table.name().clone();
+                    summary.write(path, Some(&name), &out_path, || {
+                        util::write_atomic(&out_path, |writer| {
+                            Ok(game.to_writer(writer, [table], true, opts.scramble)?)
+                        })
+                    })?;
+                    summary.file_done();
+                    progress.file_done(path);
+                    Ok::<_, anyhow::Error>(())
+                })
+        })
+    }
+}
+
+/// Converts extracted tables from one format to another, e.g. from JSON to CSV, without
+/// packing them into BDAT files.
+///
+/// `files` are the `.bschema` files of the tables to convert. Each schema is copied to
+/// `out_dir`, and its tables are written with the same layout and file names, keeping the
+/// directory structure. Computed columns are left out, as when packing.
+///
+/// Returns what was converted, see [`Summary`].
+pub fn convert(
+    files: &[PathBuf],
+    out_dir: &Path,
+    opts: &ConvertOptions,
+    progress: &dyn Progress,
+) -> Result<Summary> {
+    let deserializer = deserializer(opts.from, &opts.format)?;
+    let schemas = files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|e| e == "bschema"))
+        .cloned()
+        .collect::<Vec<_>>();
+    if schemas.is_empty() {
+        return Err(Error::from(SchemaError::MissingSchema).into());
+    }
+    if !opts.dry_run {
+        std::fs::create_dir_all(out_dir).context("Could not create output directory")?;
+    }
+
+    let output = Output::new(opts.to, &opts.format);
+    let extract_opts = ExtractOptions {
+        format: opts.format.clone(),
+        ..ExtractOptions::new(opts.to)
+    };
+    let ctx = ExtractContext {
+        opts: &extract_opts,
+        table_filter: Filter::from_iter(std::iter::empty::<Label>()),
+        unresolved: UnresolvedHashes::default(),
+        summary: SummaryCounter::new(opts.dry_run),
+        progress,
+        pools: Pools::new(opts.jobs)?,
+    };
+
+    let base_path = crate::util::get_common_denominator(&schemas);
+    progress.start(schemas.len());
+
+    let res = ctx.pools.files(|| {
+        schemas
+            .par_iter()
+            .panic_fuse()
+            .map(|schema_path| {
+                if progress.is_cancelled() {
+                    return Ok(());
+                }
+                let relative_path = schema_path
+                    .strip_prefix(&base_path)
+                    .unwrap()
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""));
+                ctx.convert_file(
+                    schema_path,
+                    &out_dir.join(relative_path),
+                    deserializer.as_ref(),
+                    &output,
+                )
+            })
+            .find_any(|r: &anyhow::Result<()>| r.is_err())
+    });
+
+    if let Some(r) = res {
+        r?;
+    }
+    if progress.is_cancelled() {
+        return Err(Error::Cancelled.into());
+    }
+
+    Ok(ctx.summary.finish(0))
+}
+
+impl<'a> ExtractContext<'a> {
+    /// Converts the tables of a schema for [`convert`], writing them and the schema to
+    /// `out_dir`.
+    fn convert_file(
+        &self,
+        schema_path: &Path,
+        out_dir: &Path,
+        deserializer: &(dyn BdatDeserialize + Send + Sync),
+        output: &Output,
+    ) -> Result<()> {
+        let schema = FileSchema::read(File::open(schema_path)?)?;
+        let table_files = schema.find_table_files(
+            schema_path.parent().unwrap(),
+            deserializer.get_table_extension(),
+        );
+        self.progress.start_file(schema_path, table_files.len());
+
+        let tables = self.pools.tables(|| {
+            table_files
+                .into_par_iter()
+                .with_min_len(self.pools.min_tables())
+                .panic_fuse()
+                .map(|(label, path)| {
+                    let label = label.into_hash(schema.version);
+                    if !schema.computed_columns(&label).is_empty() {
+                        self.summary.warn(
+                            self.progress,
+                            format!("Computed columns were left out of {}", path.display()),
+                        );
+                    }
+                    let mut reader = BufReader::new(File::open(&path)?);
+                    let mut table = self
+                        .summary
+                        .time(Stage::Read, || {
+                            deserializer.read_table(label, &schema, &mut reader)
+                        })
+                        .with_context(|| format!("Could not read {}", path.display()))?;
+                    // Tables are read with hashed names, restore the names they were extracted with
+                    schema.resolve_labels(&mut table);
+                    Ok((path, table))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        match output {
+            Output::Tables(serializer) => {
+                let tables_dir = schema.tables_dir(out_dir);
+                if !self.summary.is_dry_run() {
+                    std::fs::create_dir_all(&tables_dir)?;
+                }
+                self.pools.tables(|| {
+                    tables
+                        .into_par_iter()
+                        .with_min_len(self.pools.min_tables())
+                        .panic_fuse()
+                        .try_for_each(|(path, mut table)| {
+                            if let Some(enums) = schema.table_enums(table.name()) {
+                                enums::apply_names(&mut table, enums);
+                            }
+                            let stem = path.file_stem().and_then(OsStr::to_str).unwrap();
+                            let out_path = tables_dir.join(serializer.get_file_name(stem));
+                            self.write_rows(
+                                schema_path,
+                                &table,
+                                table.rows().collect(),
+                                &**serializer,
+                                &schema,
+                                &out_path,
+                            )?;
+                            self.progress.table_done(schema_path);
+                            Ok::<_, anyhow::Error>(())
+                        })
+                })?;
+            }
+            Output::File(output) => {
+                let tables = tables.into_iter().map(|(_, t)| t).collect::<Vec<_>>();
+                let out_path = file_output_path(*output, &schema, out_dir);
+                self.summary.write(schema_path, None, &out_path, || {
+                    write_file_output(*output, &tables, &schema, &out_path)
+                })?;
+                for table in &tables {
+                    self.summary.table_done(table.row_count());
+                    self.progress.table_done(schema_path);
+                }
+            }
+        }
+
+        self.summary
+            .write(schema_path, None, &schema.path(out_dir), || {
+                schema.write(out_dir)
+            })?;
+        self.summary.file_done();
+        self.progress.file_done(schema_path);
+        Ok(())
+    }
+}
+
+/// Returns the path of the single file written for a BDAT file's tables.
+fn file_output_path(output: FileOutput, schema: &FileSchema, out_dir: &Path) -> PathBuf {
+    let extension = match output {
+        FileOutput::Workbook => "xlsx",
+        FileOutput::Sql => "sql",
+    };
+    out_dir.join(format!("{}.{extension}", schema.file_name))
+}
+
+/// Writes all tables of a BDAT file to a single file, for outputs that don't write a
+/// file per table. `out_path` comes from [`file_output_path`].
+fn write_file_output(
+    output: FileOutput,
+    tables: &[Table],
+    schema: &FileSchema,
+    out_path: &Path,
+) -> Result<()> {
+    if let Some(dir) = out_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    util::write_atomic(out_path, |writer| match output {
+        FileOutput::Workbook => {
+            let display_tables = tables
+                .iter()
+                .map(|table| {
+                    let mut table = table.clone();
+                    if let Some(enums) = schema.table_enums(table.name()) {
+                        enums::apply_names(&mut table, enums);
+                    }
+                    table
+                })
+                .collect::<Vec<_>>();
+            xlsx::write_workbook(tables, &display_tables, schema, writer)
+                .context("Could not write workbook")
+        }
+        FileOutput::Sql => sql::write_dump(tables, schema, writer).context("Could not write SQL"),
+    })
+    .context("Could not save output file")
+}
+
+impl ExtractOptions {
+    /// Creates options that extract every table to `file_type`, with default settings.
+    pub fn new(file_type: FileType) -> Self {
+        Self {
+            file_type,
+            format: FormatOptions::default(),
+            game: None,
+            hashes: HashNameTable::empty(),
+            no_schema: false,
+            tables: Vec::new(),
+            computed: Vec::new(),
+            row_filter: None,
+            unresolved_report: None,
+            resume: false,
+            max_memory: None,
+            file_names: FileNames::default(),
+            sanitize_names: SanitizeNames::default(),
+            layout: Layout::default(),
+            dry_run: false,
+            jobs: Jobs::default(),
+        }
+    }
+}
+
+impl PackOptions {
+    /// Creates options that pack tables from `file_type` files, with default settings.
+    pub fn new(file_type: FileType) -> Self {
+        Self {
+            file_type,
+            format: FormatOptions::default(),
+            game: None,
+            preserve_order: false,
+            scramble: false,
+            no_check: false,
+            strict: false,
+            dry_run: false,
+            jobs: Jobs::default(),
+        }
+    }
+}
+
+impl ConvertOptions {
+    /// Creates options that convert tables from `from` files to `to`, with default settings.
+    pub fn new(from: FileType, to: FileType) -> Self {
+        Self {
+            from,
+            to,
+            format: FormatOptions::default(),
+            dry_run: false,
+            jobs: Jobs::default(),
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            untyped: false,
+            names_with_hashes: false,
+            pretty: false,
+            hash_format: HashFormat::Bracketed,
+            csv_separator: ',',
+            csv_lists: MultiValueEncoding::Json,
+            csv_flags: MultiValueEncoding::Columns,
+            csv_row_id: false,
+            csv_row_id_name: "$id".to_string(),
+        }
+    }
+}
+
+impl FromStr for FileType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "xlsx" => Ok(Self::Xlsx),
+            "sql" => Ok(Self::Sql),
+            t => Err(Error::UnknownFileType(t.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+            Self::Sql => "sql",
+        })
+    }
+}
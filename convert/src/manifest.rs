@@ -0,0 +1,133 @@
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+
+/// Records the BDAT files that were fully extracted into an output directory, so an
+/// interrupted extraction can be resumed with [`ExtractOptions::resume`].
+///
+/// Each line has the size of the file and its path, relative to the common directory of
+/// the extracted files, separated by a tab. Lines are added as files finish, so the
+/// manifest stays valid if the extraction is stopped at any point.
+///
+/// [`ExtractOptions::resume`]: crate::ExtractOptions::resume
+pub struct ExtractManifest {
+    done: HashSet<(u64, String)>,
+    /// `None` for a manifest that is only read, see [`ExtractManifest::read_only`]
+    file: Option<Mutex<File>>,
+}
+
+impl ExtractManifest {
+    pub const FILE_NAME: &'static str = ".extract-manifest";
+
+    /// Opens the manifest in `out_dir`. If `resume` is false, files that were extracted
+    /// before are forgotten.
+    pub fn open(out_dir: &Path, resume: bool) -> Result<Self> {
+        let mut manifest = Self::read_only(out_dir, resume)?;
+        let path = out_dir.join(Self::FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(resume)
+            .write(true)
+            .truncate(!resume)
+            .open(&path)
+            .context("Could not open extraction manifest")?;
+        manifest.file = Some(Mutex::new(file));
+        Ok(manifest)
+    }
+
+    /// Reads the manifest in `out_dir` without creating or changing it, for a dry run.
+    /// Files added to it are not recorded.
+    pub fn read_only(out_dir: &Path, resume: bool) -> Result<Self> {
+        let path = out_dir.join(Self::FILE_NAME);
+        let mut done = HashSet::new();
+        if resume && path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines() {
+                // A line cut short by a crash doesn't parse, so the file is extracted again
+                if let Some((size, path)) = line?.split_once('\t') {
+                    if let Ok(size) = size.parse() {
+                        done.insert((size, path.to_string()));
+                    }
+                }
+            }
+        }
+        Ok(Self { done, file: None })
+    }
+
+    /// Returns whether the file was already extracted. Files whose size changed since are
+    /// extracted again.
+    pub fn is_done(&self, path: &Path, size: u64) -> bool {
+        self.done.contains(&(size, manifest_path(path)))
+    }
+
+    /// Records that a file was fully extracted.
+    pub fn add(&self, path: &Path, size: u64) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let mut file = file.lock().unwrap();
+        writeln!(file, "{size}\t{}", manifest_path(path))
+            .context("Could not update extraction manifest")
+    }
+}
+
+/// Formats a relative path the same way on every platform.
+fn manifest_path(path: &Path) -> String {
+    path.iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume() {
+        let dir = std::env::temp_dir().join(format!("bdat-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let manifest = ExtractManifest::open(&dir, false).unwrap();
+        manifest.add(Path::new("a/fld.bdat"), 10).unwrap();
+        drop(manifest);
+
+        let manifest = ExtractManifest::open(&dir, true).unwrap();
+        assert!(manifest.is_done(Path::new("a/fld.bdat"), 10));
+        assert!(!manifest.is_done(Path::new("a/fld.bdat"), 11));
+        assert!(!manifest.is_done(Path::new("a/btl.bdat"), 10));
+        drop(manifest);
+
+        // Without resume, the manifest starts over
+        let manifest = ExtractManifest::open(&dir, false).unwrap();
+        drop(manifest);
+        let manifest = ExtractManifest::open(&dir, true).unwrap();
+        assert!(!manifest.is_done(Path::new("a/fld.bdat"), 10));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_only() {
+        let dir = std::env::temp_dir().join(format!("bdat-manifest-ro-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let manifest = ExtractManifest::read_only(&dir, true).unwrap();
+        manifest.add(Path::new("fld.bdat"), 10).unwrap();
+        assert!(!dir.join(ExtractManifest::FILE_NAME).exists());
+
+        let manifest = ExtractManifest::open(&dir, false).unwrap();
+        manifest.add(Path::new("fld.bdat"), 10).unwrap();
+        drop(manifest);
+        let manifest = ExtractManifest::read_only(&dir, true).unwrap();
+        assert!(manifest.is_done(Path::new("fld.bdat"), 10));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::{Condvar, Mutex};
+
+/// Limits how many bytes of BDAT data are being converted at the same time, for
+/// [`ExtractOptions::max_memory`].
+///
+/// An estimate of the memory a file needs is reserved before the file is sent to the thread
+/// pool, waiting until enough of the budget is free. Pool threads never wait for the budget:
+/// one that waits for its rows to be encoded can start another file, on top of the one it
+/// holds a reservation for. A single reservation never waits for more than the
+/// whole budget, so a file that is larger than the budget is converted on its own.
+///
+/// [`ExtractOptions::max_memory`]: crate::ExtractOptions::max_memory
+pub struct MemoryBudget {
+    max: u64,
+    used: Mutex<u64>,
+    freed: Condvar,
+}
+
+/// Memory reserved from a [`MemoryBudget`], which is released when dropped.
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    size: u64,
+}
+
+impl MemoryBudget {
+    pub fn new(max: u64) -> Self {
+        Self {
+            max,
+            used: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Waits until `size` bytes are free, then reserves them.
+    pub fn reserve(&self, size: u64) -> Reservation<'_> {
+        let size = size.min(self.max);
+        let mut used = self
+            .freed
+            .wait_while(self.used.lock().unwrap(), |used| *used + size > self.max)
+            .unwrap();
+        *used += size;
+        Reservation { budget: self, size }
+    }
+}
+
+impl<'a> Drop for Reservation<'a> {
+    fn drop(&mut self) {
+        *self.budget.used.lock().unwrap() -= self.size;
+        self.budget.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve() {
+        let budget = MemoryBudget::new(100);
+        let a = budget.reserve(60);
+        // Larger than the budget, capped to the whole budget once it is free
+        std::thread::scope(|s| {
+            let waiting = s.spawn(|| {
+                let _big = budget.reserve(500);
+                assert_eq!(100, *budget.used.lock().unwrap());
+            });
+            drop(a);
+            waiting.join().unwrap();
+        });
+        assert_eq!(0, *budget.used.lock().unwrap());
+    }
+
+    #[test]
+    fn budget_smaller_than_two_files() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, SwitchEndian, TableBuilder};
+        use bdat::{Value, ValueType};
+
+        use crate::parallel::{Jobs, ROW_CHUNK};
+        use crate::{ExtractOptions, FileType};
+
+        let dir = std::env::temp_dir().join(format!("bdat-memory-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Rows are encoded in parallel, in the same pool as files. Threads that wait for their
+        // chunks can start other files, which used to wait for the budget on top of a
+        // reservation.
+        let table = (1..=ROW_CHUNK * 4)
+            .fold(
+                TableBuilder::with_name(Label::from("Big"))
+                    .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::from("Lv"))),
+                |builder, id| {
+                    let cell = Cell::Single(Value::UnsignedInt(id as u32));
+                    builder.add_row(Row::new(id, vec![cell]))
+                },
+            )
+            .build_legacy();
+        let bytes =
+            bdat::legacy::to_vec::<SwitchEndian>([&table], BdatVersion::LegacySwitch).unwrap();
+        let files = (0..64)
+            .map(|i| {
+                let path = dir.join(format!("file{i}.bdat"));
+                std::fs::write(&path, &bytes).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+
+        let mut opts = ExtractOptions::new(FileType::Csv);
+        opts.max_memory = Some(bytes.len() as u64 * 3 / 2);
+        opts.jobs = Jobs {
+            files: Some(8),
+            tables: None,
+        };
+        let (sender, receiver) = mpsc::channel();
+        let out_dir = dir.join("out");
+        std::thread::spawn(move || {
+            let res = crate::extract(&files, &out_dir, &opts, &());
+            sender.send(res.map(|summary| summary.files)).unwrap();
+        });
+        let done = receiver
+            .recv_timeout(Duration::from_secs(60))
+            .expect("extraction did not finish");
+        assert_eq!(64, done.unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
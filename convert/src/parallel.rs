@@ -0,0 +1,188 @@
+use anyhow::{Context, Result};
+use rayon::{prelude::*, Scope, ThreadPool, ThreadPoolBuilder};
+
+/// How many threads convert files and tables at the same time.
+///
+/// By default, files and the tables in them share the current rayon thread pool, which
+/// suits most inputs. A few large files convert faster with more threads for tables, and
+/// many small files with more threads for files and fewer switches between them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Jobs {
+    /// The number of threads that read and write whole files. Threads that wait for
+    /// their tables start on other files meanwhile.
+    pub files: Option<usize>,
+    /// The number of threads that parse and convert tables, shared by every file. With 1,
+    /// each file's tables are converted one at a time on the thread of the file instead.
+    pub tables: Option<usize>,
+}
+
+/// Tables with more rows than this are encoded in chunks of this many rows, in parallel.
+pub(crate) const ROW_CHUNK: usize = 4096;
+
+/// Runs `f` on consecutive chunks of [`ROW_CHUNK`] rows in parallel, returning the results in
+/// row order. `f` also gets the index of the chunk. Tables that fit in one chunk are encoded
+/// on the current thread.
+pub(crate) fn map_row_chunks<T: Sync, R: Send>(
+    rows: &[T],
+    f: impl Fn(usize, &[T]) -> Result<R> + Sync,
+) -> Result<Vec<R>> {
+    if rows.len() <= ROW_CHUNK {
+        return Ok(vec![f(0, rows)?]);
+    }
+    rows.par_chunks(ROW_CHUNK)
+        .enumerate()
+        .map(|(i, chunk)| f(i, chunk))
+        .collect()
+}
+
+/// The thread pools for [`Jobs`].
+pub(crate) struct Pools {
+    files: Option<ThreadPool>,
+    tables: Option<ThreadPool>,
+    sequential_tables: bool,
+}
+
+impl Pools {
+    pub fn new(jobs: Jobs) -> Result<Self> {
+        let pool = |name: &'static str, threads: usize| {
+            ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(move |i| format!("bdat-{name}-{i}"))
+                .build()
+                .with_context(|| format!("Could not build {name} thread pool"))
+        };
+        Ok(Self {
+            files: jobs.files.map(|n| pool("files", n)).transpose()?,
+            tables: jobs
+                .tables
+                .filter(|&n| n > 1)
+                .map(|n| pool("tables", n))
+                .transpose()?,
+            sequential_tables: jobs.tables == Some(1),
+        })
+    }
+
+    /// Runs `op`, which converts files in parallel, in the file thread pool.
+    pub fn files<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.files {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Runs `op` on the current thread, with a scope that spawns jobs in the file thread pool.
+    /// Unlike [`Pools::files`], `op` can block without taking a thread from the pool.
+    pub fn files_scope<'scope, R>(&self, op: impl FnOnce(&Scope<'scope>) -> R) -> R {
+        match &self.files {
+            Some(pool) => pool.in_place_scope(op),
+            None => rayon::in_place_scope(op),
+        }
+    }
+
+    /// Runs `op`, which converts the tables of a file in parallel, in the table thread pool.
+    /// Parallel iterators over tables should use [`Pools::min_tables`] as their minimum
+    /// length, so tables are converted one at a time if there's only one table thread.
+    pub fn tables<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.tables {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Returns the minimum number of tables converted by each job. Iterators that can't
+    /// be split further run on the current thread.
+    pub fn min_tables(&self) -> usize {
+        match self.sequential_tables {
+            true => usize::MAX,
+            false => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn sequential_tables() {
+        let pools = Pools::new(Jobs {
+            files: Some(2),
+            tables: Some(1),
+        })
+        .unwrap();
+        let threads = pools.files(|| {
+            let file_thread = std::thread::current().id();
+            pools.tables(|| {
+                (0..100)
+                    .into_par_iter()
+                    .with_min_len(pools.min_tables())
+                    .all(|_| std::thread::current().id() == file_thread)
+            })
+        });
+        assert!(threads);
+    }
+
+    #[test]
+    fn table_pool() {
+        let pools = Pools::new(Jobs {
+            files: None,
+            tables: Some(3),
+        })
+        .unwrap();
+        let names = pools.tables(|| {
+            (0..100)
+                .into_par_iter()
+                .with_min_len(pools.min_tables())
+                .map(|_| std::thread::current().name().unwrap_or("").to_string())
+                .collect::<Vec<_>>()
+        });
+        assert!(names.iter().all(|n| n.starts_with("bdat-tables-")));
+    }
+
+    #[test]
+    fn chunked_rows() {
+        use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType};
+
+        use crate::{
+            csv::CsvConverter, json::JsonConverter, schema::FileSchema, BdatSerialize,
+            FormatOptions,
+        };
+
+        let table = (1..=ROW_CHUNK * 2 + 10)
+            .fold(
+                TableBuilder::with_name(Label::from("Big"))
+                    .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::from("Lv"))),
+                |builder, id| {
+                    let cell = Cell::Single(Value::UnsignedInt(id as u32));
+                    builder.add_row(Row::new(id, vec![cell]))
+                },
+            )
+            .build(BdatVersion::LegacySwitch);
+        let schema = FileSchema::new("big".to_string(), BdatVersion::LegacySwitch);
+        let opts = FormatOptions::default();
+        let write = |converter: &dyn BdatSerialize| {
+            let mut out = Vec::new();
+            converter
+                .write_table(&table, table.rows().collect(), &schema, &mut out)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let csv = write(&CsvConverter::new(&opts));
+        assert_eq!(ROW_CHUNK * 2 + 11, csv.lines().count());
+        assert!(csv
+            .lines()
+            .skip(1)
+            .enumerate()
+            .all(|(i, l)| l == (i + 1).to_string()));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&write(&JsonConverter::new(&opts))).unwrap();
+        let mut expected = Vec::new();
+        bdat::fmt::to_json(&table, &mut expected, &bdat::fmt::JsonOptions::new()).unwrap();
+        let expected: serde_json::Value = serde_json::from_slice(&expected).unwrap();
+        assert_eq!(expected["rows"], json["rows"]);
+    }
+}
@@ -0,0 +1,521 @@
+//! Patches, which record what changed between two versions of a BDAT file's tables, so the
+//! changes can be distributed and applied without the whole file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use anyhow::{Context, Result};
+use bdat::fmt::json::{column_keys, JsonTable, TableRow};
+use bdat::fmt::JsonOptions;
+use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, Table, TableAccessor, TableBuilder};
+use serde::de::DeserializeSeed;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{Error, FormatError},
+    hash::HashNameTable,
+    schema::FileSchema,
+    FormatOptions, JsonConverter,
+};
+
+/// The version of the patch format. Increase when making breaking changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The changes between two versions of the tables in a BDAT file.
+///
+/// Only changed cells are recorded for tables that keep their columns. Tables that were
+/// added, or whose columns or base ID changed, are recorded whole, in the same format as
+/// extracted JSON tables.
+///
+/// Tables and columns are matched by name. Hashed names can be written as plain names or as
+/// hashes. If a file has several tables with the same name, only the first one is patched.
+///
+/// Mods can reserve ranges of row IDs in `reserved`, so patches from different mods can be
+/// checked with [`Patch::conflicts`] before they are applied together.
+#[derive(Serialize, Deserialize)]
+pub struct Patch {
+    pub format_version: u32,
+    /// Row IDs reserved by the mod, keyed by table name. Rows the patch adds to a table
+    /// with reserved ranges must be inside one of them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub reserved: BTreeMap<String, Vec<IdRange>>,
+    /// Tables with changed, added or removed rows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<TablePatch>,
+    /// Tables that were added, or replaced because their columns or base ID changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_tables: Vec<JsonTable>,
+    /// Names of the tables that were removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_tables: Vec<String>,
+}
+
+/// An inclusive range of row IDs, written as `[first, last]`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdRange(pub usize, pub usize);
+
+/// A problem found by [`Patch::conflicts`]. Patches are identified by the names they were
+/// given.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// A patch adds a row outside the ranges it reserved in the table.
+    OutsideReserved {
+        patch: String,
+        table: String,
+        row: usize,
+    },
+    /// Two patches reserved overlapping ranges in the same table.
+    OverlappingRanges {
+        patches: (String, String),
+        table: String,
+        ranges: (IdRange, IdRange),
+    },
+    /// Two patches change or add the same row.
+    SameRow {
+        patches: (String, String),
+        table: String,
+        row: usize,
+    },
+}
+
+/// The changes to the rows of a table, see [`Patch`].
+#[derive(Serialize, Deserialize)]
+pub struct TablePatch {
+    pub name: String,
+    /// Changed cells, keyed by row ID, then by column name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rows: BTreeMap<usize, Map<String, Value>>,
+    /// Rows added at the end of the table, in the same format as rows of extracted JSON
+    /// tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_rows: Vec<TableRow>,
+    /// How many rows were removed from the end of the table.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub removed_rows: usize,
+}
+
+impl Patch {
+    /// Records the changes from the `old` tables to the `new` tables. Names are resolved
+    /// with `hashes` where possible, to keep patches readable.
+    pub fn from_tables(
+        old: &[Table],
+        new: &[Table],
+        version: BdatVersion,
+        hashes: &HashNameTable,
+    ) -> Result<Self> {
+        let json_opts = JsonOptions::new().version(version);
+        let mut patch = Self {
+            format_version: FORMAT_VERSION,
+            reserved: BTreeMap::new(),
+            tables: Vec::new(),
+            new_tables: Vec::new(),
+            removed_tables: Vec::new(),
+        };
+        for table in new {
+            let old_table = find_table(old, table.name(), version)
+                .map(|i| &old[i])
+                .filter(|old| {
+                    old.base_id() == table.base_id() && old.columns().eq(table.columns())
+                });
+            let mut table = table.clone();
+            hashes.convert_all(&mut table);
+            match old_table {
+                Some(old) => {
+                    let changes = TablePatch::from_tables(old, &table, &json_opts)?;
+                    if !changes.is_empty() {
+                        patch.tables.push(changes);
+                    }
+                }
+                None => patch
+                    .new_tables
+                    .push(JsonTable::from_table(&table, &json_opts)),
+            }
+        }
+        patch.removed_tables = old
+            .iter()
+            .filter(|t| find_table(new, t.name(), version).is_none())
+            .map(|t| hashes.convert_label_cow(t.name()).to_string())
+            .collect();
+        Ok(patch)
+    }
+
+    /// Returns whether the patch doesn't change anything.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.new_tables.is_empty() && self.removed_tables.is_empty()
+    }
+
+    /// Checks patches that are meant to be applied together, e.g. from different mods. Each
+    /// patch comes with a name, used in the returned conflicts.
+    ///
+    /// Rows that a patch adds (including the rows of new tables) must be inside the ranges
+    /// it reserved for the table, if it reserved any. No two patches may change or add the
+    /// same row, or reserve overlapping ranges.
+    pub fn conflicts(patches: &[(&str, &Patch)], version: BdatVersion) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        let mut ranges: Vec<(Label, IdRange, &str)> = Vec::new();
+        let mut owners: HashMap<(Label, usize), &str> = HashMap::new();
+        for &(name, patch) in patches {
+            for (table, reserved) in &patch.reserved {
+                let label = Label::parse_for_version(table, version);
+                for range in reserved {
+                    let overlap = ranges
+                        .iter()
+                        .find(|(l, r, _)| *l == label && r.overlaps(range));
+                    if let Some((_, other, owner)) = overlap {
+                        conflicts.push(Conflict::OverlappingRanges {
+                            patches: (owner.to_string(), name.to_string()),
+                            table: table.clone(),
+                            ranges: (*other, *range),
+                        });
+                    }
+                }
+                ranges.extend(reserved.iter().map(|r| (label.clone(), *r, name)));
+            }
+
+            for (table, changed, added) in patch.touched_rows() {
+                let label = Label::parse_for_version(table, version);
+                let reserved = patch
+                    .reserved
+                    .iter()
+                    .find(|(t, _)| Label::parse_for_version(t, version) == label)
+                    .map(|(_, r)| r);
+                for &row in &added {
+                    if reserved.is_some_and(|r| !r.iter().any(|r| r.contains(row))) {
+                        conflicts.push(Conflict::OutsideReserved {
+                            patch: name.to_string(),
+                            table: table.to_string(),
+                            row,
+                        });
+                    }
+                }
+                for row in changed.into_iter().chain(added) {
+                    match owners.get(&(label.clone(), row)) {
+                        Some(owner) if *owner != name => conflicts.push(Conflict::SameRow {
+                            patches: (owner.to_string(), name.to_string()),
+                            table: table.to_string(),
+                            row,
+                        }),
+                        Some(_) => {}
+                        None => {
+                            owners.insert((label.clone(), row), name);
+                        }
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Returns the IDs of the rows the patch changes and adds in each table.
+    fn touched_rows(&self) -> Vec<(&str, Vec<usize>, Vec<usize>)> {
+        let changes = self.tables.iter().map(|t| {
+            let changed = t.rows.keys().copied().collect();
+            let added = t.added_rows.iter().map(|r| r.id).collect();
+            (t.name.as_str(), changed, added)
+        });
+        let new_tables = self.new_tables.iter().map(|t| {
+            let added = t.rows.iter().map(|r| r.id).collect();
+            (t.name.as_deref().unwrap_or_default(), Vec::new(), added)
+        });
+        changes.chain(new_tables).collect()
+    }
+
+    /// Applies the patch to the tables of a BDAT file. New tables are added at the end.
+    pub fn apply(self, tables: &mut Vec<Table>, version: BdatVersion) -> Result<()> {
+        for name in &self.removed_tables {
+            let index = find_table(tables, &Label::parse_for_version(name, version), version)
+                .ok_or_else(|| Error::TableNotFound(name.clone()))?;
+            tables.remove(index);
+        }
+        for changes in self.tables {
+            let index = find_table(
+                tables,
+                &Label::parse_for_version(&changes.name, version),
+                version,
+            )
+            .ok_or_else(|| Error::TableNotFound(changes.name.clone()))?;
+            let table = tables.remove(index);
+            tables.insert(index, changes.apply(table, version)?);
+        }
+        let converter = JsonConverter::new(&FormatOptions::default());
+        for table in self.new_tables {
+            let name = table.name.as_deref().unwrap_or_default();
+            let label = Label::parse_for_version(name, version);
+            let schema = FileSchema::new(name.to_string(), version);
+            let table = converter.convert_table(label.clone(), &schema, table)?;
+            match find_table(tables, &label, version) {
+                Some(index) => tables[index] = table,
+                None => tables.push(table),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TablePatch {
+    fn from_tables(old: &Table, new: &Table, json_opts: &JsonOptions) -> Result<Self> {
+        let names = new
+            .columns()
+            .map(|c| c.label().to_string())
+            .collect::<Vec<_>>();
+        let keys = column_keys(names.iter().map(String::as_str));
+        let columns = new.columns().collect::<Vec<_>>();
+
+        let mut rows = BTreeMap::new();
+        for (old_row, new_row) in old.rows().zip(new.rows()) {
+            let cells = old_row
+                .cells()
+                .zip(new_row.cells())
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(i, (_, new))| {
+                    let value = serde_json::to_value(columns[i].cell_serializer(new))?;
+                    Ok((keys[i].clone(), value))
+                })
+                .collect::<Result<Map<_, _>>>()?;
+            if !cells.is_empty() {
+                rows.insert(new_row.id(), cells);
+            }
+        }
+        let added_rows = new.rows().skip(old.row_count());
+        Ok(Self {
+            name: new.name().to_string(),
+            rows,
+            added_rows: JsonTable::from_rows(new, added_rows, json_opts).rows,
+            removed_rows: old.row_count().saturating_sub(new.row_count()),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.added_rows.is_empty() && self.removed_rows == 0
+    }
+
+    fn apply<'b>(self, table: Table<'b>, version: BdatVersion) -> Result<Table<'b>> {
+        let name = table.name().clone();
+        let base_id = table.base_id();
+        let columns = table.columns().cloned().collect::<Vec<_>>();
+        let mut rows = table.into_rows().collect::<Vec<_>>();
+
+        for (id, cells) in self.rows {
+            let row = id
+                .checked_sub(base_id)
+                .and_then(|i| rows.get_mut(i))
+                .ok_or_else(|| FormatError::MissingRow(id).with_context(name.clone()))?;
+            let mut new_cells = row.cells().cloned().collect::<Vec<_>>();
+            for (key, value) in cells {
+                let (index, cell) = read_cell(&columns, id, &key, value, version, &name)?;
+                new_cells[index] = cell;
+            }
+            *row = Row::new(id, new_cells);
+        }
+
+        let kept = rows.len().checked_sub(self.removed_rows).ok_or_else(|| {
+            FormatError::MissingRow(base_id + rows.len()).with_context(name.clone())
+        })?;
+        rows.truncate(kept);
+        for added in self.added_rows {
+            let expected = base_id + rows.len();
+            if added.id != expected {
+                return Err(FormatError::NonConsecutiveRow(added.id, expected)
+                    .with_context(name)
+                    .into());
+            }
+            let mut cells: Vec<Option<Cell>> = vec![None; columns.len()];
+            for (key, value) in added.cells {
+                let (index, cell) = read_cell(&columns, added.id, &key, value, version, &name)?;
+                cells[index] = Some(cell);
+            }
+            let cells = cells
+                .into_iter()
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| FormatError::IncompleteRow(added.id).with_context(name.clone()))?;
+            rows.push(Row::new(added.id, cells));
+        }
+
+        Ok(TableBuilder::with_name(name)
+            .set_base_id(base_id)
+            .set_columns(columns)
+            .set_rows(rows)
+            .try_build(version)?)
+    }
+}
+
+impl IdRange {
+    pub fn contains(&self, id: usize) -> bool {
+        (self.0..=self.1).contains(&id)
+    }
+
+    pub fn overlaps(&self, other: &IdRange) -> bool {
+        self.0 <= other.1 && other.0 <= self.1
+    }
+}
+
+impl Display for IdRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.0, self.1)
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutsideReserved { patch, table, row } => write!(
+                f,
+                "{patch} adds row {row} to {table}, outside its reserved IDs"
+            ),
+            Self::OverlappingRanges {
+                patches: (a, b),
+                table,
+                ranges: (ra, rb),
+            } => write!(
+                f,
+                "{a} ({ra}) and {b} ({rb}) reserved the same IDs in {table}"
+            ),
+            Self::SameRow {
+                patches: (a, b),
+                table,
+                row,
+            } => write!(f, "{a} and {b} both change row {row} of {table}"),
+        }
+    }
+}
+
+fn find_table(tables: &[Table], name: &Label, version: BdatVersion) -> Option<usize> {
+    let name = name.clone().into_hash(version);
+    tables
+        .iter()
+        .position(|t| t.name().clone().into_hash(version) == name)
+}
+
+/// Finds a column by its key in a row. Keys of duplicate column names have an index suffix,
+/// see [`column_keys`].
+fn find_column(columns: &[ColumnDef], key: &str, version: BdatVersion) -> Option<usize> {
+    let (name, nth) = match key.rsplit_once('#').map(|(n, i)| (n, i.parse::<usize>())) {
+        Some((name, Ok(nth))) if nth >= 2 => (name, nth),
+        _ => (key, 1),
+    };
+    let label = Label::parse_for_version(name, version);
+    columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.label().clone().into_hash(version) == label)
+        .nth(nth - 1)
+        .map(|(i, _)| i)
+}
+
+/// Reads the value of a cell in row `id`, returning the index of its column.
+fn read_cell(
+    columns: &[ColumnDef],
+    id: usize,
+    key: &str,
+    value: Value,
+    version: BdatVersion,
+    table: &Label,
+) -> Result<(usize, Cell<'static>)> {
+    let index = find_column(columns, key, version).ok_or_else(|| {
+        FormatError::UnknownColumn(Box::new((id, key.to_string()))).with_context(table.clone())
+    })?;
+    let cell = columns[index]
+        .as_cell_seed()
+        .deserialize(value)
+        .with_context(|| format!("Invalid value in row {id}, column {key}"))?;
+    Ok((index, cell))
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{hash::murmur3_str, Value, ValueType};
+
+    use super::*;
+
+    fn table(name: &str, rows: &[(u32, u32)]) -> Table<'static> {
+        let rows = rows
+            .iter()
+            .enumerate()
+            .map(|(i, &(lv, hp))| {
+                let cells = [Value::UnsignedInt(lv), Value::UnsignedInt(hp)];
+                Row::new(i + 1, cells.map(Cell::Single).to_vec())
+            })
+            .collect();
+        TableBuilder::with_name(Label::Hash(murmur3_str(name)))
+            .add_column(ColumnDef::new(
+                ValueType::UnsignedInt,
+                Label::Hash(murmur3_str("Lv")),
+            ))
+            .add_column(ColumnDef::new(
+                ValueType::UnsignedInt,
+                Label::Hash(0xCAFEBABE),
+            ))
+            .set_base_id(1)
+            .set_rows(rows)
+            .build(BdatVersion::Modern)
+    }
+
+    #[test]
+    fn round_trip() {
+        let version = BdatVersion::Modern;
+        let hashes =
+            HashNameTable::load_from_names("Enemy\nShop\nLv".as_bytes(), false, &mut |_, e| {
+                panic!("{e}")
+            })
+            .unwrap();
+        let old = [
+            table("Enemy", &[(1, 10), (2, 20), (3, 30)]),
+            table("Shop", &[(1, 1)]),
+            table("Gone", &[]),
+        ];
+        let new = [
+            table("Enemy", &[(1, 10), (5, 20)]),
+            table("Shop", &[(1, 1), (2, 2)]),
+            table("Added", &[(7, 7)]),
+        ];
+
+        let patch = Patch::from_tables(&old, &new, version, &hashes).unwrap();
+        let json = serde_json::to_string(&patch).unwrap();
+        assert!(json.contains(r#""rows":{"2":{"Lv":5}},"removed_rows":1"#));
+        assert!(json.contains(r#""removed_tables":["<"#));
+
+        let patch: Patch = serde_json::from_str(&json).unwrap();
+        let mut tables = old.to_vec();
+        patch.apply(&mut tables, version).unwrap();
+        assert_eq!(3, tables.len());
+        assert!(tables[..2] == new[..2]);
+        assert_eq!(new[2].name(), &tables[2].name().clone().into_hash(version));
+        let rows = |t: &Table<'static>| t.rows().map(|r| Row::clone(&r)).collect::<Vec<_>>();
+        assert_eq!(rows(&new[2]), rows(&tables[2]));
+    }
+
+    #[test]
+    fn reserved_conflicts() {
+        let a: Patch = serde_json::from_str(
+            r#"{"format_version":1,"reserved":{"Enemy":[[100,199]]},"tables":[
+                {"name":"Enemy","rows":{"2":{"Lv":5}},"added_rows":[{"$id":100},{"$id":200}]}
+            ]}"#,
+        )
+        .unwrap();
+        // Tables can be reserved by hash as well
+        let hash = Label::Hash(murmur3_str("Enemy"));
+        let b: Patch = serde_json::from_str(&format!(
+            r#"{{"format_version":1,"reserved":{{"{hash}":[[150,299]]}},"tables":[
+                {{"name":"Enemy","rows":{{"2":{{"Lv":6}}}}}}
+            ]}}"#
+        ))
+        .unwrap();
+        let conflicts = Patch::conflicts(&[("a", &a), ("b", &b)], BdatVersion::Modern);
+        let messages = conflicts.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            [
+                "a adds row 200 to Enemy, outside its reserved IDs",
+                &format!("a (100-199) and b (150-299) reserved the same IDs in {hash}"),
+                "a and b both change row 2 of Enemy",
+            ],
+            messages.as_slice()
+        );
+        assert!(Patch::conflicts(&[("a", &a)], BdatVersion::Modern).len() == 1);
+    }
+}
@@ -0,0 +1,109 @@
+//! References to rows of other tables, as declared in file schemas.
+
+use std::collections::BTreeMap;
+
+use bdat::{Cell, Label, RowRef, Table, TableAccessor, Value};
+use serde::{Deserialize, Serialize};
+
+/// Reference annotations for a table, keyed by column name.
+pub type TableRefs = BTreeMap<String, ColumnRef>;
+
+/// Declares that a column's values are IDs of rows in another table.
+///
+/// IDs can either be plain row IDs, or hashed IDs (for modern BDATs).
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ColumnRef {
+    /// The name of the referenced table
+    pub table: String,
+    /// A column in the referenced table that gives a readable name to its rows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+/// The result of following a reference.
+pub enum RefTarget<'t, 'b> {
+    /// The cell does not reference anything (ID 0)
+    Null,
+    Row(RowRef<'t, 'b>),
+    /// The referenced row does not exist, or the cell can't hold a reference
+    Missing,
+}
+
+impl ColumnRef {
+    /// Finds the row referenced by a cell.
+    pub fn resolve<'t, 'b>(target: &'t Table<'b>, cell: &Cell) -> RefTarget<'t, 'b> {
+        let Some(value) = cell.as_single() else {
+            return RefTarget::Missing;
+        };
+        let row = match value {
+            Value::HashRef(0) => return RefTarget::Null,
+            Value::HashRef(hash) if target.is_modern() => target
+                .as_modern()
+                .get_row_by_hash(*hash)
+                .and_then(|row| target.get_row(row.id())),
+            Value::String(_)
+            | Value::DebugString(_)
+            | Value::Float(_)
+            | Value::Unknown
+            | Value::Raw(_) => return RefTarget::Missing,
+            v if v.to_integer() == 0 => return RefTarget::Null,
+            v => target.get_row(v.to_integer() as usize),
+        };
+        row.map_or(RefTarget::Missing, RefTarget::Row)
+    }
+
+    /// Formats a referenced row, using the display column if there is one.
+    pub fn describe(&self, row: &RowRef, is_label: impl Fn(&str, &Label) -> bool) -> String {
+        let display = self
+            .display
+            .as_ref()
+            .and_then(|name| row.cell_refs().find(|c| is_label(name, c.column().label())));
+        match display {
+            Some(cell) => cell.to_string(),
+            None => match row.id_hash() {
+                Some(hash) => format!("{}#{}", self.table, Label::Hash(hash)),
+                None => format!("{}#{}", self.table, row.id()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, ColumnDef, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn resolve_refs() {
+        let target = TableBuilder::with_name(Label::String("Item".into()))
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_row(Row::new(
+                1,
+                vec![Cell::Single(Value::String("Sword".into()))],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let col_ref = ColumnRef {
+            table: "Item".to_string(),
+            display: Some("Name".to_string()),
+        };
+
+        let cell = Cell::Single(Value::UnsignedShort(1));
+        match ColumnRef::resolve(&target, &cell) {
+            RefTarget::Row(row) => {
+                assert_eq!("Sword", col_ref.describe(&row, |n, l| *l == Label::from(n)))
+            }
+            _ => panic!("expected row"),
+        }
+        let null = Cell::Single(Value::UnsignedShort(0));
+        assert!(matches!(
+            ColumnRef::resolve(&target, &null),
+            RefTarget::Null
+        ));
+        let missing = Cell::Single(Value::UnsignedShort(2));
+        assert!(matches!(
+            ColumnRef::resolve(&target, &missing),
+            RefTarget::Missing
+        ));
+    }
+}
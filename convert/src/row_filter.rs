@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use bdat::{Label, RowRef, Table};
+
+use crate::expr::{Expr, ExprError, Scalar, Scope};
+
+/// A condition that rows must match to be extracted.
+///
+/// Syntax: an expression such as `Lv >= 90 && Name != ""`. Row IDs can be used
+/// with `$id`.
+#[derive(Clone, Debug)]
+pub struct RowFilter {
+    expr: Expr,
+}
+
+/// Resolves the columns of a filter expression in a single row.
+struct FilterScope<'a, 't, 'b> {
+    row: RowRef<'t, 'b>,
+    columns: &'a [(&'a Label, &'t Label)],
+}
+
+impl RowFilter {
+    /// Returns whether the table has every column the expression needs.
+    pub fn applies_to(&self, table: &Table) -> bool {
+        self.expr
+            .columns()
+            .all(|l| is_row_id(l) || find_column(table, l).is_some())
+    }
+
+    /// Returns the IDs of the rows that match the filter.
+    pub fn matching_rows(&self, table: &Table) -> anyhow::Result<Vec<usize>> {
+        let columns = self
+            .expr
+            .columns()
+            .filter_map(|l| Some((l, find_column(table, l)?)))
+            .collect::<Vec<_>>();
+        let mut ids = Vec::new();
+        for row in table.rows() {
+            let scope = FilterScope {
+                row,
+                columns: &columns,
+            };
+            let matches = self
+                .expr
+                .eval_in(&scope)
+                .with_context(|| format!("Could not filter row {}", row.id()))?;
+            if matches.is_true() {
+                ids.push(row.id());
+            }
+        }
+        Ok(ids)
+    }
+}
+
+impl Scope for FilterScope<'_, '_, '_> {
+    fn value(&self, table: Option<&str>, column: &Label) -> Result<Scalar, ExprError> {
+        if table.is_none() && is_row_id(column) {
+            return Ok(Scalar::Number(self.row.id() as f64));
+        }
+        table
+            .is_none()
+            .then(|| self.columns.iter().find(|(l, _)| *l == column))
+            .flatten()
+            .and_then(|(_, label)| self.row.get_if_present(*label))
+            .map(Scalar::from)
+            .ok_or_else(|| ExprError::MissingColumn(column.to_string()))
+    }
+}
+
+impl FromStr for RowFilter {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self { expr: s.parse()? })
+    }
+}
+
+/// Finds a column by name. Names match hashed columns that were resolved with the
+/// hash list, or unresolved ones written as `<hash>`.
+fn find_column<'t>(table: &'t Table, label: &Label) -> Option<&'t Label> {
+    table
+        .columns()
+        .map(|c| c.label())
+        .find(|l| *l == label || l.to_string() == label.to_string())
+}
+
+fn is_row_id(label: &Label) -> bool {
+    matches!(label, Label::String(s) if s == "$id")
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{Cell, ColumnDef, Row, TableBuilder, Value, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn filter_rows() {
+        let table: Table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+            .add_column(ColumnDef::new(
+                ValueType::UnsignedByte,
+                Label::Unhashed("Lv".to_string()),
+            ))
+            .add_column(ColumnDef::new(ValueType::HashRef, Label::Hash(0xDEADBEEF)))
+            .set_rows(
+                [(50, 1), (90, 0), (95, 2)]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (lv, hash))| {
+                        Row::new(
+                            i + 1,
+                            vec![
+                                Cell::Single(Value::UnsignedByte(lv)),
+                                Cell::Single(Value::HashRef(hash)),
+                            ],
+                        )
+                    })
+                    .collect(),
+            )
+            .build_modern()
+            .into();
+
+        let filter: RowFilter = "Lv >= 90 && <DEADBEEF> != '<00000000>'".parse().unwrap();
+        assert!(filter.applies_to(&table));
+        assert_eq!(vec![3], filter.matching_rows(&table).unwrap());
+
+        let filter: RowFilter = "$id < 3".parse().unwrap();
+        assert_eq!(vec![1, 2], filter.matching_rows(&table).unwrap());
+
+        let filter: RowFilter = "Missing > 1".parse().unwrap();
+        assert!(!filter.applies_to(&table));
+    }
+}
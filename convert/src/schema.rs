@@ -0,0 +1,841 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::error::{Error, FormatError, SchemaError};
+use crate::{FileNames, Layout, SanitizeNames};
+use anyhow::Context;
+use bdat::fmt::json::column_keys;
+use bdat::{BdatVersion, FlagDef, Label, Table, TableAccessor, Utf};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+use crate::enums::TableEnums;
+use crate::file_name;
+use crate::refs::{ColumnRef, RefTarget, TableRefs};
+
+/// Incremental format version, used to determine schema compatibility.
+const FORMAT_VERSION: usize = 2;
+
+/// Changes needed to bring a schema from version `i` to version `i + 1`, applied to
+/// the raw JSON by [`FileSchema::read_upgrade`].
+const MIGRATIONS: [fn(&mut Map<String, serde_json::Value>); FORMAT_VERSION] = [
+    // 0 -> 1: only the version was added
+    |_| {},
+    // 1 -> 2: table files can be in other places (`layout`, `sanitize_names` and
+    // `file_names`), and list and flag layouts were added for CSV tables. Without them,
+    // tables are where version 1 put them.
+    |_| {},
+];
+
+/// Defines the structure of a BDAT file, so it can
+/// be re-serialized properly.
+#[derive(Serialize, Deserialize)]
+pub struct FileSchema {
+    pub file_name: String,
+    pub version: BdatVersion,
+    #[serde(default)]
+    pub format_version: usize,
+    /// Where the table files are, relative to the schema.
+    #[serde(default, skip_serializing_if = "Layout::is_nested")]
+    pub layout: Layout,
+    /// What was done with table names that couldn't be used as file names. The changed
+    /// names are in `file_names`.
+    #[serde(default, skip_serializing_if = "SanitizeNames::is_default")]
+    pub sanitize_names: SanitizeNames,
+    tables: Vec<String>,
+    /// Names for the values of enum-like columns, keyed by table name, then column name.
+    ///
+    /// These are not generated by the toolset, but are kept when re-extracting to the
+    /// same directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    enums: BTreeMap<String, TableEnums>,
+    /// Columns that must not contain duplicate values, keyed by table name. Checked when
+    /// packing.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    unique: BTreeMap<String, Vec<String>>,
+    /// Columns that reference rows in other tables, keyed by table name, then column name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    references: BTreeMap<String, TableRefs>,
+    /// Columns that were added with `--computed` when extracting, keyed by table name.
+    /// These are skipped when packing.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    computed: BTreeMap<String, Vec<String>>,
+    /// Free-text descriptions of tables and their columns, keyed by table name. They are
+    /// copied to extracted JSON tables, and kept when re-extracting to the same directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    descriptions: BTreeMap<String, TableDescription>,
+    /// Names that hashed table and column names were resolved to when extracting, keyed
+    /// by hash (e.g. `<1A2B3C4D>`).
+    ///
+    /// Re-extracting to the same directory resolves these hashes even without a hash list,
+    /// and packing uses the recorded hashes for these names.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    names: BTreeMap<String, String>,
+    /// List sizes and flags of legacy columns, keyed by table name, then column key (see
+    /// [`column_keys`]). CSV tables only have the values, so these are needed to read list
+    /// and flag cells back.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    layouts: BTreeMap<String, BTreeMap<String, ColumnLayout>>,
+    /// Names of table files (without extension) that differ from the table name, keyed by
+    /// table name, e.g. because the name has characters that can't be in file names. If
+    /// several tables in the file have the same name, the key of the second one is
+    /// `Name~2`, and so on.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    file_names: BTreeMap<String, String>,
+    /// File names given to tables in this extraction, in lowercase, to avoid two tables
+    /// writing to the same file on case-insensitive file systems
+    #[serde(skip)]
+    used_file_names: HashSet<String>,
+    /// How many tables with each name were given a file name in this extraction
+    #[serde(skip)]
+    table_counts: HashMap<String, usize>,
+}
+
+/// Descriptions of a table and its columns, see [`FileSchema::table_description`].
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TableDescription {
+    /// The description of the table itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    /// Column descriptions, keyed by column name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub columns: BTreeMap<String, String>,
+}
+
+/// How the values of a list or flags column are laid out, see [`FileSchema::column_layouts`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ColumnLayout {
+    /// The number of values in a list, or 1 for flags
+    pub count: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<FlagDef>,
+}
+
+pub trait AsFileName {
+    fn as_file_name(&self) -> Utf<'_>;
+}
+
+impl FileSchema {
+    pub fn new(file_name: String, version: BdatVersion) -> Self {
+        Self {
+            file_name,
+            version,
+            format_version: FORMAT_VERSION,
+            layout: Layout::Nested,
+            sanitize_names: SanitizeNames::default(),
+            tables: Vec::new(),
+            enums: BTreeMap::new(),
+            unique: BTreeMap::new(),
+            references: BTreeMap::new(),
+            computed: BTreeMap::new(),
+            descriptions: BTreeMap::new(),
+            names: BTreeMap::new(),
+            layouts: BTreeMap::new(),
+            file_names: BTreeMap::new(),
+            used_file_names: HashSet::new(),
+            table_counts: HashMap::new(),
+        }
+    }
+
+    pub fn read(reader: impl Read) -> anyhow::Result<Self> {
+        let schema: FileSchema = serde_json::from_reader(reader)?;
+        if schema.format_version > FORMAT_VERSION {
+            return Err(Error::from(SchemaError::UnsupportedSchema(Box::new((
+                schema.file_name,
+                schema.format_version,
+                FORMAT_VERSION,
+            ))))
+            .into());
+        }
+        if schema.format_version != FORMAT_VERSION {
+            return Err(Error::from(SchemaError::OutdatedSchema(Box::new((
+                schema.file_name,
+                schema.format_version,
+                FORMAT_VERSION,
+            ))))
+            .into());
+        }
+        Ok(schema)
+    }
+
+    /// Reads a schema written by any earlier version of the toolset, migrating it to the
+    /// current format. Returns the schema, along with the version it was written in.
+    pub fn read_upgrade(reader: impl Read) -> anyhow::Result<(Self, usize)> {
+        let mut raw: Map<String, serde_json::Value> = serde_json::from_reader(reader)?;
+        // Schemas without a version predate versioning
+        let version = raw
+            .get("format_version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0) as usize;
+        if version > FORMAT_VERSION {
+            let file_name = raw.get("file_name").and_then(serde_json::Value::as_str);
+            return Err(Error::from(SchemaError::UnsupportedSchema(Box::new((
+                file_name.unwrap_or_default().to_string(),
+                version,
+                FORMAT_VERSION,
+            ))))
+            .into());
+        }
+        for migrate in &MIGRATIONS[version..] {
+            migrate(&mut raw);
+        }
+        raw.insert("format_version".to_string(), FORMAT_VERSION.into());
+        Ok((serde_json::from_value(raw.into())?, version))
+    }
+
+    /// Copies user-defined annotations (e.g. enum names) from the schema previously
+    /// written to `base_dir`, if there is one.
+    ///
+    /// Computed columns are kept as well, so tables that are not extracted again (e.g.
+    /// because of a table filter) can still be packed.
+    pub fn load_annotations(&mut self, base_dir: &Path) -> anyhow::Result<()> {
+        let path = self.path(base_dir);
+        if !path.is_file() {
+            return Ok(());
+        }
+        // Don't go through `read`, re-extracting is how outdated schemas get updated
+        let previous: FileSchema = serde_json::from_reader(BufReader::new(File::open(&path)?))
+            .with_context(|| format!("Could not read schema {}", path.display()))?;
+        self.enums = previous.enums;
+        self.unique = previous.unique;
+        self.references = previous.references;
+        self.names = previous.names;
+        self.computed = previous.computed;
+        self.descriptions = previous.descriptions;
+        self.file_names = previous.file_names;
+        Ok(())
+    }
+
+    /// Returns the enum annotations for a table, if there are any.
+    pub fn table_enums(&self, table: &Label) -> Option<&TableEnums> {
+        self.find_table(&self.enums, table)
+    }
+
+    /// Returns the names of the columns that must be unique in a table.
+    pub fn unique_columns(&self, table: &Label) -> &[String] {
+        self.find_table(&self.unique, table)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the reference annotations for a table, if there are any.
+    pub fn table_refs(&self, table: &Label) -> Option<&TableRefs> {
+        self.find_table(&self.references, table)
+    }
+
+    /// Returns the layouts of a table's list and flag columns, keyed by column key. Tables
+    /// without such columns have none.
+    pub fn column_layouts(&self, table: &Label) -> Option<&BTreeMap<String, ColumnLayout>> {
+        self.find_table(&self.layouts, table)
+    }
+
+    /// Returns the descriptions of a table and its columns, if there are any.
+    pub fn table_description(&self, table: &Label) -> Option<&TableDescription> {
+        self.find_table(&self.descriptions, table)
+    }
+
+    /// Returns the description of a column in a table, if there is one.
+    pub fn column_description(&self, table: &Label, column: &Label) -> Option<&str> {
+        self.table_description(table)?
+            .columns
+            .iter()
+            .find(|(name, _)| self.is_label(name, column))
+            .map(|(_, description)| description.as_str())
+    }
+
+    /// Checks that every annotated reference points to an existing row. Both the
+    /// referencing and the referenced table must be in `tables`.
+    pub fn check_references(&self, tables: &[Table]) -> Result<(), Error> {
+        let find = |name: &str| tables.iter().find(|t| self.is_label(name, t.name()));
+        for (table_name, refs) in &self.references {
+            let Some(table) = find(table_name) else {
+                continue;
+            };
+            for (column, col_ref) in refs {
+                let Some(label) = table
+                    .columns()
+                    .find(|c| self.is_label(column, c.label()))
+                    .map(|c| c.label())
+                else {
+                    continue;
+                };
+                let target = find(&col_ref.table).ok_or_else(|| {
+                    FormatError::MissingRefTable(col_ref.table.clone())
+                        .with_context(table.name().clone())
+                })?;
+                for row in table.rows() {
+                    let Some(cell) = row.cell(label) else {
+                        continue;
+                    };
+                    if let RefTarget::Missing = ColumnRef::resolve(target, cell) {
+                        return Err(FormatError::BrokenReference(Box::new((
+                            row.id(),
+                            label.clone().into(),
+                            cell.to_string(),
+                            col_ref.table.clone(),
+                        )))
+                        .with_context(table.name().clone()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the computed columns that were added to a table, replacing the ones
+    /// from a previous extraction.
+    pub fn add_computed(&mut self, table: &Label, columns: Vec<String>) {
+        let version = self.version;
+        self.computed
+            .retain(|name, _| !is_label(name, table, version));
+        if !columns.is_empty() {
+            self.computed.insert(table.to_string(), columns);
+        }
+    }
+
+    /// Returns the names of the computed columns in a table.
+    pub fn computed_columns(&self, table: &Label) -> &[String] {
+        self.find_table(&self.computed, table)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Registers a table in the file schema, recording the names its hashed labels were
+    /// resolved to, and the layouts of its list and flag columns.
+    pub fn feed_table(&mut self, table: &Table) {
+        self.tables.push(table.name().to_string());
+        let names = table
+            .columns()
+            .map(|c| c.label().to_string())
+            .collect::<Vec<_>>();
+        let layouts = column_keys(names.iter().map(String::as_str))
+            .into_iter()
+            .zip(table.columns())
+            .filter(|(_, c)| c.count() > 1 || !c.flags().is_empty())
+            .map(|(key, c)| {
+                let layout = ColumnLayout {
+                    count: c.count(),
+                    flags: c.flags().to_vec(),
+                };
+                (key, layout)
+            })
+            .collect::<BTreeMap<_, _>>();
+        if !layouts.is_empty() {
+            self.layouts.insert(table.name().to_string(), layouts);
+        }
+        for label in std::iter::once(table.name()).chain(table.columns().map(|c| c.label())) {
+            if let Label::Unhashed(name) = label {
+                let hash = Label::Hash(bdat::hash::murmur3_str(name));
+                self.names.insert(hash.to_string(), name.clone());
+            }
+        }
+    }
+
+    /// Resolves hashed table and column names with the names recorded by a previous
+    /// extraction.
+    pub fn resolve_labels(&self, table: &mut Table) {
+        if self.names.is_empty() {
+            return;
+        }
+        let resolve = |label: &Label| match label {
+            Label::Hash(_) => self
+                .names
+                .get(&label.to_string())
+                .map(|name| Label::Unhashed(name.clone())),
+            _ => None,
+        };
+        if let Some(name) = resolve(table.name()) {
+            table.set_name(name);
+        }
+        for col in table.columns_mut() {
+            if let Some(label) = resolve(col.label()) {
+                *col.label_mut() = label;
+            }
+        }
+    }
+
+    /// Chooses the name of a table's file, without extension, and records it if it is not
+    /// the table name. See [`FileNames`] and [`SanitizeNames`] for how names are escaped.
+    /// If another table in the file already has the name (ignoring case), a number is
+    /// added, e.g. `Name~2`.
+    ///
+    /// Hashed tables with an `alias` are named after it instead of their hash.
+    pub fn assign_file_name(
+        &mut self,
+        table: &Label,
+        alias: Option<&str>,
+        mode: FileNames,
+        policy: SanitizeNames,
+    ) -> Result<String, Error> {
+        let name = table.as_file_name();
+        let escaped = file_name::escape(alias.unwrap_or(&name), mode, policy)?;
+        self.sanitize_names = policy;
+        let mut file_name = escaped.to_string();
+        let mut n = 1;
+        while !self.used_file_names.insert(file_name.to_lowercase()) {
+            n += 1;
+            file_name = format!("{escaped}~{n}");
+        }
+        let key = table.to_string();
+        let count = self.table_counts.entry(key.clone()).or_default();
+        *count += 1;
+        let key = file_name_key(key, *count);
+        if file_name == name {
+            self.file_names.remove(&key);
+        } else {
+            self.file_names.insert(key, file_name.clone());
+        }
+        Ok(file_name)
+    }
+
+    /// Returns the hash a table or column name was resolved from when extracting, if the
+    /// name was recorded.
+    pub fn recorded_hash(&self, name: &str) -> Option<Label> {
+        self.names
+            .iter()
+            .find(|(_, n)| *n == name)
+            .and_then(|(hash, _)| hash.parse().ok())
+    }
+
+    /// Renames a table, moving its annotations to the new name. Both names can be written
+    /// hashed or unhashed, and every table with the old name is renamed.
+    ///
+    /// Table files are not renamed, so the new name is recorded in `file_names` to keep
+    /// pointing at them. In modern files, the hash of a plain new name is recorded as well,
+    /// so packing looks up the renamed table's hash from its name, as it does for extracted
+    /// names.
+    pub fn rename_table(&mut self, old: &str, new: &str) -> Result<(), Error> {
+        let version = self.version;
+        let old_label = Label::parse_for_version(old, version);
+        let new_label = Label::parse_for_version(new, version);
+        if self.tables.iter().any(|t| is_label(t, &new_label, version)) {
+            return Err(Error::TableExists(new.to_string()));
+        }
+        let renamed = (0..self.tables.len())
+            .filter(|&i| is_label(&self.tables[i], &old_label, version))
+            .collect::<Vec<_>>();
+        if renamed.is_empty() {
+            return Err(Error::TableNotFound(old.to_string()));
+        }
+
+        for (count, i) in renamed.into_iter().enumerate() {
+            let old_name = std::mem::replace(&mut self.tables[i], new.to_string());
+            let file_name = self
+                .file_names
+                .remove(&file_name_key(old_name.clone(), count + 1))
+                .unwrap_or_else(|| Label::parse(old_name, false).as_file_name().into_owned());
+            if file_name != Label::parse(new, false).as_file_name() {
+                self.file_names
+                    .insert(file_name_key(new.to_string(), count + 1), file_name);
+            }
+        }
+        rename_key(&mut self.enums, &old_label, new, version);
+        rename_key(&mut self.unique, &old_label, new, version);
+        rename_key(&mut self.references, &old_label, new, version);
+        rename_key(&mut self.computed, &old_label, new, version);
+        rename_key(&mut self.descriptions, &old_label, new, version);
+        rename_key(&mut self.layouts, &old_label, new, version);
+        if version.are_labels_hashed() && matches!(new.parse(), Ok(Label::String(_))) {
+            self.names.insert(new_label.to_string(), new.to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns the directory with the table files, given the directory with the schema.
+    pub fn tables_dir(&self, schema_dir: &Path) -> PathBuf {
+        match self.layout {
+            Layout::Nested => schema_dir.join(&self.file_name),
+            Layout::Flat => schema_dir.to_owned(),
+        }
+    }
+
+    /// Returns the name of a table file (without extension) in [`FileSchema::tables_dir`],
+    /// from the name chosen by [`FileSchema::assign_file_name`].
+    pub fn table_file_stem<'a>(&self, file_name: &'a str) -> Utf<'a> {
+        match self.layout {
+            Layout::Nested => Cow::Borrowed(file_name),
+            Layout::Flat => Cow::Owned(format!("{}_{file_name}", self.file_name)),
+        }
+    }
+
+    /// Attempts to find all deserialized table files, from the paths defined by the
+    /// file schema. `schema_dir` is the directory with the schema file.
+    pub fn find_table_files(&self, schema_dir: &Path, extension: &str) -> Vec<(Label, PathBuf)> {
+        let base_dir = self.tables_dir(schema_dir);
+        let mut files = Vec::with_capacity(self.tables.len());
+        let mut counts = HashMap::new();
+
+        for label in self
+            .tables
+            .iter()
+            .chain(std::iter::once(&self.file_name.clone()))
+        {
+            let parsed = Label::parse(label.clone(), false);
+            let count = counts.entry(label).or_insert(0);
+            *count += 1;
+            let file_name = match self.file_names.get(&file_name_key(label.clone(), *count)) {
+                Some(name) => Cow::Borrowed(name.as_str()),
+                None => parsed.as_file_name(),
+            };
+            let file_name = self.table_file_stem(&file_name);
+            let path = base_dir.join(format!("{file_name}.{extension}"));
+            if path.is_file() {
+                files.push((self.recorded_hash(label).unwrap_or(parsed), path));
+            }
+        }
+
+        files
+    }
+
+    /// Returns the names of the tables in the schema that don't have a table file, see
+    /// [`FileSchema::find_table_files`].
+    pub fn missing_table_files(&self, schema_dir: &Path, extension: &str) -> Vec<&str> {
+        let mut found = HashMap::new();
+        for (label, _) in self.find_table_files(schema_dir, extension) {
+            *found.entry(label).or_insert(0) += 1;
+        }
+        self.tables
+            .iter()
+            .filter(|name| {
+                let label = self
+                    .recorded_hash(name)
+                    .unwrap_or_else(|| Label::parse(name.to_string(), false));
+                match found.get_mut(&label) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        false
+                    }
+                    _ => true,
+                }
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Returns the number of tables defined in this file.
+    pub fn table_count(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Writes the file schema to a file.
+    pub fn write(&self, base_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.write_to(&self.path(base_dir.as_ref()))
+    }
+
+    /// Writes the file schema to the given path.
+    pub fn write_to(&self, path: &Path) -> anyhow::Result<()> {
+        crate::util::write_atomic(path, |writer| Ok(serde_json::to_writer(writer, self)?))
+    }
+
+    /// Finds a table's entry in an annotation map. Table names may be written either
+    /// hashed or unhashed.
+    fn find_table<'a, T>(&self, map: &'a BTreeMap<String, T>, table: &Label) -> Option<&'a T> {
+        map.iter()
+            .find(|(name, _)| self.is_label(name, table))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns whether a table or column name, as written in the schema, refers to
+    /// the given label.
+    pub fn is_label(&self, name: &str, label: &Label) -> bool {
+        is_label(name, label, self.version)
+    }
+
+    pub(crate) fn path(&self, base_dir: &Path) -> PathBuf {
+        base_dir.join(format!("{}.bschema", self.file_name))
+    }
+}
+
+fn is_label(name: &str, label: &Label, version: BdatVersion) -> bool {
+    Label::parse_for_version(name, version) == label.clone().into_hash(version)
+}
+
+/// Moves the entry of a table in an annotation map to a new table name.
+fn rename_key<T>(map: &mut BTreeMap<String, T>, old: &Label, new: &str, version: BdatVersion) {
+    let key = map
+        .keys()
+        .find(|name| is_label(name, old, version))
+        .cloned();
+    if let Some(value) = key.and_then(|key| map.remove(&key)) {
+        map.insert(new.to_string(), value);
+    }
+}
+
+/// Returns the key of a table in [`FileSchema::file_names`], for the `count`-th table with
+/// the name.
+fn file_name_key(table: String, count: usize) -> String {
+    match count {
+        1 => table,
+        n => format!("{table}~{n}"),
+    }
+}
+
+impl AsFileName for Label {
+    fn as_file_name(&self) -> Utf<'_> {
+        match self {
+            // {:+} displays hashed names without brackets (<>)
+            l @ Label::Hash(_) => Cow::Owned(format!("{:+}", l)),
+            Label::String(s) | Label::Unhashed(s) => Cow::Borrowed(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{hash::murmur3_str, ColumnDef, TableBuilder, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn recorded_names() {
+        let resolved: Table = TableBuilder::with_name(Label::Unhashed("CHR_Dr".to_string()))
+            .add_column(ColumnDef::new(
+                ValueType::UnsignedInt,
+                Label::Unhashed("Name".to_string()),
+            ))
+            .build_modern()
+            .into();
+        let mut schema = FileSchema::new("file".to_string(), BdatVersion::Modern);
+        schema.feed_table(&resolved);
+        assert_eq!(
+            Some(Label::Hash(murmur3_str("Name"))),
+            schema.recorded_hash("Name")
+        );
+
+        // Extracting again without a hash list
+        let mut table: Table = TableBuilder::with_name(Label::Hash(murmur3_str("CHR_Dr")))
+            .add_column(ColumnDef::new(
+                ValueType::UnsignedInt,
+                Label::Hash(murmur3_str("Name")),
+            ))
+            .build_modern()
+            .into();
+        schema.resolve_labels(&mut table);
+        assert_eq!(resolved.name(), table.name());
+        assert!(resolved.columns().eq(table.columns()));
+    }
+
+    #[test]
+    fn file_names() {
+        let mut schema = FileSchema::new("file".to_string(), BdatVersion::LegacySwitch);
+        let label = |s: &str| Label::String(s.to_string());
+        assert_eq!(
+            "Enemy",
+            schema
+                .assign_file_name(
+                    &label("Enemy"),
+                    None,
+                    FileNames::Portable,
+                    SanitizeNames::PercentEncode
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            "enemy~2",
+            schema
+                .assign_file_name(
+                    &label("enemy"),
+                    None,
+                    FileNames::Portable,
+                    SanitizeNames::PercentEncode
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            "a%3Fb",
+            schema
+                .assign_file_name(
+                    &label("a?b"),
+                    None,
+                    FileNames::Portable,
+                    SanitizeNames::PercentEncode
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            "Enemy stats",
+            schema
+                .assign_file_name(
+                    &Label::Hash(0x1A2B3C4D),
+                    Some("Enemy stats"),
+                    FileNames::Portable,
+                    SanitizeNames::PercentEncode
+                )
+                .unwrap()
+        );
+        assert_eq!(None, schema.file_names.get("Enemy"));
+        assert_eq!(
+            Some("Enemy stats"),
+            schema.file_names.get("<1A2B3C4D>").map(String::as_str)
+        );
+        assert_eq!(
+            Some("enemy~2"),
+            schema.file_names.get("enemy").map(String::as_str)
+        );
+        // Tables with the same name
+        assert_eq!(
+            "Enemy~3",
+            schema
+                .assign_file_name(
+                    &label("Enemy"),
+                    None,
+                    FileNames::Portable,
+                    SanitizeNames::PercentEncode
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            Some("Enemy~3"),
+            schema.file_names.get("Enemy~2").map(String::as_str)
+        );
+        assert_eq!(SanitizeNames::PercentEncode, schema.sanitize_names);
+
+        let replaced = schema.assign_file_name(
+            &label("a:b"),
+            None,
+            FileNames::Portable,
+            SanitizeNames::Replace,
+        );
+        assert_eq!("a_b", replaced.unwrap());
+        assert_eq!(SanitizeNames::Replace, schema.sanitize_names);
+        assert_eq!(
+            Some("a_b"),
+            schema.file_names.get("a:b").map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn rename_table() {
+        let mut schema = FileSchema::new("file".to_string(), BdatVersion::Modern);
+        schema.tables = vec!["CHR_Dr".to_string(), "<0000BEEF>".to_string()];
+        schema
+            .unique
+            .insert(Label::Hash(murmur3_str("CHR_Dr")).to_string(), Vec::new());
+        schema
+            .file_names
+            .insert("<0000BEEF>".to_string(), "Beef".to_string());
+
+        schema.rename_table("<0000BEEF>", "CHR_Mod").unwrap();
+        schema.rename_table("CHR_Dr", "0x1234").unwrap();
+        assert_eq!(["0x1234", "CHR_Mod"], schema.tables.as_slice());
+        assert!(schema.unique.contains_key("0x1234"));
+        // The tables keep their files
+        assert_eq!(
+            Some("Beef"),
+            schema.file_names.get("CHR_Mod").map(String::as_str)
+        );
+        assert_eq!(
+            Some("CHR_Dr"),
+            schema.file_names.get("0x1234").map(String::as_str)
+        );
+        assert_eq!(
+            Some(Label::Hash(murmur3_str("CHR_Mod"))),
+            schema.recorded_hash("CHR_Mod")
+        );
+        assert!(matches!(
+            schema.rename_table("CHR_Dr", "New"),
+            Err(Error::TableNotFound(_))
+        ));
+        assert!(matches!(
+            schema.rename_table("CHR_Mod", "<00001234>"),
+            Err(Error::TableExists(_))
+        ));
+    }
+
+    #[test]
+    fn flat_layout() {
+        let dir = std::env::temp_dir().join(format!("bdat-schema-flat-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut schema = FileSchema::new("fld".to_string(), BdatVersion::Modern);
+        schema.layout = Layout::Flat;
+        let table = Label::String("FLD_Map".to_string());
+        schema.tables.push(table.to_string());
+        let file_name = schema
+            .assign_file_name(
+                &table,
+                None,
+                FileNames::Portable,
+                SanitizeNames::PercentEncode,
+            )
+            .unwrap();
+
+        let path = schema
+            .tables_dir(&dir)
+            .join(format!("{}.json", schema.table_file_stem(&file_name)));
+        assert_eq!(dir.join("fld_FLD_Map.json"), path);
+        std::fs::write(&path, "{}").unwrap();
+        assert_eq!(vec![(table, path)], schema.find_table_files(&dir, "json"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn merge_computed() {
+        let dir = std::env::temp_dir().join(format!("bdat-schema-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut previous = FileSchema::new("file".to_string(), BdatVersion::Modern);
+        previous.add_computed(&"CHR_Dr".into(), vec!["TotalAtk".to_string()]);
+        previous.add_computed(&"CHR_Pc".into(), vec!["TotalHp".to_string()]);
+        previous.write(&dir).unwrap();
+
+        // Only CHR_Pc is extracted again, without computed columns
+        let mut schema = FileSchema::new("file".to_string(), BdatVersion::Modern);
+        schema.load_annotations(&dir).unwrap();
+        schema.add_computed(&"CHR_Pc".into(), Vec::new());
+        assert_eq!(["TotalAtk"], schema.computed_columns(&"CHR_Dr".into()));
+        assert!(schema.computed_columns(&"CHR_Pc".into()).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn descriptions() {
+        let json = r#"{"file_name":"file","version":"Modern","format_version":2,"tables":[],
+            "descriptions":{"CHR_Dr":{"table":"Drivers","columns":{"Name":"Message ID"}}}}"#;
+        let schema = FileSchema::read(json.as_bytes()).unwrap();
+        let table = Label::Hash(murmur3_str("CHR_Dr"));
+        let description = schema.table_description(&table).unwrap();
+        assert_eq!(Some("Drivers"), description.table.as_deref());
+        for column in [
+            Label::Hash(murmur3_str("Name")),
+            Label::Unhashed("Name".into()),
+        ] {
+            assert_eq!(
+                Some("Message ID"),
+                schema.column_description(&table, &column)
+            );
+        }
+        assert_eq!(None, schema.column_description(&table, &"Other".into()));
+    }
+
+    #[test]
+    fn upgrade_unversioned() {
+        let old = r#"{"file_name":"file","version":"Modern","tables":["CHR_Dr"]}"#;
+        assert!(FileSchema::read(old.as_bytes()).is_err());
+
+        let (schema, version) = FileSchema::read_upgrade(old.as_bytes()).unwrap();
+        assert_eq!(0, version);
+        assert_eq!(FORMAT_VERSION, schema.format_version);
+        assert_eq!(["CHR_Dr"], schema.tables.as_slice());
+
+        let newer = r#"{"file_name":"file","version":"Modern","format_version":1000,"tables":[]}"#;
+        assert!(FileSchema::read_upgrade(newer.as_bytes()).is_err());
+        assert!(FileSchema::read(newer.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn upgrade_version_1() {
+        let old =
+            r#"{"file_name":"file","version":"Modern","format_version":1,"tables":["CHR_Dr"]}"#;
+        assert!(FileSchema::read(old.as_bytes()).is_err());
+
+        let (schema, version) = FileSchema::read_upgrade(old.as_bytes()).unwrap();
+        assert_eq!(1, version);
+        assert_eq!(2, schema.format_version);
+        assert!(schema.layout.is_nested());
+        assert!(schema.file_names.is_empty());
+    }
+}
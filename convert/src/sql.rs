@@ -0,0 +1,331 @@
+//! SQL export: one script per BDAT file, with `CREATE TABLE` and `INSERT` statements for
+//! each table. Scripts can be loaded into SQLite and PostgreSQL.
+//!
+//! Columns that reference other tables (see [`ColumnRef`]) become foreign keys and are
+//! indexed. References to no row (ID 0) are written as `NULL`.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use anyhow::Result;
+use bdat::{Cell, ColumnDef, Label, Table, TableAccessor, Value, ValueType};
+
+use crate::refs::{ColumnRef, RefTarget};
+use crate::schema::{AsFileName, FileSchema};
+
+const ID_COLUMN: &str = "$id";
+
+/// A column that references rows of another table in the same file.
+struct ForeignKey {
+    column: usize,
+    table: usize,
+    /// The referenced column, if the reference can be written as a constraint. This is
+    /// not the case if some values don't reference an existing row, or if the referenced
+    /// table has no unique column for the values.
+    target_column: Option<String>,
+}
+
+/// Writes an SQL script that creates and fills a table for each BDAT table.
+///
+/// Foreign key checks are deferred until the end of the script, so rows can be inserted
+/// in any order. Tables are created after the tables they reference, unless references
+/// form a cycle, which PostgreSQL does not support.
+pub fn write_dump(tables: &[Table], schema: &FileSchema, mut writer: impl Write) -> Result<()> {
+    let keys: Vec<_> = tables
+        .iter()
+        .map(|table| find_keys(table, tables, schema))
+        .collect();
+
+    let mut sql = String::from("BEGIN;\n\n");
+    for i in creation_order(&keys) {
+        write_create(&mut sql, &tables[i], &keys[i], tables)?;
+    }
+    writer.write_all(sql.as_bytes())?;
+
+    for (table, keys) in tables.iter().zip(&keys) {
+        sql.clear();
+        write_inserts(&mut sql, table, keys)?;
+        writer.write_all(sql.as_bytes())?;
+    }
+    writer.write_all(b"COMMIT;\n")?;
+    Ok(())
+}
+
+fn write_create(
+    sql: &mut String,
+    table: &Table,
+    keys: &[ForeignKey],
+    tables: &[Table],
+) -> std::fmt::Result {
+    let name = ident(table.name());
+    let hash_key = hash_key(table);
+    writeln!(sql, "CREATE TABLE {name} (")?;
+    write!(sql, "  \"{ID_COLUMN}\" INTEGER PRIMARY KEY")?;
+    for (i, column) in table.columns().enumerate() {
+        write!(
+            sql,
+            ",\n  {} {}",
+            ident(column.label()),
+            column_type(column)
+        )?;
+        if hash_key == Some(i) {
+            sql.push_str(" UNIQUE");
+        }
+    }
+    for key in keys {
+        let Some(target_column) = &key.target_column else {
+            continue;
+        };
+        write!(
+            sql,
+            ",\n  FOREIGN KEY ({}) REFERENCES {} ({target_column}) DEFERRABLE INITIALLY DEFERRED",
+            ident(column_label(table, key.column)),
+            ident(tables[key.table].name()),
+        )?;
+    }
+    sql.push_str("\n);\n");
+
+    for key in keys {
+        let column = column_label(table, key.column).as_file_name();
+        writeln!(
+            sql,
+            "CREATE INDEX {} ON {name} ({});",
+            quote_ident(&format!("{}_{column}", table.name().as_file_name())),
+            quote_ident(&column),
+        )?;
+    }
+    sql.push('\n');
+    Ok(())
+}
+
+fn write_inserts(sql: &mut String, table: &Table, keys: &[ForeignKey]) -> std::fmt::Result {
+    let name = ident(table.name());
+    for row in table.rows() {
+        write!(sql, "INSERT INTO {name} VALUES ({}", row.id())?;
+        for (col, cell) in row.cells().enumerate() {
+            sql.push_str(", ");
+            let is_null_ref = keys.iter().any(|k| k.column == col)
+                && matches!(cell, Cell::Single(v) if integer(v) == Some(0));
+            if is_null_ref {
+                sql.push_str("NULL");
+                continue;
+            }
+            match cell {
+                Cell::Single(v) => match literal(v) {
+                    Literal::Null => sql.push_str("NULL"),
+                    Literal::Number(n) => sql.push_str(&n),
+                    Literal::Text(s) => write_string(sql, &s),
+                },
+                Cell::List(values) => write_string(sql, &json_array(values.iter().map(literal))),
+                Cell::Flags(flags) => write_string(
+                    sql,
+                    &json_array(flags.iter().map(|f| Literal::Number(f.to_string()))),
+                ),
+            }
+        }
+        sql.push_str(");\n");
+    }
+    sql.push('\n');
+    Ok(())
+}
+
+/// Finds the columns of a table that reference rows in other tables of the file.
+fn find_keys(table: &Table, tables: &[Table], schema: &FileSchema) -> Vec<ForeignKey> {
+    let Some(refs) = schema.table_refs(table.name()) else {
+        return Vec::new();
+    };
+    let is_label = |name: &str, label: &Label| schema.is_label(name, label);
+    let mut keys = Vec::new();
+    for (col, column) in table.columns().enumerate() {
+        let Some((_, col_ref)) = refs.iter().find(|(name, _)| is_label(name, column.label()))
+        else {
+            continue;
+        };
+        let Some(target_index) = tables
+            .iter()
+            .position(|t| is_label(&col_ref.table, t.name()))
+        else {
+            continue;
+        };
+        let target = &tables[target_index];
+        let target_column = if column.value_type() == ValueType::HashRef {
+            hash_key(target).map(|c| ident(column_label(target, c)))
+        } else {
+            Some(format!("\"{ID_COLUMN}\""))
+        };
+        let is_valid = table.rows().all(|row| {
+            !matches!(
+                ColumnRef::resolve(target, row.cells().nth(col).unwrap()),
+                RefTarget::Missing
+            )
+        });
+        keys.push(ForeignKey {
+            column: col,
+            table: target_index,
+            target_column: target_column.filter(|_| is_valid),
+        });
+    }
+    keys
+}
+
+/// Orders tables so that tables are created after the tables they reference. Tables that
+/// are part of a reference cycle keep their order from the file.
+fn creation_order(keys: &[Vec<ForeignKey>]) -> Vec<usize> {
+    let mut created = vec![false; keys.len()];
+    let mut order = Vec::with_capacity(keys.len());
+    while order.len() < keys.len() {
+        let mut remaining = (0..keys.len()).filter(|&i| !created[i]);
+        let first = remaining.clone().next().unwrap();
+        let next = remaining
+            .find(|&i| {
+                keys[i]
+                    .iter()
+                    .all(|key| key.table == i || created[key.table] || key.target_column.is_none())
+            })
+            .unwrap_or(first);
+        created[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+/// Returns the column that holds the hashed IDs of a modern table's rows. Every row has a
+/// unique hash, so it can be referenced by foreign keys.
+fn hash_key(table: &Table) -> Option<usize> {
+    if !table.is_modern() {
+        return None;
+    }
+    table
+        .columns()
+        .position(|c| c.value_type() == ValueType::HashRef)
+}
+
+fn column_label<'a>(table: &'a Table, column: usize) -> &'a Label {
+    table.columns().nth(column).unwrap().label()
+}
+
+fn column_type(column: &ColumnDef) -> &'static str {
+    // Lists and flags are written as JSON arrays
+    if column.count() > 1 || !column.flags().is_empty() {
+        return "TEXT";
+    }
+    match column.value_type() {
+        ValueType::UnsignedInt | ValueType::HashRef => "BIGINT",
+        ValueType::Float => "REAL",
+        ValueType::String | ValueType::DebugString | ValueType::Unknown | ValueType::Raw => "TEXT",
+        _ => "INTEGER",
+    }
+}
+
+enum Literal<'a> {
+    Null,
+    Number(String),
+    Text(Cow<'a, str>),
+}
+
+fn literal<'a>(value: &'a Value) -> Literal<'a> {
+    match value {
+        Value::Unknown => Literal::Null,
+        Value::String(s) | Value::DebugString(s) => Literal::Text(Cow::Borrowed(s)),
+        // Raw bytes are written as hex text
+        Value::Raw(_) => Literal::Text(value.to_string().into()),
+        Value::Float(f) => {
+            let f = f32::from(*f);
+            match f.is_finite() {
+                true => Literal::Number(f.to_string()),
+                false => Literal::Null,
+            }
+        }
+        v => Literal::Number(integer(v).unwrap().to_string()),
+    }
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    Some(match value {
+        Value::SignedByte(v) => *v as i64,
+        Value::SignedShort(v) => *v as i64,
+        Value::SignedInt(v) => *v as i64,
+        Value::Unknown
+        | Value::String(_)
+        | Value::DebugString(_)
+        | Value::Float(_)
+        | Value::Raw(_) => return None,
+        v => v.to_integer() as i64,
+    })
+}
+
+fn json_array<'a>(values: impl Iterator<Item = Literal<'a>>) -> String {
+    let values: Vec<_> = values
+        .map(|v| match v {
+            Literal::Null => "null".to_string(),
+            Literal::Number(n) => n,
+            Literal::Text(s) => serde_json::to_string(&s).unwrap(),
+        })
+        .collect();
+    format!("[{}]", values.join(","))
+}
+
+fn write_string(sql: &mut String, s: &str) {
+    sql.push('\'');
+    sql.push_str(&s.replace('\'', "''"));
+    sql.push('\'');
+}
+
+fn ident(label: &Label) -> String {
+    quote_ident(&label.as_file_name())
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, Row, TableBuilder};
+
+    use super::*;
+
+    #[test]
+    fn references_become_foreign_keys() {
+        let item = TableBuilder::with_name(Label::String("Item".into()))
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_row(Row::new(
+                1,
+                vec![Cell::Single(Value::String("It's".into()))],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let chr = TableBuilder::with_name(Label::String("Chr".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, "Weapon".into()))
+            .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedShort(1))]))
+            .add_row(Row::new(2, vec![Cell::Single(Value::UnsignedShort(0))]))
+            .build(BdatVersion::LegacySwitch);
+        let schema = FileSchema::read(
+            r#"{
+                "file_name": "test",
+                "version": "LegacySwitch",
+                "format_version": 2,
+                "tables": ["Chr", "Item"],
+                "references": { "Chr": { "Weapon": { "table": "Item" } } }
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        write_dump(&[chr, item], &schema, &mut out).unwrap();
+        let sql = String::from_utf8(out).unwrap();
+
+        // Item is referenced by Chr, so it must be created first
+        let item_pos = sql.find("CREATE TABLE \"Item\"").unwrap();
+        let chr_pos = sql.find("CREATE TABLE \"Chr\"").unwrap();
+        assert!(item_pos < chr_pos);
+        assert!(sql.contains(
+            "FOREIGN KEY (\"Weapon\") REFERENCES \"Item\" (\"$id\") DEFERRABLE INITIALLY DEFERRED"
+        ));
+        assert!(sql.contains("CREATE INDEX \"Chr_Weapon\" ON \"Chr\" (\"Weapon\");"));
+        assert!(sql.contains("INSERT INTO \"Chr\" VALUES (1, 1);"));
+        assert!(sql.contains("INSERT INTO \"Chr\" VALUES (2, NULL);"));
+        assert!(sql.contains("INSERT INTO \"Item\" VALUES (1, 'It''s');"));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bdat::Label;
+use serde::{Serialize, Serializer};
+
+use crate::Progress;
+
+/// What was done by [`extract`] or [`pack`].
+///
+/// [`extract`]: crate::extract
+/// [`pack`]: crate::pack
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    /// Files that were converted.
+    pub files: usize,
+    /// Files that were not extracted again, see [`ExtractOptions::resume`].
+    ///
+    /// [`ExtractOptions::resume`]: crate::ExtractOptions::resume
+    pub skipped_files: usize,
+    /// Tables that were converted.
+    pub tables: usize,
+    /// Tables that were left out by [`ExtractOptions::tables`].
+    ///
+    /// [`ExtractOptions::tables`]: crate::ExtractOptions::tables
+    pub skipped_tables: usize,
+    /// Rows in the converted tables, after filtering.
+    pub rows: usize,
+    /// Table names, column names and hash values that could not be resolved when
+    /// extracting, counted once each.
+    pub unresolved_hashes: usize,
+    /// Problems that didn't stop the conversion, as reported to [`Progress::warn`].
+    pub warnings: Vec<String>,
+    /// Files that would have been written, for a dry run. Empty otherwise.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub planned: Vec<PlannedWrite>,
+    /// Time from start to finish.
+    #[serde(serialize_with = "as_secs")]
+    pub elapsed: Duration,
+    /// Time spent reading and parsing files, added up across threads.
+    #[serde(serialize_with = "as_secs")]
+    pub read_time: Duration,
+    /// Time spent resolving names, computing columns and filtering rows, added up
+    /// across threads.
+    #[serde(serialize_with = "as_secs")]
+    pub convert_time: Duration,
+    /// Time spent writing output files, added up across threads.
+    #[serde(serialize_with = "as_secs")]
+    pub write_time: Duration,
+}
+
+/// A file that a dry run would have written.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedWrite {
+    /// The file that is converted, e.g. a BDAT file when extracting, or a schema when
+    /// packing.
+    pub source: PathBuf,
+    /// The table that would be written, for files that hold a single table.
+    pub table: Option<String>,
+    /// The file that would be written.
+    pub path: PathBuf,
+}
+
+/// A part of a conversion that is timed separately in a [`Summary`].
+#[derive(Clone, Copy)]
+pub(crate) enum Stage {
+    Read,
+    Convert,
+    Write,
+}
+
+/// Builds a [`Summary`] from files that are converted in parallel.
+pub(crate) struct SummaryCounter {
+    start: Instant,
+    files: AtomicUsize,
+    skipped_files: AtomicUsize,
+    tables: AtomicUsize,
+    skipped_tables: AtomicUsize,
+    rows: AtomicUsize,
+    /// Nanoseconds spent in each [`Stage`]
+    stages: [AtomicU64; 3],
+    warnings: Mutex<Vec<String>>,
+    /// Only records the files that would be written, see [`SummaryCounter::write`]
+    dry_run: bool,
+    planned: Mutex<Vec<PlannedWrite>>,
+}
+
+impl SummaryCounter {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            start: Instant::now(),
+            files: AtomicUsize::new(0),
+            skipped_files: AtomicUsize::new(0),
+            tables: AtomicUsize::new(0),
+            skipped_tables: AtomicUsize::new(0),
+            rows: AtomicUsize::new(0),
+            stages: Default::default(),
+            warnings: Mutex::default(),
+            dry_run,
+            planned: Mutex::default(),
+        }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Runs `write`, which writes the file at `path`, timing it as [`Stage::Write`]. For
+    /// a dry run, the file is only recorded for the summary.
+    pub fn write(
+        &self,
+        source: &Path,
+        table: Option<&Label>,
+        path: &Path,
+        write: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        if self.dry_run {
+            self.planned.lock().unwrap().push(PlannedWrite {
+                source: source.to_owned(),
+                table: table.map(ToString::to_string),
+                path: path.to_owned(),
+            });
+            return Ok(());
+        }
+        self.time(Stage::Write, write)
+    }
+
+    /// Runs `f`, adding the time it took to `stage`.
+    pub fn time<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        let nanos = start.elapsed().as_nanos() as u64;
+        self.stages[stage as usize].fetch_add(nanos, Ordering::Relaxed);
+        res
+    }
+
+    pub fn file_done(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn file_skipped(&self) {
+        self.skipped_files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn table_done(&self, rows: usize) {
+        self.tables.fetch_add(1, Ordering::Relaxed);
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    pub fn table_skipped(&self) {
+        self.skipped_tables.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reports a warning to `progress`, and keeps it for the summary.
+    pub fn warn(&self, progress: &dyn Progress, message: String) {
+        progress.warn(&message);
+        self.warnings.lock().unwrap().push(message);
+    }
+
+    pub fn finish(self, unresolved_hashes: usize) -> Summary {
+        let stage =
+            |s: Stage| Duration::from_nanos(self.stages[s as usize].load(Ordering::Relaxed));
+        Summary {
+            files: self.files.into_inner(),
+            skipped_files: self.skipped_files.into_inner(),
+            tables: self.tables.into_inner(),
+            skipped_tables: self.skipped_tables.into_inner(),
+            rows: self.rows.into_inner(),
+            unresolved_hashes,
+            elapsed: self.start.elapsed(),
+            read_time: stage(Stage::Read),
+            convert_time: stage(Stage::Convert),
+            write_time: stage(Stage::Write),
+            warnings: self.warnings.into_inner().unwrap(),
+            planned: self.planned.into_inner().unwrap(),
+        }
+    }
+}
+
+fn as_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
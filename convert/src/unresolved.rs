@@ -0,0 +1,167 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use bdat::{Cell, Label, Table, TableAccessor, Value};
+use serde::Serialize;
+
+use crate::hash::HashNameTable;
+
+/// Collects hashes that could not be resolved with the hash list, for
+/// `--unresolved-report` and the summary.
+#[derive(Default)]
+pub struct UnresolvedHashes {
+    entries: Mutex<BTreeMap<(u32, HashKind), Entry>>,
+}
+
+/// Where an unresolved hash was found.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum HashKind {
+    /// A table name
+    Table,
+    /// A column name
+    Column,
+    /// A hash value in a cell
+    Value,
+}
+
+#[derive(Default)]
+struct Entry {
+    count: usize,
+    locations: BTreeSet<String>,
+}
+
+/// A line of the report.
+#[derive(Serialize)]
+struct ReportLine {
+    hash: String,
+    kind: HashKind,
+    count: usize,
+    /// Files for table names, tables for column names and `Table.Column` for values
+    locations: String,
+}
+
+impl UnresolvedHashes {
+    /// Records the hashes left in a table after names were resolved. Hash values are kept
+    /// in cells, so only those without a name in `hashes` are recorded.
+    pub fn add_table(&self, file_name: &str, table: &Table, hashes: &HashNameTable) {
+        let mut found = Vec::new();
+        if let Label::Hash(hash) = table.name() {
+            found.push((*hash, HashKind::Table, file_name.to_string(), 1));
+        }
+        for column in table.columns() {
+            if let Label::Hash(hash) = column.label() {
+                found.push((*hash, HashKind::Column, table.name().to_string(), 1));
+            }
+        }
+
+        // (hash, column index) -> count
+        let mut values = BTreeMap::<(u32, usize), usize>::new();
+        for row in table.rows() {
+            for (i, cell) in row.cells().enumerate() {
+                // 0 is used for "no reference"
+                match cell {
+                    Cell::Single(Value::HashRef(hash @ 1..)) if hashes.unhash(*hash).is_none() => {
+                        *values.entry((*hash, i)).or_default() += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let columns = table.columns().collect::<Vec<_>>();
+        found.extend(values.into_iter().map(|((hash, i), count)| {
+            let location = format!("{}.{}", table.name(), columns[i].label());
+            (hash, HashKind::Value, location, count)
+        }));
+
+        let mut entries = self.entries.lock().unwrap();
+        for (hash, kind, location, count) in found {
+            let entry = entries.entry((hash, kind)).or_default();
+            entry.count += count;
+            entry.locations.insert(location);
+        }
+    }
+
+    /// Returns the number of unresolved hashes, counting a hash once for each kind of
+    /// name it was found as.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Writes the report as CSV, with a line for each hash and kind.
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for ((hash, kind), entry) in self.entries.lock().unwrap().iter() {
+            writer.serialize(ReportLine {
+                hash: Label::Hash(*hash).to_string(),
+                kind: *kind,
+                count: entry.count,
+                locations: entry
+                    .locations
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{hash::murmur3_str, ColumnDef, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn report() {
+        let table: Table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+            .add_column(ColumnDef::new(ValueType::HashRef, Label::Hash(0xDEADBEEF)))
+            .add_column(ColumnDef::new(ValueType::HashRef, "Item".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::HashRef(1)),
+                    Cell::Single(Value::HashRef(0)),
+                ],
+            ))
+            .add_row(Row::new(
+                2,
+                vec![
+                    Cell::Single(Value::HashRef(2)),
+                    Cell::Single(Value::HashRef(1)),
+                ],
+            ))
+            .add_row(Row::new(
+                3,
+                vec![
+                    Cell::Single(Value::HashRef(murmur3_str("Known"))),
+                    Cell::Single(Value::HashRef(0)),
+                ],
+            ))
+            .build_modern()
+            .into();
+        let names =
+            HashNameTable::load_from_names("Known".as_bytes(), false, &mut |_, e| panic!("{e}"))
+                .unwrap();
+
+        let hashes = UnresolvedHashes::default();
+        hashes.add_table("file", &table, &names);
+        let mut out = Vec::new();
+        hashes.write(&mut out).unwrap();
+        assert_eq!(
+            "hash,kind,count,locations\n\
+            <00000001>,value,2,<CAFEBABE>.<DEADBEEF>;<CAFEBABE>.Item\n\
+            <00000002>,value,1,<CAFEBABE>.<DEADBEEF>\n\
+            <CAFEBABE>,table,1,file\n\
+            <DEADBEEF>,column,1,<CAFEBABE>\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
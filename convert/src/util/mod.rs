@@ -0,0 +1,404 @@
+use bdat::legacy::LegacyWriteOptions;
+use bdat::modern::ModernWriteOptions;
+use bdat::{
+    BdatFile, BdatResult, BdatVersion, Diagnostic, SwitchEndian, Table, VersionReader, WiiEndian,
+};
+use itertools::Itertools;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+pub mod zip;
+
+/// A game that uses BDAT files, which determines how files are read and written.
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub enum BdatGame {
+    Wii,
+    Xcx,
+    LegacySwitch,
+    Modern,
+}
+
+impl BdatGame {
+    pub fn version_default(version: BdatVersion) -> Self {
+        match version {
+            BdatVersion::LegacyWii => Self::Wii,
+            BdatVersion::LegacySwitch => Self::LegacySwitch,
+            BdatVersion::LegacyX => Self::Xcx,
+            BdatVersion::Modern => Self::Modern,
+        }
+    }
+
+    /// Chooses the game to read a file with, given the version detected from the file.
+    ///
+    /// `game` is a user override, e.g. to tell XC1 and XCX files apart when the detected
+    /// version is wrong. It is only used for files in the same format (endianness and
+    /// legacy/modern) as the game, or when the version could not be detected. Other files
+    /// use the detected version, so files from different games can be read together.
+    pub fn for_file(game: Option<Self>, detected: BdatResult<BdatVersion>) -> BdatResult<Self> {
+        match (game, detected) {
+            (Some(game), Ok(version))
+                if BdatVersion::from(game) == version
+                    || BdatVersion::from(game).is_big_endian() && version.is_big_endian() =>
+            {
+                Ok(game)
+            }
+            (Some(game), Err(_)) => Ok(game),
+            (_, detected) => detected.map(Self::version_default),
+        }
+    }
+
+    /// Reads every table in a BDAT file. Non-fatal issues with the tables are passed to
+    /// `diagnostics`, in table order.
+    ///
+    /// Tables are parsed in parallel, on the current rayon thread pool.
+    pub fn from_bytes<'b>(
+        self,
+        bytes: &'b mut [u8],
+        diagnostics: &mut dyn FnMut(Diagnostic),
+    ) -> BdatResult<Vec<Table<'b>>> {
+        self.parse_bytes(bytes, 1, diagnostics)
+    }
+
+    /// Like [`BdatGame::from_bytes`], with at least `min_tables` tables parsed by each job,
+    /// see [`crate::parallel::Pools::min_tables`].
+    pub(crate) fn parse_bytes<'b>(
+        self,
+        bytes: &'b mut [u8],
+        min_tables: usize,
+        diagnostics: &mut dyn FnMut(Diagnostic),
+    ) -> BdatResult<Vec<Table<'b>>> {
+        match self {
+            Self::Wii => read_all(
+                bdat::legacy::from_bytes::<WiiEndian>(bytes, BdatVersion::LegacyWii)?,
+                min_tables,
+                diagnostics,
+            ),
+            Self::Xcx => read_all(
+                bdat::legacy::from_bytes::<WiiEndian>(bytes, BdatVersion::LegacyX)?,
+                min_tables,
+                diagnostics,
+            ),
+            Self::LegacySwitch => read_all(
+                bdat::legacy::from_bytes::<SwitchEndian>(bytes, BdatVersion::LegacySwitch)?,
+                min_tables,
+                diagnostics,
+            ),
+            Self::Modern => read_all(
+                bdat::modern::from_bytes::<SwitchEndian>(bytes)?,
+                min_tables,
+                diagnostics,
+            ),
+        }
+    }
+
+    /// Reads the header of a BDAT file from `reader`. Tables are read when requested, with
+    /// [`BdatFile::get_table`], so only one table needs to be in memory at a time.
+    pub fn from_reader<R: Read + Seek>(self, reader: R) -> BdatResult<VersionReader<R>> {
+        Ok(match self {
+            Self::Wii => {
+                VersionReader::LegacyWii(bdat::legacy::from_reader(reader, BdatVersion::LegacyWii)?)
+            }
+            Self::Xcx => {
+                VersionReader::LegacyWii(bdat::legacy::from_reader(reader, BdatVersion::LegacyX)?)
+            }
+            Self::LegacySwitch => VersionReader::LegacySwitch(bdat::legacy::from_reader(
+                reader,
+                BdatVersion::LegacySwitch,
+            )?),
+            Self::Modern => VersionReader::Modern(bdat::modern::from_reader(reader)?),
+        })
+    }
+
+    /// Writes a BDAT file for this game. `sort_tables` only applies to modern files, and
+    /// `scramble` to legacy files, whose name and string sections are then scrambled with
+    /// each table's checksum, like in the games' files.
+    pub fn to_writer<'b, W: Write>(
+        self,
+        writer: W,
+        tables: impl IntoIterator<Item = Table<'b>>,
+        sort_tables: bool,
+        scramble: bool,
+    ) -> BdatResult<()> {
+        if self == Self::Modern {
+            let tables = tables.into_iter().map(Table::into_modern).collect_vec();
+            return bdat::modern::to_writer_options::<_, SwitchEndian>(
+                writer,
+                tables,
+                ModernWriteOptions::new().sort_tables(sort_tables),
+            );
+        }
+        let tables = tables.into_iter().map(Table::into_legacy).collect_vec();
+        let opts = LegacyWriteOptions::new().scramble(scramble);
+        let version = BdatVersion::from(self);
+        match self {
+            Self::Wii | Self::Xcx => {
+                bdat::legacy::to_writer_options::<_, WiiEndian>(writer, tables, version, opts)
+            }
+            Self::LegacySwitch => {
+                bdat::legacy::to_writer_options::<_, SwitchEndian>(writer, tables, version, opts)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn to_vec<'b>(self, tables: impl IntoIterator<Item = Table<'b>>) -> BdatResult<Vec<u8>> {
+        if self == Self::Modern {
+            let tables = tables.into_iter().map(Table::into_modern).collect_vec();
+            return bdat::modern::to_vec::<SwitchEndian>(tables);
+        }
+        let tables = tables.into_iter().map(Table::into_legacy).collect_vec();
+        match self {
+            Self::Wii => bdat::legacy::to_vec::<WiiEndian>(tables, BdatVersion::LegacyWii),
+            Self::LegacySwitch => {
+                bdat::legacy::to_vec::<SwitchEndian>(tables, BdatVersion::LegacySwitch)
+            }
+            Self::Xcx => bdat::legacy::to_vec::<WiiEndian>(tables, BdatVersion::LegacyX),
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn read_all<'b, F>(
+    file: F,
+    min_tables: usize,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) -> BdatResult<Vec<Table<'b>>>
+where
+    F: BdatFile<'b> + Clone + Send + Sync,
+    F::TableOut: Into<Table<'b>>,
+{
+    // Each thread reads from its own clone of the file, which keeps its own diagnostics
+    let results = (0..file.table_count())
+        .into_par_iter()
+        .with_min_len(min_tables)
+        .map_init(
+            || file.clone(),
+            |file, i| {
+                let table = file.get_table(i).map(Into::into);
+                (table, file.take_diagnostics())
+            },
+        )
+        .collect::<Vec<_>>();
+    results
+        .into_iter()
+        .map(|(table, table_diagnostics)| {
+            table_diagnostics.into_iter().for_each(&mut *diagnostics);
+            table
+        })
+        .collect()
+}
+
+#[cfg(feature = "clap")]
+impl clap::ValueEnum for BdatGame {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Wii, Self::LegacySwitch, Self::Xcx, Self::Modern]
+    }
+
+    fn to_possible_value<'a>(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Wii => Some(clap::builder::PossibleValue::new("xc1")),
+            Self::LegacySwitch => Some(clap::builder::PossibleValue::new("xc2de")),
+            Self::Xcx => Some(clap::builder::PossibleValue::new("xcx")),
+            Self::Modern => Some(clap::builder::PossibleValue::new("xc3")),
+        }
+    }
+}
+
+impl From<BdatGame> for BdatVersion {
+    fn from(value: BdatGame) -> Self {
+        match value {
+            BdatGame::Wii => BdatVersion::LegacyWii,
+            BdatGame::Xcx => BdatVersion::LegacyX,
+            BdatGame::LegacySwitch => BdatVersion::LegacySwitch,
+            BdatGame::Modern => BdatVersion::Modern,
+        }
+    }
+}
+
+/// Calculates the greatest common denominator between the given paths.
+///
+/// In other words, this returns the biggest path that is shared by all
+/// paths in the list.
+pub fn get_common_denominator(paths: &[impl AsRef<Path>]) -> PathBuf {
+    if paths.is_empty() {
+        return PathBuf::new();
+    }
+    let mut paths = paths
+        .iter()
+        .map(|p| p.as_ref().iter().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut common = PathBuf::new();
+    'outer: for (i, to_match) in paths.remove(0).into_iter().enumerate() {
+        for path in &paths {
+            match path.get(i) {
+                None => break 'outer,
+                Some(c) if *c != to_match => break 'outer,
+                _ => {}
+            }
+        }
+        common.push(to_match);
+    }
+    common
+}
+
+/// Writes a file through a temporary file next to it, which replaces `path` once `write`
+/// has succeeded. If writing fails or the program is stopped, `path` is left as it was.
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let res = File::create(&tmp_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush()?;
+            Ok(())
+        });
+    match res {
+        Ok(()) => Ok(std::fs::rename(&tmp_path, path)?),
+        Err(e) => {
+            // The error is more useful than a failure to clean up
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_common_denominator, write_atomic, BdatGame};
+    use bdat::{BdatVersion, Cell, ColumnDef, DetectError, Row, TableBuilder, Value, ValueType};
+    use std::io::Write;
+    use std::path::Path;
+
+    #[test]
+    fn common_paths() {
+        assert_eq!(
+            get_common_denominator(&["/a/b/c", "/a/b/c/d", "/a/b/e"]),
+            Path::new("/a/b")
+        );
+
+        assert_eq!(
+            get_common_denominator(&["a/b/c", "d/e/f", "g/h/i"]),
+            Path::new("")
+        );
+
+        assert_eq!(get_common_denominator(&["/a", "/a", "/a"]), Path::new("/a"));
+
+        assert_eq!(get_common_denominator(&["/a", "/b", "/c"]), Path::new("/"));
+    }
+
+    #[test]
+    fn game_for_file() {
+        let detected = |v| BdatGame::for_file(Some(BdatGame::Xcx), Ok(v)).ok();
+        assert_eq!(Some(BdatGame::Xcx), detected(BdatVersion::LegacyWii));
+        assert_eq!(Some(BdatGame::Xcx), detected(BdatVersion::LegacyX));
+        assert_eq!(Some(BdatGame::Modern), detected(BdatVersion::Modern));
+        assert_eq!(
+            Some(BdatGame::LegacySwitch),
+            detected(BdatVersion::LegacySwitch)
+        );
+
+        let undetected = || Err(DetectError::LegacyNoTables.into());
+        assert!(BdatGame::for_file(Some(BdatGame::Wii), undetected()).is_ok());
+        assert!(BdatGame::for_file(None, undetected()).is_err());
+    }
+
+    #[test]
+    fn scrambled_round_trip() {
+        let table = TableBuilder::with_name("Enemy".into())
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_row(Row::new(
+                1,
+                vec![Cell::Single(Value::String("Bunnit".into()))],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let write = |tables, scramble| {
+            let mut out = Vec::new();
+            BdatGame::LegacySwitch
+                .to_writer(&mut out, tables, false, scramble)
+                .unwrap();
+            out
+        };
+
+        let mut scrambled = write(vec![table.clone()], true);
+        assert_ne!(write(vec![table], false), scrambled);
+        let expected = scrambled.clone();
+        let tables = BdatGame::LegacySwitch
+            .from_bytes(&mut scrambled, &mut |d| panic!("{d}"))
+            .unwrap();
+        assert_eq!(expected, write(tables, true));
+    }
+
+    #[test]
+    fn to_vec_matches_writer() {
+        for game in [BdatGame::Wii, BdatGame::LegacySwitch, BdatGame::Modern] {
+            let table = TableBuilder::with_name("Enemy".into())
+                .add_column(ColumnDef::new(ValueType::UnsignedInt, "Level".into()))
+                .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedInt(5))]))
+                .build(BdatVersion::from(game));
+            let mut out = Vec::new();
+            game.to_writer(&mut out, [table.clone()], false, false)
+                .unwrap();
+            assert_eq!(out, game.to_vec([table]).unwrap());
+        }
+    }
+
+    #[test]
+    fn parallel_read_keeps_order() {
+        for game in [BdatGame::LegacySwitch, BdatGame::Modern] {
+            let version = BdatVersion::from(game);
+            let tables = (0..32)
+                .map(|i| {
+                    TableBuilder::with_name(format!("Table{i}").into())
+                        .add_column(ColumnDef::new(ValueType::UnsignedInt, "Value".into()))
+                        .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedInt(i))]))
+                        .build(version)
+                })
+                .collect::<Vec<_>>();
+            let mut bytes = Vec::new();
+            game.to_writer(&mut bytes, tables.clone(), false, false)
+                .unwrap();
+
+            let mut sequential = bytes.clone();
+            let expected = game
+                .parse_bytes(&mut sequential, usize::MAX, &mut |d| panic!("{d}"))
+                .unwrap();
+            assert_eq!(tables.len(), expected.len());
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .unwrap();
+            let read = pool.install(|| game.from_bytes(&mut bytes, &mut |d| panic!("{d}")));
+            assert!(expected == read.unwrap());
+        }
+    }
+
+    #[test]
+    fn atomic_write() {
+        let dir = std::env::temp_dir().join(format!("bdat-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.json");
+
+        write_atomic(&path, |w| Ok(w.write_all(b"first")?)).unwrap();
+        assert_eq!("first", std::fs::read_to_string(&path).unwrap());
+
+        // A failed write keeps the previous file, and doesn't leave anything behind
+        write_atomic(&path, |w| {
+            w.write_all(b"partial")?;
+            anyhow::bail!("failed")
+        })
+        .unwrap_err();
+        assert_eq!("first", std::fs::read_to_string(&path).unwrap());
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
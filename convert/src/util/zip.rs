@@ -0,0 +1,360 @@
+//! Minimal ZIP support: a writer for uncompressed archives, used for XLSX output, and a
+//! reader for stored or deflated files, used to pack tables from an archive.
+
+use std::{
+    io::{Error, ErrorKind, Result, Write},
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const LOCAL_HEADER_SIG: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIG: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR_SIG: u32 = 0x06054b50;
+/// Version 2.0, the minimum for stored files in directories
+const ZIP_VERSION: u16 = 20;
+/// 1980-01-01, the earliest date ZIP files can store
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Compression method of files that are stored as they are
+const METHOD_STORED: u16 = 0;
+/// Compression method of deflated files
+const METHOD_DEFLATE: u16 = 8;
+
+/// Writes files to a ZIP archive without compressing them.
+pub struct ZipWriter<W> {
+    writer: W,
+    offset: u32,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a file to the archive.
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32(data),
+            size: to_u32(data.len())?,
+            offset: self.offset,
+        };
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend(LOCAL_HEADER_SIG.to_le_bytes());
+        header.extend(ZIP_VERSION.to_le_bytes());
+        entry.write_common(&mut header);
+        header.extend(0u16.to_le_bytes()); // extra field length
+        header.extend(name.as_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)?;
+        self.offset = to_u32(self.offset as usize + header.len() + data.len())?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory, completing the archive.
+    pub fn finish(mut self) -> Result<W> {
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend(CENTRAL_HEADER_SIG.to_le_bytes());
+            directory.extend(ZIP_VERSION.to_le_bytes()); // version made by
+            directory.extend(ZIP_VERSION.to_le_bytes()); // version needed
+            entry.write_common(&mut directory);
+            directory.extend([0; 12]); // extra, comment, disk, internal & external attributes
+            directory.extend(entry.offset.to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+        }
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| Error::other("too many files in ZIP archive"))?;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend(END_OF_CENTRAL_DIR_SIG.to_le_bytes());
+        end.extend([0; 4]); // disk numbers
+        end.extend(count.to_le_bytes());
+        end.extend(count.to_le_bytes());
+        end.extend(to_u32(directory.len())?.to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes()); // comment length
+
+        self.writer.write_all(&directory)?;
+        self.writer.write_all(&end)?;
+        Ok(self.writer)
+    }
+}
+
+impl Entry {
+    /// Writes the fields shared by local and central headers, from the flags
+    /// to the file name length.
+    fn write_common(&self, out: &mut Vec<u8>) {
+        out.extend(0x0800u16.to_le_bytes()); // flags: UTF-8 names
+        out.extend(0u16.to_le_bytes()); // method: stored
+        out.extend(0u16.to_le_bytes()); // time
+        out.extend(DOS_DATE.to_le_bytes());
+        out.extend(self.crc.to_le_bytes());
+        out.extend(self.size.to_le_bytes()); // compressed size
+        out.extend(self.size.to_le_bytes()); // uncompressed size
+        out.extend((self.name.len() as u16).to_le_bytes());
+    }
+}
+
+/// Reads files from a ZIP archive in memory. Only stored and deflated files are supported,
+/// and archives must not be split or use ZIP64 extensions.
+pub struct ZipReader<'a> {
+    data: &'a [u8],
+    entries: Vec<ReadEntry<'a>>,
+}
+
+/// A file listed in the central directory
+struct ReadEntry<'a> {
+    name: &'a str,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+impl<'a> ZipReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        // The end of central directory record is at the end, followed by a comment of up to
+        // 64 KiB
+        let end = (0..data.len().saturating_sub(21))
+            .rev()
+            .take(u16::MAX as usize + 1)
+            .find(|&i| read_u32(data, i) == Some(END_OF_CENTRAL_DIR_SIG))
+            .ok_or_else(|| invalid("not a ZIP archive"))?;
+        let count = read_u16(data, end + 10).unwrap();
+        let mut pos = read_u32(data, end + 16).unwrap() as usize;
+        if count == u16::MAX || pos == u32::MAX as usize {
+            return Err(invalid("ZIP64 archives are not supported"));
+        }
+
+        let mut entries = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            let header = data.get(pos..pos + 46).ok_or_else(truncated)?;
+            if read_u32(header, 0) != Some(CENTRAL_HEADER_SIG) {
+                return Err(invalid("invalid ZIP central directory"));
+            }
+            let name_len = read_u16(header, 28).unwrap() as usize;
+            let extra_len = read_u16(header, 30).unwrap() as usize;
+            let comment_len = read_u16(header, 32).unwrap() as usize;
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(truncated)?;
+            entries.push(ReadEntry {
+                name: std::str::from_utf8(name)
+                    .map_err(|_| invalid("file names in ZIP archives must be UTF-8"))?,
+                method: read_u16(header, 10).unwrap(),
+                crc: read_u32(header, 16).unwrap(),
+                compressed_size: read_u32(header, 20).unwrap(),
+                size: read_u32(header, 24).unwrap(),
+                offset: read_u32(header, 42).unwrap(),
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// Returns the names of the files in the archive, which are paths separated by `/`.
+    /// Directories end with `/`.
+    pub fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.entries.iter().map(|e| e.name)
+    }
+
+    /// Returns the uncompressed contents of the file with the given index, in the order
+    /// of [`ZipReader::names`].
+    pub fn read(&self, index: usize) -> Result<Vec<u8>> {
+        let entry = &self.entries[index];
+        // Sizes in the local header may be left out, the central directory has them
+        let pos = entry.offset as usize;
+        let header = self.data.get(pos..pos + 30).ok_or_else(truncated)?;
+        if read_u32(header, 0) != Some(LOCAL_HEADER_SIG) {
+            return Err(invalid("invalid ZIP file header"));
+        }
+        let start = pos
+            + 30
+            + read_u16(header, 26).unwrap() as usize
+            + read_u16(header, 28).unwrap() as usize;
+        let compressed = self
+            .data
+            .get(start..start + entry.compressed_size as usize)
+            .ok_or_else(truncated)?;
+
+        let data = match entry.method {
+            METHOD_STORED => compressed.to_vec(),
+            METHOD_DEFLATE => {
+                miniz_oxide::inflate::decompress_to_vec_with_limit(compressed, entry.size as usize)
+                    .map_err(|_| invalid("invalid deflated data in ZIP archive"))?
+            }
+            _ => {
+                return Err(invalid(&format!(
+                    "unsupported compression method for {} in ZIP archive",
+                    entry.name
+                )))
+            }
+        };
+        if data.len() != entry.size as usize || crc32(&data) != entry.crc {
+            return Err(invalid(&format!(
+                "{} is corrupted in ZIP archive",
+                entry.name
+            )));
+        }
+        Ok(data)
+    }
+
+    /// Writes every file in the archive to `dir`, keeping the archive's directories.
+    pub fn unpack(&self, dir: &Path) -> Result<()> {
+        for (i, name) in self.names().enumerate() {
+            let path = safe_path(dir, name)?;
+            if name.ends_with('/') {
+                std::fs::create_dir_all(path)?;
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, self.read(i)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// A ZIP archive unpacked to a temporary directory, which is removed when dropped.
+pub struct UnpackedArchive {
+    dir: PathBuf,
+}
+
+impl UnpackedArchive {
+    pub fn new(archive: &Path) -> Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "bdat-archive-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Created first, so the directory is removed if unpacking fails
+        std::fs::create_dir_all(&dir)?;
+        let unpacked = Self { dir };
+        ZipReader::new(&std::fs::read(archive)?)?.unpack(&unpacked.dir)?;
+        Ok(unpacked)
+    }
+
+    /// Lists every file in the archive, with its path in the temporary directory.
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl Drop for UnpackedArchive {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Joins a file name from an archive to `dir`, making sure it stays inside `dir`.
+fn safe_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    let mut path = dir.to_path_buf();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(c) => path.push(c),
+            Component::CurDir => {}
+            _ => return Err(invalid(&format!("invalid file name {name} in ZIP archive"))),
+        }
+    }
+    Ok(path)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(pos..pos + 2)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(pos..pos + 4)?.try_into().unwrap(),
+    ))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn truncated() -> Error {
+    invalid("ZIP archive is truncated")
+}
+
+fn to_u32(n: usize) -> Result<u32> {
+    u32::try_from(n).map_err(|_| Error::other("ZIP archive too large"))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0xcbf43926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn read_written() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add_file("a.json", b"{}").unwrap();
+        zip.add_file("dir/b.csv", b"x,y").unwrap();
+        let data = zip.finish().unwrap();
+
+        let reader = ZipReader::new(&data).unwrap();
+        assert_eq!(
+            vec!["a.json", "dir/b.csv"],
+            reader.names().collect::<Vec<_>>()
+        );
+        assert_eq!(b"x,y", reader.read(1).unwrap().as_slice());
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        let dir = Path::new("out");
+        assert_eq!(Path::new("out/a/b"), safe_path(dir, "a/./b").unwrap());
+        assert!(safe_path(dir, "../a").is_err());
+        assert!(safe_path(dir, "/etc/a").is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! XLSX export: one workbook per BDAT file, with a sheet per table.
+//!
+//! Columns that reference other tables (see [`ColumnRef`]) become hyperlinks to the
+//! referenced row, if the referenced table is in the same workbook.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::Write;
+
+use anyhow::Result;
+use bdat::{Cell, Label, Table, TableAccessor, Value};
+
+use crate::expr::numeric_value;
+use crate::refs::{ColumnRef, RefTarget};
+use crate::schema::{AsFileName, FileSchema};
+use crate::util::zip::ZipWriter;
+
+const MAX_SHEET_NAME_LEN: usize = 31;
+
+const CONTENT_TYPES_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+/// A hyperlink from a cell to a row in another sheet.
+struct Link {
+    cell: String,
+    sheet: usize,
+    row: usize,
+    display: String,
+}
+
+/// Writes a workbook with a sheet for each table.
+///
+/// `tables` should be the original tables, as references are resolved using their values.
+/// `display_tables` are the tables that get written (e.g. with enum names applied), in the
+/// same order.
+pub fn write_workbook(
+    tables: &[Table],
+    display_tables: &[Table],
+    schema: &FileSchema,
+    writer: impl Write,
+) -> Result<()> {
+    let sheet_names = sheet_names(tables.iter().map(|t| t.name()));
+    let mut zip = ZipWriter::new(writer);
+
+    let mut content_types = CONTENT_TYPES_HEADER.to_string();
+    let mut workbook = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+    );
+    let mut workbook_rels = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    );
+    for (i, name) in sheet_names.iter().enumerate() {
+        let n = i + 1;
+        write!(
+            content_types,
+            r#"<Override PartName="/xl/worksheets/sheet{n}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+        )?;
+        write!(
+            workbook,
+            r#"<sheet name="{}" sheetId="{n}" r:id="rId{n}"/>"#,
+            escape(name)
+        )?;
+        write!(
+            workbook_rels,
+            r#"<Relationship Id="rId{n}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{n}.xml"/>"#
+        )?;
+    }
+    content_types.push_str("</Types>");
+    workbook.push_str("</sheets></workbook>");
+    workbook_rels.push_str("</Relationships>");
+
+    zip.add_file("[Content_Types].xml", content_types.as_bytes())?;
+    zip.add_file("_rels/.rels", ROOT_RELS.as_bytes())?;
+    zip.add_file("xl/workbook.xml", workbook.as_bytes())?;
+    zip.add_file("xl/_rels/workbook.xml.rels", workbook_rels.as_bytes())?;
+
+    for (i, (table, display)) in tables.iter().zip(display_tables).enumerate() {
+        let links = find_links(table, tables, schema);
+        let sheet = write_sheet(display, &links, &sheet_names)?;
+        zip.add_file(
+            &format!("xl/worksheets/sheet{}.xml", i + 1),
+            sheet.as_bytes(),
+        )?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn write_sheet(table: &Table, links: &[Link], sheet_names: &[String]) -> Result<String> {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData><row r="1">"#,
+    );
+    write_string_cell(&mut xml, &cell_ref(0, 0), "$id")?;
+    for (col, column) in table.columns().enumerate() {
+        write_string_cell(&mut xml, &cell_ref(col + 1, 0), &column.label().to_string())?;
+    }
+    xml.push_str("</row>");
+
+    for (i, row) in table.rows().enumerate() {
+        write!(xml, r#"<row r="{}">"#, i + 2)?;
+        write_number_cell(&mut xml, &cell_ref(0, i + 1), row.id() as f64)?;
+        for (col, cell) in row.cells().enumerate() {
+            let r = cell_ref(col + 1, i + 1);
+            match cell {
+                Cell::Single(v @ (Value::HashRef(_) | Value::Percent(_))) => {
+                    write_string_cell(&mut xml, &r, &v.to_string())?
+                }
+                Cell::Single(v) => match numeric_value(v).filter(|n| n.is_finite()) {
+                    Some(n) => write_number_cell(&mut xml, &r, n)?,
+                    None => write_string_cell(&mut xml, &r, &v.to_string())?,
+                },
+                c => write_string_cell(&mut xml, &r, &c.to_string())?,
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData>");
+
+    if !links.is_empty() {
+        xml.push_str("<hyperlinks>");
+        for link in links {
+            let location = format!(
+                "'{}'!A{}",
+                sheet_names[link.sheet].replace('\'', "''"),
+                link.row
+            );
+            write!(
+                xml,
+                r#"<hyperlink ref="{}" location="{}" display="{}"/>"#,
+                link.cell,
+                escape(&location),
+                escape(&link.display)
+            )?;
+        }
+        xml.push_str("</hyperlinks>");
+    }
+    xml.push_str("</worksheet>");
+    Ok(xml)
+}
+
+/// Finds the cells of a table that reference rows in other tables of the workbook.
+fn find_links(table: &Table, tables: &[Table], schema: &FileSchema) -> Vec<Link> {
+    let Some(refs) = schema.table_refs(table.name()) else {
+        return Vec::new();
+    };
+    let is_label = |name: &str, label: &Label| schema.is_label(name, label);
+    let mut links = Vec::new();
+    for (col, column) in table.columns().enumerate() {
+        let Some((_, col_ref)) = refs.iter().find(|(name, _)| is_label(name, column.label()))
+        else {
+            continue;
+        };
+        let Some(sheet) = tables
+            .iter()
+            .position(|t| is_label(&col_ref.table, t.name()))
+        else {
+            continue;
+        };
+        let target = &tables[sheet];
+        // Sheet rows follow the table's row order, which isn't tied to the IDs
+        let positions: HashMap<usize, usize> = target
+            .rows()
+            .enumerate()
+            .map(|(i, row)| (row.id(), i))
+            .collect();
+        for (i, row) in table.rows().enumerate() {
+            let RefTarget::Row(target_row) =
+                ColumnRef::resolve(target, row.cells().nth(col).unwrap())
+            else {
+                continue;
+            };
+            let Some(position) = positions.get(&target_row.id()) else {
+                continue;
+            };
+            links.push(Link {
+                cell: cell_ref(col + 1, i + 1),
+                sheet,
+                // Header row + 1-based rows
+                row: position + 2,
+                display: col_ref.describe(&target_row, is_label),
+            });
+        }
+    }
+    links
+}
+
+/// Builds unique sheet names from table names, following Excel's rules: at most 31
+/// characters, and none of `[]:*?/\`.
+fn sheet_names<'a>(names: impl Iterator<Item = &'a Label>) -> Vec<String> {
+    let mut used = HashSet::new();
+    names
+        .map(|label| {
+            let base: String = label
+                .as_file_name()
+                .chars()
+                .map(|c| match c {
+                    '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+                    c => c,
+                })
+                .take(MAX_SHEET_NAME_LEN)
+                .collect();
+            let mut name = base.clone();
+            let mut n = 2;
+            while !used.insert(name.to_lowercase()) {
+                let suffix = format!("~{n}");
+                let prefix: String = base
+                    .chars()
+                    .take(MAX_SHEET_NAME_LEN - suffix.len())
+                    .collect();
+                name = format!("{prefix}{suffix}");
+                n += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+fn write_string_cell(xml: &mut String, r: &str, text: &str) -> std::fmt::Result {
+    write!(
+        xml,
+        r#"<c r="{r}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+        escape(text)
+    )
+}
+
+fn write_number_cell(xml: &mut String, r: &str, n: f64) -> std::fmt::Result {
+    write!(xml, r#"<c r="{r}"><v>{n}</v></c>"#)
+}
+
+/// Returns the A1-style reference for a cell. Indices start at 0.
+fn cell_ref(col: usize, row: usize) -> String {
+    let mut letters = Vec::new();
+    let mut col = col + 1;
+    while col > 0 {
+        col -= 1;
+        letters.push(b'A' + (col % 26) as u8);
+        col /= 26;
+    }
+    letters.reverse();
+    format!("{}{}", String::from_utf8(letters).unwrap(), row + 1)
+}
+
+/// Escapes text for XML, dropping characters XML can't represent.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, ColumnDef, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn links_point_to_sheet_rows() {
+        let item = TableBuilder::with_name(Label::String("Item".into()))
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_row(Row::new(
+                10,
+                vec![Cell::Single(Value::String("Sword".into()))],
+            ))
+            .add_row(Row::new(
+                11,
+                vec![Cell::Single(Value::String("Shield".into()))],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let chr = TableBuilder::with_name(Label::String("Chr".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, "Weapon".into()))
+            .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedShort(11))]))
+            .add_row(Row::new(2, vec![Cell::Single(Value::UnsignedShort(10))]))
+            .add_row(Row::new(3, vec![Cell::Single(Value::UnsignedShort(12))]))
+            .build(BdatVersion::LegacySwitch);
+        let schema = FileSchema::read(
+            r#"{
+                "file_name": "test",
+                "version": "LegacySwitch",
+                "format_version": 2,
+                "tables": ["Chr", "Item"],
+                "references": { "Chr": { "Weapon": { "table": "Item", "display": "Name" } } }
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let tables = [chr, item];
+        let links = find_links(&tables[0], &tables, &schema);
+        let links: Vec<_> = links
+            .iter()
+            .map(|l| (l.cell.as_str(), l.sheet, l.row, l.display.as_str()))
+            .collect();
+        // Row 12 doesn't exist, so the third cell has no link
+        assert_eq!(vec![("B2", 1, 3, "Shield"), ("B3", 1, 2, "Sword")], links);
+    }
+
+    #[test]
+    fn cell_refs() {
+        assert_eq!("A1", cell_ref(0, 0));
+        assert_eq!("Z3", cell_ref(25, 2));
+        assert_eq!("AA1", cell_ref(26, 0));
+        assert_eq!("BA10", cell_ref(52, 9));
+    }
+
+    #[test]
+    fn unique_sheet_names() {
+        let long = "A".repeat(40);
+        let labels = [
+            Label::from("Sheet[1]"),
+            Label::from(long.as_str()),
+            Label::from(long.as_str()),
+            Label::Hash(0xdeadbeef),
+        ];
+        let names = sheet_names(labels.iter());
+        assert_eq!("Sheet_1_", names[0]);
+        assert_eq!("A".repeat(31), names[1]);
+        assert_eq!(format!("{}~2", "A".repeat(29)), names[2]);
+        assert_eq!("DEADBEEF", names[3]);
+    }
+}
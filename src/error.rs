@@ -1,5 +1,5 @@
 use crate::table::FormatConvertError;
-use crate::{BdatVersion, DetectError, ValueType, Label};
+use crate::{BdatVersion, DetectError, Label, ValueType};
 use std::num::TryFromIntError;
 use std::str::Utf8Error;
 use thiserror::Error;
@@ -30,14 +30,58 @@ pub enum BdatError {
     FormatConvert(#[from] FormatConvertError),
     #[error("Unsupported cast type for {0:?}")]
     ValueCast(ValueType),
+    #[error(transparent)]
+    ValueConvert(#[from] ValueCastError),
     #[error(
         "Duplicate hash key ({}: {}) in rows {} and {}. Duplicate keys are not allowed in the primary key table.",
         _0.0, _0.1, _0.2, _0.3
     )]
     DuplicateKey(Box<(Label, Label, usize, usize)>),
+    #[error(
+        "Duplicate value ({}: {}) in rows {} and {}. The column's values must be unique.",
+        _0.0, _0.1, _0.2, _0.3
+    )]
+    DuplicateValue(Box<(Label, String, usize, usize)>),
+    #[error(
+        "Row ID {0} out of range: BDAT version {1:?} only supports row IDs up to {}",
+        _1.max_row_id()
+    )]
+    RowIdOverflow(usize, BdatVersion),
+    #[error("Row ID {1} follows row {0}: row IDs must be consecutive and in ascending order")]
+    NonConsecutiveRowId(usize, usize),
+    #[error("Duplicate column {0}: column names must be unique within modern tables")]
+    DuplicateColumn(Label),
+    #[error("Row {} has {} cells, expected {} (one per column)", _0.0, _0.1, _0.2)]
+    CellCount(Box<(usize, usize, usize)>),
+    #[error("Row {} has {} values in list column {}, expected {}", _0.0, _0.1, _0.2, _0.3)]
+    ListLength(Box<(usize, usize, Label, usize)>),
+    #[error("Row {} has {} bytes in raw column {}, expected {}", _0.0, _0.1, _0.2, _0.3)]
+    RawLength(Box<(usize, usize, Label, usize)>),
+    #[error("Raw column {0} has no original type ID")]
+    MissingRawType(Label),
+    #[error("Column {0} not found")]
+    MissingColumn(Label),
+    #[error("Row {} has a list or flags in column {}, expected a single value", _0.0, _0.1)]
+    NotSingleValue(Box<(usize, Label)>),
+    #[error("Hashed name {0}: legacy tables only support plain names")]
+    HashedLegacyName(Label),
+    #[error("{0:?} too large: offset or size {1} does not fit in the format's {2}-bit fields")]
+    OffsetOverflow(Scope, u64, u32),
+}
+
+/// Error returned by the [`TryFrom`] conversions from [`Value`] to primitive types.
+///
+/// [`Value`]: crate::Value
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("cannot convert {found:?} value to {target}")]
+pub struct ValueCastError {
+    /// The type of the value that was being converted
+    pub found: ValueType,
+    /// The name of the requested type
+    pub target: &'static str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Scope {
     Table,
     File,
@@ -0,0 +1,352 @@
+use std::io::Write;
+use std::iter::Once;
+
+use csv::WriterBuilder;
+
+use crate::serde::SerializeCell;
+use crate::{Cell, ColumnDef, FlagDef, RowRef, Table, Value};
+
+use super::ExportError;
+
+/// Options for [`to_csv`].
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    separator: u8,
+    lists: MultiValueEncoding,
+    flags: MultiValueEncoding,
+    untyped: bool,
+    names_with_hashes: bool,
+    row_id: Option<String>,
+    header: bool,
+}
+
+/// How cells with multiple values (legacy lists and flags) are written to CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiValueEncoding {
+    /// Each value gets its own column: `Col[0]`, `Col[1]`, ... for lists, and
+    /// `Col [Flag]` for flags.
+    Columns,
+    /// Values are joined into a single field, using the given separator.
+    ///
+    /// String values that contain the separator can't be told apart when reading
+    /// the field back.
+    Joined(char),
+    /// Values are written as a JSON array in a single field.
+    Json,
+}
+
+/// Utility to `flat_map` multiple iterator types
+enum ColumnIter<E, T: Iterator<Item = E>, T2: Iterator<Item = E>> {
+    Single(Once<E>),
+    Flags(T),
+    Array(T2),
+}
+
+/// Writes a table as CSV.
+///
+/// The first line contains column headers, unless disabled with [`CsvOptions::header`].
+/// Unless the options specify otherwise, each header also includes the column's value type
+/// ID, e.g. `Param {3}`.
+///
+/// By default, flag cells are split into one column per flag, and list cells are written as
+/// JSON arrays. This can be changed with [`CsvOptions::lists`] and [`CsvOptions::flags`].
+///
+/// If [`CsvOptions::row_id`] is set, the row ID is written in an extra first column.
+pub fn to_csv(table: &Table, writer: impl Write, opts: &CsvOptions) -> Result<(), ExportError> {
+    to_csv_rows(table, table.rows(), writer, opts)
+}
+
+/// Like [`to_csv`], but only writes the given rows of the table, e.g. rows that match
+/// a filter.
+pub fn to_csv_rows<'t, 'b: 't>(
+    table: &Table,
+    rows: impl IntoIterator<Item = RowRef<'t, 'b>>,
+    writer: impl Write,
+    opts: &CsvOptions,
+) -> Result<(), ExportError> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(opts.separator)
+        .from_writer(writer);
+
+    let header = opts
+        .row_id
+        .iter()
+        .cloned()
+        .chain(table.columns().flat_map(|c| opts.format_column(c)))
+        .collect::<Vec<_>>();
+
+    if opts.header {
+        writer.serialize(header)?;
+    }
+
+    for row in rows {
+        let serialized_row = row
+            .cell_refs()
+            .flat_map(|cell| opts.format_cell(cell.column(), cell.cell()))
+            .collect::<Vec<_>>();
+        if opts.row_id.is_some() {
+            writer.serialize((row.id(), serialized_row))?;
+        } else {
+            writer.serialize(serialized_row)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+impl CsvOptions {
+    pub const fn new() -> Self {
+        Self {
+            separator: b',',
+            lists: MultiValueEncoding::Json,
+            flags: MultiValueEncoding::Columns,
+            untyped: false,
+            names_with_hashes: false,
+            row_id: None,
+            header: true,
+        }
+    }
+
+    /// Sets the field separator. The default is `,`.
+    ///
+    /// ## Panics
+    /// Panics if the separator is not an ASCII character.
+    pub fn separator(mut self, separator: char) -> Self {
+        assert!(separator.is_ascii(), "CSV separator must be ASCII");
+        self.separator = separator as u8;
+        self
+    }
+
+    /// Sets whether legacy-BDAT lists should be expanded into separate columns.
+    ///
+    /// This is a shorthand for [`CsvOptions::lists`] with [`MultiValueEncoding::Columns`]
+    /// (if `true`) or [`MultiValueEncoding::Json`] (if `false`).
+    pub fn expand_lists(self, expand_lists: bool) -> Self {
+        self.lists(if expand_lists {
+            MultiValueEncoding::Columns
+        } else {
+            MultiValueEncoding::Json
+        })
+    }
+
+    /// Sets how legacy-BDAT list cells are written.
+    ///
+    /// By default, lists are written as a single JSON array.
+    pub fn lists(mut self, encoding: MultiValueEncoding) -> Self {
+        self.lists = encoding;
+        self
+    }
+
+    /// Sets how flag cells are written.
+    ///
+    /// By default, each flag gets its own column.
+    pub fn flags(mut self, encoding: MultiValueEncoding) -> Self {
+        self.flags = encoding;
+        self
+    }
+
+    /// Sets whether value types should be left out of column headers.
+    ///
+    /// By default, types are included.
+    pub fn untyped(mut self, untyped: bool) -> Self {
+        self.untyped = untyped;
+        self
+    }
+
+    /// Sets whether column names that were resolved from a hash should be followed by
+    /// their hash in headers, e.g. `Name (<1A2B3C4D>)`. See
+    /// [`Label::to_string_with_hash`](crate::Label::to_string_with_hash).
+    ///
+    /// By default, only the name is written.
+    pub fn names_with_hashes(mut self, names_with_hashes: bool) -> Self {
+        self.names_with_hashes = names_with_hashes;
+        self
+    }
+
+    /// Sets whether row IDs should be written in an extra first column, and the header
+    /// of that column.
+    ///
+    /// Some spreadsheets need IDs to refer to rows, while others would treat the extra
+    /// column as data. By default, row IDs are not included.
+    ///
+    /// ```
+    /// use bdat::fmt::CsvOptions;
+    ///
+    /// let opts = CsvOptions::new().row_id(Some("$id"));
+    /// assert!(opts.is_row_id_column("$id"));
+    /// ```
+    pub fn row_id(mut self, header: Option<impl Into<String>>) -> Self {
+        self.row_id = header.map(Into::into);
+        self
+    }
+
+    /// Sets whether the header line is written. Rows written without a header can be
+    /// appended to the output of another call with the same options, e.g. to encode a large
+    /// table in chunks.
+    ///
+    /// By default, the header is written.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Returns whether a CSV header names the row ID column, as written by [`to_csv`]
+    /// with the same options.
+    ///
+    /// This can be used by tools that read CSV tables back, to tell the ID column apart
+    /// from table columns.
+    pub fn is_row_id_column(&self, header: &str) -> bool {
+        self.row_id.as_deref() == Some(header.trim())
+    }
+
+    fn format_column<'a>(&'a self, column: &'a ColumnDef) -> impl Iterator<Item = String> + 'a {
+        let label = match self.names_with_hashes {
+            true => column.label().to_string_with_hash(),
+            false => column.label().to_string(),
+        };
+        let iter = {
+            if !column.flags().is_empty() && self.flags == MultiValueEncoding::Columns {
+                ColumnIter::Flags(
+                    column
+                        .flags()
+                        .iter()
+                        .map(move |flag| format!("{label} [{}]", flag.label())),
+                )
+            } else if column.count() > 1 && self.lists == MultiValueEncoding::Columns {
+                ColumnIter::Array((0..column.count()).map(move |i| format!("{label}[{i}]")))
+            } else {
+                ColumnIter::Single(std::iter::once(label))
+            }
+        };
+        let value_type = column.value_type() as u8;
+        iter.map(move |s| {
+            if !self.untyped {
+                format!("{s} {{{}}}", value_type)
+            } else {
+                s
+            }
+        })
+    }
+
+    fn format_cell<'b, 'a: 'b, 't: 'a>(
+        &self,
+        column: &'a ColumnDef,
+        cell: &'b Cell<'t>,
+    ) -> ColumnIter<
+        SerializeCell<'a, 'b, 't>,
+        impl Iterator<Item = SerializeCell<'a, 'b, 't>>,
+        impl Iterator<Item = SerializeCell<'a, 'b, 't>>,
+    > {
+        let single = |text: String| {
+            ColumnIter::Single(std::iter::once(
+                column.owned_cell_serializer(Cell::Single(Value::String(text.into()))),
+            ))
+        };
+        match cell {
+            // Single values: serialize normally
+            c @ Cell::Single(_) => ColumnIter::Single(std::iter::once(column.cell_serializer(c))),
+            Cell::List(values) => match self.lists {
+                // Serialize into multiple columns
+                MultiValueEncoding::Columns => ColumnIter::Array(
+                    values
+                        .iter()
+                        .map(|v| column.owned_cell_serializer(Cell::Single(v.clone()))),
+                ),
+                MultiValueEncoding::Joined(sep) => single(join_values(values, sep)),
+                MultiValueEncoding::Json => single(serde_json::to_string(values).unwrap()),
+            },
+            Cell::Flags(flags) => {
+                let values = flag_values(column, flags);
+                match self.flags {
+                    // Serialize into multiple columns
+                    MultiValueEncoding::Columns => ColumnIter::Flags(values.map(|v| {
+                        column.owned_cell_serializer(Cell::Single(Value::String(
+                            v.to_string().into(),
+                        )))
+                    })),
+                    MultiValueEncoding::Joined(sep) => single(
+                        values
+                            .map(|v| v.to_string())
+                            .collect::<Vec<_>>()
+                            .join(&sep.to_string()),
+                    ),
+                    MultiValueEncoding::Json => {
+                        single(serde_json::to_string(&values.collect::<Vec<_>>()).unwrap())
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl MultiValueEncoding {
+    /// Splits a field written with this encoding back into the text of each value.
+    ///
+    /// This is meant for tools that read CSV tables written by [`to_csv`]. With
+    /// [`MultiValueEncoding::Columns`], values are already in separate fields, so the field
+    /// is returned as-is.
+    ///
+    /// ```
+    /// use bdat::fmt::MultiValueEncoding;
+    ///
+    /// assert_eq!(vec!["1", "2"], MultiValueEncoding::Joined(';').split("1;2").unwrap());
+    /// assert_eq!(vec!["a", "2"], MultiValueEncoding::Json.split(r#"["a", 2]"#).unwrap());
+    /// ```
+    pub fn split(&self, field: &str) -> Result<Vec<String>, serde_json::Error> {
+        Ok(match self {
+            Self::Columns => vec![field.to_string()],
+            Self::Joined(_) if field.is_empty() => Vec::new(),
+            Self::Joined(sep) => field.split(*sep).map(str::to_string).collect(),
+            Self::Json => serde_json::from_str::<Vec<serde_json::Value>>(field)?
+                .into_iter()
+                .map(|v| match v {
+                    serde_json::Value::String(s) => s,
+                    v => v.to_string(),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn join_values(values: &[Value], separator: char) -> String {
+    values
+        .iter()
+        .map(|v| match serde_json::to_value(v) {
+            Ok(serde_json::Value::String(s)) => s,
+            Ok(v) => v.to_string(),
+            Err(_) => v.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(&separator.to_string())
+}
+
+/// Returns the values of a flags cell, with single-bit flags as booleans.
+fn flag_values<'a>(
+    column: &'a ColumnDef,
+    flags: &'a [u32],
+) -> impl Iterator<Item = serde_json::Value> + 'a {
+    flags.iter().enumerate().map(|(i, &value)| {
+        match column.flags().get(i).is_some_and(FlagDef::is_single_bit) {
+            true => (value != 0).into(),
+            false => value.into(),
+        }
+    })
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, T: Iterator<Item = E>, T2: Iterator<Item = E>> Iterator for ColumnIter<E, T, T2> {
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Single(i) => i.next(),
+            Self::Flags(i) => i.next(),
+            Self::Array(i) => i.next(),
+        }
+    }
+}
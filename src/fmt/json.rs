@@ -0,0 +1,696 @@
+//! JSON table format, as used by bdat-toolset.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use serde::de::DeserializeSeed;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Map;
+
+use crate::{
+    BdatVersion, Cell, ColumnBuilder, ColumnDef, FlagDef, Label, RawType, Row, RowRef, Table,
+    TableAccessor, TableBuilder, TextKind, Value, ValueType,
+};
+
+use super::{ExportError, ImportError};
+
+/// The current version of the JSON table format, written to [`JsonTable::format_version`].
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Options for [`to_json`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonOptions {
+    untyped: bool,
+    pretty: bool,
+    hashes: HashFormat,
+    names_with_hashes: bool,
+    version: Option<BdatVersion>,
+}
+
+/// How hashed labels and hash values are written in JSON tables.
+///
+/// All formats are accepted when reading tables back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashFormat {
+    /// Hashes are written as 8 hex digits between angle brackets, e.g. `"<1A2B3C4D>"`.
+    #[default]
+    Bracketed,
+    /// Hashes are written as hex strings with a `0x` prefix, e.g. `"0x1A2B3C4D"`.
+    Hex,
+    /// Hash values are written as decimal integers, e.g. `439041101`.
+    ///
+    /// Column names are JSON object keys, which must be strings, so hashed column names
+    /// are still written as `"<1A2B3C4D>"`.
+    Decimal,
+}
+
+/// The JSON representation of a table.
+///
+/// Besides columns and rows, tables include some metadata, so a single JSON file has
+/// everything needed to convert it back to BDAT. Metadata fields are optional when reading,
+/// as older versions of the format did not have them.
+#[derive(Serialize, Deserialize)]
+pub struct JsonTable {
+    /// The version of the JSON format, see [`FORMAT_VERSION`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_version: Option<u32>,
+    /// The name of the table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The BDAT version of the file the table was in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<BdatVersion>,
+    /// The ID of the first row.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_id: Option<usize>,
+    /// A free-text description of the table, for people reading the file. It is ignored
+    /// when reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Column definitions, required to convert the table back to BDAT.
+    pub schema: Option<Vec<ColumnSchema>>,
+    pub rows: Vec<TableRow>,
+}
+
+/// A row in a [`JsonTable`]. Cells are keyed by column name.
+#[derive(Serialize, Deserialize)]
+pub struct TableRow {
+    #[serde(rename = "$id")]
+    pub id: usize,
+    /// Cells, in column order.
+    ///
+    /// The map keeps insertion order (the crate enables serde_json's `preserve_order`
+    /// feature), so written rows always list cells in the same order as the columns,
+    /// and reading a table keeps the order the cells had in the file.
+    #[serde(flatten)]
+    pub cells: Map<String, serde_json::Value>,
+}
+
+/// A column definition in a [`JsonTable`]'s schema.
+#[derive(Deserialize, Serialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: ValueType,
+    /// Whether the name is hashed in the BDAT file. Defaults to `false` when reading.
+    #[serde(default)]
+    pub hashed: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<FlagDef>,
+    #[serde(default, skip_serializing_if = "col_skip_count")]
+    pub count: usize,
+    /// The kind of text the column holds, from [`ValueType::text_kind`]. Only written for
+    /// tools that need to tell player-facing text apart, it is ignored when reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextKind>,
+    /// The original type ID and cell size of [`ValueType::Raw`] columns, which are needed
+    /// to write them back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<RawType>,
+    /// A free-text description of the column, for people reading the file. It is ignored
+    /// when reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+fn col_skip_count(c: &usize) -> bool {
+    *c <= 1
+}
+
+/// Serializes the same way as [`JsonTable`], but rows are converted while they are
+/// written, so only one converted row is kept in memory at a time.
+#[derive(Serialize)]
+#[serde(bound = "I: Iterator<Item = TableRow>")]
+struct JsonTableStream<I> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<BdatVersion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_id: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    schema: Option<Vec<ColumnSchema>>,
+    #[serde(serialize_with = "serialize_row_stream")]
+    rows: RefCell<Option<I>>,
+}
+
+fn serialize_row_stream<S: Serializer, I: Iterator<Item = TableRow>>(
+    rows: &RefCell<Option<I>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(rows.borrow_mut().take().into_iter().flatten())
+}
+
+/// Returns the keys used for each column in [`TableRow::cells`], given the column names
+/// in the schema.
+///
+/// Keys are column names, except when a table has more than one column with the same name
+/// (some tables in the games do): the second column with a name gets the key `Name#2`,
+/// the third `Name#3`, and so on. Suffixes that are already used, e.g. by a column named
+/// `Name#2`, are skipped, so every key is unique. Readers can use this to match keys to
+/// columns by position.
+///
+/// ```
+/// use bdat::fmt::json::column_keys;
+///
+/// assert_eq!(vec!["A", "B", "A#2"], column_keys(["A", "B", "A"]));
+/// assert_eq!(vec!["A", "A#2", "A#3"], column_keys(["A", "A#2", "A"]));
+/// ```
+pub fn column_keys<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let names: Vec<&str> = names.into_iter().collect();
+    let mut taken: HashSet<String> = names.iter().map(|n| n.to_string()).collect();
+    let mut seen = HashMap::<&str, usize>::new();
+    names
+        .iter()
+        .map(|&name| {
+            let count = seen.entry(name).or_default();
+            *count += 1;
+            if *count == 1 {
+                return name.to_string();
+            }
+            loop {
+                let key = format!("{name}#{count}");
+                if taken.insert(key.clone()) {
+                    return key;
+                }
+                *count += 1;
+            }
+        })
+        .collect()
+}
+
+/// Parses a name from a JSON table, accepting any of the formats hashed names can be
+/// written in (see [`HashFormat`]), including names followed by their hash.
+///
+/// If `hashed` is `true`, plain names are hashed, as in [`Label::parse`]. When the BDAT
+/// version is known, use [`Label::parse_for_version`] instead.
+///
+/// ```
+/// use bdat::fmt::json::parse_label;
+/// use bdat::Label;
+///
+/// assert_eq!(Label::Hash(0xABCD), parse_label("Name (<0000ABCD>)", false));
+/// assert_eq!(Label::Hash(0xABCD), parse_label("0x0000ABCD", false));
+/// assert_eq!(Label::from("Name"), parse_label("Name", false));
+/// ```
+pub fn parse_label(name: &str, hashed: bool) -> Label {
+    if let Some((_, hash)) = Label::split_named_hash(name) {
+        return Label::Hash(hash);
+    }
+    match name.parse() {
+        Ok(label @ Label::Hash(_)) => label,
+        _ => Label::parse(name, hashed),
+    }
+}
+
+/// Writes a table as JSON.
+///
+/// The output is a serialized [`JsonTable`].
+pub fn to_json(table: &Table, writer: impl Write, opts: &JsonOptions) -> Result<(), ExportError> {
+    to_json_rows(table, table.rows(), writer, opts)
+}
+
+/// Like [`to_json`], but only writes the given rows of the table, e.g. rows that match
+/// a filter.
+pub fn to_json_rows<'t, 'b: 't>(
+    table: &Table,
+    rows: impl IntoIterator<Item = RowRef<'t, 'b>>,
+    writer: impl Write,
+    opts: &JsonOptions,
+) -> Result<(), ExportError> {
+    to_json_with_header(
+        JsonTable::from_rows(table, [], opts),
+        table,
+        rows,
+        writer,
+        opts,
+    )
+}
+
+/// Like [`to_json_rows`], but the metadata and column schema are taken from `header`,
+/// e.g. a [`JsonTable::from_rows`] with descriptions added. The rows of `header` are
+/// ignored.
+pub fn to_json_with_header<'t, 'b: 't>(
+    header: JsonTable,
+    table: &Table,
+    rows: impl IntoIterator<Item = RowRef<'t, 'b>>,
+    writer: impl Write,
+    opts: &JsonOptions,
+) -> Result<(), ExportError> {
+    let keys = JsonTable::row_keys(table, opts);
+    let JsonTable {
+        format_version,
+        name,
+        version,
+        base_id,
+        description,
+        schema,
+        ..
+    } = header;
+    let json = JsonTableStream {
+        format_version,
+        name,
+        version,
+        base_id,
+        description,
+        schema,
+        rows: RefCell::new(Some(
+            rows.into_iter()
+                .map(|row| JsonTable::convert_row(row, &keys, opts)),
+        )),
+    };
+    if opts.pretty {
+        serde_json::to_writer_pretty(writer, &json)
+    } else {
+        serde_json::to_writer(writer, &json)
+    }?;
+    Ok(())
+}
+
+impl<'b> Table<'b> {
+    /// Converts the table to its JSON representation, the same as [`to_json`] writes.
+    pub fn to_json_value(&self, opts: &JsonOptions) -> serde_json::Value {
+        serde_json::to_value(JsonTable::from_table(self, opts))
+            .expect("JSON tables always serialize")
+    }
+}
+
+impl Table<'static> {
+    /// Reads a table from its JSON representation, as written by [`Table::to_json_value`]
+    /// or [`to_json`].
+    ///
+    /// `version` is only used if the table's metadata doesn't include one, which is the
+    /// case for legacy tables unless [`JsonOptions::version`] was set.
+    ///
+    /// ```
+    /// use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, Table, TableBuilder, Value, ValueType};
+    /// use bdat::fmt::JsonOptions;
+    ///
+    /// let table = TableBuilder::with_name(Label::from("Table1"))
+    ///     .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::from("Param")))
+    ///     .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedInt(10))]))
+    ///     .build(BdatVersion::LegacySwitch);
+    ///
+    /// let json = table.to_json_value(&JsonOptions::new());
+    /// let read = Table::from_json_value(json, Some(BdatVersion::LegacySwitch)).unwrap();
+    /// assert_eq!(table, read);
+    /// ```
+    pub fn from_json_value(
+        value: serde_json::Value,
+        version: Option<BdatVersion>,
+    ) -> Result<Self, ImportError> {
+        serde_json::from_value::<JsonTable>(value)?.into_table(version)
+    }
+}
+
+impl ColumnSchema {
+    /// Returns the column's label, see [`parse_label`].
+    pub fn label(&self) -> Label {
+        parse_label(&self.name, self.hashed)
+    }
+
+    /// Converts the schema entry to a column definition with the given label.
+    pub fn into_column(self, label: Label) -> ColumnDef {
+        let mut column = ColumnBuilder::new(self.ty, label)
+            .set_flags(self.flags)
+            .set_count(self.count.max(1));
+        if let Some(raw) = self.raw {
+            column = column.set_raw_type(raw);
+        }
+        column.build()
+    }
+}
+
+impl JsonTable {
+    /// Converts the JSON representation back to a table. See [`Table::from_json_value`].
+    pub fn into_table(self, version: Option<BdatVersion>) -> Result<Table<'static>, ImportError> {
+        let version = self
+            .version
+            .or(version)
+            .ok_or(ImportError::MissingMetadata("version"))?;
+        let name = self
+            .name
+            .as_deref()
+            .map(|name| Label::parse_for_version(name, version))
+            .ok_or(ImportError::MissingMetadata("name"))?;
+        Ok(self
+            .into_builder(name, ColumnSchema::label)?
+            .try_build(version)?)
+    }
+
+    /// Like [`JsonTable::into_table`], but returns a builder with the table's columns and
+    /// rows, so more checks can be run before the table is built. Column labels are given
+    /// by `label`, e.g. [`ColumnSchema::label`].
+    pub fn into_builder(
+        self,
+        name: Label,
+        mut label: impl FnMut(&ColumnSchema) -> Label,
+    ) -> Result<TableBuilder<'static>, ImportError> {
+        let schema = self.schema.ok_or(ImportError::MissingSchema)?;
+
+        // Rows use column names as keys, with an index suffix for duplicate names. There's a
+        // table in XC2 (likely more) with a duplicate column (FLD_RequestItemSet)
+        let keys = column_keys(schema.iter().map(|c| c.name.as_str()));
+        let mut column_map = HashMap::with_capacity(schema.len());
+        let mut first_index = HashMap::with_capacity(schema.len());
+        let mut duplicate_of = Vec::with_capacity(schema.len());
+        let mut columns = Vec::with_capacity(schema.len());
+        for (idx, (col, key)) in schema.into_iter().zip(keys).enumerate() {
+            duplicate_of.push(first_index.get(&col.name).copied());
+            first_index.entry(col.name.clone()).or_insert(idx);
+            column_map.insert(key, idx);
+            let label = label(&col);
+            columns.push(col.into_column(label));
+        }
+
+        let rows = self
+            .rows
+            .into_iter()
+            .map(|r| {
+                let id = r.id;
+                let mut cells: Vec<Option<Cell>> = vec![None; columns.len()];
+                for (k, v) in r.cells {
+                    let index = *column_map
+                        .get(&k)
+                        .ok_or_else(|| ImportError::UnknownColumn(Box::new((id, k.clone()))))?;
+                    let cell = columns[index]
+                        .as_cell_seed()
+                        .deserialize(v)
+                        .map_err(|e| ImportError::InvalidValue(Box::new((id, k, e))))?;
+                    cells[index] = Some(cell);
+                }
+                // Tables extracted before duplicate columns had their own keys only have
+                // a value for the first column with a given name
+                for (idx, first) in duplicate_of.iter().enumerate() {
+                    if let (None, Some(first)) = (&cells[idx], first) {
+                        if columns[idx].value_type() == columns[*first].value_type() {
+                            cells[idx] = cells[*first].clone();
+                        }
+                    }
+                }
+                let old_len = cells.len();
+                let cells: Vec<Cell> = cells.into_iter().flatten().collect();
+                if cells.len() != old_len {
+                    return Err(ImportError::IncompleteRow(id));
+                }
+                Ok(Row::new(id, cells))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Building the table panics on unordered IDs
+        if let Some(w) = rows.windows(2).find(|w| w[1].id() != w[0].id() + 1) {
+            return Err(ImportError::NonConsecutiveRow(w[1].id(), w[0].id() + 1));
+        }
+        let mut builder = TableBuilder::with_name(name)
+            .set_columns(columns)
+            .set_rows(rows);
+        // Only needed for empty tables, otherwise the base ID comes from the first row
+        if let Some(base_id) = self.base_id {
+            builder = builder.set_base_id(base_id);
+        }
+        Ok(builder)
+    }
+
+    /// Converts a table to its JSON representation.
+    pub fn from_table(table: &Table, opts: &JsonOptions) -> Self {
+        Self::from_rows(table, table.rows(), opts)
+    }
+
+    /// Converts some of a table's rows to their JSON representation.
+    pub fn from_rows<'t, 'b: 't>(
+        table: &Table,
+        rows: impl IntoIterator<Item = RowRef<'t, 'b>>,
+        opts: &JsonOptions,
+    ) -> Self {
+        let schema = (!opts.untyped).then(|| {
+            table
+                .columns()
+                .map(|c| ColumnSchema {
+                    name: opts.format_label(c.label()),
+                    ty: c.value_type(),
+                    hashed: matches!(c.label(), Label::Unhashed(_)),
+                    flags: c.flags().to_vec(),
+                    count: c.count(),
+                    text: c.value_type().text_kind(),
+                    raw: c.raw_type(),
+                    description: None,
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let keys = Self::row_keys(table, opts);
+        let rows = rows
+            .into_iter()
+            .map(|row| Self::convert_row(row, &keys, opts))
+            .collect::<Vec<_>>();
+
+        Self {
+            format_version: Some(FORMAT_VERSION),
+            name: Some(opts.format_label(table.name())),
+            version: opts
+                .version
+                .or_else(|| table.is_modern().then_some(BdatVersion::Modern)),
+            base_id: Some(table.base_id()),
+            description: None,
+            schema,
+            rows,
+        }
+    }
+
+    fn row_keys(table: &Table, opts: &JsonOptions) -> Vec<String> {
+        let names = table
+            .columns()
+            .map(|c| opts.format_label(c.label()))
+            .collect::<Vec<_>>();
+        column_keys(names.iter().map(String::as_str))
+    }
+
+    fn convert_row(row: RowRef, keys: &[String], opts: &JsonOptions) -> TableRow {
+        let cells = row
+            .cell_refs()
+            .zip(keys)
+            .map(|(cell, key)| {
+                let value = match cell.cell() {
+                    Cell::Single(Value::HashRef(h)) => opts.hashes.format_value(*h),
+                    c => serde_json::to_value(cell.column().cell_serializer(c)).unwrap(),
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        TableRow {
+            id: row.id(),
+            cells,
+        }
+    }
+}
+
+impl JsonOptions {
+    pub const fn new() -> Self {
+        Self {
+            untyped: false,
+            pretty: false,
+            hashes: HashFormat::Bracketed,
+            names_with_hashes: false,
+            version: None,
+        }
+    }
+
+    /// Sets whether the column schema should be left out.
+    ///
+    /// Untyped tables can't be converted back to BDAT. By default, the schema is included.
+    pub fn untyped(mut self, untyped: bool) -> Self {
+        self.untyped = untyped;
+        self
+    }
+
+    /// Sets whether the output should include spaces and newlines to improve readability.
+    ///
+    /// By default, the output is compact.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Sets the BDAT version written in the table metadata.
+    ///
+    /// Tables don't know which legacy version they come from, so the version is only
+    /// included for modern tables unless it is set here.
+    pub fn version(mut self, version: BdatVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets how hashed labels and hash values are written.
+    ///
+    /// By default, hashes are written as `"<1A2B3C4D>"`.
+    pub fn hashes(mut self, format: HashFormat) -> Self {
+        self.hashes = format;
+        self
+    }
+
+    /// Sets whether names that were resolved from a hash ([`Label::Unhashed`]) should be
+    /// followed by their hash, e.g. `"Name (<1A2B3C4D>)"`.
+    ///
+    /// This keeps tables usable if the names change later, as the hash can still be read
+    /// back with [`Label::split_named_hash`]. By default, only the name is written.
+    pub fn names_with_hashes(mut self, names_with_hashes: bool) -> Self {
+        self.names_with_hashes = names_with_hashes;
+        self
+    }
+
+    fn format_label(&self, label: &Label) -> String {
+        match label {
+            Label::Unhashed(name) if self.names_with_hashes => self
+                .hashes
+                .format_named(name, crate::hash::murmur3_str(name)),
+            l => self.hashes.format_label(l),
+        }
+    }
+}
+
+impl HashFormat {
+    /// Formats a label as a JSON key or column name.
+    pub fn format_label(&self, label: &Label) -> String {
+        match (self, label) {
+            (Self::Hex, Label::Hash(h)) => format!("0x{h:08X}"),
+            (_, l) => l.to_string(),
+        }
+    }
+
+    /// Formats a hash value.
+    pub fn format_value(&self, hash: u32) -> serde_json::Value {
+        match self {
+            Self::Bracketed => Label::Hash(hash).to_string().into(),
+            Self::Hex => format!("0x{hash:08X}").into(),
+            Self::Decimal => hash.into(),
+        }
+    }
+
+    /// Formats a name followed by its hash, e.g. `"Name (<1A2B3C4D>)"`.
+    ///
+    /// Names are always text, so [`HashFormat::Decimal`] writes the hash as `<1A2B3C4D>`.
+    pub fn format_named(&self, name: &str, hash: u32) -> String {
+        format!("{name} ({})", self.format_label(&Label::Hash(hash)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BdatVersion, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn row_key_order() {
+        let names = ["Zeta", "Alpha", "$Mid", "<0000BEEF>"];
+        let mut builder = TableBuilder::with_name(Label::from("Table"));
+        for name in names {
+            builder = builder.add_column(ColumnDef::new(
+                ValueType::UnsignedByte,
+                Label::parse(name, false),
+            ));
+        }
+        let table = builder
+            .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedByte(1)); 4]))
+            .add_row(Row::new(2, vec![Cell::Single(Value::UnsignedByte(2)); 4]))
+            .build(BdatVersion::LegacySwitch);
+
+        let json = JsonTable::from_table(&table, &JsonOptions::new());
+        for row in &json.rows {
+            assert!(row.cells.keys().eq(names));
+        }
+
+        // Order is kept through a round trip
+        let text = serde_json::to_string(&json).unwrap();
+        let read: JsonTable = serde_json::from_str(&text).unwrap();
+        assert!(read.rows[1].cells.keys().eq(names));
+    }
+
+    #[test]
+    fn duplicate_column_keys() {
+        let table = TableBuilder::with_name(Label::from("Table"))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, "A".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, "B".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, "A".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::UnsignedByte(1)),
+                    Cell::Single(Value::UnsignedByte(2)),
+                    Cell::Single(Value::UnsignedByte(3)),
+                ],
+            ))
+            .build(BdatVersion::LegacySwitch);
+
+        let json = JsonTable::from_table(&table, &JsonOptions::new());
+        let schema = json.schema.unwrap();
+        assert_eq!("A", schema[2].name);
+        assert_eq!(
+            column_keys(schema.iter().map(|c| c.name.as_str())),
+            json.rows[0].cells.keys().cloned().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&3.into()), json.rows[0].cells.get("A#2"));
+
+        // A column named like a generated key keeps its own values
+        let table = TableBuilder::with_name(Label::from("Table"))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, "A".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, "A#2".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, "A".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::UnsignedByte(1)),
+                    Cell::Single(Value::UnsignedByte(2)),
+                    Cell::Single(Value::UnsignedByte(3)),
+                ],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let json = JsonTable::from_table(&table, &JsonOptions::new());
+        assert_eq!(3, json.rows[0].cells.len());
+        assert_eq!(
+            table,
+            json.into_table(Some(BdatVersion::LegacySwitch)).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_value_errors() {
+        let read = |rows: serde_json::Value| {
+            Table::from_json_value(
+                serde_json::json!({
+                    "name": "Table",
+                    "version": "Modern",
+                    "schema": [{"name": "A", "type": 1, "hashed": true}],
+                    "rows": rows,
+                }),
+                None,
+            )
+        };
+
+        let table = read(serde_json::json!([{"$id": 1, "A": 5}])).unwrap();
+        assert_eq!(
+            Label::Hash(crate::hash::murmur3_str("Table")),
+            *table.name()
+        );
+        assert!(matches!(
+            read(serde_json::json!([{"$id": 1, "B": 5}])),
+            Err(ImportError::UnknownColumn(_))
+        ));
+        assert!(matches!(
+            read(serde_json::json!([{"$id": 1}])),
+            Err(ImportError::IncompleteRow(1))
+        ));
+        assert!(matches!(
+            read(serde_json::json!([{"$id": 1, "A": 5}, {"$id": 3, "A": 5}])),
+            Err(ImportError::NonConsecutiveRow(3, 2))
+        ));
+        assert!(matches!(
+            read(serde_json::json!([{"$id": 1, "A": "x"}])),
+            Err(ImportError::InvalidValue(_))
+        ));
+    }
+}
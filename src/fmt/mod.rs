@@ -0,0 +1,81 @@
+//! Text export helpers (requires feature `csv` and/or `json`)
+//!
+//! These are the same conversions used by bdat-toolset when extracting tables, so other
+//! tools can produce identical output without depending on the CLI.
+//!
+//! ```
+//! # #[cfg(all(feature = "csv", feature = "json"))]
+//! # fn main() -> Result<(), bdat::fmt::ExportError> {
+//! use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType};
+//! use bdat::fmt::{CsvOptions, JsonOptions};
+//!
+//! let table = TableBuilder::with_name(Label::from("Table1"))
+//!     .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::from("Param")))
+//!     .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedInt(10))]))
+//!     .build(BdatVersion::LegacySwitch);
+//!
+//! let mut csv = Vec::new();
+//! bdat::fmt::to_csv(&table, &mut csv, &CsvOptions::new().untyped(true))?;
+//! assert_eq!("Param\n10\n", String::from_utf8(csv).unwrap());
+//!
+//! let mut json = Vec::new();
+//! bdat::fmt::to_json(&table, &mut json, &JsonOptions::new().untyped(true))?;
+//! assert_eq!(
+//!     r#"{"format_version":1,"name":"Table1","base_id":1,"schema":null,"rows":[{"$id":1,"Param":10}]}"#,
+//!     String::from_utf8(json).unwrap()
+//! );
+//! # Ok(())
+//! # }
+//! # #[cfg(not(all(feature = "csv", feature = "json")))]
+//! # fn main() {}
+//! ```
+
+use thiserror::Error;
+
+#[cfg(feature = "csv")]
+mod csv;
+#[cfg(feature = "json")]
+pub mod json;
+
+#[cfg(feature = "csv")]
+pub use self::csv::{to_csv, to_csv_rows, CsvOptions, MultiValueEncoding};
+#[cfg(feature = "json")]
+pub use self::json::{to_json, to_json_rows, to_json_with_header, HashFormat, JsonOptions};
+
+#[cfg(feature = "json")]
+use crate::BdatError;
+
+/// An error encountered while exporting a table to a text format.
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "csv")]
+    #[error("CSV error: {0}")]
+    Csv(#[from] ::csv::Error),
+    #[cfg(feature = "json")]
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// An error encountered while reading a table back from its JSON representation.
+#[cfg(feature = "json")]
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Missing type information, the table was exported without a schema")]
+    MissingSchema,
+    #[error("Table has no {0} metadata")]
+    MissingMetadata(&'static str),
+    #[error("Row {} has a value for column {}, which is not in the schema", _0.0, _0.1)]
+    UnknownColumn(Box<(usize, String)>),
+    #[error("Invalid value in row {}, column {}: {}", _0.0, _0.1, _0.2)]
+    InvalidValue(Box<(usize, String, serde_json::Error)>),
+    #[error("Row {0} does not have entries for all columns")]
+    IncompleteRow(usize),
+    #[error("Row ID {0} doesn't follow the previous row, expected {1}")]
+    NonConsecutiveRow(usize, usize),
+    #[error(transparent)]
+    Bdat(#[from] BdatError),
+}
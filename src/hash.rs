@@ -43,7 +43,7 @@ mod table {
 /// ## Behavior
 /// * If a string literal is passed in, the result will be `const`-evaluated.
 /// * If an expression is passed in, the value is hashed and stored in the label. The expression's
-/// value must implement `Borrow<str>`.
+///   value must implement `Borrow<str>`.
 ///
 /// [`Label`]: crate::Label
 #[macro_export]
@@ -59,6 +59,72 @@ macro_rules! label_hash {
     }};
 }
 
+/// Creates a hashed [`Label`] from a string literal, evaluated at compile time.
+///
+/// Plain names are murmur3-hashed, like [`label_hash!`]. Hashes written in the forms the
+/// toolset prints, i.e. `<01ABCDEF>` or `0x1ABCDEF`, are kept as they are, so labels can be
+/// copied from extracted data without being hashed twice.
+///
+/// ```
+/// use bdat::{label, label_hash, Label};
+///
+/// const CHR_PC: Label = label!("CHR_PC");
+/// assert_eq!(label_hash!("CHR_PC"), CHR_PC);
+/// assert_eq!(Label::Hash(0xDEADBEEF), label!("<DEADBEEF>"));
+/// assert_eq!(Label::Hash(0xBEEF), label!("0xBEEF"));
+/// ```
+///
+/// An invalid hash fails to compile:
+/// ```compile_fail
+/// const LABEL: bdat::Label = bdat::label!("<NOTAHASH>");
+/// ```
+///
+/// [`Label`]: crate::Label
+#[macro_export]
+macro_rules! label {
+    ($text:literal) => {{
+        const LABEL: $crate::Label = $crate::Label::Hash($crate::hash::const_label_hash($text));
+        LABEL
+    }};
+}
+
+/// Const-evaluable label parser used by [`label!`]: parses `<01ABCDEF>` and `0x1ABCDEF`
+/// hashes, and hashes everything else.
+///
+/// ## Panics
+/// Panics if the text looks like a hash but isn't a valid one. In a `const` context,
+/// this is a compile error.
+#[doc(hidden)]
+pub const fn const_label_hash(text: &str) -> u32 {
+    let bytes = text.as_bytes();
+    let len = bytes.len();
+    if len > 0 && bytes[0] == b'<' && bytes[len - 1] == b'>' {
+        assert!(len == 10, "hash labels must have exactly 8 hex digits");
+        return const_parse_hex(bytes, 1, len - 1);
+    }
+    if len > 2 && bytes[0] == b'0' && (bytes[1] == b'x' || bytes[1] == b'X') {
+        assert!(len <= 10, "hash labels have at most 8 hex digits");
+        return const_parse_hex(bytes, 2, len);
+    }
+    murmur3(bytes)
+}
+
+const fn const_parse_hex(bytes: &[u8], start: usize, end: usize) -> u32 {
+    let mut result = 0u32;
+    let mut i = start;
+    while i < end {
+        let digit = match bytes[i] {
+            b @ b'0'..=b'9' => b - b'0',
+            b @ b'a'..=b'f' => b - b'a' + 10,
+            b @ b'A'..=b'F' => b - b'A' + 10,
+            _ => panic!("invalid hex digit in hash label"),
+        };
+        result = (result << 4) | digit as u32;
+        i += 1;
+    }
+    result
+}
+
 // MIT-licensed const version of murmur3, adapted from
 // https://github.com/Reboare/const-murmur3
 pub const fn murmur3(data: &[u8]) -> u32 {
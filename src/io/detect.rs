@@ -230,17 +230,11 @@ fn detect_version<R: Read + Seek>(mut reader: R) -> Result<BdatVersion> {
 impl<'b, R: Read + Seek> BdatFile<'b> for VersionReader<R> {
     type TableOut = Table<'b>;
 
-    fn get_tables(&mut self) -> crate::error::Result<Vec<Table<'b>>> {
+    fn get_table(&mut self, index: usize) -> crate::error::Result<Table<'b>> {
         match self {
-            Self::LegacySwitch(r) => r
-                .get_tables()
-                .map(|v| v.into_iter().map(Into::into).collect()),
-            Self::LegacyWii(r) => r
-                .get_tables()
-                .map(|v| v.into_iter().map(Into::into).collect()),
-            Self::Modern(r) => r
-                .get_tables()
-                .map(|v| v.into_iter().map(Into::into).collect()),
+            Self::LegacySwitch(r) => r.get_table(index).map(Into::into),
+            Self::LegacyWii(r) => r.get_table(index).map(Into::into),
+            Self::Modern(r) => r.get_table(index).map(Into::into),
         }
     }
 
@@ -251,22 +245,24 @@ impl<'b, R: Read + Seek> BdatFile<'b> for VersionReader<R> {
             Self::Modern(r) => r.table_count(),
         }
     }
+
+    fn take_diagnostics(&mut self) -> Vec<crate::Diagnostic> {
+        match self {
+            Self::LegacySwitch(r) => r.take_diagnostics(),
+            Self::LegacyWii(r) => r.take_diagnostics(),
+            Self::Modern(r) => r.take_diagnostics(),
+        }
+    }
 }
 
 impl<'b> BdatFile<'b> for VersionSlice<'b> {
     type TableOut = Table<'b>;
 
-    fn get_tables(&mut self) -> crate::error::Result<Vec<Table<'b>>> {
+    fn get_table(&mut self, index: usize) -> crate::error::Result<Table<'b>> {
         match self {
-            Self::LegacySwitch(r) => r
-                .get_tables()
-                .map(|v| v.into_iter().map(Into::into).collect()),
-            Self::LegacyWii(r) => r
-                .get_tables()
-                .map(|v| v.into_iter().map(Into::into).collect()),
-            Self::Modern(r) => r
-                .get_tables()
-                .map(|v| v.into_iter().map(Into::into).collect()),
+            Self::LegacySwitch(r) => r.get_table(index).map(Into::into),
+            Self::LegacyWii(r) => r.get_table(index).map(Into::into),
+            Self::Modern(r) => r.get_table(index).map(Into::into),
         }
     }
 
@@ -277,4 +273,12 @@ impl<'b> BdatFile<'b> for VersionSlice<'b> {
             Self::Modern(r) => r.table_count(),
         }
     }
+
+    fn take_diagnostics(&mut self) -> Vec<crate::Diagnostic> {
+        match self {
+            Self::LegacySwitch(r) => r.take_diagnostics(),
+            Self::LegacyWii(r) => r.take_diagnostics(),
+            Self::Modern(r) => r.take_diagnostics(),
+        }
+    }
 }
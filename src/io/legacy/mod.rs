@@ -11,13 +11,13 @@ mod write;
 use byteorder::ByteOrder;
 use scramble::ScrambleType;
 use std::borrow::Borrow;
-use std::io::{Cursor, Read, Seek, Write};
+use std::io::{Read, Seek, Write};
 use std::ops::Range;
 
 use crate::error::Result;
 use crate::legacy::read::{LegacyBytes, LegacyReader};
 use crate::legacy::write::FileWriter;
-use crate::{BdatVersion, LegacyTable};
+use crate::{BdatVersion, LegacyTable, Progress};
 
 pub(super) const HEADER_SIZE: usize = 64;
 pub(super) const HEADER_SIZE_WII: usize = 32;
@@ -33,7 +33,7 @@ pub struct LegacyWriteOptions {
     pub(crate) scramble_key: Option<u16>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FileHeader {
     pub table_count: usize,
     file_size: usize,
@@ -50,6 +50,8 @@ pub struct TableHeader {
     row_count: usize,
     row_len: usize,
     base_id: usize,
+    /// The value at 0x14, which is 2 in every known file
+    unknown_constant: u16,
     columns: Option<ColumnNodeInfo>,
 }
 
@@ -179,8 +181,7 @@ pub fn from_bytes_copy<E: ByteOrder>(
     LegacyBytes::new_copy(bytes, version)
 }
 
-/// Writes legacy BDAT tables to a [`std::io::Write`] implementation
-/// that also implements [`std::io::Seek`].
+/// Writes legacy BDAT tables to a [`std::io::Write`] implementation.
 ///
 /// ```
 /// use std::fs::File;
@@ -193,7 +194,7 @@ pub fn from_bytes_copy<E: ByteOrder>(
 ///     Ok(())
 /// }
 /// ```
-pub fn to_writer<'t, W: Write + Seek, E: ByteOrder + 'static>(
+pub fn to_writer<'t, W: Write, E: ByteOrder + 'static>(
     writer: W,
     tables: impl IntoIterator<Item = impl Borrow<LegacyTable<'t>>>,
     version: BdatVersion,
@@ -201,8 +202,7 @@ pub fn to_writer<'t, W: Write + Seek, E: ByteOrder + 'static>(
     to_writer_options::<W, E>(writer, tables, version, LegacyWriteOptions::new())
 }
 
-/// Writes legacy BDAT tables to a [`std::io::Write`] implementation
-/// that also implements [`std::io::Seek`].
+/// Writes legacy BDAT tables to a [`std::io::Write`] implementation.
 ///
 /// This function also allows customization of a few write options, using
 /// [`LegacyWriteOptions`].
@@ -220,14 +220,46 @@ pub fn to_writer<'t, W: Write + Seek, E: ByteOrder + 'static>(
 ///     Ok(())
 /// }
 /// ```
-pub fn to_writer_options<'t, W: Write + Seek, E: ByteOrder + 'static>(
+pub fn to_writer_options<'t, W: Write, E: ByteOrder + 'static>(
+    writer: W,
+    tables: impl IntoIterator<Item = impl Borrow<LegacyTable<'t>>>,
+    version: BdatVersion,
+    opts: LegacyWriteOptions,
+) -> Result<()> {
+    to_writer_progress::<W, E>(writer, tables, version, opts, |_| {})
+}
+
+/// Writes legacy BDAT tables to a [`std::io::Write`] implementation, calling `progress`
+/// with [`Progress::TableWritten`] after each table is encoded.
+///
+/// Tables are encoded before anything is written to `writer`, so the reported size is the
+/// size of the output so far, rather than the number of bytes flushed to the writer.
+///
+/// ```
+/// use std::fs::File;
+/// use bdat::{BdatResult, LegacyTable, Progress, SwitchEndian, BdatVersion};
+/// use bdat::legacy::LegacyWriteOptions;
+///
+/// fn write_file(name: &str, tables: &[LegacyTable]) -> BdatResult<()> {
+///     let file = File::create(name)?;
+///     bdat::legacy::to_writer_progress::<_, SwitchEndian>(file, tables, BdatVersion::LegacySwitch,
+///             LegacyWriteOptions::new(), |p| {
+///         if let Progress::TableWritten { bytes, .. } = p {
+///             println!("{bytes} bytes");
+///         }
+///     })?;
+///     Ok(())
+/// }
+/// ```
+pub fn to_writer_progress<'t, W: Write, E: ByteOrder + 'static>(
     writer: W,
     tables: impl IntoIterator<Item = impl Borrow<LegacyTable<'t>>>,
     version: BdatVersion,
     opts: LegacyWriteOptions,
+    mut progress: impl FnMut(Progress),
 ) -> Result<()> {
     let mut writer = FileWriter::<W, E>::new(writer, version, opts);
-    writer.write_file(tables)
+    writer.write_file(tables, &mut progress)
 }
 
 /// Writes legacy BDAT tables to a `Vec<u8>`.
@@ -272,7 +304,7 @@ pub fn to_vec_options<'t, E: ByteOrder + 'static>(
     opts: LegacyWriteOptions,
 ) -> Result<Vec<u8>> {
     let mut vec = Vec::new();
-    to_writer_options::<_, E>(Cursor::new(&mut vec), tables, version, opts)?;
+    to_writer_options::<_, E>(&mut vec, tables, version, opts)?;
     Ok(vec)
 }
 
@@ -12,18 +12,22 @@ use crate::legacy::float::BdatReal;
 use crate::legacy::scramble::{calc_checksum, scramble, unscramble, ScrambleType};
 use crate::legacy::{ColumnNodeInfo, COLUMN_NODE_SIZE};
 use crate::{
-    BdatError, BdatFile, BdatVersion, Cell, ColumnDef, FlagDef, Label, LegacyTable, Row,
-    TableBuilder, Utf, Value, ValueType,
+    BdatError, BdatFile, BdatVersion, Cell, ColumnDef, Diagnostic, FlagDef, Label, LegacyTable,
+    Row, TableBuilder, Utf, Value, ValueType,
 };
 
 use super::{FileHeader, TableHeader};
 
 /// A legacy BDAT reader holding a blob of bytes, which is expected to contain the full file.
+///
+/// Clones share borrowed data, so tables can be read from several threads at once.
+#[derive(Clone)]
 pub struct LegacyBytes<'t, E> {
     data: Cow<'t, [u8]>,
     header: FileHeader,
     version: BdatVersion,
     table_headers: Vec<TableHeader>,
+    diagnostics: Vec<Diagnostic>,
     _endianness: PhantomData<E>,
 }
 
@@ -32,6 +36,7 @@ pub struct LegacyReader<R, E> {
     reader: R,
     header: FileHeader,
     version: BdatVersion,
+    diagnostics: Vec<Diagnostic>,
     _endianness: PhantomData<E>,
 }
 
@@ -54,8 +59,6 @@ struct ColumnReader<'a, 't, E> {
 /// Reads row and cell data.
 struct RowReader<'a, 't: 'a, E> {
     table: &'a mut TableReader<'t, E>,
-    /// The cells for the row currently being read
-    cells: Vec<Option<Cell<'t>>>,
     columns: &'a [ColumnDef],
     row_idx: usize,
 }
@@ -106,6 +109,7 @@ impl<R: Read + Seek, E: ByteOrder> LegacyReader<R, E> {
             header,
             version,
             reader,
+            diagnostics: Vec::new(),
             _endianness: PhantomData,
         })
     }
@@ -114,6 +118,7 @@ impl<R: Read + Seek, E: ByteOrder> LegacyReader<R, E> {
 impl<'t, E: ByteOrder> LegacyBytes<'t, E> {
     pub fn new(bytes: &'t mut [u8], version: BdatVersion) -> Result<Self> {
         let header = FileHeader::read::<_, E>(Cursor::new(&bytes))?;
+        header.check_bounds(bytes.len())?;
         let mut headers = vec![];
         header.for_each_table_mut(bytes, |table| {
             let header = TableHeader::read::<E>(Cursor::new(&table), version)?;
@@ -126,17 +131,20 @@ impl<'t, E: ByteOrder> LegacyBytes<'t, E> {
             version,
             data: Cow::Borrowed(bytes),
             table_headers: headers,
+            diagnostics: Vec::new(),
             _endianness: PhantomData,
         })
     }
 
     pub fn new_copy(bytes: &[u8], version: BdatVersion) -> Result<Self> {
         let header = FileHeader::read::<_, E>(Cursor::new(&bytes))?;
+        header.check_bounds(bytes.len())?;
         Ok(Self {
             header,
             version,
             data: Cow::Owned(bytes.to_vec()),
             table_headers: Vec::new(),
+            diagnostics: Vec::new(),
             _endianness: PhantomData,
         })
     }
@@ -157,6 +165,18 @@ impl FileHeader {
         })
     }
 
+    /// Checks that the file size and table offsets stay within a buffer of the given
+    /// length, so table data can be sliced without going out of bounds.
+    fn check_bounds(&self, len: usize) -> Result<()> {
+        if self.file_size > len
+            || self.table_offsets.iter().any(|&o| o > self.file_size)
+            || self.table_offsets.windows(2).any(|w| w[0] > w[1])
+        {
+            return Err(BdatError::MalformedBdat(Scope::File));
+        }
+        Ok(())
+    }
+
     pub fn for_each_table_mut<F, E>(&self, data: &mut [u8], mut f: F) -> std::result::Result<(), E>
     where
         F: FnMut(&mut [u8]) -> std::result::Result<(), E>,
@@ -203,7 +223,7 @@ impl TableHeader {
         let offset_rows = reader.read_u16::<E>()? as usize;
         let row_count = reader.read_u16::<E>()? as usize;
         let base_id = reader.read_u16::<E>()? as usize;
-        assert_eq!(2, reader.read_u16::<E>()?, "unknown constant is not 2");
+        let unknown_constant = reader.read_u16::<E>()?;
         let scramble_key = reader.read_u16::<E>()?;
         let offset_strings = reader.read_u32::<E>()? as usize;
         let strings_len = reader.read_u32::<E>()? as usize;
@@ -231,6 +251,7 @@ impl TableHeader {
             row_count,
             row_len,
             base_id,
+            unknown_constant,
             columns,
         })
     }
@@ -327,8 +348,14 @@ impl<'t, E: ByteOrder> TableReader<'t, E> {
         })
     }
 
-    fn read(mut self) -> Result<LegacyTable<'t>> {
+    /// Reads the table, adding any issues to `diagnostics`. `index` is the position of the
+    /// table in the file.
+    fn read(mut self, index: usize, diagnostics: &mut Vec<Diagnostic>) -> Result<LegacyTable<'t>> {
+        self.check(index, diagnostics);
         let name = self.read_string(self.header.offset_names)?.to_string();
+        if name.is_empty() {
+            diagnostics.push(Diagnostic::UnnamedTable { table: index });
+        }
         let TableColumns {
             columns: columns_src,
             flags,
@@ -360,14 +387,15 @@ impl<'t, E: ByteOrder> TableReader<'t, E> {
                         }
                     })
                     .collect(),
+                raw_type: None,
             })
             .collect::<Vec<_>>();
 
         self.data
             .seek(SeekFrom::Start(self.header.offset_rows.try_into()?))?;
 
-        let mut rows = vec![];
         let row_count = self.header.row_count;
+        let mut rows = Vec::with_capacity(row_count);
         let base_id = self.header.base_id;
         let mut row_reader = RowReader::new(&mut self, &columns);
         for i in 0..row_count {
@@ -379,10 +407,34 @@ impl<'t, E: ByteOrder> TableReader<'t, E> {
         Ok(TableBuilder::with_name(Label::String(name))
             .set_columns(columns)
             .set_rows(rows)
+            .set_base_id(base_id)
             .build_legacy())
     }
 
-    fn discover_columns_from_nodes(&self, info: &ColumnNodeInfo) -> Result<TableColumns> {
+    fn check(&self, index: usize, diagnostics: &mut Vec<Diagnostic>) {
+        if self.header.unknown_constant != 2 {
+            diagnostics.push(Diagnostic::UnknownHeaderValue {
+                table: index,
+                offset: 0x14,
+                value: self.header.unknown_constant.into(),
+            });
+        }
+        if let ScrambleType::Scrambled(stored) = self.header.scramble_type {
+            // The data is unscrambled at this point. When reading from a slice, it also
+            // contains the tables that follow.
+            let data = self.data.get_ref();
+            let calculated = calc_checksum(&data[..self.header.get_table_len().min(data.len())]);
+            if calculated != stored {
+                diagnostics.push(Diagnostic::ChecksumMismatch {
+                    table: index,
+                    stored,
+                    calculated,
+                });
+            }
+        }
+    }
+
+    fn discover_columns_from_nodes(&self, info: &ColumnNodeInfo) -> Result<TableColumns<'_>> {
         let mut seek = info.offset_columns.try_into()?;
         let (flags, columns) = (0..info.column_count)
             .map(|_| {
@@ -402,7 +454,7 @@ impl<'t, E: ByteOrder> TableReader<'t, E> {
         })
     }
 
-    fn discover_columns_from_hash(&self) -> Result<TableColumns> {
+    fn discover_columns_from_hash(&self) -> Result<TableColumns<'_>> {
         // In XC1, column nodes are part of the name table, but we can enumerate columns
         // from the hash table, so we get easy access to both info data and name
 
@@ -564,7 +616,6 @@ impl<'a, 't, E: ByteOrder> RowReader<'a, 't, E> {
     fn new(table: &'a mut TableReader<'t, E>, columns: &'a [ColumnDef]) -> Self {
         Self {
             table,
-            cells: vec![None; columns.len()],
             columns,
             row_idx: 0,
         }
@@ -577,16 +628,16 @@ impl<'a, 't, E: ByteOrder> RowReader<'a, 't, E> {
                 .try_into()
                 .unwrap(),
         ))?;
-        self.cells.fill(None);
         Ok(())
     }
 
     fn read_row(&mut self) -> Result<Vec<Cell<'t>>> {
-        for (i, col) in self.columns.iter().enumerate() {
+        let mut cells = Vec::with_capacity(self.columns.len());
+        for col in self.columns {
             if col.count > 1 {
                 // Array
                 let values = self.read_array(col.value_type, col.count)?;
-                self.cells[i] = Some(Cell::List(values));
+                cells.push(Cell::List(values));
                 continue;
             }
 
@@ -600,14 +651,14 @@ impl<'a, 't, E: ByteOrder> RowReader<'a, 't, E> {
                     .iter()
                     .map(|f| (value & f.mask) >> f.flag_index)
                     .collect::<Vec<_>>();
-                self.cells[i] = Some(Cell::Flags(flags));
+                cells.push(Cell::Flags(flags));
                 continue;
             }
 
-            self.cells[i] = Some(Cell::Single(value));
+            cells.push(Cell::Single(value));
         }
 
-        Ok(self.cells.iter().flatten().cloned().collect())
+        Ok(cells)
     }
 
     fn read_value(&mut self, value_type: ValueType) -> Result<Value<'t>> {
@@ -657,7 +708,7 @@ impl<'t> Flags<'t> {
         Self(src)
     }
 
-    fn get_from_parent(&self, parent_info_offset: usize) -> impl Iterator<Item = &ColumnData> {
+    fn get_from_parent(&self, parent_info_offset: usize) -> impl Iterator<Item = &ColumnData<'_>> {
         let upper = self
             .0
             .partition_point(|c| Self::extract(c) == parent_info_offset);
@@ -677,45 +728,48 @@ impl<'t> Flags<'t> {
 impl<'b, R: Read + Seek, E: ByteOrder> BdatFile<'b> for LegacyReader<R, E> {
     type TableOut = LegacyTable<'b>;
 
-    fn get_tables(&mut self) -> Result<Vec<LegacyTable<'b>>> {
-        let mut tables = Vec::with_capacity(self.header.table_count);
-        for offset in &self.header.table_offsets {
-            self.reader.seek(SeekFrom::Start(*offset as u64))?;
-            tables.push(TableReader::<E>::from_reader(&mut self.reader, self.version)?.read()?);
-        }
-        Ok(tables)
+    fn get_table(&mut self, index: usize) -> Result<LegacyTable<'b>> {
+        let offset = self.header.table_offsets[index];
+        self.reader.seek(SeekFrom::Start(offset as u64))?;
+        TableReader::<E>::from_reader(&mut self.reader, self.version)?
+            .read(index, &mut self.diagnostics)
     }
 
     fn table_count(&self) -> usize {
         self.header.table_count
     }
+
+    fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
 }
 
 impl<'b, E: ByteOrder> BdatFile<'b> for LegacyBytes<'b, E> {
     type TableOut = LegacyTable<'b>;
 
-    fn get_tables(&mut self) -> Result<Vec<LegacyTable<'b>>> {
-        let mut tables = Vec::with_capacity(self.header.table_count);
-        for (i, offset) in self.header.table_offsets.iter().enumerate() {
-            tables.push(match &self.data {
-                Cow::Owned(buf) => {
-                    TableReader::<E>::from_reader(Cursor::new(&buf[*offset..]), self.version)?
-                        .read()?
-                }
-                Cow::Borrowed(data) => TableReader::<E>::from_slice(
-                    &data[*offset..],
-                    self.version,
-                    self.table_headers.get(i).cloned(),
-                )?
-                .read()?,
-            });
+    fn get_table(&mut self, index: usize) -> Result<LegacyTable<'b>> {
+        let offset = self.header.table_offsets[index];
+        match &self.data {
+            Cow::Owned(buf) => {
+                TableReader::<E>::from_reader(Cursor::new(&buf[offset..]), self.version)?
+                    .read(index, &mut self.diagnostics)
+            }
+            Cow::Borrowed(data) => TableReader::<E>::from_slice(
+                &data[offset..],
+                self.version,
+                self.table_headers.get(index).cloned(),
+            )?
+            .read(index, &mut self.diagnostics),
         }
-        Ok(tables)
     }
 
     fn table_count(&self) -> usize {
         self.header.table_count
     }
+
+    fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
 }
 
 #[inline]
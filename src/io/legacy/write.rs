@@ -7,17 +7,18 @@ use std::rc::Rc;
 
 use byteorder::{ByteOrder, WriteBytesExt};
 
-use crate::error::Result;
-use crate::io::BDAT_MAGIC;
+use crate::error::{Result, Scope};
+use crate::io::{to_offset, BDAT_MAGIC};
 use crate::legacy::hash::HashTable;
 use crate::legacy::scramble::{calc_checksum, scramble};
 use crate::legacy::util::{pad_2, pad_32, pad_4, pad_64};
 use crate::legacy::{
     LegacyWriteOptions, COLUMN_NODE_SIZE, COLUMN_NODE_SIZE_WII, HEADER_SIZE, HEADER_SIZE_WII,
 };
+use crate::table::util::check_row_ids;
 use crate::{
-    BdatError, BdatVersion, Cell, ColumnDef, FlagDef, LegacyTable, Row, TableAccessor, Value,
-    ValueType, WiiEndian,
+    BdatError, BdatVersion, Cell, ColumnDef, FlagDef, LegacyTable, Progress, Row, TableAccessor,
+    Value, ValueType, WiiEndian,
 };
 
 /// Writes a full BDAT file to a writer.
@@ -127,7 +128,7 @@ struct StringTable {
     keep_duplicates: bool,
 }
 
-impl<W: Write + Seek, E: ByteOrder + 'static> FileWriter<W, E> {
+impl<W: Write, E: ByteOrder + 'static> FileWriter<W, E> {
     pub fn new(writer: W, version: BdatVersion, opts: LegacyWriteOptions) -> Self {
         Self {
             writer,
@@ -140,46 +141,37 @@ impl<W: Write + Seek, E: ByteOrder + 'static> FileWriter<W, E> {
     pub fn write_file<'t>(
         &mut self,
         tables: impl IntoIterator<Item = impl Borrow<LegacyTable<'t>>>,
+        progress: &mut dyn FnMut(Progress),
     ) -> Result<()> {
         let tables = tables.into_iter().by_ref().collect::<Vec<_>>();
         let mut tables = tables.iter().map(|t| t.borrow()).collect::<Vec<_>>();
         // Tables must be ordered by name
         tables.sort_unstable_by_key(|t| t.name.to_string_convert());
 
-        let (table_bytes, table_offsets, total_len, table_count) = tables
-            .into_iter()
-            .map(|table| TableWriter::<E>::new(table, self.version, self.opts).write())
-            .try_fold(
-                (Vec::new(), Vec::new(), 0, 0),
-                |(mut tot_bytes, mut offsets, len, count), table_bytes| {
-                    table_bytes.map(|mut bytes| {
-                        let new_len = bytes.len();
-                        (
-                            {
-                                tot_bytes.append(&mut bytes);
-                                tot_bytes
-                            },
-                            {
-                                offsets.push(len);
-                                offsets
-                            },
-                            len + new_len,
-                            count + 1,
-                        )
-                    })
-                },
-            )?;
-
-        let offsets = table_offsets.len();
-        let header_len = 8 + offsets * 4;
+        let table_count = tables.len();
+        let header_len = 8 + table_count * 4;
+        let mut table_bytes = Vec::new();
+        let mut table_offsets = Vec::with_capacity(table_count);
+        for (i, table) in tables.into_iter().enumerate() {
+            let mut bytes = TableWriter::<E>::new(table, self.version, self.opts).write()?;
+            table_offsets.push(table_bytes.len());
+            table_bytes.append(&mut bytes);
+            progress(Progress::TableWritten {
+                done: i + 1,
+                total: table_count,
+                bytes: header_len + table_bytes.len(),
+            });
+        }
+        let total_len = table_bytes.len();
 
-        self.writer.write_u32::<E>(table_count as u32)?;
         self.writer
-            .write_u32::<E>((total_len + header_len).try_into()?)?;
+            .write_u32::<E>(to_offset(table_count, Scope::File)?)?;
+        self.writer
+            .write_u32::<E>(to_offset(total_len + header_len, Scope::File)?)?;
 
         for offset in table_offsets {
             self.writer
-                .write_u32::<E>((offset + header_len).try_into()?)?;
+                .write_u32::<E>(to_offset(offset + header_len, Scope::File)?)?;
         }
         self.writer.write_all(&table_bytes)?;
 
@@ -321,30 +313,26 @@ impl<'a, 't, E: ByteOrder + 'static> TableWriter<'a, 't, E> {
         self.buf.write_all(&[flags, 0])?; // Flags
 
         // Name table offset = header size + column info table size
-        self.buf
-            .write_u16::<E>((self.version.table_header_size() + columns.info_len) as u16)?;
+        self.buf.write_u16::<E>(to_offset(
+            self.version.table_header_size() + columns.info_len,
+            Scope::Table,
+        )?)?;
         // Size of each row
-        self.buf.write_u16::<E>(columns.row_data_len.try_into()?)?;
+        self.buf
+            .write_u16::<E>(to_offset(columns.row_data_len, Scope::Table)?)?;
         // Hash table offset
         self.buf
-            .write_u16::<E>(self.header.hash_table_offset.try_into()?)?;
+            .write_u16::<E>(to_offset(self.header.hash_table_offset, Scope::Table)?)?;
         // Hash table modulo factor
         self.buf.write_u16::<E>(self.opts.hash_slots.try_into()?)?;
         // Row table offset
         self.buf
-            .write_u16::<E>(self.header.row_data_offset.try_into()?)?;
+            .write_u16::<E>(to_offset(self.header.row_data_offset, Scope::Table)?)?;
         // Number of rows
         self.buf.write_u16::<E>(self.table.rows.len().try_into()?)?;
         // ID of the first row
-        self.buf.write_u16::<E>(
-            self.table
-                .rows
-                .first()
-                .map(Row::id)
-                .unwrap_or_default()
-                .try_into()
-                .unwrap(),
-        )?;
+        check_row_ids(&self.table.rows, self.version)?;
+        self.buf.write_u16::<E>(self.table.base_id.try_into()?)?;
         // UNKNOWN - asserted 2 when reading
         self.buf.write_u16::<E>(2)?;
 
@@ -354,17 +342,19 @@ impl<'a, 't, E: ByteOrder + 'static> TableWriter<'a, 't, E> {
 
         // String table offset
         self.buf
-            .write_u32::<E>(self.strings.base_offset.try_into()?)?;
+            .write_u32::<E>(to_offset(self.strings.base_offset, Scope::Table)?)?;
         // String table size, includes final table padding
-        self.buf.write_u32::<E>(
-            (self.strings.size_bytes_current() + self.header.final_padding).try_into()?,
-        )?;
+        self.buf.write_u32::<E>(to_offset(
+            self.strings.size_bytes_current() + self.header.final_padding,
+            Scope::Table,
+        )?)?;
 
         if self.version != BdatVersion::LegacyWii {
             // Column node table offset
-            self.buf.write_u16::<E>(
-                (self.names.base_offset + self.names.size_bytes_current()).try_into()?,
-            )?;
+            self.buf.write_u16::<E>(to_offset(
+                self.names.base_offset + self.names.size_bytes_current(),
+                Scope::Table,
+            )?)?;
             // Column count (includes flags)
             self.buf.write_u16::<E>(columns.nodes.len().try_into()?)?;
             // Padding
@@ -388,9 +378,12 @@ impl<'a, 't, E: ByteOrder + 'static> TableWriter<'a, 't, E> {
             &mut self.buf.get_mut()[self.names.base_offset..self.header.hash_table_offset],
             key,
         );
+        // Like the string table size in the header, this includes the final table padding
         scramble(
             &mut self.buf.get_mut()[self.strings.base_offset
-                ..self.strings.base_offset + self.strings.size_bytes_current()],
+                ..self.strings.base_offset
+                    + self.strings.size_bytes_current()
+                    + self.header.final_padding],
             key,
         );
     }
@@ -563,7 +556,9 @@ impl<'a, 'b, 't, E: ByteOrder> RowWriter<'a, 'b, 't, E> {
             Value::SignedByte(b) => writer.write_i8(*b),
             Value::SignedShort(s) => writer.write_i16::<E>(*s),
             Value::SignedInt(i) => writer.write_i32::<E>(*i),
-            Value::String(s) => writer.write_u32::<E>(self.table.strings.insert(s).try_into()?),
+            Value::String(s) => {
+                writer.write_u32::<E>(to_offset(self.table.strings.insert(s), Scope::Table)?)
+            }
             Value::Float(f) => {
                 let mut f = *f;
                 f.make_known(self.table.version);
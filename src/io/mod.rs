@@ -7,8 +7,63 @@ mod read;
 
 pub use read::BdatFile;
 
+use std::fmt::Display;
+
+use crate::error::{Result, Scope};
+use crate::{BdatError, Label};
+
 const BDAT_MAGIC: [u8; 4] = [b'B', b'D', b'A', b'T'];
 
+/// A progress update from a long read or write operation, passed to the callbacks of
+/// [`BdatFile::get_tables_with_progress`], [`modern::to_writer_progress`] and
+/// [`legacy::to_writer_progress`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// A table was parsed. `done` out of `total` tables have been read.
+    TableRead { done: usize, total: usize },
+    /// A table was encoded. `done` out of `total` tables have been written, and `bytes` is
+    /// the size of the file so far, including the file header. After the last table, it is
+    /// the size of the whole file.
+    TableWritten {
+        done: usize,
+        total: usize,
+        bytes: usize,
+    },
+}
+
+/// A non-fatal issue found while reading a table. The table is still read, but it might not
+/// be exactly what the game expects. Diagnostics are collected by the file reader, see
+/// [`BdatFile::take_diagnostics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// A header field that has the same value in every known file had a different value.
+    /// The value is ignored, and it is not preserved when the table is written back.
+    UnknownHeaderValue {
+        /// The position of the table in the file
+        table: usize,
+        /// The offset of the field, relative to the start of the table
+        offset: usize,
+        value: u32,
+    },
+    /// The scramble key of a legacy table doesn't match the checksum of its contents.
+    /// Tables written by this crate get a matching key, unless a custom key is set.
+    ChecksumMismatch {
+        table: usize,
+        stored: u16,
+        calculated: u16,
+    },
+    /// A table has an empty name, or a modern table's name is the hash `0`.
+    UnnamedTable { table: usize },
+}
+
+/// Whether a table name counts as missing, for [`Diagnostic::UnnamedTable`].
+pub(crate) fn is_unnamed(name: &Label) -> bool {
+    match name {
+        Label::Hash(hash) => *hash == 0,
+        Label::String(s) | Label::Unhashed(s) => s.is_empty(),
+    }
+}
+
 /// Alias for [`byteorder::LittleEndian`], i.e. the byte order used in the Switch games.
 pub type SwitchEndian = byteorder::LittleEndian;
 /// Alias for [`byteorder::BigEndian`], i.e. the byte order used in the Wii/Wii U games.
@@ -27,6 +82,30 @@ pub enum BdatVersion {
     Modern,
 }
 
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownHeaderValue {
+                table,
+                offset,
+                value,
+            } => write!(
+                f,
+                "table #{table}: unknown value {value:#x} at header offset {offset:#x}"
+            ),
+            Self::ChecksumMismatch {
+                table,
+                stored,
+                calculated,
+            } => write!(
+                f,
+                "table #{table}: scramble key {stored:#06x} does not match checksum {calculated:#06x}"
+            ),
+            Self::UnnamedTable { table } => write!(f, "table #{table} has no name"),
+        }
+    }
+}
+
 impl BdatVersion {
     pub fn is_legacy(&self) -> bool {
         *self != BdatVersion::Modern
@@ -36,6 +115,21 @@ impl BdatVersion {
         !self.is_legacy()
     }
 
+    /// Detects the version of a BDAT file from its header, which also determines its byte
+    /// order (see [`BdatVersion::is_big_endian`]). The reader must be at the start of the
+    /// file. Same as [`detect_file_version`].
+    ///
+    /// [`detect_file_version`]: crate::detect_file_version
+    pub fn detect<R: std::io::Read + std::io::Seek>(reader: R) -> Result<Self> {
+        detect::detect_file_version(reader)
+    }
+
+    /// Gets whether files of this version are big-endian ([`WiiEndian`]) rather than
+    /// little-endian ([`SwitchEndian`]).
+    pub fn is_big_endian(&self) -> bool {
+        matches!(self, BdatVersion::LegacyWii | BdatVersion::LegacyX)
+    }
+
     /// Gets whether the version forces labels to be hashed.
     pub fn are_labels_hashed(&self) -> bool {
         self.is_modern()
@@ -49,4 +143,23 @@ impl BdatVersion {
             _ => legacy::HEADER_SIZE,
         }
     }
+
+    /// Returns the highest row ID that can be stored in tables of this version.
+    ///
+    /// Legacy formats store row IDs as 16-bit integers, while modern tables use
+    /// 32-bit IDs.
+    pub const fn max_row_id(&self) -> usize {
+        match self {
+            BdatVersion::Modern => u32::MAX as usize,
+            _ => u16::MAX as usize,
+        }
+    }
+}
+
+/// Converts an offset or size to the integer type used to store it in the file, failing
+/// with [`BdatError::OffsetOverflow`] instead of truncating it.
+pub(crate) fn to_offset<T: TryFrom<usize>>(value: usize, scope: Scope) -> Result<T> {
+    T::try_from(value).map_err(|_| {
+        BdatError::OffsetOverflow(scope, value as u64, (std::mem::size_of::<T>() * 8) as u32)
+    })
 }
@@ -1,11 +1,11 @@
 //! I/O operations for XC3 ("modern") BDATs
 
 use std::borrow::Borrow;
-use std::io::{Cursor, Read, Seek, Write};
+use std::io::{Read, Seek, Write};
 
 use self::write::BdatWriter;
 use super::read::{BdatReader, BdatSlice};
-use crate::{error::Result, ModernTable};
+use crate::{error::Result, ModernTable, Progress};
 use byteorder::ByteOrder;
 
 // doc
@@ -17,7 +17,14 @@ mod write;
 
 pub use read::FileReader;
 
-#[derive(Debug)]
+/// Options for writing modern BDAT files, used in [`to_writer_options`] and
+/// [`to_vec_options`].
+#[derive(Copy, Clone)]
+pub struct ModernWriteOptions {
+    pub(crate) sort_tables: bool,
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct FileHeader {
     pub table_count: usize,
     pub(crate) table_offsets: Vec<usize>,
@@ -71,7 +78,13 @@ pub fn from_bytes<E: ByteOrder>(bytes: &[u8]) -> Result<FileReader<BdatSlice<'_,
     FileReader::read_file(BdatSlice::new(bytes))
 }
 
-/// Writes BDAT tables to a [`std::io::Write`] implementation that also implements [`std::io::Seek`].
+/// Writes BDAT tables to a [`std::io::Write`] implementation.
+///
+/// The writer does not need to implement [`std::io::Seek`], so tables can be written directly
+/// to standard output, sockets or compression streams.
+///
+/// Tables are sorted by their name hash, as that is the order the game expects. To
+/// keep the input order instead, use [`to_writer_options`].
 ///
 /// ```
 /// use std::fs::File;
@@ -83,16 +96,75 @@ pub fn from_bytes<E: ByteOrder>(bytes: &[u8]) -> Result<FileReader<BdatSlice<'_,
 ///     Ok(())
 /// }
 /// ```
-pub fn to_writer<'t, W: Write + Seek, E: ByteOrder>(
+pub fn to_writer<'t, W: Write, E: ByteOrder>(
+    writer: W,
+    tables: impl IntoIterator<Item = impl Borrow<ModernTable<'t>>>,
+) -> Result<()> {
+    to_writer_options::<W, E>(writer, tables, ModernWriteOptions::new())
+}
+
+/// Writes BDAT tables to a [`std::io::Write`] implementation.
+///
+/// This function also allows customization of a few write options, using
+/// [`ModernWriteOptions`].
+///
+/// ```
+/// use std::fs::File;
+/// use bdat::{BdatResult, SwitchEndian, ModernTable};
+/// use bdat::modern::ModernWriteOptions;
+///
+/// fn write_file(name: &str, tables: &[ModernTable]) -> BdatResult<()> {
+///     let file = File::create(name)?;
+///     bdat::modern::to_writer_options::<_, SwitchEndian>(file, tables,
+///             ModernWriteOptions::new().sort_tables(false))?;
+///     Ok(())
+/// }
+/// ```
+pub fn to_writer_options<'t, W: Write, E: ByteOrder>(
+    writer: W,
+    tables: impl IntoIterator<Item = impl Borrow<ModernTable<'t>>>,
+    opts: ModernWriteOptions,
+) -> Result<()> {
+    to_writer_progress::<W, E>(writer, tables, opts, |_| {})
+}
+
+/// Writes BDAT tables to a [`std::io::Write`] implementation, calling `progress` with
+/// [`Progress::TableWritten`] after each table is encoded.
+///
+/// Tables are encoded before anything is written to `writer`, so the reported size is the
+/// size of the output so far, rather than the number of bytes flushed to the writer.
+///
+/// ```
+/// use std::fs::File;
+/// use bdat::{BdatResult, Progress, SwitchEndian, ModernTable};
+/// use bdat::modern::ModernWriteOptions;
+///
+/// fn write_file(name: &str, tables: &[ModernTable]) -> BdatResult<()> {
+///     let file = File::create(name)?;
+///     bdat::modern::to_writer_progress::<_, SwitchEndian>(file, tables,
+///             ModernWriteOptions::new(), |p| {
+///         if let Progress::TableWritten { done, total, .. } = p {
+///             println!("{done}/{total}");
+///         }
+///     })?;
+///     Ok(())
+/// }
+/// ```
+pub fn to_writer_progress<'t, W: Write, E: ByteOrder>(
     writer: W,
     tables: impl IntoIterator<Item = impl Borrow<ModernTable<'t>>>,
+    opts: ModernWriteOptions,
+    mut progress: impl FnMut(Progress),
 ) -> Result<()> {
-    let mut writer = BdatWriter::<W, E>::new(writer);
-    writer.write_file(tables)
+    let mut writer = BdatWriter::<W, E>::new(writer, opts);
+    writer.write_file(tables, &mut progress)
 }
 
 /// Writes BDAT tables to a `Vec<u8>`.
 ///
+/// Tables are sorted by their name hash, as that is the order the game expects. To
+/// keep the input order instead, use [`to_vec_options`].
+///
 /// ```
 /// use std::fs::File;
 /// use bdat::{BdatResult, SwitchEndian, ModernTable};
@@ -104,17 +176,64 @@ pub fn to_writer<'t, W: Write + Seek, E: ByteOrder>(
 /// ```
 pub fn to_vec<'t, E: ByteOrder>(
     tables: impl IntoIterator<Item = impl Borrow<ModernTable<'t>>>,
+) -> Result<Vec<u8>> {
+    to_vec_options::<E>(tables, ModernWriteOptions::new())
+}
+
+/// Writes BDAT tables to a `Vec<u8>`.
+///
+/// This function also allows customization of a few write options, using
+/// [`ModernWriteOptions`].
+///
+/// ```
+/// use bdat::{BdatResult, SwitchEndian, ModernTable};
+/// use bdat::modern::ModernWriteOptions;
+///
+/// fn write_vec(tables: &[ModernTable]) -> BdatResult<()> {
+///     let vec = bdat::modern::to_vec_options::<SwitchEndian>(tables,
+///             ModernWriteOptions::new().sort_tables(false))?;
+///     Ok(())
+/// }
+/// ```
+pub fn to_vec_options<'t, E: ByteOrder>(
+    tables: impl IntoIterator<Item = impl Borrow<ModernTable<'t>>>,
+    opts: ModernWriteOptions,
 ) -> Result<Vec<u8>> {
     let mut vec = Vec::new();
-    to_writer::<_, E>(Cursor::new(&mut vec), tables)?;
+    to_writer_options::<_, E>(&mut vec, tables, opts)?;
     Ok(vec)
 }
 
+impl ModernWriteOptions {
+    pub const fn new() -> Self {
+        Self { sort_tables: true }
+    }
+
+    /// Sets whether tables should be sorted by their name hash before being written.
+    ///
+    /// The game looks tables up by hash, and expects them to be in ascending hash order.
+    /// Disabling this keeps tables in the order they were passed in, which can be used
+    /// to write back a file that was not sorted byte-for-byte.
+    ///
+    /// By default, tables are sorted.
+    pub fn sort_tables(mut self, sort: bool) -> Self {
+        self.sort_tables = sort;
+        self
+    }
+}
+
+impl Default for ModernWriteOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        io::SwitchEndian, BdatFile, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType,
+        io::SwitchEndian, BdatFile, Cell, ColumnBuilder, ColumnDef, Label, RawType, Row,
+        TableAccessor, TableBuilder, Value, ValueType,
     };
 
     #[test]
@@ -129,6 +248,7 @@ mod tests {
                 label: Label::Hash(0xca_fe_ca_fe),
                 flags: Vec::new(),
                 count: 1,
+                raw_type: None,
             })
             .add_row(Row::new(
                 1,
@@ -152,8 +272,122 @@ mod tests {
             .get_tables()
             .unwrap()[0];
         assert_eq!(table, *read_back);
+        assert_eq!(
+            Some(&Cell::Single(Value::UnsignedInt(100))),
+            read_back.row(2).cell(Label::Hash(0xca_fe_ca_fe))
+        );
 
         let new_written = to_vec::<SwitchEndian>([read_back]).unwrap();
         assert_eq!(written, new_written);
     }
+
+    #[test]
+    fn unknown_value_type() {
+        let table = TableBuilder::with_name(Label::Hash(0xca_fe_ba_be))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, Label::Hash(1)))
+            .add_column(
+                ColumnBuilder::new(ValueType::Raw, Label::Hash(2))
+                    .set_raw_type(RawType::new(0x20, 3))
+                    .build(),
+            )
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, Label::Hash(3)))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::UnsignedByte(1)),
+                    Cell::Single(Value::Raw(vec![0xaa, 0xbb, 0xcc].into())),
+                    Cell::Single(Value::UnsignedShort(2)),
+                ],
+            ))
+            .build_modern();
+
+        let written = to_vec::<SwitchEndian>([&table]).unwrap();
+        let read_back = &from_bytes::<SwitchEndian>(&written)
+            .unwrap()
+            .get_tables()
+            .unwrap()[0];
+        assert_eq!(table, *read_back);
+        assert_eq!(
+            Some(RawType::new(0x20, 3)),
+            read_back.columns().nth(1).unwrap().raw_type()
+        );
+        assert_eq!(written, to_vec::<SwitchEndian>([read_back]).unwrap());
+    }
+
+    #[test]
+    fn empty_table() {
+        let table = TableBuilder::with_name(Label::Hash(0xca_fe_ba_be))
+            .add_column(ColumnDef::new(
+                ValueType::HashRef,
+                Label::Hash(0xde_ad_be_ef),
+            ))
+            .add_column(ColumnDef::new(
+                ValueType::String,
+                Label::Hash(0xca_fe_ca_fe),
+            ))
+            .set_base_id(1)
+            .build_modern();
+
+        let written = to_vec::<SwitchEndian>([&table]).unwrap();
+        let read_back = &from_bytes::<SwitchEndian>(&written)
+            .unwrap()
+            .get_tables()
+            .unwrap()[0];
+        assert_eq!(table, *read_back);
+        assert_eq!(1, read_back.base_id);
+        assert_eq!(written, to_vec::<SwitchEndian>([read_back]).unwrap());
+    }
+
+    #[test]
+    fn no_columns() {
+        let tables = [
+            TableBuilder::with_name(Label::Hash(0xca_fe_ba_be))
+                .add_row(Row::new(1, vec![]))
+                .add_row(Row::new(2, vec![]))
+                .build_modern(),
+            TableBuilder::with_name(Label::Hash(0xde_ad_be_ef)).build_modern(),
+        ];
+
+        let written = to_vec::<SwitchEndian>(&tables).unwrap();
+        let read_back = from_bytes::<SwitchEndian>(&written)
+            .unwrap()
+            .get_tables()
+            .unwrap();
+        assert_eq!(&tables[..], &read_back[..]);
+        assert_eq!(written, to_vec::<SwitchEndian>(&read_back).unwrap());
+    }
+
+    #[test]
+    fn table_order() {
+        let table = |hash| {
+            TableBuilder::with_name(Label::Hash(hash))
+                .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::Hash(1)))
+                .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedInt(hash))]))
+                .build_modern()
+        };
+        let tables = [table(0xff), table(0x10), table(0x80)];
+        let names = |bytes: &[u8]| {
+            from_bytes::<SwitchEndian>(bytes)
+                .unwrap()
+                .get_tables()
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect::<Vec<_>>()
+        };
+
+        let sorted = to_vec::<SwitchEndian>(&tables).unwrap();
+        assert_eq!(
+            vec![Label::Hash(0x10), Label::Hash(0x80), Label::Hash(0xff)],
+            names(&sorted)
+        );
+
+        let unsorted =
+            to_vec_options::<SwitchEndian>(&tables, ModernWriteOptions::new().sort_tables(false))
+                .unwrap();
+        assert_eq!(
+            vec![Label::Hash(0xff), Label::Hash(0x10), Label::Hash(0x80)],
+            names(&unsorted)
+        );
+    }
 }
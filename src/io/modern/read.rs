@@ -8,11 +8,12 @@ use std::{
 use byteorder::{ByteOrder, ReadBytesExt};
 
 use crate::io::read::{BdatReader, BdatSlice};
-use crate::io::BDAT_MAGIC;
+use crate::io::{is_unnamed, BDAT_MAGIC};
 use crate::legacy::float::BdatReal;
 use crate::{
     error::{BdatError, Result, Scope},
-    BdatFile, Cell, ColumnDef, Label, ModernTable, Row, TableBuilder, Utf, Value, ValueType,
+    BdatFile, Cell, ColumnDef, Diagnostic, Label, ModernTable, RawType, Row, TableAccessor,
+    TableBuilder, Utf, Value, ValueType,
 };
 
 use super::FileHeader;
@@ -20,9 +21,15 @@ use super::FileHeader;
 const LEN_COLUMN_DEF_V2: usize = 3;
 const LEN_HASH_DEF_V2: usize = 8;
 
+/// Reads tables from a modern BDAT file.
+///
+/// Readers over byte slices can be cloned cheaply, so tables can be read from several
+/// threads at once.
+#[derive(Clone)]
 pub struct FileReader<R, E> {
     tables: TableReader<R, E>,
     header: FileHeader,
+    diagnostics: Vec<Diagnostic>,
     _endianness: PhantomData<E>,
 }
 
@@ -47,6 +54,7 @@ struct HeaderReader<R, E> {
     _endianness: PhantomData<E>,
 }
 
+#[derive(Clone)]
 struct TableReader<R, E> {
     reader: R,
     _endianness: PhantomData<E>,
@@ -68,8 +76,20 @@ where
         }
     }
 
-    fn read_table(&mut self) -> Result<ModernTable<'b>> {
-        self.tables.read_table_v2()
+    fn read_table(&mut self, index: usize) -> Result<ModernTable<'b>> {
+        let (table, unknown) = self.tables.read_table_v2()?;
+        if unknown != 0 {
+            self.diagnostics.push(Diagnostic::UnknownHeaderValue {
+                table: index,
+                offset: 0x14,
+                value: unknown,
+            });
+        }
+        if is_unnamed(table.name()) {
+            self.diagnostics
+                .push(Diagnostic::UnnamedTable { table: index });
+        }
+        Ok(table)
     }
 
     fn new_with_header(reader: R) -> Result<Self> {
@@ -78,6 +98,7 @@ where
         Ok(Self {
             tables: TableReader::new(header_reader.reader),
             header,
+            diagnostics: Vec::new(),
             _endianness: PhantomData,
         })
     }
@@ -116,7 +137,8 @@ impl<'b, R: ModernRead<'b>, E: ByteOrder> TableReader<R, E> {
         }
     }
 
-    fn read_table_v2(&mut self) -> Result<ModernTable<'b>> {
+    /// Reads a table, along with the value at 0x14, which is 0 in every known file.
+    fn read_table_v2(&mut self) -> Result<(ModernTable<'b>, u32)> {
         if self.reader.read_u32()? != u32::from_le_bytes(BDAT_MAGIC)
             || self.reader.read_u32()? != 0x3004
         {
@@ -126,9 +148,7 @@ impl<'b, R: ModernRead<'b>, E: ByteOrder> TableReader<R, E> {
         let columns = self.reader.read_u32()? as usize;
         let rows = self.reader.read_u32()? as usize;
         let base_id = self.reader.read_u32()? as usize;
-        if self.reader.read_u32()? != 0 {
-            panic!("Found unknown value at index 0x14 that was not 0");
-        }
+        let unknown = self.reader.read_u32()?;
 
         let offset_col = self.reader.read_u32()? as usize;
         let offset_hash = self.reader.read_u32()? as usize;
@@ -140,17 +160,18 @@ impl<'b, R: ModernRead<'b>, E: ByteOrder> TableReader<R, E> {
         offset_string = self.reader.read_u32()? as usize;
         let str_length = self.reader.read_u32()? as usize;
 
+        // Computed with 64-bit integers, as malformed or huge tables could otherwise overflow
         let lengths = [
-            offset_col + LEN_COLUMN_DEF_V2 * columns,
-            offset_hash + LEN_HASH_DEF_V2 * rows,
-            offset_row + row_length * rows,
-            offset_string + str_length,
+            offset_col as u64 + LEN_COLUMN_DEF_V2 as u64 * columns as u64,
+            offset_hash as u64 + LEN_HASH_DEF_V2 as u64 * rows as u64,
+            offset_row as u64 + row_length as u64 * rows as u64,
+            offset_string as u64 + str_length as u64,
         ];
         let table_len = lengths
-            .iter()
-            .max_by_key(|&i| i)
+            .into_iter()
+            .max()
             .expect("could not determine table length");
-        let table_raw = self.reader.read_table_data(*table_len)?;
+        let table_raw = self.reader.read_table_data(table_len.try_into()?)?;
         let table_data = TableData::new(table_raw, offset_string);
 
         let name = table_data.get_name::<E>()?;
@@ -159,45 +180,72 @@ impl<'b, R: ModernRead<'b>, E: ByteOrder> TableReader<R, E> {
 
         for i in 0..columns {
             let col = &table_data.data[offset_col + i * LEN_COLUMN_DEF_V2..];
-            let ty =
-                ValueType::try_from(col[0]).map_err(|_| BdatError::UnknownValueType(col[0]))?;
             let name_offset = (&col[1..]).read_u16::<E>()?;
             let label = table_data.get_label::<E>(name_offset as usize)?;
+            // Types we don't know are read as raw bytes, their size is determined below
+            let (ty, raw_type) = match ValueType::try_from(col[0]) {
+                Ok(ty) if ty != ValueType::Raw => (ty, None),
+                _ => (ValueType::Raw, Some(RawType::new(col[0], 0))),
+            };
 
             col_data.push(ColumnDef {
                 value_type: ty,
                 label,
                 flags: Vec::new(),
                 count: 1,
+                raw_type,
             });
         }
+        Self::size_raw_columns(&mut col_data, row_length)?;
 
         for i in 0..rows {
             let row = &table_data.data[offset_row + i * row_length..];
             let mut cells = Vec::with_capacity(col_data.len());
             let mut cursor = Cursor::new(row);
             for col in &col_data {
-                let value = Self::read_value_v2(&table_data, &mut cursor, col.value_type)?;
+                let value = Self::read_value_v2(&table_data, &mut cursor, col)?;
                 cells.push(Cell::Single(value));
             }
-            row_data.push(Row {
-                id: base_id + i,
-                cells,
-            });
+            row_data.push(Row::new(base_id + i, cells));
         }
 
-        Ok(TableBuilder::with_name(name)
+        let table = TableBuilder::with_name(name)
             .set_columns(col_data)
             .set_rows(row_data)
-            .build_modern())
+            .set_base_id(base_id)
+            .build_modern();
+        Ok((table, unknown))
+    }
+
+    /// Cells are stored back to back, so the size of unknown types can only be inferred
+    /// from what is left of the row. This works when there is one unknown column, or several
+    /// with the same type ID, which then share the remaining space equally.
+    fn size_raw_columns(columns: &mut [ColumnDef], row_length: usize) -> Result<()> {
+        let mut raw_ids = columns.iter().filter_map(|c| c.raw_type).map(|r| r.id);
+        let Some(id) = raw_ids.next() else {
+            return Ok(());
+        };
+        if let Some(other) = raw_ids.find(|&other| other != id) {
+            return Err(BdatError::UnknownValueType(other));
+        }
+        let raw_count = columns.iter().filter(|c| c.raw_type.is_some()).count();
+        let known_size: usize = columns.iter().map(ColumnDef::data_size).sum();
+        let remaining = row_length
+            .checked_sub(known_size)
+            .filter(|len| *len > 0 && len.is_multiple_of(raw_count))
+            .ok_or(BdatError::UnknownValueType(id))?;
+        for raw in columns.iter_mut().filter_map(|c| c.raw_type.as_mut()) {
+            raw.size = remaining / raw_count;
+        }
+        Ok(())
     }
 
     fn read_value_v2(
         table_data: &TableData<'b>,
         mut buf: impl Read,
-        col_type: ValueType,
+        col: &ColumnDef,
     ) -> Result<Value<'b>> {
-        Ok(match col_type {
+        Ok(match col.value_type {
             ValueType::Unknown => Value::Unknown,
             ValueType::UnsignedByte => Value::UnsignedByte(buf.read_u8()?),
             ValueType::UnsignedShort => Value::UnsignedShort(buf.read_u16::<E>()?),
@@ -215,7 +263,12 @@ impl<'b, R: ModernRead<'b>, E: ByteOrder> TableReader<R, E> {
                 table_data.get_string(buf.read_u32::<E>()? as usize, usize::MAX)?,
             ),
             ValueType::Unknown2 => Value::Unknown2(buf.read_u8()?),
-            ValueType::Unknown3 => Value::Unknown3(buf.read_u16::<E>()?),
+            ValueType::MessageId => Value::MessageId(buf.read_u16::<E>()?),
+            ValueType::Raw => {
+                let mut bytes = vec![0; col.data_size()];
+                buf.read_exact(&mut bytes)?;
+                Value::Raw(bytes.into())
+            }
         })
     }
 }
@@ -284,9 +337,11 @@ where
     E: ByteOrder,
 {
     fn read_table_data(&mut self, length: usize) -> Result<Cow<'b, [u8]>> {
-        Ok(Cow::Borrowed(
-            &self.data.clone().into_inner()[self.table_offset..self.table_offset + length],
-        ))
+        let data: &'b [u8] = self.data.get_ref();
+        data.get(self.table_offset..)
+            .and_then(|data| data.get(..length))
+            .map(Cow::Borrowed)
+            .ok_or(BdatError::MalformedBdat(Scope::Table))
     }
 
     #[inline]
@@ -333,23 +388,19 @@ where
 {
     type TableOut = ModernTable<'b>;
 
-    /// Reads all tables from the BDAT source.
-    fn get_tables(&mut self) -> Result<Vec<ModernTable<'b>>> {
-        let mut tables = Vec::with_capacity(self.header.table_count);
-
-        for i in 0..self.header.table_count {
-            self.tables
-                .reader
-                .seek_table(self.header.table_offsets[i])?;
-            let table = self.read_table()?;
-            tables.push(table);
-        }
-
-        Ok(tables)
+    fn get_table(&mut self, index: usize) -> Result<ModernTable<'b>> {
+        self.tables
+            .reader
+            .seek_table(self.header.table_offsets[index])?;
+        self.read_table(index)
     }
 
     /// Returns the number of tables in the BDAT file.
     fn table_count(&self) -> usize {
         self.header.table_count
     }
+
+    fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
 }
@@ -9,31 +9,39 @@ use std::{
 
 use byteorder::{ByteOrder, WriteBytesExt};
 
-use crate::{BdatError, ValueType};
-use crate::io::BDAT_MAGIC;
-use crate::{error::Result, Cell, Label, ModernTable, Row, TableAccessor, Value};
+use crate::error::Scope;
+use crate::io::{to_offset, BDAT_MAGIC};
+use crate::table::util::check_row_ids;
+use crate::{error::Result, Cell, Label, ModernTable, Progress, TableAccessor, Value};
+use crate::{BdatError, BdatVersion, ValueType};
 
-use super::FileHeader;
+use super::{FileHeader, ModernWriteOptions};
 
 pub(crate) struct BdatWriter<W, E> {
     stream: W,
+    opts: ModernWriteOptions,
     _endianness: PhantomData<E>,
 }
 
 struct LabelTable {
-    map: HashMap<Rc<Label>, u32>,
-    pairs: Vec<(Rc<Label>, u32)>,
-    offset: u32,
+    map: HashMap<Rc<Label>, usize>,
+    pairs: Vec<(Rc<Label>, usize)>,
+    offset: usize,
 }
 
+/// The size of a table's header: magic, table version, column/row counts, base ID,
+/// an unknown field, then 6 offset/length fields.
+const TABLE_HEADER_LEN: usize = 4 * 12;
+
 impl<W, E> BdatWriter<W, E>
 where
-    W: Write + Seek,
+    W: Write,
     E: ByteOrder,
 {
-    pub fn new(writer: W) -> Self {
+    pub fn new(writer: W, opts: ModernWriteOptions) -> Self {
         Self {
             stream: writer,
+            opts,
             _endianness: PhantomData,
         }
     }
@@ -41,44 +49,37 @@ where
     pub fn write_file<'t>(
         &mut self,
         tables: impl IntoIterator<Item = impl Borrow<ModernTable<'t>>>,
+        progress: &mut dyn FnMut(Progress),
     ) -> Result<()> {
-        let (table_bytes, table_offsets, total_len, table_count) = tables
-            .into_iter()
-            .map(|table| {
-                let mut data = vec![];
-                let cursor = Cursor::new(&mut data);
-
-                BdatWriter::<_, E>::new(cursor)
-                    .write_table(table.borrow())
-                    .map(|_| data)
-            })
-            .try_fold(
-                (Vec::new(), Vec::new(), 0, 0),
-                |(mut tot_bytes, mut offsets, len, count), table_bytes| {
-                    table_bytes.map(|mut bytes| {
-                        let new_len = bytes.len();
-                        (
-                            {
-                                tot_bytes.append(&mut bytes);
-                                tot_bytes
-                            },
-                            {
-                                offsets.push(len);
-                                offsets
-                            },
-                            len + new_len,
-                            count + 1,
-                        )
-                    })
-                },
-            )?;
+        let mut tables = tables.into_iter().collect::<Vec<_>>();
+        if self.opts.sort_tables {
+            // Stable sort, so tables with the same hash keep their relative order
+            tables.sort_by_key(|t| table_hash(t.borrow()));
+        }
+
+        let table_count = tables.len();
+        // Magic, version, table count, file size, then an offset for each table
+        let header_len = 16 + table_count * 4;
+        let mut table_bytes = Vec::new();
+        let mut table_offsets = Vec::with_capacity(table_count);
+        for (i, table) in tables.into_iter().enumerate() {
+            let mut data = vec![];
+            BdatWriter::<_, E>::new(&mut data, self.opts).write_table(table.borrow())?;
+            table_offsets.push(table_bytes.len());
+            table_bytes.append(&mut data);
+            progress(Progress::TableWritten {
+                done: i + 1,
+                total: table_count,
+                bytes: header_len + table_bytes.len(),
+            });
+        }
 
         let header = FileHeader {
             table_count,
             table_offsets,
         };
 
-        self.write_header(header, total_len)?;
+        self.write_header(header, table_bytes.len())?;
         self.stream.write_all(&table_bytes)?;
 
         Ok(())
@@ -91,31 +92,24 @@ where
             8
         };
 
-        let header_len = 4 + 4 + magic_len + u32::try_from(header.table_offsets.len() * 4)?;
+        let header_len = 4 + 4 + magic_len + header.table_offsets.len() * 4;
 
-        self.w_u32(u32::try_from(header.table_count)?)?;
+        self.w_u32(to_offset(header.table_count, Scope::File)?)?;
         // File size
-        self.w_u32(u32::try_from(table_data_len)? + header_len)?;
+        self.w_u32(to_offset(table_data_len + header_len, Scope::File)?)?;
         for offset in header.table_offsets {
-            self.w_u32(u32::try_from(offset)? + header_len)?;
+            self.w_u32(to_offset(offset + header_len, Scope::File)?)?;
         }
         Ok(())
     }
 
     fn write_table(&mut self, table: &ModernTable) -> Result<()> {
-        let table_offset = self.stream.stream_position()?;
-
         let columns = table.columns.as_slice();
 
         let column_count = columns.len().try_into()?;
         let row_count = table.rows.len().try_into()?;
-        let base_id = table
-            .rows
-            .iter()
-            .map(Row::id)
-            .min()
-            .unwrap_or_default()
-            .try_into()?;
+        check_row_ids(&table.rows, BdatVersion::Modern)?;
+        let base_id = table.base_id.try_into()?;
 
         let mut primary_keys = vec![];
         let mut label_table = LabelTable::default();
@@ -131,8 +125,11 @@ where
                 if col.value_type() == ValueType::HashRef {
                     primary_col.get_or_insert_with(|| (col.label.clone(), i));
                 }
-                data.write_u8(col.value_type as u8)?;
-                data.write_u16::<E>(u16::try_from(label_table.get(Cow::Borrowed(&col.label)))?)?;
+                data.write_u8(col.raw_type.map_or(col.value_type as u8, |raw| raw.id))?;
+                data.write_u16::<E>(to_offset(
+                    label_table.get(Cow::Borrowed(&col.label)),
+                    Scope::Table,
+                )?)?;
             }
 
             data
@@ -171,9 +168,17 @@ where
             primary_keys.sort_unstable();
 
             // Make sure there are no duplicate hashes
-            if let Some(dups) = primary_keys.windows(2).find(|w| w.len() > 1 && w[0].0 == w[1].0) {
+            if let Some(dups) = primary_keys
+                .windows(2)
+                .find(|w| w.len() > 1 && w[0].0 == w[1].0)
+            {
                 let &[a, b] = dups else { unreachable!() };
-                return Err(BdatError::DuplicateKey(Box::new((primary_col.unwrap().0, Label::Hash(a.0), a.1.try_into()?, b.1.try_into()?))));
+                return Err(BdatError::DuplicateKey(Box::new((
+                    primary_col.unwrap().0,
+                    Label::Hash(a.0),
+                    a.1.try_into()?,
+                    b.1.try_into()?,
+                ))));
             }
 
             let mut buf = Vec::with_capacity(primary_keys.len() * 8);
@@ -196,23 +201,26 @@ where
 
         // Build tables. Order probably doesn't matter, but we stick to the order the game uses:
         // columns, hashes, row, strings
-        let mut base_offset = (self.stream.stream_position()? - table_offset) as u32 + 4 * 6;
-        self.w_u32(base_offset)?; // column offset, relative to the start of the table
-        base_offset += u32::try_from(column_table.len())?;
-        self.w_u32(base_offset)?; // hash table offset, relative to the start of the table
-        base_offset += u32::try_from(primary_key_table.len())?;
-        self.w_u32(base_offset)?; // rows offset, relative to the start of the table
-        base_offset += u32::try_from(row_table.len())?;
-        self.w_u32(row_len.try_into()?)?; // data length of a single row
-        self.w_u32(base_offset)?;
-        self.w_u32(ser_strings_table.len().try_into()?)?;
+        let mut base_offset = TABLE_HEADER_LEN;
+        // column offset, relative to the start of the table
+        self.w_u32(to_offset(base_offset, Scope::Table)?)?;
+        base_offset += column_table.len();
+        // hash table offset, relative to the start of the table
+        self.w_u32(to_offset(base_offset, Scope::Table)?)?;
+        base_offset += primary_key_table.len();
+        // rows offset, relative to the start of the table
+        self.w_u32(to_offset(base_offset, Scope::Table)?)?;
+        base_offset += row_table.len();
+        self.w_u32(to_offset(row_len, Scope::Table)?)?; // data length of a single row
+        self.w_u32(to_offset(base_offset, Scope::Table)?)?;
+        self.w_u32(to_offset(ser_strings_table.len(), Scope::Table)?)?;
 
         self.stream.write_all(&column_table)?;
         self.stream.write_all(&primary_key_table)?;
         self.stream.write_all(&row_table)?;
         self.stream.write_all(&ser_strings_table)?;
 
-        let written = self.stream.stream_position()? - table_offset;
+        let written = base_offset + ser_strings_table.len();
         let padding = (4 - written % 4) % 4;
         for _ in 0..padding {
             self.stream.write_u8(0)?;
@@ -225,22 +233,24 @@ where
         writer: &mut impl Write,
         value: &Value,
         string_map: &mut LabelTable,
-    ) -> std::io::Result<()> {
-        match value {
+    ) -> Result<()> {
+        Ok(match value {
             Value::Unknown => panic!("tried to serialize unknown value"),
             Value::UnsignedByte(b) | Value::Percent(b) | Value::Unknown2(b) => writer.write_u8(*b),
-            Value::UnsignedShort(s) | Value::Unknown3(s) => writer.write_u16::<E>(*s),
+            Value::UnsignedShort(s) | Value::MessageId(s) => writer.write_u16::<E>(*s),
             Value::UnsignedInt(i) | Value::HashRef(i) => writer.write_u32::<E>(*i),
             Value::SignedByte(b) => writer.write_i8(*b),
             Value::SignedShort(s) => writer.write_i16::<E>(*s),
             Value::SignedInt(i) => writer.write_i32::<E>(*i),
             Value::String(s) | Value::DebugString(s) => {
                 // TODO to_string necessary?
-                writer.write_u32::<E>(string_map.get(Cow::Owned(Label::String(s.to_string()))))
+                let offset = string_map.get(Cow::Owned(Label::String(s.to_string())));
+                writer.write_u32::<E>(to_offset(offset, Scope::Table)?)
             }
             // TODO only accept CFloat
             Value::Float(f) => writer.write_f32::<E>((*f).into()),
-        }
+            Value::Raw(bytes) => writer.write_all(bytes),
+        }?)
     }
 
     #[inline(always)]
@@ -250,7 +260,7 @@ where
 }
 
 impl LabelTable {
-    pub fn get(&mut self, label: Cow<Label>) -> u32 {
+    pub fn get(&mut self, label: Cow<Label>) -> usize {
         if let Label::String(s) = &*label {
             if s.is_empty() {
                 // The game often uses the 0 at the start of the table for an empty string
@@ -276,7 +286,7 @@ impl LabelTable {
         self.map.insert(label.clone(), offset);
         self.pairs.push((label.clone(), offset));
         self.offset += match &*label {
-            Label::String(s) | Label::Unhashed(s) => u32::try_from(s.len()).unwrap() + 1,
+            Label::String(s) | Label::Unhashed(s) => s.len() + 1,
             _ => 4,
         };
 
@@ -284,7 +294,7 @@ impl LabelTable {
     }
 
     pub fn write<E: ByteOrder>(self) -> std::io::Result<Vec<u8>> {
-        let mut data = vec![0u8; self.offset as usize];
+        let mut data = vec![0u8; self.offset];
         let mut cursor = Cursor::new(&mut data);
         let mut written = 1;
 
@@ -301,7 +311,7 @@ impl LabelTable {
                 Label::String(s) | Label::Unhashed(s) => {
                     cursor.write_all(s.as_bytes())?;
                     cursor.write_u8(0)?;
-                    written += 1 + s.len() as u32;
+                    written += 1 + s.len();
                 }
                 Label::Hash(h) => {
                     cursor.write_u32::<E>(*h)?;
@@ -323,3 +333,11 @@ impl Default for LabelTable {
         }
     }
 }
+
+/// Returns the hash the game uses to look up a table.
+fn table_hash(table: &ModernTable) -> u32 {
+    match &table.name {
+        Label::Hash(h) => *h,
+        Label::String(s) | Label::Unhashed(s) => crate::hash::murmur3_str(s),
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::Result;
-use crate::{Label, TableAccessor};
+use crate::{Diagnostic, Label, Progress, TableAccessor};
 use std::collections::HashMap;
 use std::io::Cursor;
 use std::marker::PhantomData;
@@ -17,6 +17,14 @@ pub struct BdatSlice<'b, E> {
     _endianness: PhantomData<E>,
 }
 
+/// A source of BDAT tables.
+///
+/// Implementors must provide [`get_table`] or [`get_tables`] (or both): each one is
+/// implemented with the other by default. Readers in this crate provide [`get_table`], which
+/// only parses the requested table.
+///
+/// [`get_table`]: BdatFile::get_table
+/// [`get_tables`]: BdatFile::get_tables
 pub trait BdatFile<'b> {
     /// The output table type
     type TableOut;
@@ -26,11 +34,56 @@ pub trait BdatFile<'b> {
     /// ## Future compatibility
     /// This function might start returning an iterator when Rust 1.75.0
     /// hits stable (specifically [this issue](https://github.com/rust-lang/rust/issues/91611)).
-    fn get_tables(&mut self) -> Result<Vec<Self::TableOut>>;
+    fn get_tables(&mut self) -> Result<Vec<Self::TableOut>> {
+        self.get_tables_with_progress(&mut |_| {})
+    }
+
+    /// Reads all tables from the BDAT source, calling `progress` with
+    /// [`Progress::TableRead`] after each table is parsed.
+    ///
+    /// The default implementation reads each table with [`BdatFile::get_table`].
+    fn get_tables_with_progress(
+        &mut self,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Vec<Self::TableOut>> {
+        let total = self.table_count();
+        (0..total)
+            .map(|i| {
+                let table = self.get_table(i)?;
+                progress(Progress::TableRead { done: i + 1, total });
+                Ok(table)
+            })
+            .collect()
+    }
+
+    /// Reads a single table, by its position in the file.
+    ///
+    /// Tables are only parsed when requested, so reading tables one at a time lets each table
+    /// be dropped before the next one is read. When reading from a [`std::io::Read`]
+    /// implementation, only that table's data is loaded into memory.
+    ///
+    /// The default implementation reads every table with [`BdatFile::get_tables`], and
+    /// keeps the requested one.
+    ///
+    /// ## Panics
+    /// Panics if `index` is not less than [`BdatFile::table_count`].
+    fn get_table(&mut self, index: usize) -> Result<Self::TableOut> {
+        Ok(self
+            .get_tables()?
+            .into_iter()
+            .nth(index)
+            .expect("table index out of bounds"))
+    }
 
     /// Returns the number of tables in the BDAT file.
     fn table_count(&self) -> usize;
 
+    /// Returns the non-fatal issues found in the tables that were read so far, and clears
+    /// them, so a caller reading one table at a time can report them per table.
+    fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
     /// Reads all tables from the BDAT source, then groups them by name.
     ///
     /// ## Future compatibility
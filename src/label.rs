@@ -3,6 +3,7 @@
 use crate::io::BdatVersion;
 use crate::Utf;
 use std::borrow::Cow;
+use std::str::FromStr;
 use std::{cmp::Ordering, fmt::Display};
 
 /// The label is hashed and an operation on a plain string (e.g. comparison) was requested.
@@ -10,6 +11,15 @@ use std::{cmp::Ordering, fmt::Display};
 #[error("label is not a string")]
 pub struct LabelNotStringError;
 
+/// A label could not be parsed from a string. See [`Label`]'s [`FromStr`] implementation.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum LabelParseError {
+    #[error("label is empty")]
+    Empty,
+    #[error("invalid hash label {0:?}")]
+    InvalidHash(String),
+}
+
 /// A name for a BDAT element (table, column, ID, etc.)
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -46,6 +56,37 @@ impl Label {
         }
     }
 
+    /// Parses a table or column name for a BDAT version.
+    ///
+    /// In [`BdatVersion::Modern`] files, names can be written as plain names, which are
+    /// hashed, or in any of the formats hashed labels are displayed in: `<01ABCDEF>`,
+    /// `0x1ABCDEF` or `Name (<01ABCDEF>)`. Legacy formats have no hashed labels, so names are
+    /// always kept as written.
+    ///
+    /// ```
+    /// use bdat::{BdatVersion, Label, label_hash};
+    ///
+    /// let modern = BdatVersion::Modern;
+    /// assert_eq!(label_hash!("Name"), Label::parse_for_version("Name", modern));
+    /// assert_eq!(Label::Hash(0xABCD), Label::parse_for_version("0xABCD", modern));
+    /// assert_eq!(Label::Hash(0xABCD), Label::parse_for_version("Name (<0000ABCD>)", modern));
+    ///
+    /// let legacy = BdatVersion::LegacySwitch;
+    /// assert_eq!(Label::from("<0000ABCD>"), Label::parse_for_version("<0000ABCD>", legacy));
+    /// ```
+    pub fn parse_for_version(text: &str, version: BdatVersion) -> Self {
+        if !version.are_labels_hashed() {
+            return Label::String(text.to_string());
+        }
+        if let Some((_, hash)) = Self::split_named_hash(text) {
+            return Label::Hash(hash);
+        }
+        match text.parse() {
+            Ok(label @ Label::Hash(_)) => label,
+            _ => Label::Hash(crate::hash::murmur3_str(text)),
+        }
+    }
+
     /// If needed, turns the label into a hashed label.
     pub fn into_hash(self, version: BdatVersion) -> Self {
         if !version.are_labels_hashed() {
@@ -84,18 +125,107 @@ impl Label {
 
     /// An alternative to [`ToString::to_string`] that returns a reference to the label if it's
     /// already a string.
-    pub fn to_string_convert(&self) -> Utf {
+    pub fn to_string_convert(&self) -> Utf<'_> {
         match self {
             Self::String(s) | Self::Unhashed(s) => Cow::Borrowed(s.as_str()),
             _ => Cow::Owned(self.to_string()),
         }
     }
 
+    /// Like [`ToString::to_string`], but names that were resolved from a hash
+    /// ([`Label::Unhashed`]) are followed by the hash, e.g. `Name (<1A2B3C4D>)`.
+    ///
+    /// The hash can be read back with [`Label::split_named_hash`], even if the name
+    /// is no longer known.
+    ///
+    /// ```
+    /// use bdat::Label;
+    ///
+    /// let label = Label::Unhashed("Name".to_string());
+    /// assert_eq!("Name (<25EFA387>)", label.to_string_with_hash());
+    /// assert_eq!("Name", Label::from("Name").to_string_with_hash());
+    /// ```
+    pub fn to_string_with_hash(&self) -> String {
+        match self {
+            Self::Unhashed(s) => format!("{s} ({})", Self::Hash(crate::hash::murmur3_str(s))),
+            l => l.to_string(),
+        }
+    }
+
+    /// Splits a name that is followed by its hash, as written by
+    /// [`Label::to_string_with_hash`], into the name and the hash.
+    ///
+    /// The hash can be written in any of the formats accepted by [`Label`]'s [`FromStr`]
+    /// implementation. Returns `None` if the text doesn't end with a hash in parentheses.
+    ///
+    /// ```
+    /// use bdat::Label;
+    ///
+    /// assert_eq!(Some(("Name", 0x1A2B3C4D)), Label::split_named_hash("Name (<1A2B3C4D>)"));
+    /// assert_eq!(Some(("Name", 0x1A2B3C4D)), Label::split_named_hash("Name (0x1A2B3C4D)"));
+    /// assert_eq!(None, Label::split_named_hash("Name (Old)"));
+    /// ```
+    pub fn split_named_hash(text: &str) -> Option<(&str, u32)> {
+        let (name, hash) = text.strip_suffix(')')?.rsplit_once(" (")?;
+        match hash.parse() {
+            Ok(Self::Hash(hash)) => Some((name, hash)),
+            _ => None,
+        }
+    }
+
     fn as_str(&self) -> &str {
         self.try_into().expect("label is not a string")
     }
 }
 
+/// Parses a label from the formats it is displayed in:
+/// * `<01ABCDEF>` (exactly 8 hex digits) => `Label::Hash(0x01abcdef)`
+/// * `0x1ABCDEF` (up to 8 hex digits) => `Label::Hash(0x01abcdef)`
+/// * anything else => `Label::String`
+///
+/// Unlike [`Label::parse`], malformed hashes are rejected instead of being treated
+/// as plain strings.
+///
+/// ```
+/// use bdat::Label;
+///
+/// assert_eq!(Ok(Label::Hash(0xDEADBEEF)), "<DEADBEEF>".parse());
+/// assert_eq!(Ok(Label::Hash(0xBEEF)), "0xbeef".parse());
+/// assert_eq!(Ok(Label::String("CHR_PC".to_string())), "CHR_PC".parse());
+/// assert!("<XYZ>".parse::<Label>().is_err());
+/// ```
+impl FromStr for Label {
+    type Err = LabelParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LabelParseError::InvalidHash(s.to_string());
+        if s.is_empty() {
+            return Err(LabelParseError::Empty);
+        }
+        if let Some(hex) = s.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            if hex.len() != 8 {
+                return Err(invalid());
+            }
+            return parse_hex(hex).map(Label::Hash).ok_or_else(invalid);
+        }
+        if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            if hex.is_empty() || hex.len() > 8 {
+                return Err(invalid());
+            }
+            return parse_hex(hex).map(Label::Hash).ok_or_else(invalid);
+        }
+        Ok(Label::String(s.to_string()))
+    }
+}
+
+/// Like `u32::from_str_radix`, but doesn't accept signs.
+fn parse_hex(hex: &str) -> Option<u32> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
 impl From<String> for Label {
     fn from(s: String) -> Self {
         Self::String(s)
@@ -15,6 +15,7 @@
 //! to parse Xenoblade 3 BDAT files from a slice or a [`std::io::Read`] stream respectively.
 //!
 //! The [`label_hash!`] macro can be used to quickly generate hashed labels from plain-text strings.
+//! For string literals, [`label!`] also accepts hashes in their displayed form, e.g. `<01ABCDEF>`.
 //!
 //! See also: [`ModernTable`]
 //!
@@ -147,9 +148,19 @@
 //! to transcode BDAT to other formats.  
 //! The [bdat-toolset] crate will convert BDAT to CSV and JSON, and JSON to BDAT.
 //!
+//! The `csv` and `json` feature flags enable the `fmt` module, which exports tables the same
+//! way bdat-toolset does. With `json`, tables can also be converted to and from
+//! `serde_json::Value`s, see `Table::to_json_value` and `Table::from_json_value`.
+//!
+//! The `test-utils` feature flag enables the `test_utils` module, with table fixtures, round
+//! trips and an `assert_tables_eq!` macro that lists what differs, for tests in tools that
+//! work with BDAT tables.
+//!
 //! [MONOLITHSOFT]: https://www.monolithsoft.co.jp/
 //! [bdat-toolset]: https://github.com/RoccoDev/bdat-rs/tree/master/toolset
 
+#[cfg(any(feature = "csv", feature = "json"))]
+pub mod fmt;
 pub mod hash;
 #[cfg(feature = "serde")]
 pub mod serde;
@@ -158,9 +169,12 @@ pub(crate) mod error;
 pub(crate) mod io;
 pub mod label;
 pub mod table;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 pub use error::BdatError;
 pub use error::Result as BdatResult;
+pub use error::ValueCastError;
 pub use io::detect::*;
 pub use io::*;
 pub use label::Label;
@@ -168,4 +182,6 @@ pub use label::Label;
 pub use table::cell::*;
 pub use table::column::*;
 pub use table::row::*;
-pub use table::{LegacyTable, ModernTable, Table, TableAccessor, TableBuilder};
+pub use table::{
+    ColumnStats, LegacyTable, ModernTable, Table, TableAccessor, TableBuilder, TableSlice,
+};
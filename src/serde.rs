@@ -34,13 +34,23 @@ enum ValueTypeFields {
     Value,
 }
 
+/// A flag value, written as a boolean for single-bit flags. Numbers are accepted for every
+/// flag, as written by earlier versions.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FlagValue {
+    Bool(bool),
+    Number(u32),
+}
+
 struct HexVisitor;
+struct RawVisitor;
 
 /// An implementation of [`DeserializeSeed`] for [`Cell`]s.
 pub struct CellSeed<'a>(&'a ColumnDef);
 
 impl ColumnDef {
-    pub fn as_cell_seed(&self) -> CellSeed {
+    pub fn as_cell_seed(&self) -> CellSeed<'_> {
         CellSeed(self)
     }
 
@@ -51,7 +61,7 @@ impl ColumnDef {
         }
     }
 
-    pub fn owned_cell_serializer<'a, 't>(&'a self, cell: Cell<'t>) -> SerializeCell<'a, '_, 't> {
+    pub fn owned_cell_serializer<'a, 't>(&'a self, cell: Cell<'t>) -> SerializeCell<'a, 'a, 't> {
         SerializeCell {
             column: self,
             cell: Cow::Owned(cell),
@@ -71,10 +81,14 @@ impl<'a, 'b, 't> Serialize for SerializeCell<'a, 'b, 't> {
                 let keys = self.column.flags();
                 let mut map = serializer.serialize_map(Some(flag_values.len()))?;
                 for (i, val) in flag_values.iter().enumerate() {
-                    let name = keys.get(i).map(|f| &f.label).ok_or_else(|| {
+                    let flag = keys.get(i).ok_or_else(|| {
                         ser::Error::custom(format!("no name for flag at index {i}"))
                     })?;
-                    map.serialize_entry(&name, val)?;
+                    // Single-bit flags are booleans
+                    match flag.is_single_bit() {
+                        true => map.serialize_entry(&flag.label, &(*val != 0))?,
+                        false => map.serialize_entry(&flag.label, val)?,
+                    }
                 }
                 map.end()
             }
@@ -92,13 +106,20 @@ impl<'b> Serialize for Value<'b> {
             Value::UnsignedByte(b) | Value::Percent(b) | Value::Unknown2(b) => {
                 serializer.serialize_u8(*b)
             }
-            Value::UnsignedShort(s) | Value::Unknown3(s) => serializer.serialize_u16(*s),
+            Value::UnsignedShort(s) | Value::MessageId(s) => serializer.serialize_u16(*s),
             Value::UnsignedInt(i) => serializer.serialize_u32(*i),
             Value::SignedByte(b) => serializer.serialize_i8(*b),
             Value::SignedShort(s) => serializer.serialize_i16(*s),
             Value::SignedInt(i) => serializer.serialize_i32(*i),
             Value::String(s) | Value::DebugString(s) => serializer.serialize_str(s),
             Value::Float(f) => serializer.serialize_f32((*f).into()),
+            Value::Raw(bytes) => {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&self.to_string())
+                } else {
+                    serializer.serialize_bytes(bytes)
+                }
+            }
             Value::HashRef(h) => {
                 if serializer.is_human_readable() {
                     serializer.serialize_str(&format!("{}", Label::Hash(*h)))
@@ -130,7 +151,8 @@ impl ValueType {
             Self::Percent => Value::Percent(u8::deserialize(deserializer)?),
             Self::DebugString => Value::DebugString(Cow::deserialize(deserializer)?),
             Self::Unknown2 => Value::Unknown2(u8::deserialize(deserializer)?),
-            Self::Unknown3 => Value::Unknown3(u16::deserialize(deserializer)?),
+            Self::MessageId => Value::MessageId(u16::deserialize(deserializer)?),
+            Self::Raw => Value::Raw(deserializer.deserialize_any(RawVisitor)?.into()),
         })
     }
 }
@@ -161,14 +183,50 @@ impl<'de> Visitor<'de> for HexVisitor {
     where
         E: de::Error,
     {
+        // Name (<XXXXXXXX>), see Label::to_string_with_hash
+        if let Some((_, hash)) = Label::split_named_hash(v) {
+            return Ok(hash);
+        }
         match v.len() {
             10 if v.as_bytes()[0] == b'<' => u32::from_str_radix(&v[1..=8], 16), // <XXXXXXXX>
-            _ => u32::from_str_radix(v, 16),
+            _ => match v.strip_prefix("0x").or_else(|| v.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(hex, 16), // 0xXXXXXXXX
+                None => u32::from_str_radix(v, 16),
+            },
         }
         .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self))
     }
 }
 
+impl<'de> Visitor<'de> for RawVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("bytes or hex string")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let invalid = || de::Error::invalid_value(de::Unexpected::Str(v), &self);
+        if !v.len().is_multiple_of(2) || !v.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        (0..v.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&v[i..i + 2], 16).map_err(|_| invalid()))
+            .collect()
+    }
+}
+
 impl<'b> From<Value<'b>> for ValueWithType<'b> {
     fn from(v: Value<'b>) -> Self {
         Self {
@@ -327,13 +385,17 @@ impl<'a, 'de> DeserializeSeed<'de> for CellSeed<'a> {
                 A: MapAccess<'de>,
             {
                 // Cell::Flags
-                let map = HashMap::<String, u32>::deserialize(MapAccessDeserializer::new(map))?;
+                let map =
+                    HashMap::<String, FlagValue>::deserialize(MapAccessDeserializer::new(map))?;
                 let values = self
                     .0
                     .flags
                     .iter()
                     .filter_map(|f| map.get(&f.label))
-                    .copied()
+                    .map(|v| match v {
+                        FlagValue::Bool(b) => *b as u32,
+                        FlagValue::Number(n) => *n,
+                    })
                     .collect();
                 Ok(Cell::Flags(values))
             }
@@ -434,11 +496,11 @@ mod tests {
 
     #[test]
     fn deser_external() {
-        let ty = ValueType::Unknown3;
+        let ty = ValueType::MessageId;
         let value = ty
             .deser_value(&mut serde_json::Deserializer::from_str("1024"))
             .unwrap();
-        assert_eq!(value, Value::Unknown3(1024));
+        assert_eq!(value, Value::MessageId(1024));
     }
 
     #[test]
@@ -460,6 +522,18 @@ mod tests {
                 .unwrap(),
             Value::HashRef(0x01abcdef)
         );
+        assert_eq!(
+            ty.deser_value(&mut serde_json::Deserializer::from_str("\"0x01ABCDEF\""))
+                .unwrap(),
+            Value::HashRef(0x01abcdef)
+        );
+        assert_eq!(
+            ty.deser_value(&mut serde_json::Deserializer::from_str(
+                "\"ITM_Potion (<01ABCDEF>)\""
+            ))
+            .unwrap(),
+            Value::HashRef(0x01abcdef)
+        );
     }
 
     #[test]
@@ -470,6 +544,21 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn json_raw() {
+        let value = Value::Raw(vec![0x0a, 0xff, 0x00].into());
+        assert_eq!(r#""0aff00""#, serde_json::to_string(&value).unwrap());
+        assert_eq!(
+            value,
+            ValueType::Raw
+                .deser_value(&mut serde_json::Deserializer::from_str(r#""0aFF00""#))
+                .unwrap()
+        );
+        assert!(ValueType::Raw
+            .deser_value(&mut serde_json::Deserializer::from_str(r#""+a""#))
+            .is_err());
+    }
+
     #[test]
     #[allow(clippy::approx_constant)]
     fn deser_cell() {
@@ -522,6 +611,7 @@ mod tests {
                     flag_index: 2,
                 },
             ],
+            raw_type: None,
         };
 
         assert_eq!(
@@ -539,4 +629,31 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn serde_single_bit_flags() {
+        let column = ColumnDef {
+            label: Label::Hash(0),
+            value_type: ValueType::UnsignedInt,
+            count: 1,
+            flags: vec![FlagDef::new_bit("Bit", 0), FlagDef::new("Bits", 0b110, 1)],
+            raw_type: None,
+        };
+
+        assert_eq!(
+            r#"{"Bit":true,"Bits":3}"#,
+            serde_json::to_string(&column.cell_serializer(&Cell::Flags(vec![1, 3]))).unwrap()
+        );
+
+        // Numbers are still read for single-bit flags
+        for json in [r#"{"Bit":true,"Bits":3}"#, r#"{"Bit":1,"Bits":3}"#] {
+            assert_eq!(
+                Cell::Flags(vec![1, 3]),
+                column
+                    .as_cell_seed()
+                    .deserialize(&mut serde_json::Deserializer::from_str(json))
+                    .unwrap()
+            );
+        }
+    }
 }
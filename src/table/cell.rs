@@ -1,5 +1,5 @@
 use crate::legacy::float::BdatReal;
-use crate::{BdatError, BdatResult, BdatVersion, Label, RowRef};
+use crate::{BdatError, BdatResult, BdatVersion, Label, RowRef, ValueCastError};
 use enum_kinds::EnumKind;
 use num_enum::TryFromPrimitive;
 use std::borrow::Cow;
@@ -12,7 +12,7 @@ use std::fmt::Display;
 /// * Single-value cells ([`Cell::Single`]), containing a single [`Value`].
 /// * Arrays ([`Cell::List`]), containing multiple [`Value`]s, but all of the same type.
 /// * Flag containers ([`Cell::Flags`]), stored as a number, but interpreted as flags by masking
-/// bits.
+///   bits.
 ///
 /// Modern BDAT versions only support single-value cells.
 ///
@@ -64,8 +64,11 @@ pub enum Cell<'b> {
     ValueType,
     derive(TryFromPrimitive),
     repr(u8),
-    cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize)),
-    cfg_attr(feature = "serde", serde(into = "u8", try_from = "u8"))
+    cfg_attr(
+        feature = "serde",
+        derive(serde::Serialize, serde::Deserialize),
+        serde(into = "u8", try_from = "u8")
+    )
 )]
 pub enum Value<'b> {
     Unknown,
@@ -75,6 +78,8 @@ pub enum Value<'b> {
     SignedByte(i8),
     SignedShort(i16),
     SignedInt(i32),
+    /// Text, e.g. player-facing names in legacy tables. See [`ValueType::text_kind`] for
+    /// the other kinds of text.
     String(Utf<'b>),
     Float(BdatReal),
     /// A hash referencing a row in the same or some other table
@@ -85,10 +90,16 @@ pub enum Value<'b> {
     DebugString(Utf<'b>),
     /// [`BdatVersion::Modern`] unknown type (0xc)
     Unknown2(u8),
-    /// [`BdatVersion::Modern`] unknown type (0xd)
-    /// It seems to be some sort of translation index, mostly used for
-    /// `Name` and `Caption` fields.
-    Unknown3(u16),
+    /// [`BdatVersion::Modern`] reference to a row in a message table, which has the text
+    /// in each language. Mostly used for `Name` and `Caption` fields, this is how modern
+    /// tables refer to player-facing text.
+    MessageId(u16),
+    /// [`BdatVersion::Modern`] value of a type this crate doesn't know, kept as the bytes
+    /// from the file so it can be written back unchanged. The column records the type ID
+    /// from the file, see [`ColumnDef::raw_type`].
+    ///
+    /// [`ColumnDef::raw_type`]: crate::ColumnDef::raw_type
+    Raw(Cow<'b, [u8]>),
 }
 
 /// An optionally-borrowed clone-on-write UTF-8 string.
@@ -119,7 +130,7 @@ pub trait FromCell<'t, 'tb> {
 impl<'b> Cell<'b> {
     /// Gets a reference to the cell's value, if it
     /// is a [`Cell::Single`], and returns [`None`] otherwise.
-    pub fn as_single(&self) -> Option<&Value> {
+    pub fn as_single(&self) -> Option<&Value<'b>> {
         match self {
             Self::Single(v) => Some(v),
             _ => None,
@@ -173,6 +184,16 @@ impl<'b> Cell<'b> {
 }
 
 impl<'b> Value<'b> {
+    /// Creates a [`Value::MessageId`], the former name of the variant.
+    ///
+    /// This only replaces the variant in expressions. Patterns must use
+    /// [`Value::MessageId`].
+    #[deprecated(note = "renamed to `Value::MessageId`")]
+    #[allow(non_snake_case)]
+    pub const fn Unknown3(id: u16) -> Self {
+        Self::MessageId(id)
+    }
+
     /// Casts the underlying value to `V`.
     ///
     /// For strings, the [`Utf`] type alias, or `&str` can be used.
@@ -215,7 +236,7 @@ impl<'b> Value<'b> {
             Self::SignedByte(b) => *b as u32,
             Self::Percent(b) | Self::UnsignedByte(b) | Self::Unknown2(b) => *b as u32,
             Self::SignedShort(s) => *s as u32,
-            Self::UnsignedShort(s) | Self::Unknown3(s) => *s as u32,
+            Self::UnsignedShort(s) | Self::MessageId(s) => *s as u32,
             Self::SignedInt(i) => *i as u32,
             Self::UnsignedInt(i) | Self::HashRef(i) => *i,
             _ => panic!("value is not an integer"),
@@ -260,14 +281,54 @@ impl<'b> Value<'b> {
     }
 }
 
+/// The kinds of text a value can hold, see [`ValueType::text_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TextKind {
+    /// A string stored in the table ([`ValueType::String`])
+    String,
+    /// A string only used by developers, like `DebugName` ([`ValueType::DebugString`])
+    Debug,
+    /// A reference to text in a message table ([`ValueType::MessageId`])
+    Message,
+}
+
 impl ValueType {
+    /// The former name of [`ValueType::MessageId`].
+    #[deprecated(note = "renamed to `ValueType::MessageId`")]
+    #[allow(non_upper_case_globals)]
+    pub const Unknown3: Self = Self::MessageId;
+
+    /// Returns the kind of text values of this type hold, or `None` if they aren't text.
+    ///
+    /// Localization tools can use this to skip debug strings, which are never shown to
+    /// players. In modern tables, player-facing text is in message tables, referenced by
+    /// [`TextKind::Message`] columns.
+    pub fn text_kind(self) -> Option<TextKind> {
+        match self {
+            Self::String => Some(TextKind::String),
+            Self::DebugString => Some(TextKind::Debug),
+            Self::MessageId => Some(TextKind::Message),
+            _ => None,
+        }
+    }
+
     /// Returns the size of a single cell with this value type.
+    ///
+    /// The size of [`ValueType::Raw`] cells depends on the column, so this returns 0 for them.
+    /// Use [`ColumnDef::data_size`] instead.
+    ///
+    /// [`ColumnDef::data_size`]: crate::ColumnDef::data_size
     pub fn data_len(self) -> usize {
         use ValueType::*;
         match self {
-            Unknown => 0,
+            Unknown | Raw => 0,
             UnsignedByte | SignedByte | Percent | Unknown2 => 1,
-            UnsignedShort | SignedShort | Unknown3 => 2,
+            UnsignedShort | SignedShort | MessageId => 2,
             UnsignedInt | SignedInt | String | Float | HashRef | DebugString => 4,
         }
     }
@@ -276,7 +337,9 @@ impl ValueType {
     pub fn is_supported(self, version: BdatVersion) -> bool {
         use ValueType::*;
         match self {
-            Percent | Unknown2 | Unknown3 | HashRef | DebugString => version == BdatVersion::Modern,
+            Percent | Unknown2 | MessageId | HashRef | DebugString | Raw => {
+                version == BdatVersion::Modern
+            }
             _ => true,
         }
     }
@@ -329,8 +392,9 @@ impl<'b> Display for Value<'b> {
             Self::Unknown => Ok(()),
             Self::HashRef(h) => Label::Hash(*h).fmt(f),
             Self::Percent(v) => write!(f, "{}%", v),
+            Self::Raw(bytes) => bytes.iter().try_for_each(|b| write!(f, "{b:02x}")),
             v => {
-                default_display!(f, v, SignedByte SignedShort SignedInt UnsignedByte UnsignedShort UnsignedInt DebugString Unknown2 Unknown3 String Float)
+                default_display!(f, v, SignedByte SignedShort SignedInt UnsignedByte UnsignedShort UnsignedInt DebugString Unknown2 MessageId String Float)
             }
         }
     }
@@ -378,7 +442,7 @@ macro_rules! from_value {
 }
 
 from_value!(u32, Value::UnsignedInt Value::HashRef);
-from_value!(u16, Value::UnsignedShort Value::Unknown3);
+from_value!(u16, Value::UnsignedShort Value::MessageId);
 from_value!(u8, Value::UnsignedByte Value::Unknown2 Value::Percent);
 from_value!(i32, Value::SignedInt);
 from_value!(i16, Value::SignedShort);
@@ -408,3 +472,142 @@ impl<'t, 'tb> FromValue<'t, 'tb> for &'t str {
         }
     }
 }
+
+impl ValueCastError {
+    fn new<T>(value: &Value) -> Self {
+        Self {
+            found: value.into(),
+            target: std::any::type_name::<T>(),
+        }
+    }
+}
+
+/// Implements `TryFrom<&Value>` and `TryFrom<Value>` through [`FromValue`]
+macro_rules! try_from_value {
+    ($($ty:ty),*) => {
+        $(
+            impl<'t, 'tb> TryFrom<&'t Value<'tb>> for $ty {
+                type Error = ValueCastError;
+
+                fn try_from(value: &'t Value<'tb>) -> Result<Self, Self::Error> {
+                    <$ty>::extract(value).ok_or_else(|| ValueCastError::new::<$ty>(value))
+                }
+            }
+
+            impl<'tb> TryFrom<Value<'tb>> for $ty {
+                type Error = ValueCastError;
+
+                fn try_from(value: Value<'tb>) -> Result<Self, Self::Error> {
+                    Self::try_from(&value)
+                }
+            }
+        )*
+    };
+}
+
+try_from_value!(u32, u16, u8, i32, i16, i8, f32, BdatReal);
+
+impl<'t, 'tb> TryFrom<&'t Value<'tb>> for &'t str {
+    type Error = ValueCastError;
+
+    fn try_from(value: &'t Value<'tb>) -> Result<Self, Self::Error> {
+        <&str>::extract(value).ok_or_else(|| ValueCastError::new::<&str>(value))
+    }
+}
+
+impl<'t, 'tb> TryFrom<&'t Value<'tb>> for String {
+    type Error = ValueCastError;
+
+    fn try_from(value: &'t Value<'tb>) -> Result<Self, Self::Error> {
+        <&str>::try_from(value)
+            .map(ToString::to_string)
+            .map_err(|_| ValueCastError::new::<String>(value))
+    }
+}
+
+/// Moves the string out of the value, without copying it if the value owns it.
+impl<'tb> TryFrom<Value<'tb>> for String {
+    type Error = ValueCastError;
+
+    fn try_from(value: Value<'tb>) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) | Value::DebugString(s) => Ok(s.into_owned()),
+            v => Err(ValueCastError::new::<String>(&v)),
+        }
+    }
+}
+
+/// Integer values are `true` if they're not zero. Other types can't be converted.
+impl<'t, 'tb> TryFrom<&'t Value<'tb>> for bool {
+    type Error = ValueCastError;
+
+    fn try_from(value: &'t Value<'tb>) -> Result<Self, Self::Error> {
+        match value {
+            Value::UnsignedByte(_)
+            | Value::UnsignedShort(_)
+            | Value::UnsignedInt(_)
+            | Value::SignedByte(_)
+            | Value::SignedShort(_)
+            | Value::SignedInt(_)
+            | Value::Percent(_)
+            | Value::Unknown2(_)
+            | Value::MessageId(_) => Ok(value.to_integer() != 0),
+            _ => Err(ValueCastError::new::<bool>(value)),
+        }
+    }
+}
+
+impl<'tb> TryFrom<Value<'tb>> for bool {
+    type Error = ValueCastError;
+
+    fn try_from(value: Value<'tb>) -> Result<Self, Self::Error> {
+        Self::try_from(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TextKind, Value, ValueCastError, ValueType};
+
+    #[test]
+    fn text_kinds() {
+        assert_eq!(Some(TextKind::String), ValueType::String.text_kind());
+        assert_eq!(Some(TextKind::Debug), ValueType::DebugString.text_kind());
+        assert_eq!(Some(TextKind::Message), ValueType::MessageId.text_kind());
+        assert_eq!(None, ValueType::UnsignedShort.text_kind());
+        assert_eq!(ValueType::MessageId, ValueType::try_from(13).unwrap());
+        #[allow(deprecated)]
+        {
+            assert_eq!(ValueType::MessageId, ValueType::Unknown3);
+            assert_eq!(Value::MessageId(5), Value::Unknown3(5));
+            assert!(matches!(ValueType::MessageId, ValueType::Unknown3));
+        }
+    }
+
+    #[test]
+    fn value_try_from() {
+        assert_eq!(Ok(5u32), u32::try_from(&Value::UnsignedInt(5)));
+        assert_eq!(Ok(-3i8), i8::try_from(Value::SignedByte(-3)));
+        assert_eq!(Ok(1.5f32), f32::try_from(&Value::Float(1.5.into())));
+        assert_eq!(
+            Ok("text".to_string()),
+            String::try_from(Value::String("text".into()))
+        );
+        assert_eq!(
+            Ok("text"),
+            <&str>::try_from(&Value::DebugString("text".into()))
+        );
+        assert_eq!(Ok(true), bool::try_from(&Value::UnsignedByte(2)));
+        assert_eq!(Ok(false), bool::try_from(&Value::SignedShort(0)));
+
+        assert_eq!(
+            Err(ValueCastError {
+                found: ValueType::SignedInt,
+                target: "u32"
+            }),
+            u32::try_from(&Value::SignedInt(5))
+        );
+        assert!(bool::try_from(Value::String("true".into())).is_err());
+        assert!(bool::try_from(&Value::Raw(vec![1].into())).is_err());
+    }
+}
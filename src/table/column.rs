@@ -7,6 +7,7 @@ pub struct ColumnDef {
     pub(crate) label: Label,
     pub(crate) count: usize,
     pub(crate) flags: Vec<FlagDef>,
+    pub(crate) raw_type: Option<RawType>,
 }
 
 /// A builder interface for [`ColumnDef`].
@@ -31,6 +32,18 @@ pub struct FlagDef {
     pub(crate) flag_index: usize,
 }
 
+/// The original type of a [`ValueType::Raw`] column, whose type ID this crate doesn't know.
+///
+/// Cells in the column are kept as `size` bytes each, and written back with the original ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawType {
+    /// The type ID found in the file
+    pub(crate) id: u8,
+    /// The size of a single cell, in bytes
+    pub(crate) size: usize,
+}
+
 impl ColumnDef {
     /// Creates a new [`ColumnDef`]. For more advanced settings, such as item count or flag
     /// data, use [`ColumnBuilder`].
@@ -44,6 +57,7 @@ impl ColumnDef {
             label,
             flags,
             count: 1,
+            raw_type: None,
         }
     }
 
@@ -78,9 +92,35 @@ impl ColumnDef {
         &self.flags
     }
 
+    /// Returns the original type of a [`ValueType::Raw`] column, or [`None`] for columns
+    /// of known types.
+    pub fn raw_type(&self) -> Option<RawType> {
+        self.raw_type
+    }
+
     /// Returns the total space occupied by a cell of this column.
     pub fn data_size(&self) -> usize {
-        self.value_type.data_len() * self.count
+        match self.raw_type {
+            Some(raw) => raw.size * self.count,
+            None => self.value_type.data_len() * self.count,
+        }
+    }
+}
+
+impl RawType {
+    /// Creates a raw type with the given type ID and cell size in bytes.
+    pub fn new(id: u8, size: usize) -> Self {
+        Self { id, size }
+    }
+
+    /// Returns the type ID found in the file.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns the size of a single cell, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
     }
 }
 
@@ -119,6 +159,22 @@ impl FlagDef {
     pub fn shift_amount(&self) -> usize {
         self.flag_index
     }
+
+    /// Returns whether this flag masks a single bit, so its values are 0 or 1. Such flags
+    /// are written as booleans by the `serde` and `csv` features.
+    ///
+    /// ```
+    /// use bdat::FlagDef;
+    ///
+    /// assert!(FlagDef::new_bit("Bit", 3).is_single_bit());
+    /// assert!(!FlagDef::new("Bits", 0b110, 1).is_single_bit());
+    /// ```
+    pub fn is_single_bit(&self) -> bool {
+        u32::try_from(self.flag_index)
+            .ok()
+            .and_then(|shift| self.mask.checked_shr(shift))
+            == Some(1)
+    }
 }
 
 impl ColumnBuilder {
@@ -139,6 +195,12 @@ impl ColumnBuilder {
         self
     }
 
+    /// Sets the original type of a [`ValueType::Raw`] column.
+    pub fn set_raw_type(mut self, raw_type: RawType) -> Self {
+        self.0.raw_type = Some(raw_type);
+        self
+    }
+
     pub fn build(self) -> ColumnDef {
         self.0
     }
@@ -0,0 +1,158 @@
+//! Stable hashing of a table's logical content, see [`Table::content_hash`].
+
+use crate::{BdatVersion, Cell, Label, Table, TableAccessor, Value, ValueType};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit FNV-1a. Every number is written as fixed-size little-endian bytes, so the digest
+/// doesn't depend on the platform.
+struct Digest(u64);
+
+pub(crate) fn content_hash(table: &Table) -> u64 {
+    let version = match table.is_modern() {
+        true => BdatVersion::Modern,
+        false => BdatVersion::LegacySwitch,
+    };
+    let mut digest = Digest(FNV_OFFSET);
+    digest.label(table.name(), version);
+
+    digest.u64(table.columns().count() as u64);
+    for column in table.columns() {
+        digest.label(column.label(), version);
+        digest.bytes(&[column.value_type().into()]);
+        digest.u64(column.count() as u64);
+        digest.bytes(&[column.raw_type().map_or(0, |t| t.id())]);
+        digest.u64(column.flags().len() as u64);
+        for flag in column.flags() {
+            digest.str(flag.label());
+            digest.u32(flag.mask());
+        }
+    }
+
+    let mut rows = table.rows().collect::<Vec<_>>();
+    rows.sort_by_key(|r| r.id());
+    digest.u64(rows.len() as u64);
+    for row in rows {
+        digest.u64(row.id() as u64);
+        for cell in row.cells() {
+            match cell {
+                Cell::Single(value) => {
+                    digest.bytes(&[0]);
+                    digest.value(value);
+                }
+                Cell::List(values) => {
+                    digest.bytes(&[1]);
+                    digest.u64(values.len() as u64);
+                    values.iter().for_each(|v| digest.value(v));
+                }
+                Cell::Flags(flags) => {
+                    digest.bytes(&[2]);
+                    digest.u64(flags.len() as u64);
+                    flags.iter().for_each(|f| digest.u32(*f));
+                }
+            }
+        }
+    }
+    digest.0
+}
+
+impl Digest {
+    fn bytes(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn u64(&mut self, n: u64) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u64(s.len() as u64);
+        self.bytes(s.as_bytes());
+    }
+
+    /// Names of modern tables are hashed first, so resolved names hash the same as the
+    /// hashes they were resolved from.
+    fn label(&mut self, label: &Label, version: BdatVersion) {
+        match label.clone().into_hash(version) {
+            Label::Hash(hash) => {
+                self.bytes(&[0]);
+                self.u32(hash);
+            }
+            Label::String(s) | Label::Unhashed(s) => {
+                self.bytes(&[1]);
+                self.str(&s);
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        self.bytes(&[ValueType::from(value).into()]);
+        match value {
+            Value::Unknown => {}
+            Value::UnsignedByte(n) | Value::Percent(n) | Value::Unknown2(n) => self.bytes(&[*n]),
+            Value::UnsignedShort(n) | Value::MessageId(n) => self.bytes(&n.to_le_bytes()),
+            Value::UnsignedInt(n) | Value::HashRef(n) => self.u32(*n),
+            Value::SignedByte(n) => self.bytes(&n.to_le_bytes()),
+            Value::SignedShort(n) => self.bytes(&n.to_le_bytes()),
+            Value::SignedInt(n) => self.bytes(&n.to_le_bytes()),
+            // Fixed-point and floating-point reals with the same value are the same
+            Value::Float(f) => self.u32(f32::from(*f).to_bits()),
+            Value::String(s) | Value::DebugString(s) => self.str(s),
+            Value::Raw(bytes) => {
+                self.u64(bytes.len() as u64);
+                self.bytes(bytes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{label_hash, BdatFile, ColumnDef, Row, SwitchEndian, TableBuilder};
+
+    use super::*;
+
+    fn table(name: Label, rows: &[(usize, u32)], version: BdatVersion) -> Table<'static> {
+        rows.iter()
+            .fold(
+                TableBuilder::with_name(name)
+                    .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::from("Lv"))),
+                |builder, &(id, lv)| {
+                    builder.add_row(Row::new(id, vec![Cell::Single(Value::UnsignedInt(lv))]))
+                },
+            )
+            .build(version)
+    }
+
+    #[test]
+    fn same_content() {
+        let legacy = BdatVersion::LegacySwitch;
+        let original = table(Label::from("Enemy"), &[(1, 10), (2, 20)], legacy);
+        let hash = original.content_hash();
+        let mut bytes =
+            crate::legacy::to_vec::<SwitchEndian>([original.as_legacy()], legacy).unwrap();
+        let read = crate::legacy::from_bytes::<SwitchEndian>(&mut bytes, legacy)
+            .unwrap()
+            .get_tables()
+            .unwrap();
+        assert_eq!(hash, Table::from(read[0].clone()).content_hash());
+        // The digest must not change between runs or platforms
+        assert_eq!(0xac424914f0b544be, hash);
+
+        let changed = table(Label::from("Enemy"), &[(1, 10), (2, 21)], legacy);
+        assert_ne!(hash, changed.content_hash());
+
+        let modern = BdatVersion::Modern;
+        let resolved = table(Label::Unhashed("Enemy".into()), &[(1, 10)], modern);
+        let hashed = table(label_hash!("Enemy"), &[(1, 10)], modern);
+        assert_eq!(resolved.content_hash(), hashed.content_hash());
+    }
+}
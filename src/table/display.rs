@@ -0,0 +1,217 @@
+//! Human-readable [`Display`] and compact [`Debug`] output for tables.
+//!
+//! Displaying a table prints its name, its column headers and the first few rows, which
+//! is enough to tell tables apart in logs without flooding them. At most
+//! [`DISPLAY_ROW_LIMIT`] rows are printed by default, and the precision flag changes the
+//! limit, e.g. `{:.50}`. `Debug` only lists the columns and the number of rows.
+
+use std::fmt::{Debug, Display, Formatter, Result};
+
+use crate::{ColumnDef, Label, LegacyTable, ModernTable, Row, Table, TableSlice};
+
+use super::TableInner;
+
+/// The number of rows printed when displaying a table, unless a different amount is
+/// requested with the precision flag, e.g. `format!("{table:.50}")`.
+pub const DISPLAY_ROW_LIMIT: usize = 10;
+
+/// Cell contents longer than this are cut off when displaying a table.
+const MAX_CELL_WIDTH: usize = 32;
+
+struct TableView<'a, 'b> {
+    name: &'a Label,
+    format: &'static str,
+    base_id: usize,
+    columns: &'a [ColumnDef],
+    rows: &'a [Row<'b>],
+}
+
+impl<'a, 'b> TableView<'a, 'b> {
+    fn display(&self, f: &mut Formatter<'_>) -> Result {
+        let limit = f.precision().unwrap_or(DISPLAY_ROW_LIMIT);
+        writeln!(
+            f,
+            "{} ({} table, {} columns, {} rows)",
+            self.name,
+            self.format,
+            self.columns.len(),
+            self.rows.len()
+        )?;
+
+        let header = std::iter::once("ID".to_string())
+            .chain(self.columns.iter().map(|c| c.label().to_string()))
+            .map(truncate)
+            .collect::<Vec<_>>();
+        let rows = self
+            .rows
+            .iter()
+            .take(limit)
+            .map(|row| {
+                std::iter::once(row.id().to_string())
+                    .chain(row.cells().map(|c| c.to_string()))
+                    .map(truncate)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = header.iter().map(|s| s.chars().count()).collect::<Vec<_>>();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        write_line(f, &header, &widths)?;
+        let separator = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>();
+        write_line(f, &separator, &widths)?;
+        for row in &rows {
+            write_line(f, row, &widths)?;
+        }
+
+        if self.rows.len() > rows.len() {
+            writeln!(f, "... {} more rows", self.rows.len() - rows.len())?;
+        }
+        Ok(())
+    }
+
+    fn debug(&self, f: &mut Formatter<'_>, type_name: &str) -> Result {
+        f.debug_struct(type_name)
+            .field("name", self.name)
+            .field("base_id", &self.base_id)
+            .field(
+                "columns",
+                &self.columns.iter().map(ColumnSummary).collect::<Vec<_>>(),
+            )
+            .field("rows", &self.rows.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Prints a column as `label: type`
+struct ColumnSummary<'a>(&'a ColumnDef);
+
+impl<'a> Debug for ColumnSummary<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}: {:?}", self.0.label(), self.0.value_type())
+    }
+}
+
+fn truncate(mut text: String) -> String {
+    if let Some((idx, _)) = text.char_indices().nth(MAX_CELL_WIDTH) {
+        text.truncate(idx);
+        text.push('…');
+    }
+    text
+}
+
+fn write_line(f: &mut Formatter<'_>, cells: &[String], widths: &[usize]) -> Result {
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if i != 0 {
+            write!(f, " | ")?;
+        }
+        if i == cells.len() - 1 {
+            // No trailing whitespace
+            write!(f, "{cell}")?;
+        } else {
+            write!(f, "{cell:width$}")?;
+        }
+    }
+    writeln!(f)
+}
+
+impl<'b> ModernTable<'b> {
+    fn view(&self) -> TableView<'_, 'b> {
+        TableView {
+            name: &self.name,
+            format: "modern",
+            base_id: self.base_id,
+            columns: self.columns.as_slice(),
+            rows: &self.rows,
+        }
+    }
+}
+
+impl<'b> LegacyTable<'b> {
+    fn view(&self) -> TableView<'_, 'b> {
+        TableView {
+            name: &self.name,
+            format: "legacy",
+            base_id: self.base_id,
+            columns: self.columns.as_slice(),
+            rows: &self.rows,
+        }
+    }
+}
+
+impl<'b> Table<'b> {
+    fn view(&self) -> TableView<'_, 'b> {
+        match &self.inner {
+            TableInner::Modern(m) => m.view(),
+            TableInner::Legacy(l) => l.view(),
+        }
+    }
+}
+
+impl<'t, 'b> TableSlice<'t, 'b> {
+    fn view(&self) -> TableView<'t, 'b> {
+        TableView {
+            name: self.name,
+            format: if self.modern { "modern" } else { "legacy" },
+            base_id: self.rows.first().map(Row::id).unwrap_or_default(),
+            columns: self.columns.as_slice(),
+            rows: self.rows,
+        }
+    }
+}
+
+/// Prints a preview of the table, see [`DISPLAY_ROW_LIMIT`].
+impl<'b> Display for ModernTable<'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().display(f)
+    }
+}
+
+/// Prints a preview of the table, see [`DISPLAY_ROW_LIMIT`].
+impl<'b> Display for LegacyTable<'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().display(f)
+    }
+}
+
+/// Prints a preview of the table, see [`DISPLAY_ROW_LIMIT`].
+impl<'b> Display for Table<'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().display(f)
+    }
+}
+
+/// Prints a preview of the rows in the slice, see [`DISPLAY_ROW_LIMIT`].
+impl<'t, 'b> Display for TableSlice<'t, 'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().display(f)
+    }
+}
+
+impl<'b> Debug for ModernTable<'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().debug(f, "ModernTable")
+    }
+}
+
+impl<'b> Debug for LegacyTable<'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().debug(f, "LegacyTable")
+    }
+}
+
+impl<'b> Debug for Table<'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().debug(f, "Table")
+    }
+}
+
+impl<'t, 'b> Debug for TableSlice<'t, 'b> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.view().debug(f, "TableSlice")
+    }
+}
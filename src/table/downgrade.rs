@@ -0,0 +1,197 @@
+use std::fmt::Display;
+
+use crate::{BdatVersion, Cell, ColumnDef, Label, Row, Value, ValueType};
+
+use super::{Table, TableAccessor, TableBuilder};
+
+/// What was changed or left out by [`Table::downgrade_to`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DowngradeReport {
+    pub issues: Vec<DowngradeIssue>,
+}
+
+/// A part of a modern table that could not be converted as it is to a legacy table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DowngradeIssue {
+    /// A hashed table or column name has no known name, so it is kept as a hash, and
+    /// written as `<hash>` text.
+    UnresolvedLabel(Label),
+    /// A column's type is not supported by legacy tables, so its values were converted to
+    /// a type that holds the same numbers or text.
+    ChangedType {
+        column: Label,
+        from: ValueType,
+        to: ValueType,
+    },
+    /// A column has a type that can't be converted ([`ValueType::Raw`]), so the column was
+    /// removed.
+    RemovedColumn {
+        column: Label,
+        value_type: ValueType,
+    },
+    /// A row's ID is too big for the target version, so the row was removed.
+    RemovedRow(usize),
+}
+
+impl DowngradeReport {
+    /// Returns whether any data was removed. Unresolved labels and changed types keep
+    /// the values as they are.
+    pub fn has_removed_data(&self) -> bool {
+        self.issues.iter().any(|i| {
+            matches!(
+                i,
+                DowngradeIssue::RemovedColumn { .. } | DowngradeIssue::RemovedRow(_)
+            )
+        })
+    }
+}
+
+impl Display for DowngradeIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnresolvedLabel(label) => write!(f, "no name for {label}"),
+            Self::ChangedType { column, from, to } => {
+                write!(f, "column {column}: {from:?} values written as {to:?}")
+            }
+            Self::RemovedColumn { column, value_type } => {
+                write!(
+                    f,
+                    "column {column}: {value_type:?} is not supported, removed"
+                )
+            }
+            Self::RemovedRow(id) => write!(f, "row {id}: ID out of range, removed"),
+        }
+    }
+}
+
+impl<'b> Table<'b> {
+    /// Converts a modern table to a legacy table for `version`, as closely as possible.
+    ///
+    /// Unlike [`Table::to_legacy`], this doesn't fail: hashed names are looked up with
+    /// `unhash`, types that legacy tables don't support are narrowed to one that holds the
+    /// same values, and what can't be converted is removed. Every change is listed in the
+    /// returned report.
+    ///
+    /// If the table is already legacy, or `version` is modern, the table is returned as it
+    /// is.
+    ///
+    /// ```
+    /// use bdat::{
+    ///     BdatVersion, Cell, ColumnDef, Label, Row, TableAccessor, TableBuilder, Value, ValueType,
+    /// };
+    ///
+    /// let table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+    ///     .add_column(ColumnDef::new(ValueType::MessageId, Label::Hash(1)))
+    ///     .add_row(Row::new(1, vec![Cell::Single(Value::MessageId(5))]))
+    ///     .build(BdatVersion::Modern);
+    /// let (table, report) = table.downgrade_to(BdatVersion::LegacySwitch, |hash| {
+    ///     (hash == 0xCAFEBABE).then(|| "Enemy".to_string())
+    /// });
+    /// assert_eq!(&Label::String("Enemy".to_string()), table.name());
+    /// assert_eq!(2, report.issues.len()); // column name, MessageId column
+    /// ```
+    pub fn downgrade_to(
+        self,
+        version: BdatVersion,
+        unhash: impl Fn(u32) -> Option<String>,
+    ) -> (Table<'b>, DowngradeReport) {
+        let mut report = DowngradeReport::default();
+        if self.is_legacy() || version == BdatVersion::Modern {
+            return (self, report);
+        }
+        let name = legacy_label(self.name(), &unhash, &mut report);
+        // The new columns, and whether each column of the table is kept
+        let mut columns = Vec::new();
+        let mut kept = Vec::new();
+        for column in self.columns() {
+            let from = column.value_type();
+            let column_label = legacy_label(column.label(), &unhash, &mut report);
+            match legacy_type(from) {
+                Some(to) => {
+                    if to != from {
+                        report.issues.push(DowngradeIssue::ChangedType {
+                            column: column_label.clone(),
+                            from,
+                            to,
+                        });
+                    }
+                    columns.push(ColumnDef::new(to, column_label));
+                    kept.push(true);
+                }
+                None => {
+                    report.issues.push(DowngradeIssue::RemovedColumn {
+                        column: column_label,
+                        value_type: from,
+                    });
+                    kept.push(false);
+                }
+            }
+        }
+
+        let base_id = self.base_id();
+        let mut rows = Vec::new();
+        for row in self.into_rows() {
+            if row.id() > version.max_row_id() {
+                report.issues.push(DowngradeIssue::RemovedRow(row.id()));
+                continue;
+            }
+            let id = row.id();
+            let cells = row
+                .into_cells()
+                .zip(&kept)
+                .filter(|(_, kept)| **kept)
+                .map(|(cell, _)| match cell {
+                    Cell::Single(value) => Cell::Single(legacy_value(value)),
+                    c => c,
+                })
+                .collect();
+            rows.push(Row::new(id, cells));
+        }
+
+        let table = TableBuilder::with_name(name)
+            .set_columns(columns)
+            .set_rows(rows)
+            .set_base_id(base_id)
+            .build_legacy()
+            .into();
+        (table, report)
+    }
+}
+
+fn legacy_label(
+    label: &Label,
+    unhash: impl Fn(u32) -> Option<String>,
+    report: &mut DowngradeReport,
+) -> Label {
+    match label {
+        Label::Hash(hash) => unhash(*hash).map(Label::String).unwrap_or_else(|| {
+            report
+                .issues
+                .push(DowngradeIssue::UnresolvedLabel(label.clone()));
+            label.clone()
+        }),
+        Label::String(s) | Label::Unhashed(s) => Label::String(s.clone()),
+    }
+}
+
+/// Returns the legacy type that holds the values of `value_type`, if any.
+fn legacy_type(value_type: ValueType) -> Option<ValueType> {
+    match value_type {
+        ValueType::Percent | ValueType::Unknown2 => Some(ValueType::UnsignedByte),
+        ValueType::MessageId => Some(ValueType::UnsignedShort),
+        ValueType::HashRef => Some(ValueType::UnsignedInt),
+        ValueType::DebugString => Some(ValueType::String),
+        ValueType::Raw => None,
+        t => Some(t),
+    }
+}
+
+fn legacy_value(value: Value) -> Value {
+    match value {
+        Value::Percent(b) | Value::Unknown2(b) => Value::UnsignedByte(b),
+        Value::MessageId(s) => Value::UnsignedShort(s),
+        Value::HashRef(h) => Value::UnsignedInt(h),
+        Value::DebugString(s) => Value::String(s),
+        v => v,
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    BdatVersion, ColumnDef, ColumnMap, Label, LegacyCell, ModernTable, Row, RowRef, RowRefMut,
-    Table, TableAccessor, TableBuilder,
+    BdatVersion, ColumnDef, ColumnMap, Label, LegacyCell, ModernTable, Row, RowIter, RowRef,
+    RowRefMut, Table, TableAccessor, TableBuilder,
 };
 
 use super::{FormatConvertError, TableInner};
@@ -30,7 +30,7 @@ use super::{FormatConvertError, TableInner};
 ///     cell.as_single().unwrap().get_as::<u32>()
 /// }
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct LegacyTable<'b> {
     pub(crate) name: Label,
     pub(crate) base_id: usize,
@@ -41,21 +41,28 @@ pub struct LegacyTable<'b> {
 impl<'b> LegacyTable<'b> {
     pub(crate) fn new(builder: TableBuilder<'b>) -> Self {
         Self {
+            base_id: builder.first_row_id(),
             name: builder.name,
             columns: builder.columns,
-            base_id: builder
-                .rows
-                .iter()
-                .map(|r| r.id())
-                .min()
-                .unwrap_or_default(),
             rows: builder.rows,
         }
     }
 
+    /// Creates a table with the same name, columns and base ID as this one, but no rows.
+    ///
+    /// This is useful to generate companion tables, or to split a table into multiple parts.
+    pub fn empty_like<'n>(&self) -> LegacyTable<'n> {
+        LegacyTable {
+            name: self.name.clone(),
+            base_id: self.base_id,
+            columns: self.columns.clone(),
+            rows: Vec::new(),
+        }
+    }
+
     /// Gets an iterator that visits this table's rows
-    pub fn rows(&self) -> impl Iterator<Item = RowRef<'_, 'b, LegacyCell<'_, 'b>>> {
-        self.rows.iter().map(|row| RowRef::new(row, &self.columns))
+    pub fn rows(&self) -> RowIter<'_, 'b, LegacyCell<'_, 'b>> {
+        RowIter::new(&self.rows, &self.columns)
     }
 
     /// Gets an iterator over mutable references to this table's
@@ -132,12 +139,31 @@ impl<'t, 'b: 't> TableAccessor<'t, 'b> for LegacyTable<'b> {
     }
 }
 
+impl<'t, 'b> IntoIterator for &'t LegacyTable<'b> {
+    type Item = RowRef<'t, 'b, LegacyCell<'t, 'b>>;
+    type IntoIter = RowIter<'t, 'b, LegacyCell<'t, 'b>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows()
+    }
+}
+
+impl<'b> IntoIterator for LegacyTable<'b> {
+    type Item = Row<'b>;
+    type IntoIter = std::vec::IntoIter<Row<'b>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
 impl<'b> From<LegacyTable<'b>> for TableBuilder<'b> {
     fn from(value: LegacyTable<'b>) -> Self {
         Self {
             name: value.name,
             columns: value.columns,
             rows: value.rows,
+            base_id: value.base_id,
         }
     }
 }
@@ -162,6 +188,13 @@ impl<'b> TryFrom<ModernTable<'b>> for LegacyTable<'b> {
         {
             return Err(FormatConvertError::UnsupportedValueType(col.value_type()));
         }
+        if let Some(row) = value
+            .rows
+            .last()
+            .filter(|r| r.id() > BdatVersion::LegacySwitch.max_row_id())
+        {
+            return Err(FormatConvertError::RowIdOutOfRange(row.id()));
+        }
         Ok(LegacyTable::new(TableBuilder::from(value)))
     }
 }
@@ -1,21 +1,33 @@
 //! BDAT table, row, cell implementations
 
 use crate::{
-    BdatResult, BdatVersion, Cell, ColumnDef, ColumnMap, Label, Row, RowRef, RowRefMut, ValueType,
+    BdatError, BdatResult, BdatVersion, Cell, ColumnDef, ColumnMap, FromValue, Label, Row, RowIter,
+    RowRef, RowRefMut, ValueType,
 };
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
 use thiserror::Error;
-use util::VersionedIter;
 
 pub mod cell;
 pub mod column;
 pub mod row;
 
+mod digest;
+mod display;
+mod downgrade;
 mod legacy;
 mod modern;
-mod util;
+mod slice;
+mod stats;
+pub(crate) mod util;
 
+pub use display::DISPLAY_ROW_LIMIT;
+pub use downgrade::{DowngradeIssue, DowngradeReport};
 pub use legacy::LegacyTable;
 pub use modern::ModernTable;
+pub use slice::TableSlice;
+pub use stats::ColumnStats;
 
 /// A BDAT table. Depending on how they were read, BDAT tables can either own their data source
 /// or borrow from it.
@@ -57,7 +69,7 @@ pub use modern::ModernTable;
 ///
 /// [`as_legacy`]: Table::as_legacy
 /// [`as_modern`]: Table::as_modern
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct Table<'b> {
     inner: TableInner<'b>,
 }
@@ -73,6 +85,7 @@ pub struct TableBuilder<'b> {
     name: Label,
     columns: ColumnMap,
     rows: Vec<Row<'b>>,
+    base_id: usize,
 }
 
 /// Error encountered while converting tables
@@ -89,6 +102,11 @@ pub enum FormatConvertError {
     /// For instance, modern tables only support single-value cells.
     #[error("unsupported cell")]
     UnsupportedCell,
+    /// One of the table's rows has an ID that doesn't fit in the target format.
+    ///
+    /// For example, legacy tables only support 16-bit row IDs.
+    #[error("row ID {0} out of range")]
+    RowIdOutOfRange(usize),
 }
 
 /// Provides common functions to access rows and columns from a table.
@@ -265,7 +283,7 @@ impl<'b> Table<'b> {
     ///
     /// * If the table is modern, this does nothing and returns it.
     /// * If the table is legacy, it tries to convert it to the
-    /// modern format, and returns the result.
+    ///   modern format, and returns the result.
     ///
     /// This is not to be confused with [`into_modern`], which panics if
     /// the table is not modern.
@@ -282,7 +300,7 @@ impl<'b> Table<'b> {
     ///
     /// * If the table is legacy, this does nothing and returns it.
     /// * If the table is modern, it tries to convert it to the
-    /// legacy format, and returns the result.
+    ///   legacy format, and returns the result.
     ///
     /// This is not to be confused with [`into_legacy`], which panics if
     /// the table is not legacy.
@@ -295,12 +313,178 @@ impl<'b> Table<'b> {
         }
     }
 
+    /// Creates a table with the same name, format, columns and base ID as this one, but
+    /// no rows.
+    ///
+    /// To add rows to the new table, convert it to a builder with `TableBuilder::from`.
+    pub fn empty_like<'n>(&self) -> Table<'n> {
+        match &self.inner {
+            TableInner::Modern(m) => m.empty_like().into(),
+            TableInner::Legacy(l) => l.empty_like().into(),
+        }
+    }
+
     /// Gets an iterator that visits this table's rows
-    pub fn rows(&self) -> impl Iterator<Item = RowRef<'_, 'b>> {
+    pub fn rows(&self) -> RowIter<'_, 'b> {
         match &self.inner {
-            TableInner::Modern(m) => VersionedIter::Modern(m.rows().map(RowRef::up_cast)),
-            TableInner::Legacy(l) => VersionedIter::Legacy(l.rows()),
+            TableInner::Modern(m) => RowIter::new(&m.rows, &m.columns),
+            TableInner::Legacy(l) => RowIter::new(&l.rows, &l.columns),
+        }
+    }
+
+    /// Checks that no two rows have the same value in the given column, e.g. for columns
+    /// that store internal names.
+    ///
+    /// Cells are compared by their displayed value. Columns that don't exist are ignored.
+    ///
+    /// ## Errors
+    /// Returns [`BdatError::DuplicateValue`] with the IDs of the first two rows that share
+    /// a value.
+    ///
+    /// [`BdatError::DuplicateValue`]: crate::BdatError::DuplicateValue
+    pub fn check_unique(&self, column: impl Borrow<Label>) -> BdatResult<()> {
+        match &self.inner {
+            TableInner::Modern(m) => util::check_unique(&m.rows, &m.columns, column.borrow()),
+            TableInner::Legacy(l) => util::check_unique(&l.rows, &l.columns, column.borrow()),
+        }
+    }
+
+    /// Renames the table, keeping the name in the form its BDAT version needs.
+    ///
+    /// Modern tables are looked up by the hash of their name, so plain names are hashed
+    /// right away. Otherwise, they would be written as strings where the game expects the
+    /// hash. Legacy tables only support plain names.
+    ///
+    /// ```
+    /// use bdat::{label_hash, BdatVersion, Label, TableAccessor, TableBuilder};
+    ///
+    /// let mut table = TableBuilder::with_name(label_hash!("Old")).build(BdatVersion::Modern);
+    /// table.rename(Label::from("New")).unwrap();
+    /// assert_eq!(&label_hash!("New"), table.name());
+    /// ```
+    ///
+    /// ## Errors
+    /// Returns [`BdatError::HashedLegacyName`] if the table is legacy and the name is
+    /// hashed.
+    ///
+    /// [`BdatError::HashedLegacyName`]: crate::BdatError::HashedLegacyName
+    pub fn rename(&mut self, name: Label) -> BdatResult<()> {
+        let name = match (&self.inner, name) {
+            (TableInner::Modern(_), name) => name.into_hash(BdatVersion::Modern),
+            (TableInner::Legacy(_), name @ Label::Hash(_)) => {
+                return Err(BdatError::HashedLegacyName(name))
+            }
+            (TableInner::Legacy(_), Label::String(s) | Label::Unhashed(s)) => Label::String(s),
+        };
+        self.set_name(name);
+        Ok(())
+    }
+
+    /// Returns statistics about the values of a column: the number of distinct values,
+    /// the range and mean of numeric values, and the range and mean length of strings.
+    /// Returns `None` if the table has no such column.
+    ///
+    /// Flag columns have no values of their own, see [`ColumnStats::add`] to collect the
+    /// values of a flag.
+    ///
+    /// ```
+    /// use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType};
+    ///
+    /// let table = TableBuilder::with_name(Label::from("Enemies"))
+    ///     .add_column(ColumnDef::new(ValueType::UnsignedShort, Label::from("Lv")))
+    ///     .add_row(Row::new(1, vec![Cell::Single(Value::UnsignedShort(5))]))
+    ///     .add_row(Row::new(2, vec![Cell::Single(Value::UnsignedShort(15))]))
+    ///     .build(BdatVersion::LegacySwitch);
+    /// let stats = table.column_stats(Label::from("Lv")).unwrap();
+    /// assert_eq!(2, stats.distinct());
+    /// assert_eq!(Some((5.0, 15.0)), stats.range());
+    /// assert_eq!(Some(10.0), stats.mean());
+    /// ```
+    pub fn column_stats(&self, column: impl Borrow<Label>) -> Option<ColumnStats> {
+        let index = self.columns().position(|c| c.label() == column.borrow())?;
+        let mut stats = ColumnStats::default();
+        for row in self.rows() {
+            stats.add_cell(&row.cells[index]);
         }
+        Some(stats)
+    }
+
+    /// Reads two columns as a map from the values of `key` to the values of `value`, for
+    /// tables that map e.g. an internal name to a parameter.
+    ///
+    /// Both columns must hold single values, which are cast like [`Value::try_get_as`], so
+    /// the types must match exactly.
+    ///
+    /// ```
+    /// use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType};
+    ///
+    /// let table = TableBuilder::with_name(Label::from("Params"))
+    ///     .add_column(ColumnDef::new(ValueType::String, Label::from("Key")))
+    ///     .add_column(ColumnDef::new(ValueType::UnsignedInt, Label::from("Value")))
+    ///     .add_row(Row::new(1, vec![
+    ///         Cell::Single(Value::String("MaxLv".into())),
+    ///         Cell::Single(Value::UnsignedInt(99)),
+    ///     ]))
+    ///     .build(BdatVersion::LegacySwitch);
+    /// let params = table
+    ///     .as_map::<&str, u32>(Label::from("Key"), Label::from("Value"))
+    ///     .unwrap();
+    /// assert_eq!(Some(&99), params.get("MaxLv"));
+    /// ```
+    ///
+    /// ## Errors
+    /// * [`BdatError::MissingColumn`] if the table has no such column.
+    /// * [`BdatError::NotSingleValue`] if a column has list or flag cells.
+    /// * [`BdatError::ValueCast`] if a value doesn't have the requested type.
+    /// * [`BdatError::DuplicateValue`] with the IDs of the first two rows that share a key.
+    ///
+    /// [`Value::try_get_as`]: crate::Value::try_get_as
+    /// [`BdatError::MissingColumn`]: crate::BdatError::MissingColumn
+    /// [`BdatError::NotSingleValue`]: crate::BdatError::NotSingleValue
+    /// [`BdatError::ValueCast`]: crate::BdatError::ValueCast
+    /// [`BdatError::DuplicateValue`]: crate::BdatError::DuplicateValue
+    pub fn as_map<'t, K, V>(
+        &'t self,
+        key: impl Borrow<Label>,
+        value: impl Borrow<Label>,
+    ) -> BdatResult<HashMap<K, V>>
+    where
+        K: FromValue<'t, 'b> + Eq + Hash,
+        V: FromValue<'t, 'b>,
+    {
+        match &self.inner {
+            TableInner::Modern(m) => {
+                util::as_map(&m.rows, &m.columns, key.borrow(), value.borrow())
+            }
+            TableInner::Legacy(l) => {
+                util::as_map(&l.rows, &l.columns, key.borrow(), value.borrow())
+            }
+        }
+    }
+
+    /// Returns a digest of the table's content: its name, columns, and rows in ID order.
+    ///
+    /// The digest only depends on what the table holds, not on how it is stored, so it can
+    /// tell whether a table changed between two reads, e.g. to skip repacking it. Rows are
+    /// hashed in ID order, names of modern tables are hashed whether or not they were
+    /// resolved, and fixed-point reals hash the same as floats with the same value.
+    ///
+    /// The digest is stable across platforms and runs, and only changes between
+    /// versions of this crate with breaking changes.
+    pub fn content_hash(&self) -> u64 {
+        digest::content_hash(self)
+    }
+
+    /// Gets an iterator that visits every cell in the table, row by row.
+    ///
+    /// Each item contains the ID of the row the cell belongs to, the cell's column definition,
+    /// and the cell itself.
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, &ColumnDef, &Cell<'b>)> {
+        self.rows().flat_map(|row| {
+            let id = row.id();
+            row.cell_refs()
+                .map(move |cell| (id, cell.column(), cell.cell()))
+        })
     }
 
     /// Gets an iterator over mutable references to this table's
@@ -389,12 +573,43 @@ impl<'t, 'b: 't> TableAccessor<'t, 'b> for Table<'b> {
     }
 }
 
+impl<'t, 'b> IntoIterator for &'t Table<'b> {
+    type Item = RowRef<'t, 'b>;
+    type IntoIter = RowIter<'t, 'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows()
+    }
+}
+
+impl<'b> IntoIterator for Table<'b> {
+    type Item = Row<'b>;
+    type IntoIter = std::vec::IntoIter<Row<'b>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.inner {
+            TableInner::Modern(m) => m.into_iter(),
+            TableInner::Legacy(l) => l.into_iter(),
+        }
+    }
+}
+
+impl<'b> From<Table<'b>> for TableBuilder<'b> {
+    fn from(value: Table<'b>) -> Self {
+        match value.inner {
+            TableInner::Modern(m) => m.into(),
+            TableInner::Legacy(l) => l.into(),
+        }
+    }
+}
+
 impl<'b> TableBuilder<'b> {
     pub fn with_name(name: Label) -> Self {
         Self {
             name,
             columns: ColumnMap::default(),
             rows: vec![],
+            base_id: 0,
         }
     }
 
@@ -412,11 +627,17 @@ impl<'b> TableBuilder<'b> {
         // ID sanity check
         if let Some(last_row) = self.rows.last() {
             if last_row.id() == u32::MAX as usize {
-                panic!("row limit of {} reached, no more rows can be added", u32::MAX);
+                panic!(
+                    "row limit of {} reached, no more rows can be added",
+                    u32::MAX
+                );
             }
             if last_row.id() + 1 != row.id() {
-                panic!("attempted to add non-consecutive row ID, expected {}, found {}",
-                    last_row.id() + 1, row.id());
+                panic!(
+                    "attempted to add non-consecutive row ID, expected {}, found {}",
+                    last_row.id() + 1,
+                    row.id()
+                );
             }
         }
         self.rows.push(row);
@@ -436,19 +657,48 @@ impl<'b> TableBuilder<'b> {
                 panic!("found pair of wrongly-ordered IDs, {} >= {}", a, b);
             }
             if b - a != 1 {
-                panic!("found pair of non-consecutive row IDs, expected {}/{}, found {}/{}",
-                    a, a + 1, a, b);
+                panic!(
+                    "found pair of non-consecutive row IDs, expected {}/{}, found {}/{}",
+                    a,
+                    a + 1,
+                    a,
+                    b
+                );
             }
         }
         self.rows = rows;
         self
     }
 
+    /// Sets the ID of the first row, for tables without rows. If the table has rows, the
+    /// base ID is always the ID of the first row.
+    pub fn set_base_id(mut self, base_id: usize) -> Self {
+        self.base_id = base_id;
+        self
+    }
+
+    /// Returns the ID the first row of the table will have.
+    pub(crate) fn first_row_id(&self) -> usize {
+        self.rows.first().map_or(self.base_id, Row::id)
+    }
+
     pub fn set_columns(mut self, columns: Vec<ColumnDef>) -> Self {
         self.columns = ColumnMap::from(columns);
         self
     }
 
+    /// Gets an iterator over the columns added so far.
+    pub fn columns(&self) -> impl Iterator<Item = &ColumnDef> {
+        self.columns.as_slice().iter()
+    }
+
+    /// Checks that no two of the rows added so far have the same value in the given column.
+    ///
+    /// See [`Table::check_unique`].
+    pub fn check_unique(&self, column: impl Borrow<Label>) -> BdatResult<()> {
+        util::check_unique(&self.rows, &self.columns, column.borrow())
+    }
+
     pub fn build_modern(self) -> ModernTable<'b> {
         ModernTable::new(self)
     }
@@ -464,4 +714,36 @@ impl<'b> TableBuilder<'b> {
             self.build_modern().into()
         }
     }
+
+    /// Like [`build`], but checks the table's layout first. Fails with:
+    /// * [`BdatError::DuplicateColumn`] if two columns have the same name (modern only, as
+    ///   some legacy tables in the games have duplicate column names)
+    /// * [`BdatError::CellCount`] if a row doesn't have exactly one cell per column
+    /// * [`BdatError::ListLength`] if a list cell doesn't match its column's count
+    /// * [`BdatError::RawLength`] if a raw cell doesn't match its column's size, or
+    ///   [`BdatError::MissingRawType`] if a raw column has no original type
+    /// * [`BdatError::RowIdOverflow`] if any of the row IDs can't be represented in the
+    ///   given version. Legacy formats only support 16-bit row IDs, while modern tables
+    ///   support 32-bit IDs.
+    /// * [`BdatError::NonConsecutiveRowId`] if row IDs have gaps or are out of order, as
+    ///   rows are written with consecutive IDs
+    /// * [`BdatError::DuplicateKey`] if two rows of a modern table have the same ID hash
+    ///
+    /// [`build`]: TableBuilder::build
+    /// [`BdatError::DuplicateColumn`]: crate::BdatError::DuplicateColumn
+    /// [`BdatError::CellCount`]: crate::BdatError::CellCount
+    /// [`BdatError::ListLength`]: crate::BdatError::ListLength
+    /// [`BdatError::RawLength`]: crate::BdatError::RawLength
+    /// [`BdatError::MissingRawType`]: crate::BdatError::MissingRawType
+    /// [`BdatError::RowIdOverflow`]: crate::BdatError::RowIdOverflow
+    /// [`BdatError::NonConsecutiveRowId`]: crate::BdatError::NonConsecutiveRowId
+    /// [`BdatError::DuplicateKey`]: crate::BdatError::DuplicateKey
+    pub fn try_build(self, version: BdatVersion) -> BdatResult<Table<'b>> {
+        util::check_layout(&self.rows, &self.columns, version)?;
+        util::check_row_ids(&self.rows, version)?;
+        if version.is_modern() {
+            util::check_id_hashes(&self.rows, &self.columns)?;
+        }
+        Ok(self.build(version))
+    }
 }
@@ -1,6 +1,6 @@
 use crate::hash::PreHashedMap;
 use crate::{
-    BdatVersion, Cell, ColumnDef, ColumnMap, Label, LegacyTable, ModernCell, Row, RowRef,
+    BdatVersion, Cell, ColumnDef, ColumnMap, Label, LegacyTable, ModernCell, Row, RowIter, RowRef,
     RowRefMut, Table, TableAccessor, TableBuilder,
 };
 
@@ -51,7 +51,7 @@ use super::{FormatConvertError, TableInner};
 /// [`row_by_hash`]: ModernTable::row_by_hash
 /// [`ModernCell`]: crate::ModernCell
 /// [`Cell::Single`]: crate::Cell::Single
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct ModernTable<'b> {
     pub(crate) name: Label,
     pub(crate) base_id: usize,
@@ -64,20 +64,29 @@ pub struct ModernTable<'b> {
 impl<'b> ModernTable<'b> {
     pub(crate) fn new(builder: TableBuilder<'b>) -> Self {
         Self {
+            base_id: builder.first_row_id(),
             name: builder.name,
             columns: builder.columns,
-            base_id: builder
-                .rows
-                .iter()
-                .map(|r| r.id())
-                .min()
-                .unwrap_or_default(),
             #[cfg(feature = "hash-table")]
             row_hash_table: build_id_map_checked(&builder.rows),
             rows: builder.rows,
         }
     }
 
+    /// Creates a table with the same name, columns and base ID as this one, but no rows.
+    ///
+    /// This is useful to generate companion tables, or to split a table into multiple parts.
+    pub fn empty_like<'n>(&self) -> ModernTable<'n> {
+        ModernTable {
+            name: self.name.clone(),
+            base_id: self.base_id,
+            columns: self.columns.clone(),
+            rows: Vec::new(),
+            #[cfg(feature = "hash-table")]
+            row_hash_table: PreHashedMap::default(),
+        }
+    }
+
     /// Attempts to get a row by its hashed 32-bit ID.
     /// If there is no row for the given ID, this returns [`None`].
     ///
@@ -104,8 +113,8 @@ impl<'b> ModernTable<'b> {
     }
 
     /// Gets an iterator that visits this table's rows
-    pub fn rows(&self) -> impl Iterator<Item = RowRef<'_, 'b, ModernCell<'_, 'b>>> {
-        self.rows.iter().map(|row| RowRef::new(row, &self.columns))
+    pub fn rows(&self) -> RowIter<'_, 'b, ModernCell<'_, 'b>> {
+        RowIter::new(&self.rows, &self.columns)
     }
 
     /// Gets an iterator over mutable references to this table's
@@ -164,7 +173,10 @@ fn build_id_map_checked(rows: &[Row]) -> PreHashedMap<u32, usize> {
     for row in rows {
         let Some(hash) = row.id_hash() else { continue };
         match res.entry(hash) {
-            Entry::Occupied(_) => panic!("failed to build row hash table: duplicate key {:?}", Label::Hash(hash)),
+            Entry::Occupied(_) => panic!(
+                "failed to build row hash table: duplicate key {:?}",
+                Label::Hash(hash)
+            ),
             e => e.or_insert(row.id()),
         };
     }
@@ -231,12 +243,31 @@ impl<'t, 'b: 't> TableAccessor<'t, 'b> for ModernTable<'b> {
     }
 }
 
+impl<'t, 'b> IntoIterator for &'t ModernTable<'b> {
+    type Item = RowRef<'t, 'b, ModernCell<'t, 'b>>;
+    type IntoIter = RowIter<'t, 'b, ModernCell<'t, 'b>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows()
+    }
+}
+
+impl<'b> IntoIterator for ModernTable<'b> {
+    type Item = Row<'b>;
+    type IntoIter = std::vec::IntoIter<Row<'b>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
 impl<'b> From<ModernTable<'b>> for TableBuilder<'b> {
     fn from(value: ModernTable<'b>) -> Self {
         Self {
             name: value.name,
             columns: value.columns,
             rows: value.rows,
+            base_id: value.base_id,
         }
     }
 }
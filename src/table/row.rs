@@ -1,4 +1,4 @@
-use crate::{Cell, Label, Value};
+use crate::{Cell, ColumnDef, Label, Value};
 use crate::{ColumnMap, FromCell};
 use std::borrow::Borrow;
 use std::marker::PhantomData;
@@ -9,7 +9,9 @@ use std::ops::{Deref, DerefMut, Index};
 #[derive(Debug, Clone, PartialEq)]
 pub struct Row<'b> {
     pub(crate) id: usize,
-    pub(crate) cells: Vec<Cell<'b>>,
+    /// Rows never change their cell count after creation, so a boxed slice is used
+    /// instead of a `Vec` to save the capacity field and any excess allocation.
+    pub(crate) cells: Box<[Cell<'b>]>,
 }
 
 /// A reference to a row that also keeps information about the parent table.
@@ -39,6 +41,24 @@ pub struct RowRef<'t, 'tb, C = &'t Cell<'tb>> {
     _cell: PhantomData<C>,
 }
 
+/// A reference to a cell, along with the definition of the column it belongs to.
+///
+/// Obtained from [`RowRef::cell_refs`] or [`RowRef::cell_ref`], this lets read-only
+/// consumers look at both the cell and its column without cloning either.
+#[derive(Clone, Copy, Debug)]
+pub struct CellRef<'t, 'tb> {
+    cell: &'t Cell<'tb>,
+    column: &'t ColumnDef,
+}
+
+/// An iterator over [`RowRef`]s, returned by iterating over a borrowed table.
+#[derive(Clone, Debug)]
+pub struct RowIter<'t, 'tb, C = &'t Cell<'tb>> {
+    rows: std::slice::Iter<'t, Row<'tb>>,
+    columns: &'t ColumnMap,
+    _cell: PhantomData<C>,
+}
+
 #[derive(Debug)]
 pub struct RowRefMut<'t, 'tb> {
     row: &'t mut Row<'tb>,
@@ -48,7 +68,10 @@ pub struct RowRefMut<'t, 'tb> {
 impl<'b> Row<'b> {
     /// Creates a new [`Row`].
     pub fn new(id: usize, cells: Vec<Cell<'b>>) -> Self {
-        Self { id, cells }
+        Self {
+            id,
+            cells: cells.into_boxed_slice(),
+        }
     }
 
     /// Gets the row's ID
@@ -58,7 +81,7 @@ impl<'b> Row<'b> {
 
     /// Gets an owning iterator over this row's cells
     pub fn into_cells(self) -> impl Iterator<Item = Cell<'b>> {
-        self.cells.into_iter()
+        self.cells.into_vec().into_iter()
     }
 
     /// Gets an iterator over this row's cells
@@ -66,6 +89,11 @@ impl<'b> Row<'b> {
         self.cells.iter()
     }
 
+    /// Gets an iterator over mutable references to this row's cells
+    pub fn cells_mut(&mut self) -> impl Iterator<Item = &mut Cell<'b>> {
+        self.cells.iter_mut()
+    }
+
     /// Searches the row's cells for a ID hash field, returning the ID
     /// of this row if found.
     pub fn id_hash(&self) -> Option<u32> {
@@ -104,6 +132,37 @@ where
         self.get_if_present(column).expect("no such column")
     }
 
+    /// Returns the cell at the given column, or [`None`] if there is no column with the
+    /// given label.
+    ///
+    /// Unlike [`get_if_present`], this always returns a plain [`Cell`], regardless of the
+    /// row's cell type.
+    ///
+    /// [`get_if_present`]: RowRef::get_if_present
+    pub fn cell(&self, column: impl Borrow<Label>) -> Option<&'t Cell<'tb>> {
+        let index = self.columns.position(column.borrow())?;
+        self.row.cells.get(index)
+    }
+
+    /// Returns a [`CellRef`] for the cell at the given column, or [`None`] if there is
+    /// no column with the given label.
+    pub fn cell_ref(&self, column: impl Borrow<Label>) -> Option<CellRef<'t, 'tb>> {
+        let index = self.columns.position(column.borrow())?;
+        Some(CellRef {
+            cell: self.row.cells.get(index)?,
+            column: &self.columns.as_slice()[index],
+        })
+    }
+
+    /// Gets an iterator over this row's cells, paired with their column definitions.
+    pub fn cell_refs(&self) -> impl Iterator<Item = CellRef<'t, 'tb>> {
+        self.row
+            .cells
+            .iter()
+            .zip(self.columns.as_slice())
+            .map(|(cell, column)| CellRef { cell, column })
+    }
+
     pub(crate) fn up_cast(self) -> RowRef<'t, 'tb> {
         RowRef {
             row: self.row,
@@ -123,6 +182,56 @@ impl<'t, 'tb> RowRef<'t, 'tb> {
     }
 }
 
+impl<'t, 'tb, C> RowIter<'t, 'tb, C> {
+    pub(crate) fn new(rows: &'t [Row<'tb>], columns: &'t ColumnMap) -> Self {
+        Self {
+            rows: rows.iter(),
+            columns,
+            _cell: PhantomData,
+        }
+    }
+}
+
+impl<'t, 'tb, C> Iterator for RowIter<'t, 'tb, C>
+where
+    C: FromCell<'t, 'tb>,
+{
+    type Item = RowRef<'t, 'tb, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next().map(|row| RowRef::new(row, self.columns))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl<'t, 'tb, C> DoubleEndedIterator for RowIter<'t, 'tb, C>
+where
+    C: FromCell<'t, 'tb>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.rows
+            .next_back()
+            .map(|row| RowRef::new(row, self.columns))
+    }
+}
+
+impl<'t, 'tb, C> ExactSizeIterator for RowIter<'t, 'tb, C> where C: FromCell<'t, 'tb> {}
+
+impl<'t, 'tb> CellRef<'t, 'tb> {
+    /// Returns the referenced cell.
+    pub fn cell(&self) -> &'t Cell<'tb> {
+        self.cell
+    }
+
+    /// Returns the definition of the cell's column.
+    pub fn column(&self) -> &'t ColumnDef {
+        self.column
+    }
+}
+
 impl<'a, 't: 'a, 'tb> RowRefMut<'t, 'tb> {
     pub(crate) fn new(row: &'t mut Row<'tb>, columns: &'t ColumnMap) -> Self {
         Self { row, columns }
@@ -144,14 +253,14 @@ impl<'a, 't: 'a, 'tb> RowRefMut<'t, 'tb> {
 ///
 /// Since: 0.4.0  
 /// To be removed in 0.5.0
-impl<'a, 't: 'a, 'tb, S> Index<S> for RowRef<'t, 'tb>
+impl<'t, 'tb, S> Index<S> for RowRef<'t, 'tb>
 where
     S: Into<Label> + PartialEq,
 {
     type Output = Cell<'tb>;
 
     fn index(&self, index: S) -> &Self::Output {
-        self.get(&index.into())
+        self.get(index.into())
     }
 }
 
@@ -163,6 +272,14 @@ impl<'t, 'tb, C> Deref for RowRef<'t, 'tb, C> {
     }
 }
 
+impl<'t, 'tb> Deref for CellRef<'t, 'tb> {
+    type Target = Cell<'tb>;
+
+    fn deref(&self) -> &Self::Target {
+        self.cell
+    }
+}
+
 impl<'t, 'tb> Deref for RowRefMut<'t, 'tb> {
     type Target = Row<'tb>;
 
@@ -0,0 +1,113 @@
+//! Borrowed views over a range of a table's rows.
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::{ColumnDef, ColumnMap, Label, Row, RowIter, RowRef, Table};
+
+use super::TableInner;
+
+/// A view over a contiguous range of a table's rows, obtained with [`Table::slice`] or
+/// [`Table::paginate`].
+///
+/// Creating a slice does not copy any rows.
+#[derive(Clone, Copy)]
+pub struct TableSlice<'t, 'b> {
+    pub(crate) name: &'t Label,
+    pub(crate) modern: bool,
+    pub(crate) columns: &'t ColumnMap,
+    pub(crate) rows: &'t [Row<'b>],
+}
+
+impl<'b> Table<'b> {
+    /// Returns a view over a range of the table's rows.
+    ///
+    /// The range refers to row positions, not row IDs: `0..10` always selects the
+    /// first 10 rows, regardless of the table's base ID. Bounds past the end of the
+    /// table are clamped, so the resulting slice may be shorter than requested, or empty.
+    ///
+    /// ```
+    /// use bdat::{Table, TableAccessor};
+    ///
+    /// fn print_first_rows(table: &Table) {
+    ///     for row in table.slice(..5).rows() {
+    ///         println!("{}", row.id());
+    ///     }
+    /// }
+    /// ```
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> TableSlice<'_, 'b> {
+        let (name, modern, columns, rows) = match &self.inner {
+            TableInner::Modern(m) => (&m.name, true, &m.columns, m.rows.as_slice()),
+            TableInner::Legacy(l) => (&l.name, false, &l.columns, l.rows.as_slice()),
+        };
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(rows.len());
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.saturating_add(1),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => rows.len(),
+        }
+        .clamp(start, rows.len());
+        TableSlice {
+            name,
+            modern,
+            columns,
+            rows: &rows[start..end],
+        }
+    }
+
+    /// Returns a view over a page of rows, where each page has `page_size` rows. Page
+    /// numbers start at 0.
+    ///
+    /// The last page may have fewer rows, and pages past the end of the table are empty.
+    pub fn paginate(&self, page: usize, page_size: usize) -> TableSlice<'_, 'b> {
+        let start = page.saturating_mul(page_size);
+        self.slice(start..start.saturating_add(page_size))
+    }
+}
+
+impl<'t, 'b> TableSlice<'t, 'b> {
+    /// Returns the name of the table the slice was taken from.
+    pub fn name(&self) -> &'t Label {
+        self.name
+    }
+
+    /// Gets an iterator that visits the rows in the slice.
+    pub fn rows(&self) -> RowIter<'t, 'b> {
+        RowIter::new(self.rows, self.columns)
+    }
+
+    /// Gets an iterator that visits the table's column definitions.
+    pub fn columns(&self) -> impl Iterator<Item = &'t ColumnDef> {
+        self.columns.as_slice().iter()
+    }
+
+    /// Attempts to get a row in the slice by its ID.
+    pub fn get_row(&self, id: usize) -> Option<RowRef<'t, 'b>> {
+        let first = self.rows.first()?.id();
+        let row = self.rows.get(id.checked_sub(first)?)?;
+        Some(RowRef::new(row, self.columns))
+    }
+
+    /// Returns the number of rows in the slice.
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns whether the slice has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl<'t, 'b> IntoIterator for TableSlice<'t, 'b> {
+    type Item = RowRef<'t, 'b>;
+    type IntoIter = RowIter<'t, 'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows()
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use crate::{Cell, Value};
+
+/// Summary statistics of the values in a column, see [`Table::column_stats`].
+///
+/// Every value of list cells is counted. Values are compared by their displayed value, so
+/// e.g. `1` and `1.0` in different columns are distinct, but hash references to the same
+/// row are not.
+///
+/// [`Table::column_stats`]: crate::Table::column_stats
+#[derive(Debug, Clone, Default)]
+pub struct ColumnStats {
+    count: usize,
+    distinct: HashSet<String>,
+    numbers: Option<Summary<f64>>,
+    lengths: Option<Summary<usize>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Summary<T> {
+    min: T,
+    max: T,
+    sum: f64,
+    count: usize,
+}
+
+impl ColumnStats {
+    /// Adds the values of a cell. Flag cells are skipped, use [`ColumnStats::add`] with
+    /// the values of a single flag instead.
+    pub fn add_cell(&mut self, cell: &Cell) {
+        match cell {
+            Cell::Single(value) => self.add(value),
+            Cell::List(values) => values.iter().for_each(|v| self.add(v)),
+            Cell::Flags(_) => {}
+        }
+    }
+
+    /// Adds a single value.
+    pub fn add(&mut self, value: &Value) {
+        self.count += 1;
+        self.distinct.insert(value.to_string());
+        match value {
+            Value::String(s) | Value::DebugString(s) => {
+                add_to(&mut self.lengths, s.chars().count(), |n| n as f64)
+            }
+            Value::Unknown | Value::HashRef(_) | Value::Raw(_) => {}
+            Value::Float(f) => add_to(&mut self.numbers, f32::from(*f) as f64, |n| n),
+            Value::SignedByte(n) => add_to(&mut self.numbers, *n as f64, |n| n),
+            Value::SignedShort(n) => add_to(&mut self.numbers, *n as f64, |n| n),
+            Value::SignedInt(n) => add_to(&mut self.numbers, *n as f64, |n| n),
+            v => add_to(&mut self.numbers, v.to_integer() as f64, |n| n),
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of distinct values.
+    pub fn distinct(&self) -> usize {
+        self.distinct.len()
+    }
+
+    /// Returns the smallest and largest numeric value, if there are numeric values.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        self.numbers.map(|s| (s.min, s.max))
+    }
+
+    /// Returns the mean of the numeric values, if there are any.
+    pub fn mean(&self) -> Option<f64> {
+        self.numbers.map(|s| s.mean())
+    }
+
+    /// Returns the shortest and longest string length, in characters, if there are strings.
+    pub fn length_range(&self) -> Option<(usize, usize)> {
+        self.lengths.map(|s| (s.min, s.max))
+    }
+
+    /// Returns the mean string length, in characters, if there are strings.
+    pub fn mean_length(&self) -> Option<f64> {
+        self.lengths.map(|s| s.mean())
+    }
+}
+
+impl<T> Summary<T> {
+    fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+fn add_to<T: PartialOrd + Copy>(summary: &mut Option<Summary<T>>, n: T, as_f64: fn(T) -> f64) {
+    let summary = summary.get_or_insert(Summary {
+        min: n,
+        max: n,
+        sum: 0.0,
+        count: 0,
+    });
+    if n < summary.min {
+        summary.min = n;
+    }
+    if n > summary.max {
+        summary.max = n;
+    }
+    summary.sum += as_f64(n);
+    summary.count += 1;
+}
@@ -1,3 +1,12 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{
+    BdatError, BdatResult, BdatVersion, Cell, ColumnMap, FromValue, Label, Row, Value, ValueType,
+};
+
+use super::FormatConvertError;
+
 pub enum VersionedIter<M, L> {
     Modern(M),
     Legacy(L),
@@ -17,3 +26,215 @@ where
         }
     }
 }
+
+/// Reads two columns as a map, see [`Table::as_map`].
+///
+/// [`Table::as_map`]: crate::Table::as_map
+pub(crate) fn as_map<'t, 'b, K, V>(
+    rows: &'t [Row<'b>],
+    columns: &ColumnMap,
+    key: &Label,
+    value: &Label,
+) -> BdatResult<HashMap<K, V>>
+where
+    K: FromValue<'t, 'b> + Eq + Hash,
+    V: FromValue<'t, 'b>,
+{
+    let position = |label: &Label| {
+        columns
+            .position(label)
+            .ok_or_else(|| BdatError::MissingColumn(label.clone()))
+    };
+    let (key_index, value_index) = (position(key)?, position(value)?);
+    let single = |row: &'t Row<'b>, index: usize, label: &Label| {
+        row.cells[index]
+            .as_single()
+            .ok_or_else(|| BdatError::NotSingleValue(Box::new((row.id(), label.clone()))))
+    };
+
+    let mut map = HashMap::with_capacity(rows.len());
+    // Values can't be hashed, so keys are compared by their displayed value
+    let mut ids = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let k = single(row, key_index, key)?;
+        let v = single(row, value_index, value)?;
+        if let Some(first) = ids.insert(k.to_string(), row.id()) {
+            return Err(BdatError::DuplicateValue(Box::new((
+                key.clone(),
+                k.to_string(),
+                first,
+                row.id(),
+            ))));
+        }
+        map.insert(k.try_get_as()?, v.try_get_as()?);
+    }
+    Ok(map)
+}
+
+/// Checks that no two rows have the same value in the given column.
+///
+/// Cells are compared by their displayed value. If the column doesn't exist,
+/// this does nothing.
+pub(crate) fn check_unique(rows: &[Row], columns: &ColumnMap, column: &Label) -> BdatResult<()> {
+    let Some(index) = columns.position(column) else {
+        return Ok(());
+    };
+    let mut seen = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let value = row.cells[index].to_string();
+        if let Some(first) = seen.get(&value) {
+            return Err(BdatError::DuplicateValue(Box::new((
+                column.clone(),
+                value,
+                *first,
+                row.id(),
+            ))));
+        }
+        seen.insert(value, row.id());
+    }
+    Ok(())
+}
+
+/// Checks that every row has a cell for each column, and that list cells have as many
+/// values as their column's count. Raw cells must have as many bytes as their column's
+/// original type (see [`crate::RawType`]). For modern tables, column names must also be unique,
+/// as columns are looked up by their name hash, and cells must hold single values.
+/// (Some legacy tables in the games have duplicate column names)
+pub(crate) fn check_layout(
+    rows: &[Row],
+    columns: &ColumnMap,
+    version: BdatVersion,
+) -> BdatResult<()> {
+    let unique_columns = if version.is_legacy() {
+        0
+    } else {
+        columns.as_slice().len()
+    };
+    if let Some(column) = columns
+        .as_slice()
+        .iter()
+        .find(|c| c.value_type() == ValueType::Raw && c.raw_type().is_none())
+    {
+        return Err(BdatError::MissingRawType(column.label().clone()));
+    }
+    for (i, column) in columns.as_slice()[..unique_columns].iter().enumerate() {
+        if columns.as_slice()[..i]
+            .iter()
+            .any(|c| c.label() == column.label())
+        {
+            return Err(BdatError::DuplicateColumn(column.label().clone()));
+        }
+    }
+    for row in rows {
+        let cells = row.cells.len();
+        if cells != columns.as_slice().len() {
+            return Err(BdatError::CellCount(Box::new((
+                row.id(),
+                cells,
+                columns.as_slice().len(),
+            ))));
+        }
+        for (cell, column) in row.cells.iter().zip(columns.as_slice()) {
+            // Modern tables only support single-value cells
+            if !version.is_legacy() && !matches!(cell, Cell::Single(_)) {
+                return Err(FormatConvertError::UnsupportedCell.into());
+            }
+            if let (Cell::Single(Value::Raw(bytes)), Some(raw)) = (cell, column.raw_type()) {
+                if bytes.len() != raw.size() {
+                    return Err(BdatError::RawLength(Box::new((
+                        row.id(),
+                        bytes.len(),
+                        column.label().clone(),
+                        raw.size(),
+                    ))));
+                }
+            }
+            if let Cell::List(values) = cell {
+                if values.len() != column.count() {
+                    return Err(BdatError::ListLength(Box::new((
+                        row.id(),
+                        values.len(),
+                        column.label().clone(),
+                        column.count(),
+                    ))));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that row IDs are consecutive and in ascending order, as they are written from
+/// the table's base ID, and that every ID fits in the row ID fields of the given version.
+pub(crate) fn check_row_ids(rows: &[Row], version: BdatVersion) -> BdatResult<()> {
+    let mut previous: Option<usize> = None;
+    for id in rows.iter().map(Row::id) {
+        if id > version.max_row_id() {
+            return Err(BdatError::RowIdOverflow(id, version));
+        }
+        match previous {
+            Some(previous) if previous.checked_add(1) != Some(id) => {
+                return Err(BdatError::NonConsecutiveRowId(previous, id));
+            }
+            _ => previous = Some(id),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that no two rows have the same ID hash, which modern tables use as a primary key.
+pub(crate) fn check_id_hashes(rows: &[Row], columns: &ColumnMap) -> BdatResult<()> {
+    let mut seen = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let Some(hash) = row.id_hash() else { continue };
+        if let Some(first) = seen.insert(hash, row.id()) {
+            let column = row
+                .cells()
+                .position(|c| matches!(c, Cell::Single(Value::HashRef(_))))
+                .and_then(|i| columns.as_slice().get(i))
+                .map(|c| c.label().clone())
+                .unwrap_or(Label::Hash(0));
+            return Err(BdatError::DuplicateKey(Box::new((
+                column,
+                Label::Hash(hash),
+                first,
+                row.id(),
+            ))));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row_id_order() {
+        let rows = |ids: &[usize]| {
+            ids.iter()
+                .map(|&id| Row::new(id, vec![Cell::Single(Value::UnsignedInt(id as u32))]))
+                .collect::<Vec<_>>()
+        };
+        let version = BdatVersion::LegacySwitch;
+
+        assert!(check_row_ids(&rows(&[1, 2, 3]), version).is_ok());
+        assert!(matches!(
+            check_row_ids(&rows(&[1, 3, 2]), version),
+            Err(BdatError::NonConsecutiveRowId(1, 3))
+        ));
+        assert!(matches!(
+            check_row_ids(&rows(&[2, 1]), BdatVersion::Modern),
+            Err(BdatError::NonConsecutiveRowId(2, 1))
+        ));
+        assert!(matches!(
+            check_row_ids(&rows(&[1, 2, 4]), version),
+            Err(BdatError::NonConsecutiveRowId(2, 4))
+        ));
+        // Every ID is checked, not only the last one
+        assert!(matches!(
+            check_row_ids(&rows(&[0x10000, 1]), version),
+            Err(BdatError::RowIdOverflow(0x10000, _))
+        ));
+    }
+}
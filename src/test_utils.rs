@@ -0,0 +1,229 @@
+//! Helpers for testing code that reads or writes BDAT tables, e.g. round trips in mod
+//! tools. Enabled with the `test-utils` feature flag.
+//!
+//! ```
+//! use bdat::test_utils::{round_trip, TableFixture};
+//! use bdat::{assert_tables_eq, BdatVersion, Value, ValueType};
+//!
+//! let table = TableFixture::new("Enemy")
+//!     .column("Lv", ValueType::UnsignedShort)
+//!     .column("Name", ValueType::String)
+//!     .row([Value::UnsignedShort(5), Value::String("Bunnit".into())])
+//!     .build(BdatVersion::LegacySwitch);
+//! assert_tables_eq!(table, round_trip(&table, BdatVersion::LegacySwitch).unwrap());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io::Cursor;
+
+use crate::{
+    BdatFile, BdatResult, BdatVersion, Cell, ColumnDef, Label, Row, SwitchEndian, Table,
+    TableAccessor, TableBuilder, Value, ValueType, WiiEndian,
+};
+
+/// Asserts that two tables are equal. On failure, the panic message lists what differs,
+/// see [`table_diff`].
+///
+/// [`table_diff`]: crate::test_utils::table_diff
+#[macro_export]
+macro_rules! assert_tables_eq {
+    ($expected:expr, $actual:expr $(,)?) => {
+        if let Some(diff) = $crate::test_utils::table_diff(&$expected, &$actual) {
+            panic!("tables are not equal:\n{diff}");
+        }
+    };
+}
+
+/// Builds small tables for tests. Column names are hashed for modern tables, and rows get
+/// consecutive IDs starting from 1, unless set with [`TableFixture::row_with_id`].
+#[derive(Debug, Clone)]
+pub struct TableFixture {
+    name: String,
+    columns: Vec<(String, ValueType)>,
+    rows: Vec<(Option<usize>, Vec<Cell<'static>>)>,
+}
+
+impl TableFixture {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn column(mut self, name: impl Into<String>, value_type: ValueType) -> Self {
+        self.columns.push((name.into(), value_type));
+        self
+    }
+
+    /// Adds a row with one value per column, with the ID after the previous row's.
+    pub fn row(mut self, values: impl IntoIterator<Item = Value<'static>>) -> Self {
+        self.rows
+            .push((None, values.into_iter().map(Cell::Single).collect()));
+        self
+    }
+
+    /// Adds a row with the given ID and cells, e.g. for list or flag cells.
+    pub fn row_with_id(mut self, id: usize, cells: Vec<Cell<'static>>) -> Self {
+        self.rows.push((Some(id), cells));
+        self
+    }
+
+    pub fn build(self, version: BdatVersion) -> Table<'static> {
+        let label = |name: String| Label::from(name).into_hash(version);
+        let mut builder = TableBuilder::with_name(label(self.name));
+        for (name, value_type) in self.columns {
+            builder = builder.add_column(ColumnDef::new(value_type, label(name)));
+        }
+        let mut next_id = 1;
+        for (id, cells) in self.rows {
+            let id = id.unwrap_or(next_id);
+            next_id = id + 1;
+            builder = builder.add_row(Row::new(id, cells));
+        }
+        builder.build(version)
+    }
+}
+
+/// Writes a table to a file of the given version and reads it back, in the byte order the
+/// version uses.
+pub fn round_trip(table: &Table, version: BdatVersion) -> BdatResult<Table<'static>> {
+    let table = table.clone();
+    let bytes = Cursor::new(match version {
+        BdatVersion::Modern => crate::modern::to_vec::<SwitchEndian>([table.to_modern()?])?,
+        BdatVersion::LegacySwitch => {
+            crate::legacy::to_vec::<SwitchEndian>([table.to_legacy()?], version)?
+        }
+        _ => crate::legacy::to_vec::<WiiEndian>([table.to_legacy()?], version)?,
+    });
+    match version {
+        BdatVersion::Modern => crate::modern::from_reader::<_, SwitchEndian>(bytes)?
+            .get_table(0)
+            .map(Into::into),
+        BdatVersion::LegacySwitch => crate::legacy::from_reader::<_, SwitchEndian>(bytes, version)?
+            .get_table(0)
+            .map(Into::into),
+        _ => crate::legacy::from_reader::<_, WiiEndian>(bytes, version)?
+            .get_table(0)
+            .map(Into::into),
+    }
+}
+
+/// Describes the differences between two tables, one per line, or returns `None` if they
+/// are equal.
+///
+/// Rows are matched by ID, and cells are compared by column. Column differences are listed
+/// first; if the columns differ, rows are not compared.
+pub fn table_diff(expected: &Table, actual: &Table) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let mut diff = String::new();
+    if expected.name() != actual.name() {
+        line(&mut diff, "name", expected.name(), actual.name());
+    }
+    if expected.base_id() != actual.base_id() {
+        line(&mut diff, "base ID", expected.base_id(), actual.base_id());
+    }
+
+    let (expected_columns, actual_columns) = (
+        expected.columns().collect::<Vec<_>>(),
+        actual.columns().collect::<Vec<_>>(),
+    );
+    if expected_columns != actual_columns {
+        let count = expected_columns.len().max(actual_columns.len());
+        for i in 0..count {
+            let (e, a) = (expected_columns.get(i), actual_columns.get(i));
+            if e != a {
+                line(&mut diff, &format!("column #{i}"), Column(e), Column(a));
+            }
+        }
+        return Some(diff);
+    }
+
+    let (expected_rows, actual_rows) = (rows(expected), rows(actual));
+    for (id, cells) in &expected_rows {
+        let Some(other) = actual_rows.get(id) else {
+            let _ = writeln!(diff, "row {id}: missing");
+            continue;
+        };
+        for ((column, e), a) in expected_columns.iter().zip(cells).zip(other) {
+            if e != a {
+                line(&mut diff, &format!("row {id}, {}", column.label()), e, a);
+            }
+        }
+    }
+    for id in actual_rows
+        .keys()
+        .filter(|id| !expected_rows.contains_key(id))
+    {
+        let _ = writeln!(diff, "row {id}: unexpected");
+    }
+    if diff.is_empty() {
+        // e.g. list lengths or flag definitions that display the same
+        diff.push_str("tables differ in details that display the same\n");
+    }
+    Some(diff)
+}
+
+fn rows<'t, 'b>(table: &'t Table<'b>) -> BTreeMap<usize, Vec<&'t Cell<'b>>> {
+    let mut rows = BTreeMap::<_, Vec<_>>::new();
+    for (id, _, cell) in table.iter_cells() {
+        rows.entry(id).or_default().push(cell);
+    }
+    rows
+}
+
+struct Column<'a>(Option<&'a &'a ColumnDef>);
+
+impl std::fmt::Display for Column<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(c) => write!(f, "{} ({:?} x{})", c.label(), c.value_type(), c.count()),
+            None => write!(f, "none"),
+        }
+    }
+}
+
+fn line(
+    diff: &mut String,
+    what: &str,
+    expected: impl std::fmt::Display,
+    actual: impl std::fmt::Display,
+) {
+    let _ = writeln!(diff, "{what}: expected {expected}, got {actual}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readable_diff() {
+        let table = |lv: u32, rows: usize| {
+            (0..rows)
+                .fold(
+                    TableFixture::new("Enemy").column("Lv", ValueType::UnsignedInt),
+                    |f, _| f.row([Value::UnsignedInt(lv)]),
+                )
+                .build(BdatVersion::LegacySwitch)
+        };
+        let expected = table(5, 2);
+        assert_eq!(
+            None,
+            table_diff(
+                &expected,
+                &round_trip(&expected, BdatVersion::LegacyWii).unwrap()
+            )
+        );
+        assert_eq!(
+            Some(
+                "row 1, Lv: expected 6, got 5\nrow 2, Lv: expected 6, got 5\nrow 3: missing\n"
+                    .to_string()
+            ),
+            table_diff(&table(6, 3), &expected)
+        );
+    }
+}
@@ -39,3 +39,25 @@ pub fn duplicate_table_create() -> LegacyTable<'static> {
         ))
         .build_legacy()
 }
+
+pub fn empty_table_create() -> LegacyTable<'static> {
+    TableBuilder::with_name(Label::String("Empty".to_string()))
+        .add_column(ColumnDef::new(
+            ValueType::SignedInt,
+            "Label1".to_string().into(),
+        ))
+        .add_column(
+            ColumnBuilder::new(ValueType::UnsignedByte, "Label2".to_string().into())
+                .set_count(2)
+                .build(),
+        )
+        .set_base_id(1)
+        .build_legacy()
+}
+
+pub fn no_columns_table_create() -> LegacyTable<'static> {
+    TableBuilder::with_name(Label::String("NoColumns".to_string()))
+        .add_row(Row::new(1, vec![]))
+        .add_row(Row::new(2, vec![]))
+        .build_legacy()
+}
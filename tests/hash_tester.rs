@@ -2,7 +2,7 @@
 
 use bdat::legacy::{LegacyHashTable, LegacyWriteOptions};
 use bdat::{
-    BdatVersion, ColumnBuilder, ColumnDef, FlagDef, LegacyTable, SwitchEndian, Table, TableBuilder,
+    BdatVersion, ColumnBuilder, ColumnDef, FlagDef, LegacyTable, SwitchEndian, TableBuilder,
     ValueType, WiiEndian,
 };
 use byteorder::ByteOrder;
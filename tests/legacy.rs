@@ -1,5 +1,9 @@
 use bdat::legacy::LegacyWriteOptions;
-use bdat::{BdatFile, BdatVersion, Cell, Label, SwitchEndian, TableAccessor, Value};
+use bdat::table::{DowngradeIssue, FormatConvertError};
+use bdat::{
+    BdatError, BdatFile, BdatVersion, Cell, Diagnostic, Label, Progress, SwitchEndian, Table,
+    TableAccessor, Value, ValueType,
+};
 
 type FileEndian = SwitchEndian;
 
@@ -61,7 +65,7 @@ fn basic_read() {
         ),
     ];
 
-    for (row, data) in table.rows().zip(data_t1.into_iter()) {
+    for (row, data) in table.rows().zip(data_t1) {
         let mut cells = row.cells();
         let a = cells
             .next()
@@ -109,12 +113,106 @@ fn write_back() {
         LegacyWriteOptions::new().scramble(true),
     )
     .unwrap();
-    let new_tables =
-        bdat::legacy::from_bytes::<FileEndian>(&mut new_out, BdatVersion::LegacySwitch)
+    let mut new_file =
+        bdat::legacy::from_bytes::<FileEndian>(&mut new_out, BdatVersion::LegacySwitch).unwrap();
+    let new_tables = new_file.get_tables().unwrap();
+    assert_eq!(tables, new_tables);
+    // Scrambled sections must match the stored checksum
+    assert_eq!(Vec::<Diagnostic>::new(), new_file.take_diagnostics());
+}
+
+#[test]
+fn progress() {
+    let mut events = Vec::new();
+    let tables =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables_with_progress(&mut |p| events.push(p))
+            .unwrap();
+    let total = tables.len();
+    assert_eq!(
+        (1..=total)
+            .map(|done| Progress::TableRead { done, total })
+            .collect::<Vec<_>>(),
+        events
+    );
+
+    let mut events = Vec::new();
+    let mut out = Vec::new();
+    bdat::legacy::to_writer_progress::<_, FileEndian>(
+        &mut out,
+        &tables,
+        BdatVersion::LegacySwitch,
+        LegacyWriteOptions::new(),
+        |p| events.push(p),
+    )
+    .unwrap();
+    assert_eq!(total, events.len());
+    assert_eq!(
+        Some(&Progress::TableWritten {
+            done: total,
+            total,
+            bytes: out.len()
+        }),
+        events.last()
+    );
+}
+
+#[test]
+fn diagnostics() {
+    let mut file =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap();
+    let tables = file.get_tables().unwrap();
+    assert!(file.take_diagnostics().is_empty());
+
+    let mut out = bdat::legacy::to_vec_options::<FileEndian>(
+        &tables,
+        BdatVersion::LegacySwitch,
+        LegacyWriteOptions::new()
+            .scramble(true)
+            .scramble_key(0x1234),
+    )
+    .unwrap();
+    let mut reader = bdat::legacy::from_reader::<_, FileEndian>(
+        std::io::Cursor::new(&out),
+        BdatVersion::LegacySwitch,
+    )
+    .unwrap();
+    reader.get_table(0).unwrap();
+    let diagnostics = reader.take_diagnostics();
+    assert!(matches!(
+        diagnostics.as_slice(),
+        [Diagnostic::ChecksumMismatch {
+            table: 0,
+            stored: 0x1234,
+            ..
+        }]
+    ));
+    assert!(reader.take_diagnostics().is_empty());
+
+    let mut file =
+        bdat::legacy::from_bytes::<FileEndian>(&mut out, BdatVersion::LegacySwitch).unwrap();
+    file.get_tables().unwrap();
+    assert_eq!(diagnostics, file.take_diagnostics());
+}
+
+#[test]
+fn single_table() {
+    let tables =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
             .unwrap()
             .get_tables()
             .unwrap();
-    assert_eq!(tables, new_tables);
+    let mut reader = bdat::legacy::from_reader::<_, FileEndian>(
+        std::io::Cursor::new(TEST_FILE_1),
+        BdatVersion::LegacySwitch,
+    )
+    .unwrap();
+    // Tables can be read in any order
+    for i in (0..tables.len()).rev() {
+        assert!(tables[i] == reader.get_table(i).unwrap());
+    }
 }
 
 #[test]
@@ -130,6 +228,38 @@ fn duplicate_columns() {
     assert_eq!(tables[0], back[0]);
 }
 
+#[test]
+fn empty_table() {
+    let tables = [common::empty_table_create()];
+
+    let mut bytes = bdat::legacy::to_vec::<FileEndian>(&tables, BdatVersion::LegacySwitch).unwrap();
+    let back = bdat::legacy::from_bytes::<FileEndian>(&mut bytes, BdatVersion::LegacySwitch)
+        .unwrap()
+        .get_tables()
+        .unwrap();
+
+    assert_eq!(tables[0], back[0]);
+    assert_eq!(0, back[0].row_count());
+    assert_eq!(1, back[0].base_id());
+}
+
+#[test]
+fn no_columns() {
+    let tables = [
+        common::no_columns_table_create(),
+        bdat::TableBuilder::with_name(Label::from("Nothing")).build_legacy(),
+    ];
+
+    let mut bytes = bdat::legacy::to_vec::<FileEndian>(&tables, BdatVersion::LegacySwitch).unwrap();
+    let back = bdat::legacy::from_bytes::<FileEndian>(&mut bytes, BdatVersion::LegacySwitch)
+        .unwrap()
+        .get_tables()
+        .unwrap();
+
+    assert_eq!(&tables[..], &back[..]);
+    assert_eq!(2, back[0].row_count());
+}
+
 #[test]
 fn table_map() {
     let tables =
@@ -147,3 +277,393 @@ fn table_map() {
     // Lifetime test
     assert_ne!(0, table.column_count());
 }
+
+#[test]
+fn truncated_file() {
+    let mut bytes = TEST_FILE_1[..TEST_FILE_1.len() / 2].to_vec();
+    assert!(matches!(
+        bdat::legacy::from_bytes::<FileEndian>(&mut bytes, BdatVersion::LegacySwitch),
+        Err(BdatError::MalformedBdat(_))
+    ));
+}
+
+#[test]
+fn cell_refs() {
+    let tables =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap();
+    let table = &tables[0];
+    let row = table.row(1);
+
+    let labels = row
+        .cell_refs()
+        .map(|c| c.column().label().to_string_convert().into_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ["value_u32", "value_f32", "value_str_arr", "value_flags"],
+        labels.as_slice()
+    );
+
+    let cell = row.cell_ref(Label::from("value_u32")).unwrap();
+    assert_eq!(ValueType::UnsignedInt, cell.column().value_type());
+    assert_eq!(Some(&Value::UnsignedInt(36)), cell.as_single());
+    assert!(row.cell_ref(Label::from("missing")).is_none());
+
+    assert_eq!(
+        Some(&Cell::Single(Value::UnsignedInt(36))),
+        row.cell(Label::from("value_u32"))
+    );
+    assert_eq!(None, row.cell(Label::from("missing")));
+}
+
+#[test]
+fn table_iterators() {
+    let table: Table =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .remove(0)
+            .into();
+
+    let mut ids = vec![];
+    for row in &table {
+        ids.push(row.id());
+    }
+    assert_eq!(vec![1, 2, 3, 4], ids);
+    assert_eq!(4, table.rows().len());
+
+    let cells = table.iter_cells().collect::<Vec<_>>();
+    assert_eq!(table.row_count() * table.column_count(), cells.len());
+    let (id, column, cell) = cells[4];
+    assert_eq!(2, id);
+    assert_eq!(ValueType::UnsignedInt, column.value_type());
+    assert_eq!(Some(&Value::UnsignedInt(2147583648)), cell.as_single());
+
+    let owned = table.into_iter().map(|row| row.id()).collect::<Vec<_>>();
+    assert_eq!(ids, owned);
+}
+
+#[test]
+fn table_display() {
+    let tables =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap();
+    let table = &tables[0];
+
+    let text = format!("{table:.2}");
+    let mut lines = text.lines();
+    assert_eq!(
+        Some("Table1 (legacy table, 4 columns, 4 rows)"),
+        lines.next()
+    );
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("ID | value_u32  | value_f32 |"));
+    assert_eq!(Some("... 2 more rows"), text.lines().last());
+    assert!(format!("{table:?}").contains("rows: 4"));
+}
+
+#[test]
+fn unique_columns() {
+    let table: Table =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .remove(0)
+            .into();
+
+    match table.check_unique(Label::from("value_u32")) {
+        Err(BdatError::DuplicateValue(dup)) => {
+            assert_eq!(("36", 1, 4), (dup.1.as_str(), dup.2, dup.3));
+        }
+        r => panic!("expected duplicate value, got {r:?}"),
+    }
+    assert!(table.check_unique(Label::from("missing")).is_ok());
+}
+
+#[test]
+fn column_maps() {
+    let table: Table =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .remove(0)
+            .into();
+    let (ints, floats) = (Label::from("value_u32"), Label::from("value_f32"));
+
+    match table.as_map::<u32, f32>(&ints, &floats) {
+        Err(BdatError::DuplicateValue(dup)) => {
+            assert_eq!(("36", 1, 4), (dup.1.as_str(), dup.2, dup.3));
+        }
+        r => panic!("expected duplicate key, got {r:?}"),
+    }
+    assert!(matches!(
+        table.as_map::<u32, &str>(&ints, Label::from("value_str_arr")),
+        Err(BdatError::NotSingleValue(e)) if e.0 == 1
+    ));
+    assert!(matches!(
+        table.as_map::<u32, f32>(&ints, Label::from("missing")),
+        Err(BdatError::MissingColumn(_))
+    ));
+    assert!(matches!(
+        table.as_map::<i32, f32>(&ints, &floats),
+        Err(BdatError::ValueCast(ValueType::UnsignedInt))
+    ));
+}
+
+#[test]
+fn table_slices() {
+    let table: Table =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .remove(0)
+            .into();
+
+    let ids = |slice: bdat::TableSlice| slice.rows().map(|r| r.id()).collect::<Vec<_>>();
+    assert_eq!(vec![2, 3], ids(table.slice(1..3)));
+    assert_eq!(vec![3, 4], ids(table.slice(2..)));
+    assert_eq!(vec![4], ids(table.slice(3..=10)));
+    assert!(table.slice(10..).is_empty());
+
+    assert_eq!(vec![3, 4], ids(table.paginate(1, 2)));
+    assert_eq!(vec![4], ids(table.paginate(1, 3)));
+    assert!(table.paginate(5, 3).is_empty());
+
+    let slice = table.slice(1..3);
+    assert_eq!(2, slice.row_count());
+    assert_eq!(Some(3), slice.get_row(3).map(|r| r.id()));
+    assert!(slice.get_row(1).is_none());
+    assert!(format!("{slice}").starts_with("Table1 (legacy table, 4 columns, 2 rows)"));
+}
+
+#[test]
+fn empty_like() {
+    let table: Table =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .remove(0)
+            .into();
+
+    let empty = table.empty_like();
+    assert!(empty.is_legacy());
+    assert_eq!(table.name(), empty.name());
+    assert_eq!(table.base_id(), empty.base_id());
+    assert_eq!(0, empty.row_count());
+    assert!(table.columns().eq(empty.columns()));
+
+    let cells = table.row(1).cells().cloned().collect();
+    let rebuilt = bdat::TableBuilder::from(empty)
+        .add_row(bdat::Row::new(1, cells))
+        .build(BdatVersion::LegacySwitch);
+    assert_eq!(1, rebuilt.row_count());
+    assert_eq!(table.row(1).cells().count(), rebuilt.row(1).cells().count());
+}
+
+#[test]
+fn row_id_range() {
+    let builder = || {
+        bdat::TableBuilder::with_name(Label::from("Table"))
+            .add_column(bdat::ColumnDef::new(ValueType::UnsignedInt, "value".into()))
+            .add_row(bdat::Row::new(
+                0xffff,
+                vec![Cell::Single(Value::UnsignedInt(1))],
+            ))
+            .add_row(bdat::Row::new(
+                0x10000,
+                vec![Cell::Single(Value::UnsignedInt(2))],
+            ))
+    };
+
+    assert!(matches!(
+        builder().try_build(BdatVersion::LegacySwitch),
+        Err(BdatError::RowIdOverflow(0x10000, BdatVersion::LegacySwitch))
+    ));
+    let modern = builder().try_build(BdatVersion::Modern).unwrap();
+    assert!(matches!(
+        modern.to_legacy(),
+        Err(BdatError::FormatConvert(e)) if e.to_string() == "row ID 65536 out of range"
+    ));
+
+    let legacy = builder().build_legacy();
+    assert!(matches!(
+        bdat::legacy::to_vec::<FileEndian>([legacy], BdatVersion::LegacySwitch),
+        Err(BdatError::RowIdOverflow(0x10000, _))
+    ));
+}
+
+#[test]
+fn downgrade() {
+    let table = bdat::TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+        .add_column(bdat::ColumnDef::new(ValueType::HashRef, Label::Hash(1)))
+        .add_column(bdat::ColumnDef::new(ValueType::DebugString, Label::Hash(2)))
+        .add_row(bdat::Row::new(
+            0xffff,
+            vec![
+                Cell::Single(Value::HashRef(0xABCD)),
+                Cell::Single(Value::DebugString("a".into())),
+            ],
+        ))
+        .add_row(bdat::Row::new(
+            0x10000,
+            vec![
+                Cell::Single(Value::HashRef(0)),
+                Cell::Single(Value::DebugString("b".into())),
+            ],
+        ))
+        .build(BdatVersion::Modern);
+    let names = [(0xCAFEBABE, "Enemy"), (2, "Comment")];
+    let (table, report) = table.downgrade_to(BdatVersion::LegacySwitch, |hash| {
+        names
+            .iter()
+            .find(|(h, _)| *h == hash)
+            .map(|(_, n)| n.to_string())
+    });
+
+    assert_eq!(
+        vec![
+            DowngradeIssue::UnresolvedLabel(Label::Hash(1)),
+            DowngradeIssue::ChangedType {
+                column: Label::Hash(1),
+                from: ValueType::HashRef,
+                to: ValueType::UnsignedInt
+            },
+            DowngradeIssue::ChangedType {
+                column: "Comment".into(),
+                from: ValueType::DebugString,
+                to: ValueType::String
+            },
+            DowngradeIssue::RemovedRow(0x10000),
+        ],
+        report.issues
+    );
+    assert!(report.has_removed_data());
+    assert!(table.is_legacy());
+    assert_eq!(&Label::from("Enemy"), table.name());
+    assert_eq!(
+        Some(&Value::UnsignedInt(0xABCD)),
+        table.row(0xffff).get(Label::Hash(1)).as_single()
+    );
+    assert!(
+        bdat::legacy::to_vec::<FileEndian>([table.into_legacy()], BdatVersion::LegacySwitch)
+            .is_ok()
+    );
+}
+
+#[test]
+fn builder_layout() {
+    let column = |name: &str| bdat::ColumnDef::new(ValueType::UnsignedInt, name.into());
+    let row = |cells: usize| bdat::Row::new(1, vec![Cell::Single(Value::UnsignedInt(1)); cells]);
+
+    let duplicate = || {
+        bdat::TableBuilder::with_name(Label::from("Table"))
+            .add_column(column("value"))
+            .add_column(column("value"))
+            .add_row(row(2))
+    };
+    assert!(matches!(
+        duplicate().try_build(BdatVersion::Modern),
+        Err(BdatError::DuplicateColumn(Label::String(s))) if s == "value"
+    ));
+    // Legacy tables can have duplicate column names
+    assert!(duplicate().try_build(BdatVersion::LegacySwitch).is_ok());
+
+    let missing = bdat::TableBuilder::with_name(Label::from("Table"))
+        .add_column(column("value"))
+        .add_column(column("value2"))
+        .add_row(row(1));
+    assert!(matches!(
+        missing.try_build(BdatVersion::LegacySwitch),
+        Err(BdatError::CellCount(e)) if *e == (1, 1, 2)
+    ));
+
+    let list = || {
+        bdat::TableBuilder::with_name(Label::from("Table"))
+            .add_column(
+                bdat::ColumnBuilder::new(ValueType::UnsignedInt, "list".into())
+                    .set_count(3)
+                    .build(),
+            )
+            .add_row(bdat::Row::new(
+                1,
+                vec![Cell::List(vec![Value::UnsignedInt(1); 2])],
+            ))
+    };
+    assert!(matches!(
+        list().try_build(BdatVersion::LegacySwitch),
+        Err(BdatError::ListLength(e)) if e.1 == 2 && e.3 == 3
+    ));
+    // Modern tables don't support lists
+    assert!(matches!(
+        list().try_build(BdatVersion::Modern),
+        Err(BdatError::FormatConvert(
+            FormatConvertError::UnsupportedCell
+        ))
+    ));
+
+    // Modern tables use ID hashes as primary keys
+    let id = |hash: u32| Cell::Single(Value::HashRef(hash));
+    let keys = bdat::TableBuilder::with_name(Label::from("Table"))
+        .add_column(bdat::ColumnDef::new(ValueType::HashRef, "id".into()))
+        .add_row(bdat::Row::new(1, vec![id(0xABCD)]))
+        .add_row(bdat::Row::new(2, vec![id(0xABCD)]));
+    assert!(matches!(
+        keys.try_build(BdatVersion::Modern),
+        Err(BdatError::DuplicateKey(e)) if e.1 == Label::Hash(0xABCD) && (e.2, e.3) == (1, 2)
+    ));
+}
+
+#[test]
+fn bdat_file_with_tables_only() {
+    // Implementors from before `get_table` was added only provide `get_tables`
+    struct Tables(Vec<Table<'static>>);
+
+    impl<'b> BdatFile<'b> for Tables {
+        type TableOut = Table<'static>;
+
+        fn get_tables(&mut self) -> bdat::BdatResult<Vec<Table<'static>>> {
+            Ok(self.0.clone())
+        }
+
+        fn table_count(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    let tables =
+        bdat::legacy::from_bytes_copy::<FileEndian>(TEST_FILE_1, BdatVersion::LegacySwitch)
+            .unwrap()
+            .get_tables()
+            .unwrap()
+            .into_iter()
+            .map(Table::from)
+            .collect::<Vec<_>>();
+    let mut file = Tables(tables.clone());
+    assert_eq!(tables[0], file.get_table(0).unwrap());
+
+    // Progress is reported by the default implementation, one table at a time
+    let mut events = Vec::new();
+    assert_eq!(
+        tables,
+        file.get_tables_with_progress(&mut |p| events.push(p))
+            .unwrap()
+    );
+    let total = tables.len();
+    assert_eq!(
+        (1..=total)
+            .map(|done| Progress::TableRead { done, total })
+            .collect::<Vec<_>>(),
+        events
+    );
+}
@@ -10,6 +10,9 @@ mod common;
 
 #[test]
 fn version_detect() {
+    let version = BdatVersion::detect(std::io::Cursor::new(TEST_FILE_1)).unwrap();
+    assert_eq!(BdatVersion::LegacyWii, version);
+    assert!(version.is_big_endian());
     assert_eq!(
         BdatVersion::LegacyWii,
         bdat::detect_bytes_version(TEST_FILE_1).unwrap()
@@ -61,7 +64,7 @@ fn basic_read() {
         ),
     ];
 
-    for (row, data) in table.rows().zip(data_t1.into_iter()) {
+    for (row, data) in table.rows().zip(data_t1) {
         let mut cells = row.cells();
         let a = cells
             .next()
@@ -127,3 +130,29 @@ fn duplicate_columns() {
 
     assert_eq!(tables[0], back[0]);
 }
+
+#[test]
+fn empty_table() {
+    let tables = [common::empty_table_create()];
+
+    let mut bytes = bdat::legacy::to_vec::<FileEndian>(&tables, VERSION).unwrap();
+    let back = bdat::legacy::from_bytes::<FileEndian>(&mut bytes, VERSION)
+        .unwrap()
+        .get_tables()
+        .unwrap();
+
+    assert_eq!(tables[0], back[0]);
+}
+
+#[test]
+fn no_columns() {
+    let tables = [common::no_columns_table_create()];
+
+    let mut bytes = bdat::legacy::to_vec::<FileEndian>(&tables, VERSION).unwrap();
+    let back = bdat::legacy::from_bytes::<FileEndian>(&mut bytes, VERSION)
+        .unwrap()
+        .get_tables()
+        .unwrap();
+
+    assert_eq!(tables[0], back[0]);
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, Table, TableAccessor, TableBuilder};
+use bdat_convert::error::{Error, FormatError};
+use bdat_convert::util::write_atomic;
+use clap::Args;
+use serde::de::DeserializeSeed;
+use serde_json::Map;
+
+use crate::{warn_diagnostics, InputData};
+
+#[derive(Args)]
+pub struct AppendArgs {
+    /// The table to add rows to
+    #[arg(short, long)]
+    table: String,
+}
+
+/// Appends rows from JSON files to a table in a BDAT file. The BDAT file is modified in place.
+///
+/// JSON files have an array of rows, in the same format as rows in extracted JSON tables.
+/// The `$id` of each row can be left out or set to `"auto"` to use the next free ID. If it is
+/// a number, it must be the next row ID. The IDs of the new rows are printed.
+pub fn append_rows(input: InputData, args: AppendArgs) -> Result<()> {
+    let (row_files, bdat_files): (Vec<_>, Vec<_>) = input
+        .files
+        .iter()
+        .map(PathBuf::from)
+        .partition(|path| path.extension().is_some_and(|e| e == "json"));
+    let ([bdat_path], [_, ..]) = (bdat_files.as_slice(), row_files.as_slice()) else {
+        return Err(Error::AppendFiles.into());
+    };
+
+    let mut bytes = std::fs::read(bdat_path)?;
+    let game = input.game_from_bytes(&bytes)?;
+    let version = BdatVersion::from(game);
+    let mut tables = game
+        .from_bytes(&mut bytes, &mut warn_diagnostics(bdat_path))
+        .with_context(|| format!("Could not parse BDAT tables ({})", bdat_path.display()))?;
+
+    let table_label = Label::parse_for_version(&args.table, version);
+    let index = tables
+        .iter()
+        .position(|t| t.name().clone().into_hash(version) == table_label)
+        .ok_or_else(|| Error::TableNotFound(args.table.clone()))?;
+
+    let table = tables.remove(index);
+    let first_id = table.base_id() + table.row_count();
+    let mut next_id = first_id;
+    let mut rows = Vec::new();
+    for path in &row_files {
+        rows.extend(
+            read_rows(path, &table, &mut next_id, version)
+                .with_context(|| format!("Could not read rows from {}", path.display()))?,
+        );
+    }
+    let count = rows.len();
+    let builder = rows
+        .into_iter()
+        .fold(TableBuilder::from(table), TableBuilder::add_row);
+    tables.insert(index, builder.try_build(version)?);
+
+    // Keep the original table order
+    write_atomic(bdat_path, |writer| {
+        Ok(game.to_writer(writer, tables, false, false)?)
+    })
+    .context("Could not save BDAT file")?;
+    match count {
+        0 => println!("Appended 0 rows to {}", args.table),
+        1 => println!("Appended 1 row to {} with ID {first_id}", args.table),
+        _ => println!(
+            "Appended {count} rows to {} with IDs {first_id} to {}",
+            args.table,
+            next_id - 1
+        ),
+    }
+    Ok(())
+}
+
+/// Reads the rows in a JSON file, checking them against the table's columns. `next_id` is
+/// the ID of the next row to be added, which is assigned to rows without an explicit ID.
+fn read_rows(
+    path: &Path,
+    table: &Table,
+    next_id: &mut usize,
+    version: BdatVersion,
+) -> Result<Vec<Row<'static>>> {
+    let rows: Vec<Map<String, serde_json::Value>> =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    let columns = table.columns().collect::<Vec<_>>();
+
+    let mut result = Vec::with_capacity(rows.len());
+    for mut row in rows {
+        let id = match row.remove("$id") {
+            Some(serde_json::Value::String(id)) if id == "auto" => *next_id,
+            Some(id) => serde_json::from_value(id)
+                .with_context(|| format!("Invalid $id, expected {next_id} or \"auto\""))?,
+            None => *next_id,
+        };
+        if id != *next_id {
+            return Err(FormatError::NonConsecutiveRow(id, *next_id)
+                .with_context(table.name().clone())
+                .into());
+        }
+
+        let mut cells: Vec<Option<Cell>> = vec![None; columns.len()];
+        for (key, value) in row {
+            let index = find_column(&columns, &key, version).ok_or_else(|| {
+                FormatError::UnknownColumn(Box::new((id, key.clone())))
+                    .with_context(table.name().clone())
+            })?;
+            let cell = columns[index]
+                .as_cell_seed()
+                .deserialize(value)
+                .with_context(|| format!("Invalid value in row {id}, column {key}"))?;
+            cells[index] = Some(cell);
+        }
+        let cells = cells
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| FormatError::IncompleteRow(id).with_context(table.name().clone()))?;
+        result.push(Row::new(id, cells));
+        *next_id += 1;
+    }
+    Ok(result)
+}
+
+/// Finds a column by name. Hashed names can be written as plain names or as hashes.
+fn find_column(columns: &[&ColumnDef], name: &str, version: BdatVersion) -> Option<usize> {
+    let label = Label::parse_for_version(name, version);
+    columns
+        .iter()
+        .position(|c| c.label().clone().into_hash(version) == label)
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::ValueType;
+
+    use super::*;
+
+    #[test]
+    fn column_names() {
+        let columns = [
+            ColumnDef::new(ValueType::UnsignedInt, Label::Hash(0x9143A12E)), // Lv
+            ColumnDef::new(ValueType::UnsignedInt, Label::from("Plain")),
+        ];
+        let columns = columns.iter().collect::<Vec<_>>();
+        for name in ["Lv", "<9143A12E>", "Lv (<9143A12E>)"] {
+            assert_eq!(Some(0), find_column(&columns, name, BdatVersion::Modern));
+        }
+        assert_eq!(Some(1), find_column(&columns, "Plain", BdatVersion::Modern));
+        assert_eq!(None, find_column(&columns, "Missing", BdatVersion::Modern));
+    }
+}
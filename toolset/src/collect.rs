@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use bdat::{fmt::json::JsonTable, Cell, Label, Table, TableAccessor, Value};
+use bdat_convert::{error::Error, hash::parse_line};
+use clap::Args;
+
+use crate::{warn_diagnostics, InputData};
+
+#[derive(Args)]
+pub struct CollectArgs {
+    /// The hash list to write. It can be used with "--hashes" to resolve hashed names.
+    #[arg(short, long)]
+    out_file: Option<String>,
+    /// Write "hash,name" pairs (with a header) instead of one name in each line
+    #[arg(long)]
+    with_hashes: bool,
+}
+
+/// Collects every unhashed name and string from BDAT files and extracted JSON tables,
+/// and writes them to a hash list.
+///
+/// Names used by older games are often hashed in newer ones, so this can recover names
+/// for hashes that are otherwise unknown.
+pub fn collect_names(input: InputData, args: CollectArgs) -> Result<()> {
+    let out_file = args
+        .out_file
+        .as_ref()
+        .ok_or(Error::MissingRequiredArgument("out-file"))?;
+
+    let mut names = BTreeSet::new();
+    for file in input.list_files(None::<&str>, false)? {
+        let path = file?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("bdat") => collect_bdat(&input, &path, &mut names)?,
+            Some("json") => collect_json(&path, &mut names)?,
+            _ => {}
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(out_file).context("Could not create hash list")?);
+    if args.with_hashes {
+        writeln!(writer, "hash,name")?;
+    }
+    let mut count = 0;
+    for name in names {
+        let hash = bdat::hash::murmur3_str(&name);
+        let line = match args.with_hashes {
+            true => format!("{},{name}", Label::Hash(hash)),
+            false => name.clone(),
+        };
+        // Skip strings that can't be read back, e.g. with line breaks or commas
+        if parse_line(&line) != Ok(Some((hash, &name))) {
+            continue;
+        }
+        writeln!(writer, "{line}")?;
+        count += 1;
+    }
+    writer.flush()?;
+    println!("Wrote {count} names to {out_file}");
+    Ok(())
+}
+
+fn collect_bdat(input: &InputData, path: &Path, names: &mut BTreeSet<String>) -> Result<()> {
+    let mut file = std::fs::read(path)?;
+    let tables = input
+        .game_from_bytes(&file)?
+        .from_bytes(&mut file, &mut warn_diagnostics(path))
+        .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
+    for table in &tables {
+        collect_table(table, names);
+    }
+    Ok(())
+}
+
+fn collect_table(table: &Table, names: &mut BTreeSet<String>) {
+    insert_label(table.name(), names);
+    for column in table.columns() {
+        insert_label(column.label(), names);
+        names.extend(column.flags().iter().map(|f| f.label().to_string()));
+    }
+    for row in table.rows() {
+        for cell in row.cells() {
+            match cell {
+                Cell::Single(v) => insert_value(v, names),
+                Cell::List(values) => values.iter().for_each(|v| insert_value(v, names)),
+                Cell::Flags(_) => {}
+            }
+        }
+    }
+}
+
+fn collect_json(path: &Path, names: &mut BTreeSet<String>) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    // Other JSON files, like schemas, are not tables
+    let Ok(table) = serde_json::from_reader::<_, JsonTable>(reader) else {
+        return Ok(());
+    };
+    if let Some(name) = table.name {
+        insert_text(&name, names);
+    }
+    for column in table.schema.iter().flatten().filter(|c| !c.hashed) {
+        names.insert(column.name.clone());
+        names.extend(column.flags.iter().map(|f| f.label().to_string()));
+    }
+    for row in &table.rows {
+        if table.schema.is_none() {
+            row.cells.keys().for_each(|k| insert_text(k, names));
+        }
+        row.cells.values().for_each(|v| insert_json(v, names));
+    }
+    Ok(())
+}
+
+fn insert_label(label: &Label, names: &mut BTreeSet<String>) {
+    if let Label::String(s) = label {
+        names.insert(s.to_string());
+    }
+}
+
+fn insert_value(value: &Value, names: &mut BTreeSet<String>) {
+    if let Value::String(s) | Value::DebugString(s) = value {
+        names.insert(s.to_string());
+    }
+}
+
+/// Inserts a string from a JSON table, unless it's a hash (e.g. `<1A2B3C4D>`).
+fn insert_text(text: &str, names: &mut BTreeSet<String>) {
+    insert_label(&Label::parse(text, false), names);
+}
+
+fn insert_json(value: &serde_json::Value, names: &mut BTreeSet<String>) {
+    match value {
+        serde_json::Value::String(s) => insert_text(s, names),
+        serde_json::Value::Array(values) => values.iter().for_each(|v| insert_json(v, names)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{ColumnDef, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn collect_strings() {
+        let table: Table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+            .add_column(ColumnDef::new(ValueType::HashRef, Label::Hash(0xDEADBEEF)))
+            .add_column(ColumnDef::new(ValueType::String, "DebugName".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::HashRef(1)),
+                    Cell::Single(Value::String("ITM_Potion".into())),
+                ],
+            ))
+            .build_modern()
+            .into();
+
+        let mut names = BTreeSet::new();
+        collect_table(&table, &mut names);
+        assert!(names.iter().eq(["DebugName", "ITM_Potion"]));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{info::format_unhashed_label, warn_diagnostics, InputData};
+use anyhow::{Context, Result};
+use bdat::{ColumnDef, Label, TableAccessor, TextKind};
+use bdat_convert::{
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Args)]
+pub struct ColumnsArgs {
+    /// Only list columns from these tables. If absent, lists columns from all tables.
+    #[arg(short, long)]
+    tables: Vec<String>,
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = ColumnsFormat::Text)]
+    format: ColumnsFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ColumnsFormat {
+    /// One line per column, grouped by table
+    Text,
+    /// An array with an object per column
+    Json,
+    /// A row per column
+    Csv,
+}
+
+/// A column, as listed in JSON and CSV output.
+#[derive(Serialize)]
+struct ColumnInfo {
+    file: String,
+    table: String,
+    column: String,
+    #[serde(rename = "type")]
+    value_type: String,
+    count: usize,
+    /// Whether the column name is hashed in the BDAT file
+    hashed: bool,
+    /// The hash of the column name, if it is hashed
+    hash: Option<String>,
+    /// The kind of text in the column: "string", "debug" (never shown to players) or
+    /// "message" (a reference to player-facing text)
+    text: Option<TextKind>,
+}
+
+pub fn list_columns(input: InputData, args: ColumnsArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let table_filter: Filter = args.tables.into_iter().map(FilterArg).collect();
+
+    let mut columns = Vec::new();
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let mut file = std::fs::read(&path)?;
+        let tables = input
+            .game_from_bytes(&file)?
+            .from_bytes(&mut file, &mut warn_diagnostics(&path))
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
+        let file_name = path.file_stem().unwrap_or_default().to_string_lossy();
+        for table in tables {
+            if !table_filter.contains(table.name()) {
+                continue;
+            }
+            if let ColumnsFormat::Text = args.format {
+                print_table(&table, &hash_table);
+                continue;
+            }
+            let table_name = hash_table.convert_label_cow(table.name()).to_string();
+            columns.extend(table.columns().map(|col| ColumnInfo {
+                file: file_name.to_string(),
+                table: table_name.clone(),
+                column: hash_table.convert_label_cow(col.label()).to_string(),
+                value_type: format!("{:?}", col.value_type()),
+                count: col.count(),
+                hashed: matches!(col.label(), Label::Hash(_)),
+                hash: match col.label() {
+                    l @ Label::Hash(_) => Some(l.to_string()),
+                    _ => None,
+                },
+                text: col.value_type().text_kind(),
+            }));
+        }
+    }
+
+    let stdout = std::io::stdout();
+    match args.format {
+        ColumnsFormat::Text => {}
+        ColumnsFormat::Json => {
+            let mut stdout = stdout.lock();
+            serde_json::to_writer_pretty(&mut stdout, &columns)?;
+            writeln!(stdout)?;
+        }
+        ColumnsFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(stdout.lock());
+            for column in columns {
+                writer.serialize(column)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn print_table(table: &bdat::Table, hash_table: &HashNameTable) {
+    println!("Table {}", format_unhashed_label(table.name(), hash_table));
+    for col in table.columns() {
+        println!(
+            "  {}: {}",
+            format_unhashed_label(col.label(), hash_table),
+            format_type(col)
+        );
+    }
+}
+
+fn format_type(col: &ColumnDef) -> String {
+    match col.count() {
+        1 => format!("{:?}", col.value_type()),
+        n => format!("{:?}[{n}]", col.value_type()),
+    }
+}
@@ -0,0 +1,575 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{Context, Result};
+use bdat::fmt::{HashFormat, MultiValueEncoding};
+use bdat_convert::{
+    error::Error, ComputedColumn, ConvertOptions, ExtractOptions, FileNames, FileType,
+    FormatOptions, Jobs, Layout, PackOptions, Progress, RowFilter, SanitizeNames, Summary,
+};
+use clap::{Args, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::{
+    util::{ProgressBarState, RayonPoolJobs},
+    InputData,
+};
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// The output directory that should contain the conversion result.
+    #[arg(short, long)]
+    out_dir: Option<String>,
+    /// Specifies the file type for the output file (when extracting) and input files (when packing).
+    #[arg(short, long)]
+    file_type: Option<String>,
+    /// (Convert only) The file type of the extracted tables to convert, "json" or "csv".
+    #[arg(long)]
+    from: Option<String>,
+    /// (Convert only) The file type to convert the tables to.
+    #[arg(long)]
+    to: Option<String>,
+    /// (Extract only) If this is set, types are not included in the serialized files. Note: the extracted output
+    /// cannot be repacked without type information
+    #[arg(short, long)]
+    untyped: bool,
+    /// (Extract only) If this is set, a schema file is not generated. Note: the extracted output cannot be
+    /// repacked without a schema
+    #[arg(short = 's', long)]
+    no_schema: bool,
+    /// Only convert these tables. If absent, converts all tables from all files.
+    #[arg(short, long)]
+    tables: Vec<String>,
+    /// (Extract only) Adds a column calculated from other columns, e.g. "TotalAtk=Atk*Level".
+    /// Only tables that have all the required columns get the new column. Computed columns
+    /// are ignored when packing. Syntax: "--computed <col1> --computed <col2> ...".
+    #[arg(long = "computed", value_name = "NAME=EXPR")]
+    computed: Vec<ComputedColumn>,
+    /// (Extract only) Only extracts rows that match the condition, e.g. 'Lv >= 90 && Name != ""'.
+    /// Computed columns and "$id" (the row ID) can be used. Tables that don't have all the
+    /// required columns are extracted in full. Not supported for XLSX and SQL output.
+    #[arg(long = "where", value_name = "EXPR")]
+    row_filter: Option<RowFilter>,
+    /// (Extract only) Writes a CSV report of hashes that could not be resolved with the hash
+    /// list: table names, column names and hash values, with where they were found and how
+    /// many times.
+    #[arg(long, value_name = "FILE")]
+    unresolved_report: Option<String>,
+    /// (Extract only) Writes names that were resolved from hashes together with the hash,
+    /// e.g. "Name (<1A2B3C4D>)", for table names, column names and hash values. Tables stay
+    /// usable if the hash list changes later. Not supported for XLSX and SQL output.
+    #[arg(long)]
+    names_with_hashes: bool,
+    /// (Pack only) Keeps tables in the order they are listed in the schema, instead of
+    /// sorting them by name hash. Only affects modern (XC3) files; use it to repack
+    /// files that were not sorted byte-for-byte.
+    #[arg(long)]
+    preserve_order: bool,
+    /// (Pack only) Scrambles the name and string sections of legacy tables, like in the
+    /// games' files. Use it to repack scrambled files byte-for-byte.
+    #[arg(long)]
+    scramble: bool,
+    /// (Pack only) Skips reading every table before packing. By default, all tables are
+    /// checked first and every problem is reported at once, before any file is written.
+    #[arg(long)]
+    no_check: bool,
+    /// (Pack only) Fails if there are table files that no schema lists, e.g. because of a
+    /// typo or a renamed table, instead of leaving them out with a warning.
+    #[arg(long)]
+    strict: bool,
+    /// (Extract only) Skips files that were fully extracted into the output directory by a
+    /// previous, interrupted run. Files whose size changed are extracted again.
+    #[arg(long)]
+    resume: bool,
+    /// (Extract only) Limits the BDAT data converted at the same time to about this many
+    /// megabytes, by reading files one table at a time and converting fewer files in
+    /// parallel. Useful for files with very large tables. Not supported for XLSX and SQL.
+    #[arg(long, value_name = "MB")]
+    max_memory: Option<u64>,
+    /// (Extract only) How table names are turned into file names. Characters that can't
+    /// be used are written as "%XX", and the schema records the original names.
+    #[arg(long, value_enum, default_value_t = FileNameMode::Portable)]
+    file_names: FileNameMode,
+    /// (Extract only) What to do with table names that can't be used as file names. The
+    /// schema records the original names, so packing restores them.
+    #[arg(long, value_enum, default_value_t = SanitizeArg::PercentEncode)]
+    sanitize_names: SanitizeArg,
+    /// (Extract only) Whether tables are written to a directory for each BDAT file
+    /// ("<file>/<table>.json"), or next to the schema with the file name as a prefix
+    /// ("<file>_<table>.json").
+    #[arg(long, value_enum, default_value_t = LayoutArg::Nested)]
+    layout: LayoutArg,
+    /// Also writes the summary printed after converting to this file, as JSON.
+    #[arg(long, value_name = "FILE")]
+    summary: Option<PathBuf>,
+    /// Reads the input files, and lists the files and tables that would be converted and
+    /// the files that would be written, without creating or overwriting anything.
+    #[arg(long, conflicts_with = "summary")]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    jobs: RayonPoolJobs,
+    /// The number of threads that read and write whole files. Use more for many small
+    /// files. By default, files and tables share the threads set by --jobs.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    jobs_files: Option<u16>,
+    /// The number of threads that convert tables, shared by all files. Use more for a few
+    /// large files, or 1 to convert each file's tables on the thread of the file.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    jobs_tables: Option<u16>,
+
+    #[clap(flatten)]
+    csv_opts: CsvOptions,
+    #[clap(flatten)]
+    json_opts: JsonOptions,
+}
+
+#[derive(Args)]
+pub struct CsvOptions {
+    #[arg(long)]
+    csv_separator: Option<char>,
+    /// When converting to CSV, expands legacy-BDAT lists into separate columns.
+    /// Same as "--csv-lists columns"
+    #[arg(long, conflicts_with = "csv_lists")]
+    expand_lists: bool,
+    /// When converting to or from CSV, how legacy-BDAT lists are written. Defaults to "json"
+    #[arg(long, value_enum)]
+    csv_lists: Option<CsvEncoding>,
+    /// When converting to or from CSV, how flags are written. Defaults to "columns"
+    #[arg(long, value_enum)]
+    csv_flags: Option<CsvEncoding>,
+    /// The separator used for lists and flags written with the "joined" encoding
+    #[arg(long, default_value_t = ';')]
+    csv_join_separator: char,
+    /// When converting to CSV, adds a first column with row IDs
+    #[arg(long)]
+    csv_row_id: bool,
+    /// The header of the row ID column, if enabled with --csv-row-id
+    #[arg(long, default_value = "$id", requires = "csv_row_id")]
+    csv_row_id_name: String,
+}
+
+/// How cells with multiple values are written to CSV files.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum CsvEncoding {
+    /// One column per value
+    Columns,
+    /// A single field, with values separated by --csv-join-separator
+    Joined,
+    /// A single field, with values in a JSON array
+    Json,
+}
+
+#[derive(Args)]
+pub struct JsonOptions {
+    /// If this is set, JSON output will include spaces and newlines
+    /// to improve readability.
+    #[arg(long)]
+    pretty: bool,
+    /// How hashed names and hash values are written in JSON output. Packing accepts all formats.
+    #[arg(long, value_enum, default_value_t = JsonHashFormat::Bracketed)]
+    hash_format: JsonHashFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum JsonHashFormat {
+    /// "<1A2B3C4D>"
+    Bracketed,
+    /// "0x1A2B3C4D"
+    Hex,
+    /// 439041101 (hashed column names are still written as "<1A2B3C4D>")
+    Decimal,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum FileNameMode {
+    /// Valid on every platform (escapes e.g. ':', '*' and '?', which Windows doesn't allow)
+    Portable,
+    /// Only escapes characters that are invalid on this platform
+    Native,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum SanitizeArg {
+    /// Replace characters that can't be used with "_"
+    Replace,
+    /// Write characters that can't be used as "%XX"
+    PercentEncode,
+    /// Fail on the first name that can't be used
+    Error,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum LayoutArg {
+    /// A directory for each BDAT file
+    Nested,
+    /// Table files next to the schema, prefixed with the BDAT file name
+    Flat,
+}
+
+/// Shows the progress of a conversion with a bar for files, and a bar for the tables
+/// of each file that is being converted.
+struct TerminalProgress {
+    bars: ProgressBarState,
+    tables: Mutex<HashMap<PathBuf, ProgressBar>>,
+    /// Files that were fully converted, for the summary printed after an interruption
+    completed: Mutex<Vec<PathBuf>>,
+    total: AtomicUsize,
+    /// Set when Ctrl-C is pressed
+    cancelled: Arc<AtomicBool>,
+}
+
+pub fn run_conversions(input: InputData, args: ConvertArgs, is_extracting: bool) -> Result<()> {
+    args.jobs.configure()?;
+
+    let out_dir = args
+        .out_dir
+        .as_ref()
+        .ok_or(Error::MissingRequiredArgument("out-dir"))?;
+    let file_type: FileType = args
+        .file_type
+        .as_ref()
+        .ok_or(Error::MissingRequiredArgument("file-type"))?
+        .parse()?;
+    let format = args.format_options();
+    let jobs = args.parallel_jobs();
+
+    if is_extracting {
+        let files = input
+            .list_files("bdat", false)?
+            .into_iter()
+            .collect::<walkdir::Result<Vec<_>>>()?;
+        let opts = ExtractOptions {
+            file_type,
+            format,
+            game: input.game,
+            hashes: input.load_hashes()?,
+            no_schema: args.no_schema,
+            tables: args.tables,
+            computed: args.computed,
+            row_filter: args.row_filter,
+            unresolved_report: args.unresolved_report.map(PathBuf::from),
+            resume: args.resume,
+            max_memory: args.max_memory.map(|mb| mb * 1024 * 1024),
+            file_names: match args.file_names {
+                FileNameMode::Portable => FileNames::Portable,
+                FileNameMode::Native => FileNames::Native,
+            },
+            sanitize_names: match args.sanitize_names {
+                SanitizeArg::Replace => SanitizeNames::Replace,
+                SanitizeArg::PercentEncode => SanitizeNames::PercentEncode,
+                SanitizeArg::Error => SanitizeNames::Error,
+            },
+            layout: match args.layout {
+                LayoutArg::Nested => Layout::Nested,
+                LayoutArg::Flat => Layout::Flat,
+            },
+            dry_run: args.dry_run,
+            jobs,
+        };
+        let progress = TerminalProgress::new()?;
+        let res = bdat_convert::extract(&files, Path::new(out_dir), &opts, &progress);
+        let summary = progress.finish(res)?;
+        if args.dry_run {
+            print_plan(&summary, "extract");
+            return Ok(());
+        }
+        print_summary(&summary, "Extracted");
+        write_summary(&summary, args.summary.as_deref())
+    } else {
+        // Schemas and standalone tables are picked from the listed files
+        let files = input
+            .list_files(None, false)?
+            .into_iter()
+            .collect::<walkdir::Result<Vec<_>>>()?;
+        let opts = PackOptions {
+            file_type,
+            format,
+            game: input.game,
+            preserve_order: args.preserve_order,
+            scramble: args.scramble,
+            no_check: args.no_check,
+            strict: args.strict,
+            dry_run: args.dry_run,
+            jobs,
+        };
+        let progress = TerminalProgress::new()?;
+        let res = bdat_convert::pack(&files, Path::new(out_dir), &opts, &progress);
+        let summary = progress.finish(res)?;
+        if args.dry_run {
+            print_plan(&summary, "pack");
+            return Ok(());
+        }
+        print_summary(&summary, "Packed");
+        write_summary(&summary, args.summary.as_deref())
+    }
+}
+
+/// Converts extracted tables from one format to another, for "bdat-toolset convert".
+pub fn convert_formats(input: InputData, args: ConvertArgs) -> Result<()> {
+    args.jobs.configure()?;
+
+    let out_dir = args
+        .out_dir
+        .as_ref()
+        .ok_or(Error::MissingRequiredArgument("out-dir"))?;
+    let from: FileType = args
+        .from
+        .as_ref()
+        .ok_or(Error::MissingRequiredArgument("from"))?
+        .parse()?;
+    let to: FileType = args
+        .to
+        .as_ref()
+        .ok_or(Error::MissingRequiredArgument("to"))?
+        .parse()?;
+    let files = input
+        .list_files("bschema", false)?
+        .into_iter()
+        .collect::<walkdir::Result<Vec<_>>>()?;
+    let opts = ConvertOptions {
+        from,
+        to,
+        format: args.format_options(),
+        dry_run: args.dry_run,
+        jobs: args.parallel_jobs(),
+    };
+    let progress = TerminalProgress::new()?;
+    let res = bdat_convert::convert(&files, Path::new(out_dir), &opts, &progress);
+    let summary = progress.finish(res)?;
+    if args.dry_run {
+        print_plan(&summary, "convert");
+        return Ok(());
+    }
+    print_summary(&summary, "Converted");
+    write_summary(&summary, args.summary.as_deref())
+}
+
+impl ConvertArgs {
+    fn parallel_jobs(&self) -> Jobs {
+        Jobs {
+            files: self.jobs_files.map(usize::from),
+            tables: self.jobs_tables.map(usize::from),
+        }
+    }
+
+    fn format_options(&self) -> FormatOptions {
+        let csv = &self.csv_opts;
+        let encoding = |e: CsvEncoding| match e {
+            CsvEncoding::Columns => MultiValueEncoding::Columns,
+            CsvEncoding::Joined => MultiValueEncoding::Joined(csv.csv_join_separator),
+            CsvEncoding::Json => MultiValueEncoding::Json,
+        };
+        let defaults = FormatOptions::default();
+        let csv_lists = match csv.csv_lists {
+            Some(lists) => encoding(lists),
+            None if csv.expand_lists => MultiValueEncoding::Columns,
+            None => defaults.csv_lists,
+        };
+        FormatOptions {
+            untyped: self.untyped,
+            names_with_hashes: self.names_with_hashes,
+            pretty: self.json_opts.pretty,
+            hash_format: match self.json_opts.hash_format {
+                JsonHashFormat::Bracketed => HashFormat::Bracketed,
+                JsonHashFormat::Hex => HashFormat::Hex,
+                JsonHashFormat::Decimal => HashFormat::Decimal,
+            },
+            csv_separator: csv.csv_separator.unwrap_or(defaults.csv_separator),
+            csv_lists,
+            csv_flags: csv.csv_flags.map_or(defaults.csv_flags, encoding),
+            csv_row_id: csv.csv_row_id,
+            csv_row_id_name: csv.csv_row_id_name.clone(),
+        }
+    }
+}
+
+impl TerminalProgress {
+    /// Creates the progress bars, and stops the conversion when Ctrl-C is pressed. Pressing
+    /// it again exits immediately.
+    fn new() -> Result<Self> {
+        let bars = ProgressBarState::new("Files", "Tables", 0);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let handler_bars = bars.clone();
+        let handler_cancelled = cancelled.clone();
+        ctrlc::set_handler(move || {
+            if handler_cancelled.swap(true, Ordering::Relaxed) {
+                std::process::exit(130);
+            }
+            let _ = handler_bars.println(
+                "Interrupted, finishing the files in progress (press Ctrl-C again to exit now)",
+            );
+        })
+        .context("Could not set Ctrl-C handler")?;
+        Ok(Self {
+            bars,
+            tables: Mutex::default(),
+            completed: Mutex::default(),
+            total: AtomicUsize::new(0),
+            cancelled,
+        })
+    }
+
+    /// Stops the progress bars, and prints which files were converted if the conversion
+    /// was interrupted.
+    fn finish(&self, res: Result<Summary>) -> Result<Summary> {
+        self.bars.finish();
+        if matches!(res, Err(ref e) if matches!(e.downcast_ref(), Some(Error::Cancelled))) {
+            let completed = self.completed.lock().unwrap();
+            let total = self.total.load(Ordering::Relaxed);
+            println!("Converted {} of {total} files:", completed.len());
+            for path in completed.iter() {
+                println!("  {}", path.display());
+            }
+        }
+        res
+    }
+}
+
+/// Prints what was converted. `action` describes the conversion, e.g. "Extracted".
+fn print_summary(summary: &Summary, action: &str) {
+    println!(
+        "{action} {}: {}, {} in {:.2}s",
+        plural(summary.files, "file", "files"),
+        plural(summary.tables, "table", "tables"),
+        plural(summary.rows, "row", "rows"),
+        summary.elapsed.as_secs_f64()
+    );
+    if summary.skipped_files > 0 {
+        println!(
+            "  Skipped {} that were already extracted",
+            plural(summary.skipped_files, "file", "files")
+        );
+    }
+    if summary.skipped_tables > 0 {
+        println!(
+            "  Skipped {} that were not selected",
+            plural(summary.skipped_tables, "table", "tables")
+        );
+    }
+    if summary.unresolved_hashes > 0 {
+        println!(
+            "  {} could not be resolved",
+            plural(summary.unresolved_hashes, "hash", "hashes")
+        );
+    }
+    if !summary.warnings.is_empty() {
+        println!(
+            "  {}",
+            plural(summary.warnings.len(), "warning", "warnings")
+        );
+    }
+    // Files are converted in parallel, so stages can add up to more than the elapsed time
+    println!(
+        "  Time across threads: reading {:.2}s, converting {:.2}s, writing {:.2}s",
+        summary.read_time.as_secs_f64(),
+        summary.convert_time.as_secs_f64(),
+        summary.write_time.as_secs_f64()
+    );
+}
+
+/// Prints what a dry run would convert, with the files that would be written for each
+/// input file. `action` describes the conversion, e.g. "extract".
+fn print_plan(summary: &Summary, action: &str) {
+    println!(
+        "Would {action} {}: {}, {}. Nothing was written",
+        plural(summary.files, "file", "files"),
+        plural(summary.tables, "table", "tables"),
+        plural(summary.rows, "row", "rows")
+    );
+    // Files are converted in parallel, so sort them for a stable listing
+    let mut planned = summary.planned.iter().collect::<Vec<_>>();
+    planned.sort_by(|a, b| (&a.source, &a.path).cmp(&(&b.source, &b.path)));
+    let mut source = None;
+    for write in planned {
+        if source != Some(&write.source) {
+            println!("  {}", write.source.display());
+            source = Some(&write.source);
+        }
+        match &write.table {
+            Some(table) => println!("    {table} -> {}", write.path.display()),
+            None => println!("    -> {}", write.path.display()),
+        }
+    }
+    if summary.skipped_files > 0 {
+        println!(
+            "  Would skip {} that were already extracted",
+            plural(summary.skipped_files, "file", "files")
+        );
+    }
+    if summary.skipped_tables > 0 {
+        println!(
+            "  Would skip {} that were not selected",
+            plural(summary.skipped_tables, "table", "tables")
+        );
+    }
+}
+
+pub(crate) fn plural(n: usize, one: &str, many: &str) -> String {
+    match n {
+        1 => format!("1 {one}"),
+        n => format!("{n} {many}"),
+    }
+}
+
+fn write_summary(summary: &Summary, path: Option<&Path>) -> Result<()> {
+    let Some(path) = path else { return Ok(()) };
+    bdat_convert::util::write_atomic(path, |writer| {
+        Ok(serde_json::to_writer_pretty(writer, summary)?)
+    })
+    .context("Could not write summary")
+}
+
+impl Progress for TerminalProgress {
+    fn start(&self, files: usize) {
+        self.total.store(files, Ordering::Relaxed);
+        self.bars.master_bar.set_length(files as u64);
+        self.bars.master_bar.inc(0);
+    }
+
+    fn start_file(&self, file: &Path, tables: usize) {
+        let bar = self.bars.add_child(tables);
+        self.tables.lock().unwrap().insert(file.to_owned(), bar);
+    }
+
+    fn table_done(&self, file: &Path) {
+        if let Some(bar) = self.tables.lock().unwrap().get(file) {
+            bar.inc(1);
+        }
+    }
+
+    fn file_done(&self, file: &Path) {
+        if let Some(bar) = self.tables.lock().unwrap().remove(file) {
+            self.bars.remove_child(&bar);
+        }
+        self.completed.lock().unwrap().push(file.to_owned());
+        self.bars.master_bar.inc(1);
+    }
+
+    fn file_skipped(&self, _file: &Path) {
+        self.bars.master_bar.inc(1);
+    }
+
+    fn warn(&self, message: &str) {
+        // Failing to print a warning shouldn't stop the conversion
+        let _ = self.bars.println(format!("[Warn] {message}"));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+pub fn build_progress_style(label: &str, with_time: bool) -> ProgressStyle {
+    ProgressStyle::with_template(&match with_time {
+        true => format!("{{spinner:.cyan}} [{{elapsed_precise:.cyan}}] {label}{{msg}}: {{human_pos}}/{{human_len}} ({{percent}}%) [{{bar:.cyan/blue}}] ETA: {{eta}}"),
+        false => format!("{{spinner:.green}} {label}{{msg}}: {{human_pos}}/{{human_len}} ({{percent}}%) [{{bar}}]"),
+    })
+    .unwrap()
+}
@@ -2,21 +2,30 @@ use std::hash::{Hash, Hasher};
 use std::{
     borrow::Cow,
     cmp::Ordering,
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fs::File,
-    io::BufReader,
+    io::{BufReader, BufWriter},
     path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
 use indicatif::ProgressBar;
 use itertools::Itertools;
 use rayon::{iter::Either, prelude::*};
 
-use bdat::{BdatFile, Cell, Label, Table, TableAccessor};
+use bdat::{Cell, Label, Table, TableAccessor};
 
-use crate::{hash::MurmurHashSet, InputData};
+use bdat_convert::{
+    error::Error,
+    hash::{HashNameTable, MurmurHashSet},
+    patch::Patch,
+    refs::{ColumnRef, RefTarget},
+    schema::FileSchema,
+    BdatDeserialize, CsvConverter, FormatOptions, JsonConverter,
+};
+
+use crate::{read_tables, warn_diagnostics, InputData};
 
 #[derive(Args)]
 pub struct DiffArgs {
@@ -31,6 +40,34 @@ pub struct DiffArgs {
     /// Don't print file names.
     #[arg(long)]
     no_file_names: bool,
+    /// Schema files (.bschema) that declare references between tables. When a changed value
+    /// references another row, the referenced row is printed next to it.
+    /// Syntax: "--schema <path1> --schema <path2> ...".
+    #[arg(long = "schema", action = clap::ArgAction::Append)]
+    schemas: Vec<String>,
+    /// Compare two dumps, e.g. of different game versions. Files are matched by their path
+    /// in the old and new directories, and added and removed files are listed before the
+    /// changed tables of each file. Dumps can be directories of BDAT files, or of extracted
+    /// JSON or CSV tables with their .bschema files.
+    #[arg(long)]
+    dump: bool,
+    /// (With --dump) Only print how many rows were added, removed and changed in each table.
+    #[arg(long, requires = "dump")]
+    stat: bool,
+    /// Instead of printing the differences, write them to a patch file that can be applied
+    /// with "patch". Only changed cells are written for tables that keep their columns.
+    /// Expects a single old and a single new BDAT file.
+    #[arg(long, value_name = "FILE", conflicts_with = "dump")]
+    emit_patch: Option<PathBuf>,
+}
+
+/// The files of a dump, keyed by their path relative to the dump's directory, without
+/// extension, so BDAT files and extracted files are matched alike.
+pub(crate) type Dump = BTreeMap<PathBuf, Vec<Table<'static>>>;
+
+/// Resolves references to rows of other tables, as declared in file schemas.
+struct References {
+    schemas: Vec<FileSchema>,
 }
 
 #[derive(Debug)]
@@ -65,9 +102,15 @@ struct ColumnChange<'t, 'tb> {
 }
 
 #[derive(Debug)]
-struct ValueOrderedLabel(Label);
+pub(crate) struct ValueOrderedLabel(pub(crate) Label);
 
 pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
+    if args.dump {
+        return run_dump_diff(input, args);
+    }
+    if let Some(out) = &args.emit_patch {
+        return emit_patch(&input, &args.old_files, out);
+    }
     let progress = ProgressBar::new(3)
         .with_style(crate::convert::build_progress_style("Diff", true))
         .with_message(" (Reading files)");
@@ -80,6 +123,7 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
         .list_files("bdat", !args.no_file_names)?
         .into_iter();
     let hash_table = input.load_hashes()?;
+    let references = References::read(&args.schemas)?;
 
     let files_to_read = new_files
         .map(|f| f.map(|f| (f, true)))
@@ -95,8 +139,8 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
         .par_iter()
         .flat_map(|(file, new)| {
             let reader = BufReader::new(File::open(file)?);
-            let mut tables = bdat::from_reader(reader).and_then(|mut f| {
-                Ok(f.get_tables()?
+            let tables = bdat::from_reader(reader).and_then(|f| {
+                Ok(read_tables(f, file)?
                     .into_iter()
                     .map(|table| TableWithSource {
                         table,
@@ -104,9 +148,6 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
                     })
                     .collect_vec())
             })?;
-            for table in &mut tables {
-                hash_table.convert_all(&mut table.table);
-            }
             Ok::<(Vec<TableWithSource>, bool), anyhow::Error>((tables, *new))
         })
         .partition_map::<Vec<Result<_>>, Vec<Result<_>>, _, Result<_>, Result<_>>(
@@ -122,34 +163,33 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
         old_tables
             .into_iter()
             .flatten_ok()
-            .map_ok(|t| (ValueOrderedLabel(t.table.name().clone()), t))
+            .map_ok(|t| (ValueOrderedLabel::resolve(t.table.name(), &hash_table), t))
             .try_collect()?,
         new_tables
             .into_iter()
             .flatten_ok()
-            .map_ok(|t| (ValueOrderedLabel(t.table.name().clone()), t))
+            .map_ok(|t| (ValueOrderedLabel::resolve(t.table.name(), &hash_table), t))
             .try_collect()?,
     );
     progress.inc(1);
 
     let added = new_tables
         .iter()
-        .filter_map(|(name, table)| (!old_tables.contains_key(name)).then_some(table));
+        .filter(|(name, _)| !old_tables.contains_key(name));
     let removed = old_tables
         .iter()
-        .filter_map(|(name, table)| (!new_tables.contains_key(name)).then_some(table));
+        .filter(|(name, _)| !new_tables.contains_key(name));
 
     progress.inc(1);
     progress.set_message(" (Processing result)");
 
     println!("------------\nAdded Tables\n------------");
-    added.for_each(|table| {
+    added.for_each(|(ValueOrderedLabel(name), table)| {
         if args.no_file_names {
-            println!("+ Table \"{}\"", table.table.name());
+            println!("+ Table \"{name}\"");
         } else {
             println!(
-                "+ Table \"{}\" (new: {})",
-                table.table.name(),
+                "+ Table \"{name}\" (new: {})",
                 table
                     .source_file
                     .strip_prefix(&working_directory)
@@ -160,13 +200,12 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
     });
 
     println!("\n--------------\nRemoved Tables\n--------------");
-    removed.for_each(|table| {
+    removed.for_each(|(ValueOrderedLabel(name), table)| {
         if args.no_file_names {
-            println!("- Table \"{}\"", table.table.name());
+            println!("- Table \"{name}\"");
         } else {
             println!(
-                "- Table \"{}\" (old: {})",
-                table.table.name(),
+                "- Table \"{name}\" (old: {})",
                 table
                     .source_file
                     .strip_prefix(&working_directory)
@@ -177,7 +216,7 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
     });
 
     println!("\n--------------\nChanged Tables\n--------------");
-    for (ref l @ ValueOrderedLabel(ref name), table) in old_tables.into_iter() {
+    for (l @ ValueOrderedLabel(name), table) in old_tables.iter() {
         let new_table = match new_tables.get(l) {
             Some(table) => table,
             None => continue,
@@ -204,7 +243,10 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
                 );
             }
             for row_changed in row_changes {
-                row_changed.print();
+                row_changed.print(&hash_table, |column, added, cell| {
+                    let tables = if added { &new_tables } else { &old_tables };
+                    references.describe(name, column, cell, tables)
+                });
             }
         }
     }
@@ -212,6 +254,200 @@ pub fn run_diff(input: InputData, args: DiffArgs) -> Result<()> {
     Ok(())
 }
 
+fn run_dump_diff(input: InputData, args: DiffArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let old = read_dump(&args.old_files)?;
+    let new = read_dump(&input.files)?;
+
+    println!("-----------\nAdded Files\n-----------");
+    for path in new.keys().filter(|path| !old.contains_key(*path)) {
+        println!("+ {}", path.display());
+    }
+    println!("\n-------------\nRemoved Files\n-------------");
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        println!("- {}", path.display());
+    }
+    println!("\n-------------\nChanged Files\n-------------");
+    for (path, old_tables) in &old {
+        if let Some(new_tables) = new.get(path) {
+            diff_dump_file(path, old_tables, new_tables, &hash_table, args.stat);
+        }
+    }
+    Ok(())
+}
+
+/// Writes the changes from an old BDAT file to a new one as a patch.
+fn emit_patch(input: &InputData, old_files: &[String], out: &Path) -> Result<()> {
+    let ([old_path], [new_path]) = (old_files, input.files.as_slice()) else {
+        return Err(Error::EmitPatchFiles.into());
+    };
+    let hash_table = input.load_hashes()?;
+    let mut old_bytes = std::fs::read(old_path)?;
+    let mut new_bytes = std::fs::read(new_path)?;
+    let game = input.game_from_bytes(&new_bytes)?;
+    let old = game
+        .from_bytes(&mut old_bytes, &mut warn_diagnostics(Path::new(old_path)))
+        .with_context(|| format!("Could not parse BDAT tables ({old_path})"))?;
+    let new = game
+        .from_bytes(&mut new_bytes, &mut warn_diagnostics(Path::new(new_path)))
+        .with_context(|| format!("Could not parse BDAT tables ({new_path})"))?;
+
+    let patch = Patch::from_tables(&old, &new, game.into(), &hash_table)?;
+    let writer = BufWriter::new(File::create(out).context("Could not create patch file")?);
+    serde_json::to_writer_pretty(writer, &patch)?;
+    println!(
+        "Changed {} table(s), added or replaced {}, removed {}",
+        patch.tables.len(),
+        patch.new_tables.len(),
+        patch.removed_tables.len()
+    );
+    Ok(())
+}
+
+/// Reads every file of a dump. If there are schema files, the dump is read as extracted
+/// tables, otherwise as BDAT files.
+pub(crate) fn read_dump(paths: &[String]) -> Result<Dump> {
+    let input = InputData {
+        files: paths.to_vec(),
+        ..Default::default()
+    };
+    let files = input
+        .list_files(None, false)?
+        .into_iter()
+        .collect::<walkdir::Result<Vec<_>>>()?;
+    let has_extension = |path: &PathBuf, ext: &str| path.extension().is_some_and(|e| e == ext);
+    let schemas = files
+        .iter()
+        .filter(|path| has_extension(path, "bschema"))
+        .collect::<Vec<_>>();
+    let files = match schemas.is_empty() {
+        true => files
+            .iter()
+            .filter(|path| has_extension(path, "bdat"))
+            .collect(),
+        false => schemas,
+    };
+    // With a single file, the common directory is the file itself
+    let root = match files.as_slice() {
+        [file] => file.parent().unwrap_or(Path::new("")).to_owned(),
+        files => bdat_convert::util::get_common_denominator(files),
+    };
+
+    files
+        .into_par_iter()
+        .map(|path| {
+            let tables = match has_extension(path, "bschema") {
+                true => read_extracted(path),
+                false => bdat::from_reader(BufReader::new(File::open(path)?))
+                    .and_then(|file| read_tables(file, path))
+                    .map_err(Into::into),
+            }
+            .with_context(|| format!("Could not read {}", path.display()))?;
+            let key = path.strip_prefix(&root).unwrap().with_extension("");
+            Ok((key, tables))
+        })
+        .collect()
+}
+
+/// Reads the tables of an extracted file from the JSON or CSV files next to its schema.
+fn read_extracted(schema_path: &Path) -> Result<Vec<Table<'static>>> {
+    let schema = FileSchema::read(BufReader::new(File::open(schema_path)?))?;
+    let format = FormatOptions::default();
+    let readers: [Box<dyn BdatDeserialize>; 2] = [
+        Box::new(JsonConverter::new(&format)),
+        Box::new(CsvConverter::new(&format)),
+    ];
+    let schema_dir = schema_path.parent().unwrap();
+    let Some((reader, files)) = readers.iter().find_map(|reader| {
+        let files = schema.find_table_files(schema_dir, reader.get_table_extension());
+        (!files.is_empty()).then_some((reader, files))
+    }) else {
+        return Ok(Vec::new());
+    };
+    files
+        .into_iter()
+        .map(|(label, path)| {
+            let mut file = BufReader::new(File::open(&path)?);
+            let mut table = reader
+                .read_table(label.into_hash(schema.version), &schema, &mut file)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            schema.resolve_labels(&mut table);
+            Ok(table)
+        })
+        .collect()
+}
+
+/// Prints the tables that were added, removed and changed in a file of a dump.
+fn diff_dump_file(
+    path: &Path,
+    old: &[Table<'static>],
+    new: &[Table<'static>],
+    hash_table: &HashNameTable,
+    stat: bool,
+) {
+    let (old, new) = (by_name(old, hash_table), by_name(new, hash_table));
+    let mut printed_path = false;
+    let mut print_path = || {
+        if !std::mem::replace(&mut printed_path, true) {
+            println!("\n{}:", path.display());
+        }
+    };
+
+    for (ValueOrderedLabel(name), table) in new.iter().filter(|(n, _)| !old.contains_key(n)) {
+        print_path();
+        println!("+ Table \"{name}\" ({} rows)", table.row_count());
+    }
+    for (ValueOrderedLabel(name), table) in old.iter().filter(|(n, _)| !new.contains_key(n)) {
+        print_path();
+        println!("- Table \"{name}\" ({} rows)", table.row_count());
+    }
+    for (l @ ValueOrderedLabel(name), old_table) in &old {
+        let Some(new_table) = new.get(l) else {
+            continue;
+        };
+        let ids: BTreeSet<usize> = old_table
+            .rows()
+            .chain(new_table.rows())
+            .map(|row| row.id())
+            .collect();
+        let row_changes = ids
+            .into_iter()
+            .filter_map(|id| RowDiff::new(old_table, new_table, id).diff())
+            .collect_vec();
+        if row_changes.is_empty() {
+            continue;
+        }
+        let count = |table: &Table| {
+            row_changes
+                .iter()
+                .filter(|row| table.get_row(row.row_id).is_none())
+                .count()
+        };
+        let (added, removed) = (count(old_table), count(new_table));
+        print_path();
+        println!(
+            "~ Table \"{name}\": {added} rows added, {removed} removed, {} changed",
+            row_changes.len() - added - removed
+        );
+        if !stat {
+            for row_changed in row_changes {
+                row_changed.print(hash_table, |_, _, _| None);
+            }
+        }
+    }
+}
+
+/// Keys tables by their name, resolved with `hash_table`.
+pub(crate) fn by_name<'t>(
+    tables: &'t [Table<'static>],
+    hash_table: &HashNameTable,
+) -> BTreeMap<ValueOrderedLabel, &'t Table<'static>> {
+    tables
+        .iter()
+        .map(|t| (ValueOrderedLabel::resolve(t.name(), hash_table), t))
+        .collect()
+}
+
 impl<'t, 'tb> RowDiff<'t, 'tb> {
     fn new(old: &'t Table<'tb>, new: &'t Table<'tb>, row_id: usize) -> Self {
         Self { row_id, old, new }
@@ -273,29 +509,81 @@ impl<'t, 'tb> RowDiff<'t, 'tb> {
     }
 }
 
+impl References {
+    fn read(paths: &[String]) -> Result<Self> {
+        let schemas = paths
+            .iter()
+            .map(|path| FileSchema::read(BufReader::new(File::open(path)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self { schemas })
+    }
+
+    /// Describes the row referenced by a cell, if the cell's column is a reference.
+    fn describe(
+        &self,
+        table: &Label,
+        column: &Label,
+        cell: &Cell,
+        tables: &BTreeMap<ValueOrderedLabel, TableWithSource>,
+    ) -> Option<String> {
+        self.schemas.iter().find_map(|schema| {
+            let (_, col_ref) = schema
+                .table_refs(table)?
+                .iter()
+                .find(|(name, _)| schema.is_label(name, column))?;
+            let target = tables
+                .values()
+                .find(|t| schema.is_label(&col_ref.table, t.table.name()))?;
+            match ColumnRef::resolve(&target.table, cell) {
+                RefTarget::Row(row) => {
+                    Some(col_ref.describe(&row, |name, label| schema.is_label(name, label)))
+                }
+                RefTarget::Missing => Some(format!("missing row in {}", col_ref.table)),
+                RefTarget::Null => None,
+            }
+        })
+    }
+}
+
 impl<'t, 'tb> RowChanges<'t, 'tb> {
-    fn print(self) {
+    /// Prints the row's changes. `describe` provides an optional description for changed
+    /// values, e.g. the name of a referenced row.
+    ///
+    /// Hashed column names are resolved with `hash_table` when they are printed.
+    fn print(
+        self,
+        hash_table: &HashNameTable,
+        describe: impl Fn(&Label, bool, &Cell) -> Option<String>,
+    ) {
+        let format_change = |change: &ColumnChange| {
+            let ColumnChange {
+                label,
+                added,
+                value,
+            } = change;
+            let name = hash_table.convert_label_cow(label);
+            let text = match value {
+                Cell::Single(v) => serde_json::to_string(v),
+                Cell::List(values) => serde_json::to_string(values),
+                Cell::Flags(flags) => serde_json::to_string(flags),
+            }
+            .unwrap();
+            match describe(label, *added, value) {
+                Some(desc) => format!("{name}: {text} ({desc})"),
+                None => format!("{name}: {text}"),
+            }
+        };
         let removed = self
             .changes
             .iter()
-            .filter(|&ColumnChange { added, .. }| (!added))
-            .map(|ColumnChange { label, value, .. }| {
-                format!(
-                    "{label}: {}",
-                    serde_json::to_string(value.as_single().unwrap()).unwrap()
-                )
-            })
+            .filter(|&ColumnChange { added, .. }| !added)
+            .map(format_change)
             .join(" / ");
         let added = self
             .changes
             .iter()
             .filter(|ColumnChange { added, .. }| *added)
-            .map(|ColumnChange { label, value, .. }| {
-                format!(
-                    "{label}: {}",
-                    serde_json::to_string(value.as_single().unwrap()).unwrap()
-                )
-            })
+            .map(format_change)
             .join(" / ");
 
         if !removed.is_empty() {
@@ -370,6 +658,14 @@ impl<'t, 'tb> From<(&'t Label, bool, &'t Cell<'tb>)> for ColumnChange<'t, 'tb> {
     }
 }
 
+impl ValueOrderedLabel {
+    /// Table names are resolved once, as keys. The tables themselves keep their
+    /// hashed labels, which are only resolved when printed.
+    fn resolve(label: &Label, hash_table: &HashNameTable) -> Self {
+        Self(hash_table.convert_label_cow(label).into_owned())
+    }
+}
+
 impl PartialOrd for ValueOrderedLabel {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(Ord::cmp(self, other))
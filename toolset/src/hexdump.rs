@@ -0,0 +1,303 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+use bdat::{BdatVersion, Label, ValueType};
+use bdat_convert::{
+    error::Error,
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+};
+use clap::Args;
+
+use crate::{
+    info::format_unhashed_label,
+    layout::{self, ModernHeader, MODERN_COLUMN_LEN, MODERN_ROW_ID_LEN},
+    InputData,
+};
+
+const BYTES_PER_LINE: usize = 16;
+
+#[derive(Args)]
+pub struct HexdumpArgs {
+    /// The table to dump
+    #[arg(short, long)]
+    table: String,
+    /// Only dump the first N rows. If absent, dumps all rows.
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+/// Prints an annotated hex dump of a table in a modern BDAT file.
+///
+/// Headers and sections are read directly from the bytes, rather than through the `bdat`
+/// crate, so tables that can't be parsed (e.g. from a new game update) can still be dumped.
+pub fn hexdump(input: InputData, args: HexdumpArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let filter: Filter = [FilterArg(args.table.clone())].into_iter().collect();
+
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let bytes = std::fs::read(&path)?;
+        if bdat::detect_bytes_version(&bytes)
+            .with_context(|| format!("Could not detect BDAT version ({})", path.display()))?
+            != BdatVersion::Modern
+        {
+            return Err(Error::NotModern.into());
+        }
+        let mut out = std::io::stdout().lock();
+        let dump = Dump {
+            bytes: &bytes,
+            hash_table: &hash_table,
+            out: &mut out,
+        };
+        if dump.find_and_dump(&filter, args.rows)? {
+            return Ok(());
+        }
+    }
+    Err(Error::TableNotFound(args.table).into())
+}
+
+struct Dump<'a, W> {
+    bytes: &'a [u8],
+    hash_table: &'a HashNameTable,
+    out: &'a mut W,
+}
+
+impl<'a, W: Write> Dump<'a, W> {
+    /// Dumps the first table that matches the filter, returning whether one was found.
+    fn find_and_dump(mut self, filter: &Filter, rows: Option<usize>) -> Result<bool> {
+        let offsets = layout::table_offsets(self.bytes, BdatVersion::Modern)?;
+        for &offset in &offsets {
+            let header = ModernHeader::read(self.bytes, offset)?;
+            let name = self.label(offset, &header, self.name_offset(offset, &header)?)?;
+            if !filter.contains(&name) {
+                continue;
+            }
+            self.file_header(&offsets)?;
+            self.table(offset, &header, &name, rows)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn file_header(&mut self, offsets: &[usize]) -> Result<()> {
+        writeln!(self.out, "File header")?;
+        self.field(0..4, "magic")?;
+        self.field(4..5, "version")?;
+        self.field(5..7, "???")?;
+        self.field(7..8, "???")?;
+        self.field(8..12, format!("table count: {}", offsets.len()))?;
+        self.field(12..16, format!("file size: {}", self.u32(12)?))?;
+        for (i, offset) in offsets.iter().enumerate() {
+            let start = 16 + i * 4;
+            self.field(start..start + 4, format!("table {i} offset: 0x{offset:x}"))?;
+        }
+        Ok(())
+    }
+
+    fn table(
+        &mut self,
+        base: usize,
+        header: &ModernHeader,
+        name: &Label,
+        rows: Option<usize>,
+    ) -> Result<()> {
+        let name = format_unhashed_label(name, self.hash_table);
+        writeln!(self.out, "\nTable {name} at 0x{base:x}")?;
+        self.field(base..base + 4, "magic")?;
+        self.field(base + 4..base + 5, "version")?;
+        self.field(base + 5..base + 8, "???")?;
+        let fields = [
+            ("columns", Some(header.columns)),
+            ("rows", Some(header.rows)),
+            ("base ID", Some(header.base_id)),
+            ("???", None),
+            ("column info offset", Some(header.offset_col)),
+            ("row ID table offset", Some(header.offset_hash)),
+            ("row data offset", Some(header.offset_row)),
+            ("row length", Some(header.row_length)),
+            ("string table offset", Some(header.offset_string)),
+            ("string table length", Some(header.str_length)),
+        ];
+        for (i, (field, value)) in fields.into_iter().enumerate() {
+            let start = base + 8 + i * 4;
+            match value {
+                Some(v) => self.field(start..start + 4, format!("{field}: {v} (0x{v:x})"))?,
+                None => self.field(start..start + 4, field)?,
+            }
+        }
+
+        writeln!(self.out, "\nColumn info")?;
+        let mut sizes = Vec::with_capacity(header.columns);
+        let mut names = Vec::with_capacity(header.columns);
+        for i in 0..header.columns {
+            let start = base + header.offset_col + i * MODERN_COLUMN_LEN;
+            let id = self.range(start..start + 1)?[0];
+            let label = self.label(base, header, self.u16(start + 1)?)?;
+            let label = format_unhashed_label(&label, self.hash_table);
+            let ty = ValueType::try_from(id).ok().filter(|t| t.data_len() > 0);
+            let ty_name = ty.map_or_else(|| "unknown type".to_string(), |t| format!("{t:?}"));
+            self.field(
+                start..start + MODERN_COLUMN_LEN,
+                format!("column {i}: {label}, type {id} ({ty_name})"),
+            )?;
+            sizes.push(ty.map(ValueType::data_len));
+            names.push(label);
+        }
+
+        let row_ids = header.row_id_count();
+        writeln!(self.out, "\nRow ID table ({row_ids} entries)")?;
+        for i in 0..row_ids {
+            let start = base + header.offset_hash + i * MODERN_ROW_ID_LEN;
+            let hash = Label::Hash(self.u32(start)? as u32);
+            let index = self.u32(start + 4)?;
+            self.field(
+                start..start + MODERN_ROW_ID_LEN,
+                format!(
+                    "{} -> row index {index}",
+                    format_unhashed_label(&hash, self.hash_table)
+                ),
+            )?;
+        }
+
+        // Cells are stored back to back. Types with an unknown size share the rest of the
+        // row, as there's no way to tell where they end.
+        let known: usize = sizes.iter().flatten().sum();
+        let unknown = sizes.iter().filter(|s| s.is_none()).count().max(1);
+        let unknown_size = header.row_length.saturating_sub(known) / unknown;
+        let shown = rows.unwrap_or(header.rows).min(header.rows);
+        writeln!(
+            self.out,
+            "\nRow data ({} rows of {} bytes, showing {shown})",
+            header.rows, header.row_length
+        )?;
+        for i in 0..shown {
+            let mut start = base + header.offset_row + i * header.row_length;
+            let end = start + header.row_length;
+            writeln!(self.out, "  Row {} (index {i})", header.base_id + i)?;
+            for (size, name) in sizes.iter().zip(&names) {
+                let size = size.unwrap_or(unknown_size);
+                let cell = start..(start + size).min(end);
+                start = cell.end;
+                self.field(cell, name)?;
+            }
+            if start < end {
+                self.field(start..end, "(unused)")?;
+            }
+        }
+
+        let strings = base + header.offset_string;
+        writeln!(self.out, "\nString table ({} bytes)", header.str_length)?;
+        let table = self.range(strings..strings + header.str_length)?;
+        for (i, chunk) in table.chunks(BYTES_PER_LINE).enumerate() {
+            let text = chunk
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7e => b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            self.line(strings + i * BYTES_PER_LINE, chunk, text)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the offset of the table name in the string table. If the first byte is 0,
+    /// names are hashed and start after it.
+    fn name_offset(&self, base: usize, header: &ModernHeader) -> Result<usize> {
+        let start = base + header.offset_string;
+        Ok(usize::from(self.range(start..start + 1)?[0] == 0))
+    }
+
+    /// Reads a hashed or plain name at an offset in the string table.
+    fn label(&self, base: usize, header: &ModernHeader, offset: usize) -> Result<Label> {
+        let strings = base + header.offset_string;
+        if self.range(strings..strings + 1)?[0] == 0 {
+            return Ok(Label::Hash(self.u32(strings + offset)? as u32));
+        }
+        let text = self.range(strings + offset..strings + header.str_length)?;
+        let len = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+        Ok(Label::String(
+            String::from_utf8_lossy(&text[..len]).into_owned(),
+        ))
+    }
+
+    /// Prints the bytes in a range, with a note on the first line.
+    fn field(&mut self, range: Range<usize>, note: impl Display) -> Result<()> {
+        let bytes = self.range(range.clone())?;
+        let mut note = Some(note);
+        for (i, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+            match note.take() {
+                Some(note) => self.line(range.start + i * BYTES_PER_LINE, chunk, note)?,
+                None => self.line(range.start + i * BYTES_PER_LINE, chunk, "")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, offset: usize, bytes: &[u8], note: impl Display) -> Result<()> {
+        let hex = bytes
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let width = BYTES_PER_LINE * 3 - 1;
+        writeln!(self.out, "  {offset:08x}  {hex:<width$}  {note}")?;
+        Ok(())
+    }
+
+    fn range(&self, range: Range<usize>) -> Result<&'a [u8]> {
+        layout::range(self.bytes, range)
+    }
+
+    fn u16(&self, offset: usize) -> Result<usize> {
+        layout::read_u16(self.bytes, offset, false)
+    }
+
+    fn u32(&self, offset: usize) -> Result<usize> {
+        layout::read_u32(self.bytes, offset, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{Cell, ColumnDef, Row, SwitchEndian, TableBuilder, Value};
+
+    use super::*;
+
+    #[test]
+    fn dump_table() {
+        let table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+            .add_column(ColumnDef::new(ValueType::HashRef, Label::Hash(1)))
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, Label::Hash(2)))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::HashRef(0xAB)),
+                    Cell::Single(Value::UnsignedShort(7)),
+                ],
+            ))
+            .build_modern();
+        let bytes = bdat::modern::to_vec::<SwitchEndian>([&table]).unwrap();
+
+        let mut out = Vec::new();
+        let dump = Dump {
+            bytes: &bytes,
+            hash_table: &HashNameTable::empty(),
+            out: &mut out,
+        };
+        let filter: Filter = [FilterArg("<CAFEBABE>".to_string())].into_iter().collect();
+        assert!(dump.find_and_dump(&filter, None).unwrap());
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Table <CAFEBABE>"));
+        assert!(out.contains("column 1: <00000002>, type 2 (UnsignedShort)"));
+        let line = |bytes: &str, note: &str| format!("{bytes:<47}  {note}");
+        assert!(out.contains(&line(
+            "ab 00 00 00 00 00 00 00",
+            "<000000AB> -> row index 0"
+        )));
+        assert!(out.contains(&line("07 00", "<00000002>")));
+    }
+}
@@ -1,10 +1,10 @@
-use crate::{
+use crate::{layout, warn_diagnostics, InputData};
+use anyhow::{Context, Result};
+use bdat::{BdatVersion, Label, TableAccessor};
+use bdat_convert::{
     filter::{Filter, FilterArg},
     hash::HashNameTable,
-    InputData,
 };
-use anyhow::{Context, Result};
-use bdat::{Label, TableAccessor};
 use clap::Args;
 use std::borrow::Cow;
 
@@ -16,6 +16,9 @@ pub struct InfoArgs {
     /// Only print these columns. If absent, prints all columns.
     #[arg(short, long)]
     columns: Vec<String>,
+    /// Also print the byte offsets and sizes of each table and its sections.
+    #[arg(long)]
+    layout: bool,
 }
 
 pub fn get_info(input: InputData, args: InfoArgs) -> Result<()> {
@@ -26,11 +29,18 @@ pub fn get_info(input: InputData, args: InfoArgs) -> Result<()> {
     for file in input.list_files("bdat", false)? {
         let path = file?;
         let mut file = std::fs::read(&path)?;
-        let tables = input
-            .game_from_bytes(&file)?
-            .from_bytes(&mut file)
+        let game = input.game_from_bytes(&file)?;
+        // Read before parsing, legacy tables are unscrambled in place
+        let layouts = match args.layout {
+            true => layout::table_layouts(&file, BdatVersion::from(game)).with_context(|| {
+                format!("Could not read table layout ({})", path.to_string_lossy())
+            })?,
+            false => Vec::new(),
+        };
+        let tables = game
+            .from_bytes(&mut file, &mut warn_diagnostics(&path))
             .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
-        for table in tables {
+        for (i, table) in tables.into_iter().enumerate() {
             let name = table.name();
             if !table_filter.contains(name) {
                 continue;
@@ -41,6 +51,9 @@ pub fn get_info(input: InputData, args: InfoArgs) -> Result<()> {
                 table.column_count(),
                 table.row_count()
             );
+            if let Some(layout) = layouts.get(i) {
+                print_layout(layout);
+            }
 
             if table.column_count() != 0 {
                 println!("  Columns:");
@@ -80,12 +93,35 @@ pub fn get_info(input: InputData, args: InfoArgs) -> Result<()> {
     Ok(())
 }
 
-fn format_unhashed_label(label: &Label, hash_table: &HashNameTable) -> String {
+fn print_layout(layout: &layout::TableLayout) {
+    println!(
+        "  Layout: 0x{:x}..0x{:x} ({} bytes), {} rows of {} bytes",
+        layout.offset,
+        layout.offset + layout.size,
+        layout.size,
+        layout.row_count,
+        layout.row_length
+    );
+    for section in &layout.sections {
+        println!(
+            "    - {:<12} 0x{:x}..0x{:x} ({} bytes)",
+            section.name,
+            section.offset,
+            section.offset + section.size,
+            section.size
+        );
+    }
+}
+
+pub fn format_unhashed_label(label: &Label, hash_table: &HashNameTable) -> String {
     let previous_hash = match label {
         Label::Hash(h) => Some(*h),
         _ => None,
     };
 
+    if let (Some(alias), Some(hash)) = (hash_table.alias(label), previous_hash) {
+        return format!("\"{alias}\" (<{hash:08X}>)");
+    }
     match (hash_table.convert_label_cow(label).as_ref(), previous_hash) {
         (l @ Label::Unhashed(_), Some(hash)) => format!("{l} (<{hash:08X}>)"),
         (l, _) => l.to_string(),
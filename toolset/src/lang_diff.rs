@@ -0,0 +1,328 @@
+use std::path::Path;
+
+use anyhow::Result;
+use bdat::{Cell, Label, Table, TableAccessor, TextKind, Value};
+use bdat_convert::{
+    error::Error,
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+};
+use clap::Args;
+
+use crate::{
+    convert::plural,
+    diff::{by_name, read_dump, Dump, ValueOrderedLabel},
+    InputData,
+};
+
+#[derive(Args)]
+pub struct LangDiffArgs {
+    /// Only compare these tables. If absent, compares every table with text columns.
+    #[arg(short, long)]
+    tables: Vec<String>,
+    /// Write the issues as CSV (language, file, table, row, issue, text) instead of a report
+    #[arg(long)]
+    csv: bool,
+}
+
+/// A problem found in a translation, compared to the reference language.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    MissingFile,
+    MissingTable,
+    /// The row is in the reference language, but not in the translation
+    MissingRow,
+    /// The row is only in the translation
+    ExtraRow,
+    /// The row has the same text as in the reference language
+    Unchanged,
+}
+
+struct Finding {
+    language: String,
+    file: String,
+    table: String,
+    row: Option<usize>,
+    issue: Issue,
+    /// The text in the reference language
+    text: String,
+}
+
+/// Compares the message tables of several language dumps (e.g. `gb/` and `fr/`) to the
+/// first one. Files are matched by their path in each dump, tables by name and rows by ID.
+///
+/// Only tables with string columns are compared, as other tables are the same in every
+/// language.
+pub fn lang_diff(input: InputData, args: LangDiffArgs) -> Result<()> {
+    let [base_path, others @ ..] = input.files.as_slice() else {
+        return Err(Error::LanguageFiles.into());
+    };
+    if others.is_empty() {
+        return Err(Error::LanguageFiles.into());
+    }
+    let hash_table = input.load_hashes()?;
+    let filter: Filter = args.tables.into_iter().map(FilterArg).collect();
+    let base = read_dump(std::slice::from_ref(base_path))?;
+
+    let mut findings = Vec::new();
+    for path in others {
+        let language = language_name(path);
+        let dump = read_dump(std::slice::from_ref(path))?;
+        findings.extend(compare_dumps(&base, &dump, &language, &filter, &hash_table));
+    }
+
+    if args.csv {
+        let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+        writer.write_record(["language", "file", "table", "row", "issue", "text"])?;
+        for finding in &findings {
+            writer.write_record([
+                finding.language.as_str(),
+                &finding.file,
+                &finding.table,
+                &finding.row.map(|id| id.to_string()).unwrap_or_default(),
+                finding.issue.name(),
+                &finding.text,
+            ])?;
+        }
+        writer.flush()?;
+    } else {
+        print_report(&language_name(base_path), others, &findings);
+    }
+    Ok(())
+}
+
+fn compare_dumps(
+    base: &Dump,
+    other: &Dump,
+    language: &str,
+    filter: &Filter,
+    hash_table: &HashNameTable,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (path, base_tables) in base {
+        let file = path.display().to_string();
+        let mut add = |table: String, row, issue, text| {
+            findings.push(Finding {
+                language: language.to_string(),
+                file: file.clone(),
+                table,
+                row,
+                issue,
+                text,
+            })
+        };
+        let base_tables = by_name(base_tables, hash_table);
+        let base_tables = base_tables
+            .iter()
+            .filter(|(ValueOrderedLabel(name), table)| {
+                filter.contains(name) && !text_columns(table).is_empty()
+            })
+            .collect::<Vec<_>>();
+        if base_tables.is_empty() {
+            continue;
+        }
+        let Some(other_tables) = other.get(path) else {
+            add(String::new(), None, Issue::MissingFile, String::new());
+            continue;
+        };
+        let other_tables = by_name(other_tables, hash_table);
+
+        for (label @ ValueOrderedLabel(name), base_table) in base_tables {
+            let name = name.to_string();
+            let Some(other_table) = other_tables.get(label) else {
+                add(name.clone(), None, Issue::MissingTable, String::new());
+                continue;
+            };
+            let columns = text_columns(base_table);
+            let mut unchanged = Vec::new();
+            for row in base_table.rows() {
+                let text = row_text(base_table, row.id(), &columns);
+                if other_table.get_row(row.id()).is_none() {
+                    add(
+                        name.clone(),
+                        Some(row.id()),
+                        Issue::MissingRow,
+                        text.join(" | "),
+                    );
+                } else if text.iter().any(|t| !t.is_empty())
+                    && row_text(other_table, row.id(), &columns) == text
+                {
+                    unchanged.push((row.id(), text.join(" | ")));
+                }
+            }
+            for row in other_table.rows() {
+                if base_table.get_row(row.id()).is_none() {
+                    add(name.clone(), Some(row.id()), Issue::ExtraRow, String::new());
+                }
+            }
+            for (id, text) in unchanged {
+                add(name.clone(), Some(id), Issue::Unchanged, text);
+            }
+        }
+    }
+    findings
+}
+
+fn print_report(base: &str, languages: &[String], findings: &[Finding]) {
+    for language in languages.iter().map(|l| language_name(l)) {
+        let findings = findings
+            .iter()
+            .filter(|f| f.language == language)
+            .collect::<Vec<_>>();
+        println!("{language} (compared to {base}):");
+        let mut current = None;
+        for (i, finding) in findings.iter().enumerate() {
+            match finding.issue {
+                Issue::MissingFile => println!("  Missing file {}", finding.file),
+                Issue::MissingTable => {
+                    println!("  Missing table {} in {}", finding.table, finding.file)
+                }
+                issue => {
+                    let key = (&finding.file, &finding.table, issue);
+                    if current == Some(key) {
+                        continue;
+                    }
+                    if current.map(|(file, table, _)| (file, table)) != Some((key.0, key.1)) {
+                        println!("  Table {} in {}", finding.table, finding.file);
+                    }
+                    current = Some(key);
+                    let ids = findings[i..]
+                        .iter()
+                        .take_while(|f| (&f.file, &f.table, f.issue) == key)
+                        .filter_map(|f| f.row)
+                        .collect::<Vec<_>>();
+                    println!("    {}: {}", issue.title(), format_ids(&ids));
+                }
+            }
+        }
+        let count = |issue| findings.iter().filter(|f| f.issue == issue).count();
+        println!(
+            "  {} missing, {} extra, {} unchanged\n",
+            plural(count(Issue::MissingRow), "row", "rows"),
+            plural(count(Issue::ExtraRow), "row", "rows"),
+            plural(count(Issue::Unchanged), "row", "rows"),
+        );
+    }
+}
+
+impl Issue {
+    fn name(self) -> &'static str {
+        match self {
+            Self::MissingFile => "missing-file",
+            Self::MissingTable => "missing-table",
+            Self::MissingRow => "missing",
+            Self::ExtraRow => "extra",
+            Self::Unchanged => "unchanged",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::MissingRow => "Missing rows",
+            Self::ExtraRow => "Extra rows",
+            Self::Unchanged => "Unchanged rows",
+            Self::MissingFile | Self::MissingTable => unreachable!(),
+        }
+    }
+}
+
+/// Returns the labels of the columns with player-facing text.
+pub(crate) fn text_columns(table: &Table) -> Vec<Label> {
+    table
+        .columns()
+        .filter(|c| c.value_type().text_kind() == Some(TextKind::String))
+        .map(|c| c.label().clone())
+        .collect()
+}
+
+/// Returns the text of a row in the given columns. Columns the table doesn't have are empty.
+pub(crate) fn row_text(table: &Table, id: usize, columns: &[Label]) -> Vec<String> {
+    let row = table.row(id);
+    columns
+        .iter()
+        .map(|label| match row.cell(label) {
+            Some(Cell::Single(Value::String(s))) => s.to_string(),
+            Some(cell) => cell.to_string(),
+            None => String::new(),
+        })
+        .collect()
+}
+
+/// Formats sorted row IDs, writing runs of consecutive IDs as ranges, e.g. `1-3, 5`.
+fn format_ids(ids: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &id in ids {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == id => *end = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Names a language after the last component of its dump's path, e.g. `gb` for `bdat/gb`.
+pub(crate) fn language_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or_else(|| path.to_string(), |n| n.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, ColumnDef, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    fn dump(names: &[(usize, &str)]) -> Dump {
+        let table = names
+            .iter()
+            .fold(
+                TableBuilder::with_name(Label::from("msg"))
+                    .add_column(ColumnDef::new(ValueType::String, Label::from("name"))),
+                |builder, (id, name)| {
+                    let cell = Cell::Single(Value::String(name.to_string().into()));
+                    builder.add_row(Row::new(*id, vec![cell]))
+                },
+            )
+            .build(BdatVersion::LegacySwitch);
+        [("file".into(), vec![table])].into()
+    }
+
+    #[test]
+    fn compare_languages() {
+        let base = dump(&[(1, "Sword"), (2, "Shield"), (3, ""), (4, "Bow")]);
+        let other = dump(&[(1, "Épée"), (2, "Shield"), (3, "")]);
+        let findings = compare_dumps(
+            &base,
+            &other,
+            "fr",
+            &Filter::from_iter(Vec::<FilterArg>::new()),
+            &HashNameTable::empty(),
+        );
+        let issues = findings
+            .iter()
+            .map(|f| (f.row, f.issue, f.text.as_str()))
+            .collect::<Vec<_>>();
+        // Empty text is the same in every language, so it is not reported
+        assert!(
+            issues
+                == [
+                    (Some(4), Issue::MissingRow, "Bow"),
+                    (Some(2), Issue::Unchanged, "Shield")
+                ]
+        );
+    }
+
+    #[test]
+    fn id_ranges() {
+        assert_eq!("1-3, 5, 7-8", format_ids(&[1, 2, 3, 5, 7, 8]));
+        assert_eq!("", format_ids(&[]));
+    }
+}
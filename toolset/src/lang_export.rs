@@ -0,0 +1,241 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bdat::{Table, TableAccessor};
+use bdat_convert::{
+    error::Error,
+    file_name,
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+    schema::AsFileName,
+    FileNames, SanitizeNames,
+};
+use clap::{Args, ValueEnum};
+use serde_json::{Map, Value as JsonValue};
+
+use crate::{
+    diff::{by_name, read_dump, ValueOrderedLabel},
+    lang_diff::{language_name, row_text, text_columns},
+    InputData,
+};
+
+#[derive(Args)]
+pub struct LangExportArgs {
+    /// The output directory
+    #[arg(short, long)]
+    out_dir: PathBuf,
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = LangExportFormat::Csv)]
+    format: LangExportFormat,
+    /// Only export these tables. If absent, exports every table with text columns.
+    #[arg(short, long)]
+    tables: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum LangExportFormat {
+    Csv,
+    /// An array with an object per row
+    Json,
+}
+
+/// The text of a table in every language, keyed by row ID.
+struct MergedTable {
+    /// Column names, after `$id`
+    headers: Vec<String>,
+    /// The text of each column, `None` if the row is not in that language
+    rows: BTreeMap<usize, Vec<Option<String>>>,
+}
+
+/// Merges the text tables of several language dumps (e.g. `gb/` and `fr/`) into one file
+/// per table, with a column for each language. Tables are taken from the first dump, and
+/// matched by file path and table name in the others.
+///
+/// Tables with several text columns get a column for each text column and language, named
+/// `column.language`.
+pub fn lang_export(input: InputData, args: LangExportArgs) -> Result<()> {
+    if input.files.is_empty() {
+        return Err(Error::LanguageFiles.into());
+    }
+    let hash_table = input.load_hashes()?;
+    let filter: Filter = args.tables.into_iter().map(FilterArg).collect();
+    let mut languages = Vec::with_capacity(input.files.len());
+    for path in &input.files {
+        languages.push((language_name(path), read_dump(std::slice::from_ref(path))?));
+    }
+    let names = languages
+        .iter()
+        .map(|(l, _)| l.as_str())
+        .collect::<Vec<_>>();
+
+    let mut count = 0;
+    for (path, base_tables) in &languages[0].1 {
+        let file_tables = languages
+            .iter()
+            .map(|(_, dump)| dump.get(path).map(|t| by_name(t, &hash_table)))
+            .collect::<Vec<_>>();
+        for (label @ ValueOrderedLabel(name), table) in &by_name(base_tables, &hash_table) {
+            if !filter.contains(name) || text_columns(table).is_empty() {
+                continue;
+            }
+            let tables = file_tables
+                .iter()
+                .map(|t| t.as_ref().and_then(|t| t.get(label)).copied())
+                .collect::<Vec<_>>();
+            let merged = MergedTable::new(table, &tables, &names, &hash_table);
+            let table_name = name.as_file_name();
+            let file_name = file_name::escape(
+                &table_name,
+                FileNames::Portable,
+                SanitizeNames::PercentEncode,
+            )?;
+            let out_dir = args.out_dir.join(path);
+            std::fs::create_dir_all(&out_dir)?;
+            match args.format {
+                LangExportFormat::Csv => {
+                    merged.write_csv(&out_dir.join(format!("{file_name}.csv")))
+                }
+                LangExportFormat::Json => {
+                    merged.write_json(&out_dir.join(format!("{file_name}.json")))
+                }
+            }
+            .with_context(|| format!("Could not write table {name}"))?;
+            count += 1;
+        }
+    }
+    println!(
+        "Exported {count} table(s) in {} language(s) to {}",
+        names.len(),
+        args.out_dir.display()
+    );
+    Ok(())
+}
+
+impl MergedTable {
+    /// Merges a table with the same table in every language. `tables` has the table of each
+    /// language, if the language has it.
+    fn new(
+        base: &Table,
+        tables: &[Option<&Table>],
+        languages: &[&str],
+        hash_table: &HashNameTable,
+    ) -> Self {
+        let columns = text_columns(base);
+        let single = columns.len() == 1;
+        let headers = columns
+            .iter()
+            .flat_map(|column| {
+                let column = hash_table.convert_label_cow(column);
+                languages.iter().map(move |language| match single {
+                    true => language.to_string(),
+                    false => format!("{column}.{language}"),
+                })
+            })
+            .collect();
+
+        let ids = tables
+            .iter()
+            .flatten()
+            .flat_map(|t| t.rows().map(|r| r.id()))
+            .collect::<BTreeSet<_>>();
+        let rows = ids
+            .into_iter()
+            .map(|id| {
+                let text = tables
+                    .iter()
+                    .map(|t| {
+                        t.filter(|t| t.get_row(id).is_some())
+                            .map(|t| row_text(t, id, &columns))
+                    })
+                    .collect::<Vec<_>>();
+                // Column-major, to match the headers
+                let cells = (0..columns.len())
+                    .flat_map(|i| text.iter().map(move |t| t.as_ref().map(|t| t[i].clone())))
+                    .collect();
+                (id, cells)
+            })
+            .collect();
+        Self { headers, rows }
+    }
+
+    fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer
+            .write_record(std::iter::once("$id").chain(self.headers.iter().map(String::as_str)))?;
+        for (id, cells) in &self.rows {
+            writer.write_record(
+                std::iter::once(id.to_string())
+                    .chain(cells.iter().map(|c| c.clone().unwrap_or_default())),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_json(&self, path: &Path) -> Result<()> {
+        let rows = self
+            .rows
+            .iter()
+            .map(|(id, cells)| {
+                let mut row = Map::new();
+                row.insert("$id".to_string(), (*id).into());
+                for (header, cell) in self.headers.iter().zip(cells) {
+                    row.insert(
+                        header.clone(),
+                        cell.clone().map_or(JsonValue::Null, Into::into),
+                    );
+                }
+                row
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &rows)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, Cell, ColumnDef, Label, Row, TableBuilder, Value, ValueType};
+
+    use super::*;
+
+    fn table(names: &[&str]) -> Table<'static> {
+        names
+            .iter()
+            .enumerate()
+            .fold(
+                TableBuilder::with_name(Label::from("msg"))
+                    .add_column(ColumnDef::new(ValueType::String, Label::from("name"))),
+                |builder, (i, name)| {
+                    let cell = Cell::Single(Value::String(name.to_string().into()));
+                    builder.add_row(Row::new(i + 1, vec![cell]))
+                },
+            )
+            .build(BdatVersion::LegacySwitch)
+    }
+
+    #[test]
+    fn merge_languages() {
+        let (gb, fr) = (table(&["Sword", "Shield"]), table(&["Épée"]));
+        let merged = MergedTable::new(
+            &gb,
+            &[Some(&gb), Some(&fr), None],
+            &["gb", "fr", "jp"],
+            &HashNameTable::empty(),
+        );
+        assert_eq!(["gb", "fr", "jp"], merged.headers.as_slice());
+        let text = |s: &str| Some(s.to_string());
+        assert_eq!(
+            BTreeMap::from([
+                (1, vec![text("Sword"), text("Épée"), None]),
+                (2, vec![text("Shield"), None, None]),
+            ]),
+            merged.rows
+        );
+    }
+}
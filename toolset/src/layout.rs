@@ -0,0 +1,254 @@
+//! Table positions and sections, read from the raw file and table headers.
+
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+use bdat::BdatVersion;
+
+const MODERN_FILE_HEADER_LEN: usize = 16;
+const LEGACY_FILE_HEADER_LEN: usize = 8;
+pub const MODERN_COLUMN_LEN: usize = 3;
+pub const MODERN_ROW_ID_LEN: usize = 8;
+
+/// Where a table and its sections are in a BDAT file. Offsets are from the start of the file.
+pub struct TableLayout {
+    pub offset: usize,
+    /// The size of the table, up to the next table or the end of the file
+    pub size: usize,
+    pub row_count: usize,
+    pub row_length: usize,
+    pub sections: Vec<Section>,
+}
+
+pub struct Section {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// The fields of a modern table header. Offsets are from the start of the table.
+pub struct ModernHeader {
+    pub columns: usize,
+    pub rows: usize,
+    pub base_id: usize,
+    pub offset_col: usize,
+    pub offset_hash: usize,
+    pub offset_row: usize,
+    pub row_length: usize,
+    pub offset_string: usize,
+    pub str_length: usize,
+}
+
+impl ModernHeader {
+    pub const LEN: usize = 48;
+
+    pub fn read(bytes: &[u8], base: usize) -> Result<Self> {
+        range(bytes, base..base + Self::LEN)?;
+        let u32 = |offset| read_u32(bytes, base + offset, false);
+        Ok(Self {
+            columns: u32(8)?,
+            rows: u32(12)?,
+            base_id: u32(16)?,
+            offset_col: u32(24)?,
+            offset_hash: u32(28)?,
+            offset_row: u32(32)?,
+            row_length: u32(36)?,
+            offset_string: u32(40)?,
+            str_length: u32(44)?,
+        })
+    }
+
+    /// Returns the number of entries in the row ID table. Only tables with a hash column
+    /// have row IDs, the section is otherwise empty.
+    pub fn row_id_count(&self) -> usize {
+        let next_section = [self.offset_col, self.offset_row, self.offset_string]
+            .into_iter()
+            .filter(|&o| o > self.offset_hash)
+            .min()
+            .unwrap_or(self.offset_hash);
+        self.rows
+            .min((next_section - self.offset_hash) / MODERN_ROW_ID_LEN)
+    }
+}
+
+/// Returns the offsets of the tables in a file, in file order.
+pub fn table_offsets(bytes: &[u8], version: BdatVersion) -> Result<Vec<usize>> {
+    let big_endian = version.is_big_endian();
+    let (count_offset, header_len) = match version {
+        BdatVersion::Modern => (8, MODERN_FILE_HEADER_LEN),
+        _ => (0, LEGACY_FILE_HEADER_LEN),
+    };
+    let count = read_u32(bytes, count_offset, big_endian)?;
+    (0..count)
+        .map(|i| read_u32(bytes, header_len + i * 4, big_endian))
+        .collect()
+}
+
+/// Reads the layout of every table in a file, in file order.
+pub fn table_layouts(bytes: &[u8], version: BdatVersion) -> Result<Vec<TableLayout>> {
+    let offsets = table_offsets(bytes, version)?;
+    let mut sorted = offsets.clone();
+    sorted.sort_unstable();
+    offsets
+        .iter()
+        .map(|&offset| {
+            let end = sorted
+                .iter()
+                .find(|&&o| o > offset)
+                .copied()
+                .unwrap_or(bytes.len());
+            let mut layout = match version {
+                BdatVersion::Modern => modern_layout(bytes, offset)?,
+                _ => legacy_layout(bytes, offset, version)?,
+            };
+            layout.size = end.saturating_sub(offset);
+            Ok(layout)
+        })
+        .collect()
+}
+
+fn modern_layout(bytes: &[u8], offset: usize) -> Result<TableLayout> {
+    let header = ModernHeader::read(bytes, offset)?;
+    let section = |name, start: usize, size| Section {
+        name,
+        offset: offset + start,
+        size,
+    };
+    Ok(TableLayout {
+        offset,
+        size: 0,
+        row_count: header.rows,
+        row_length: header.row_length,
+        sections: vec![
+            section("header", 0, ModernHeader::LEN),
+            section(
+                "column info",
+                header.offset_col,
+                header.columns * MODERN_COLUMN_LEN,
+            ),
+            section(
+                "row IDs",
+                header.offset_hash,
+                header.row_id_count() * MODERN_ROW_ID_LEN,
+            ),
+            section(
+                "row data",
+                header.offset_row,
+                header.rows * header.row_length,
+            ),
+            section("string table", header.offset_string, header.str_length),
+        ],
+    })
+}
+
+fn legacy_layout(bytes: &[u8], offset: usize, version: BdatVersion) -> Result<TableLayout> {
+    let header_len = version.table_header_size();
+    range(bytes, offset..offset + header_len)?;
+    let big_endian = version.is_big_endian();
+    let u16 = |field| read_u16(bytes, offset + field, big_endian);
+    let u32 = |field| read_u32(bytes, offset + field, big_endian);
+    let offset_names = u16(6)?;
+    let row_length = u16(8)?;
+    let offset_hashes = u16(10)?;
+    let hash_slots = u16(12)?;
+    let offset_rows = u16(14)?;
+    let row_count = u16(16)?;
+    let offset_strings = u32(24)?;
+    let strings_len = u32(28)?;
+
+    let section = |name, start: usize, end: usize| Section {
+        name,
+        offset: offset + start,
+        size: end.saturating_sub(start),
+    };
+    Ok(TableLayout {
+        offset,
+        size: 0,
+        row_count,
+        row_length,
+        sections: vec![
+            section("header", 0, header_len),
+            section("column info", header_len, offset_names),
+            section("names", offset_names, offset_hashes),
+            section("hash table", offset_hashes, offset_hashes + hash_slots * 2),
+            section(
+                "row data",
+                offset_rows,
+                offset_rows + row_count * row_length,
+            ),
+            section("string table", offset_strings, offset_strings + strings_len),
+        ],
+    })
+}
+
+pub fn range(bytes: &[u8], range: Range<usize>) -> Result<&[u8]> {
+    bytes.get(range.clone()).with_context(|| {
+        format!(
+            "Bytes 0x{:x}..0x{:x} are out of bounds (file size: 0x{:x})",
+            range.start,
+            range.end,
+            bytes.len()
+        )
+    })
+}
+
+pub fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> Result<usize> {
+    let b = range(bytes, offset..offset + 2)?;
+    let b = [b[0], b[1]];
+    Ok(match big_endian {
+        true => u16::from_be_bytes(b),
+        false => u16::from_le_bytes(b),
+    }
+    .into())
+}
+
+pub fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Result<usize> {
+    let b = range(bytes, offset..offset + 4)?;
+    let b = [b[0], b[1], b[2], b[3]];
+    Ok(match big_endian {
+        true => u32::from_be_bytes(b),
+        false => u32::from_le_bytes(b),
+    } as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{Cell, ColumnDef, Label, Row, SwitchEndian, TableBuilder, Value, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn modern_sections() {
+        let table = TableBuilder::with_name(Label::Hash(0xCAFEBABE))
+            .add_column(ColumnDef::new(ValueType::HashRef, Label::Hash(1)))
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, Label::Hash(2)))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::HashRef(0xAB)),
+                    Cell::Single(Value::UnsignedShort(7)),
+                ],
+            ))
+            .build_modern();
+        let bytes = bdat::modern::to_vec::<SwitchEndian>([&table]).unwrap();
+
+        let layouts = table_layouts(&bytes, BdatVersion::Modern).unwrap();
+        assert_eq!(1, layouts.len());
+        let layout = &layouts[0];
+        assert_eq!(0x14, layout.offset);
+        assert_eq!(bytes.len() - 0x14, layout.size);
+        assert_eq!((1, 6), (layout.row_count, layout.row_length));
+        let sizes: Vec<_> = layout.sections.iter().map(|s| (s.name, s.size)).collect();
+        assert_eq!(
+            vec![
+                ("header", 48),
+                ("column info", 6),
+                ("row IDs", 8),
+                ("row data", 6),
+                ("string table", layout.sections[4].size)
+            ],
+            sizes
+        );
+        assert_eq!(0x14 + 48, layout.sections[1].offset);
+    }
+}
@@ -4,23 +4,51 @@ use std::path::Path;
 use std::{fs::File, path::PathBuf};
 
 use crate::scramble::ScrambleArgs;
-use crate::util::BdatGame;
 use anyhow::{Context, Result};
+use append::AppendArgs;
+use bdat::{BdatFile, Diagnostic};
+use bdat_convert::{hash::HashNameTable, util::BdatGame};
 use clap::{Args, Parser, Subcommand};
+use collect::CollectArgs;
+use columns::ColumnsArgs;
 use convert::ConvertArgs;
 use diff::DiffArgs;
-use hash::HashNameTable;
+use hexdump::HexdumpArgs;
 use info::InfoArgs;
 use itertools::Itertools;
+use lang_diff::LangDiffArgs;
+use lang_export::LangExportArgs;
+use mod_conflicts::ModConflictsArgs;
+use patch::PatchArgs;
+use query::QueryArgs;
+use scan::ScanArgs;
+use schema::SchemaArgs;
+use script::ScriptArgs;
+use split::SplitArgs;
+use stats::StatsArgs;
+use template::TemplateArgs;
 use walkdir::WalkDir;
 
+mod append;
+mod collect;
+mod columns;
 mod convert;
 mod diff;
-pub mod error;
-pub mod filter;
-pub mod hash;
+mod hexdump;
 mod info;
+mod lang_diff;
+mod lang_export;
+mod layout;
+mod mod_conflicts;
+mod patch;
+mod query;
+mod scan;
+mod schema;
 mod scramble;
+mod script;
+mod split;
+mod stats;
+mod template;
 pub mod util;
 
 #[derive(Parser)]
@@ -45,10 +73,55 @@ enum Commands {
     Extract(ConvertArgs),
     /// Convert from extracted data to BDAT files
     Pack(ConvertArgs),
+    /// Convert extracted tables to another format, e.g. "convert dir --from json --to csv -o out".
+    /// The tables are not packed into BDAT files in between.
+    Convert(ConvertArgs),
+    /// Append rows from JSON files to a table in a BDAT file, e.g.
+    /// "append file.bdat -t ITM_Item rows.json". The BDAT file is modified in place.
+    Append(AppendArgs),
+    /// Write the tables of a BDAT file to several smaller files, grouped by table name, e.g.
+    /// "split file.bdat -o out --group battle=BTL_* --rest other"
+    Split(SplitArgs),
+    /// Write a template for a new table, with the columns of an existing table and an
+    /// example row of default values
+    Template(TemplateArgs),
     /// Print info about the structure of the BDAT file and the tables contained within
     Info(InfoArgs),
+    /// List the columns of every table, with their types and hashed names
+    Columns(ColumnsArgs),
+    /// Print the number of distinct values and the range of each column, and optionally the
+    /// most frequent values
+    Stats(StatsArgs),
+    /// Print an annotated hex dump of a table in a modern BDAT file (header, column info,
+    /// row IDs, rows and string table), e.g. "hexdump file.bdat -t ITM_Item --rows 2"
+    Hexdump(HexdumpArgs),
+    /// Run an SQL-like query over the tables, e.g. "SELECT Name FROM BTL_Enemy WHERE Lv > 50"
+    Query(QueryArgs),
+    /// Search files of any kind (executables, memory dumps, archives) for embedded BDAT
+    /// files and list their offsets, e.g. "scan game.elf -o found" to also extract them
+    Scan(ScanArgs),
+    /// Collect unhashed names from BDAT files and extracted JSON tables into a hash list
+    CollectNames(CollectArgs),
     /// Print the differences between two BDAT dumps
     Diff(DiffArgs),
+    /// Compare the message tables of several language dumps to the first one, e.g.
+    /// "lang-diff bdat/gb bdat/fr", listing rows that are missing or have the same text
+    LangDiff(LangDiffArgs),
+    /// Merge the text tables of several language dumps into one CSV or JSON file per table,
+    /// with a column for each language, e.g. "lang-export bdat/gb bdat/fr -o out"
+    LangExport(LangExportArgs),
+    /// Apply a patch written by "diff --emit-patch" to a BDAT file, e.g.
+    /// "patch file.bdat -p mod.json". The BDAT file is modified in place unless "-o" is given.
+    /// Several patches can be merged with "-p a.json -p b.json", and "--check" only checks them.
+    Patch(PatchArgs),
+    /// Run a Rhai script over the tables of BDAT files, e.g. "script file.bdat -s drops.rhai".
+    /// Modified files are written in place unless "-o" is given.
+    Script(ScriptArgs),
+    /// Compare the patches of several mods, each a directory of patch files, and list cells
+    /// that the mods patch to different values, e.g. "mod-conflicts mods/a mods/b --json"
+    ModConflicts(ModConflictsArgs),
+    /// Manage .bschema files generated by "extract"
+    Schema(SchemaArgs),
     /// Scramble all tables in legacy (XC1/X/2/DE) BDAT files
     Scramble(ScrambleArgs),
     /// Unscramble all tables in legacy (XC1/X/2/DE) BDAT files
@@ -57,13 +130,27 @@ enum Commands {
 
 #[derive(Args, Default)]
 pub struct InputData {
-    /// A file containing unhashed names, one in each line. If provided, all matched hashes will
-    /// be replaced with the unhashed names.
+    /// A file containing unhashed names, one in each line, or "hash,name" pairs. Blank lines
+    /// and lines starting with '#' are ignored. If provided, all matched hashes will be replaced
+    /// with the unhashed names.
     #[arg(long, global = true)]
     hashes: Option<String>,
 
+    /// A file containing "hash,alias" pairs, one in each line. Hashed table and column names
+    /// without a known name are shown as their alias, and tables are extracted to files named
+    /// after it. Aliases are never written to tables.
+    #[arg(long, global = true)]
+    aliases: Option<String>,
+
+    /// Store hash names in a compact table instead of a hash map. This greatly reduces memory
+    /// usage with large hash lists, at the cost of slightly slower lookups.
+    #[arg(long, global = true)]
+    compact_hashes: bool,
+
     /// The Xenoblade Chronicles game to choose BDAT settings for. Automatically detected
-    /// for reading and writing, but it may be sometimes necessary to override.
+    /// for reading and writing, but it may be sometimes necessary to override. When reading,
+    /// files in a different format (e.g. XC3 files with "--game xc1") still use the detected
+    /// game, so files from several games can be read at once.
     #[arg(long, short, value_enum, global = true)]
     game: Option<BdatGame>,
 
@@ -77,9 +164,25 @@ fn main() -> anyhow::Result<()> {
 
     match cli.command {
         Some(Commands::Info(args)) => info::get_info(cli.input, args),
+        Some(Commands::Columns(args)) => columns::list_columns(cli.input, args),
+        Some(Commands::Stats(args)) => stats::print_stats(cli.input, args),
         Some(Commands::Extract(args)) => convert::run_conversions(cli.input, args, true),
         Some(Commands::Pack(args)) => convert::run_conversions(cli.input, args, false),
+        Some(Commands::Convert(args)) => convert::convert_formats(cli.input, args),
+        Some(Commands::Append(args)) => append::append_rows(cli.input, args),
+        Some(Commands::Split(args)) => split::split_file(cli.input, args),
+        Some(Commands::Template(args)) => template::write_template(cli.input, args),
+        Some(Commands::Hexdump(args)) => hexdump::hexdump(cli.input, args),
+        Some(Commands::Query(args)) => query::run_query(cli.input, args),
+        Some(Commands::Scan(args)) => scan::scan_files(cli.input, args),
+        Some(Commands::CollectNames(args)) => collect::collect_names(cli.input, args),
         Some(Commands::Diff(args)) => diff::run_diff(cli.input, args),
+        Some(Commands::LangDiff(args)) => lang_diff::lang_diff(cli.input, args),
+        Some(Commands::LangExport(args)) => lang_export::lang_export(cli.input, args),
+        Some(Commands::Patch(args)) => patch::apply_patch(cli.input, args),
+        Some(Commands::Script(args)) => script::run_script(cli.input, args),
+        Some(Commands::ModConflicts(args)) => mod_conflicts::mod_conflicts(cli.input, args),
+        Some(Commands::Schema(args)) => schema::run_schema(cli.input, args),
         Some(Commands::Scramble(args)) => scramble::scramble(cli.input, args),
         Some(Commands::Unscramble(args)) => scramble::unscramble(cli.input, args),
         _ => Ok(()),
@@ -125,30 +228,52 @@ impl InputData {
     }
 
     pub fn load_hashes(&self) -> Result<HashNameTable> {
-        match &self.hashes {
+        let mut hashes = match &self.hashes {
             Some(path) => {
                 let file = File::open(path).context("Could not open hashes file")?;
-                Ok(HashNameTable::load_from_names(file)?)
+                HashNameTable::load_from_names(file, self.compact_hashes, &mut |line, e| {
+                    eprintln!("[Warn] Skipping line {line} of the hash list: {e}")
+                })?
             }
-            None => Ok(HashNameTable::empty()),
+            None => HashNameTable::empty(),
+        };
+        if let Some(path) = &self.aliases {
+            let file = File::open(path).context("Could not open aliases file")?;
+            hashes.load_aliases(file, &mut |line, e| {
+                eprintln!("[Warn] Skipping line {line} of the alias list: {e}")
+            })?;
         }
+        Ok(hashes)
     }
 
     pub fn game_from_bytes(&self, bytes: &[u8]) -> Result<BdatGame> {
-        if let Some(game) = self.game {
-            return Ok(game);
-        }
-        Ok(BdatGame::version_default(bdat::detect_bytes_version(
-            bytes,
-        )?))
+        Ok(BdatGame::for_file(
+            self.game,
+            bdat::detect_bytes_version(bytes),
+        )?)
     }
 
     pub fn game_from_reader<R: Read + Seek>(&self, reader: R) -> Result<BdatGame> {
-        if let Some(game) = self.game {
-            return Ok(game);
-        }
-        Ok(BdatGame::version_default(bdat::detect_file_version(
-            reader,
-        )?))
+        Ok(BdatGame::for_file(
+            self.game,
+            bdat::detect_file_version(reader),
+        )?)
     }
 }
+
+/// Prints the non-fatal issues found while reading a BDAT file as warnings.
+pub fn warn_diagnostics(path: &Path) -> impl FnMut(Diagnostic) + '_ {
+    move |diagnostic| eprintln!("[Warn] {}: {diagnostic}", path.display())
+}
+
+/// Reads every table in a BDAT file, printing non-fatal issues as warnings.
+pub fn read_tables<'b, F: BdatFile<'b>>(
+    mut file: F,
+    path: &Path,
+) -> bdat::BdatResult<Vec<F::TableOut>> {
+    let tables = file.get_tables()?;
+    file.take_diagnostics()
+        .into_iter()
+        .for_each(warn_diagnostics(path));
+    Ok(tables)
+}
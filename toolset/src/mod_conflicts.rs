@@ -0,0 +1,266 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bdat::{fmt::json::parse_label, hash::murmur3_str, Label};
+use bdat_convert::{error::Error, patch::Patch};
+use clap::Args;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use walkdir::WalkDir;
+
+use crate::{convert::plural, InputData};
+
+#[derive(Args)]
+pub struct ModConflictsArgs {
+    /// Write the conflicts as JSON instead of a report, e.g. for mod managers
+    #[arg(long)]
+    json: bool,
+}
+
+/// A cell that mods patched to different values.
+#[derive(Serialize)]
+struct Conflict {
+    /// The path of the patch file, relative to the mod directory
+    file: String,
+    table: String,
+    row: usize,
+    column: String,
+    /// The value from each mod that patched the cell, keyed by mod name
+    values: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    mods: Vec<String>,
+    conflicts: Vec<Conflict>,
+}
+
+/// A patched cell. Tables and columns are compared by hash, so plain and hashed names
+/// match.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct CellKey {
+    file: String,
+    table: u32,
+    row: usize,
+    column: u32,
+}
+
+/// The cells changed by every mod, with the names they were written with in the first mod.
+#[derive(Default)]
+struct Changes {
+    cells: BTreeMap<CellKey, Vec<(usize, Value)>>,
+    names: BTreeMap<(String, u32, u32), (String, String)>,
+}
+
+/// Compares the patches of several mods, each a directory of patch files written by
+/// "diff --emit-patch". Patches at the same path in different mods are for the same BDAT
+/// file.
+///
+/// Cells are compared in changed rows, added rows and new tables. A cell is a conflict if
+/// mods patch it to different values; mods that agree on a value are not reported. Removed
+/// rows and tables are not compared.
+pub fn mod_conflicts(input: InputData, args: ModConflictsArgs) -> Result<()> {
+    if input.files.len() < 2 {
+        return Err(Error::ModDirectories.into());
+    }
+    let mut changes = Changes::default();
+    let mut mods = Vec::with_capacity(input.files.len());
+    for (i, dir) in input.files.iter().enumerate() {
+        mods.push(mod_name(dir));
+        for (file, patch) in read_patches(Path::new(dir))? {
+            changes.add(i, &file, &patch);
+        }
+    }
+    let conflicts = changes.conflicts(&mods);
+
+    if args.json {
+        let report = Report { mods, conflicts };
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &report)?;
+        println!();
+        return Ok(());
+    }
+    for conflict in &conflicts {
+        println!(
+            "{}, table {}, row {}, column {}:",
+            conflict.file, conflict.table, conflict.row, conflict.column
+        );
+        for (name, value) in &conflict.values {
+            println!("  {name}: {value}");
+        }
+    }
+    println!(
+        "Found {} between {} mods",
+        plural(conflicts.len(), "conflict", "conflicts"),
+        mods.len()
+    );
+    Ok(())
+}
+
+/// Reads every patch file in a mod directory, keyed by its path relative to the directory.
+fn read_patches(dir: &Path) -> Result<Vec<(String, Patch)>> {
+    let mut patches = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let path = entry?.into_path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let file = File::open(&path)
+            .with_context(|| format!("Could not open patch file {}", path.display()))?;
+        let patch: Patch = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Could not read patch file {}", path.display()))?;
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        patches.push((relative.to_string_lossy().replace('\\', "/"), patch));
+    }
+    Ok(patches)
+}
+
+impl Changes {
+    fn add(&mut self, mod_index: usize, file: &str, patch: &Patch) {
+        for table in &patch.tables {
+            for (&row, cells) in &table.rows {
+                self.add_cells(mod_index, file, &table.name, row, cells);
+            }
+            for row in &table.added_rows {
+                self.add_cells(mod_index, file, &table.name, row.id, &row.cells);
+            }
+        }
+        for table in &patch.new_tables {
+            let name = table.name.as_deref().unwrap_or_default();
+            for row in &table.rows {
+                self.add_cells(mod_index, file, name, row.id, &row.cells);
+            }
+        }
+    }
+
+    fn add_cells(
+        &mut self,
+        mod_index: usize,
+        file: &str,
+        table: &str,
+        row: usize,
+        cells: &Map<String, Value>,
+    ) {
+        let table_hash = name_hash(table);
+        for (column, value) in cells {
+            let column_hash = name_hash(column);
+            self.names
+                .entry((file.to_string(), table_hash, column_hash))
+                .or_insert_with(|| (table.to_string(), column.clone()));
+            let key = CellKey {
+                file: file.to_string(),
+                table: table_hash,
+                row,
+                column: column_hash,
+            };
+            self.cells
+                .entry(key)
+                .or_default()
+                .push((mod_index, value.clone()));
+        }
+    }
+
+    fn conflicts(self, mods: &[String]) -> Vec<Conflict> {
+        self.cells
+            .into_iter()
+            .filter(|(_, values)| values.iter().any(|(_, v)| *v != values[0].1))
+            .map(|(key, values)| {
+                let (table, column) = &self.names[&(key.file.clone(), key.table, key.column)];
+                Conflict {
+                    table: table.clone(),
+                    column: column.clone(),
+                    row: key.row,
+                    values: values
+                        .into_iter()
+                        .map(|(i, value)| (mods[i].clone(), value))
+                        .collect(),
+                    file: key.file,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Hashes a table or column name, unless it is already written as a hash.
+fn name_hash(name: &str) -> u32 {
+    match parse_label(name, true) {
+        Label::Hash(hash) => hash,
+        Label::String(s) | Label::Unhashed(s) => murmur3_str(&s),
+    }
+}
+
+/// Names a mod after its directory, e.g. `better_drops` for `mods/better_drops`.
+fn mod_name(dir: &str) -> String {
+    PathBuf::from(dir)
+        .file_name()
+        .map_or_else(|| dir.to_string(), |n| n.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(json: &str) -> Patch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn cell_conflicts() {
+        let a = patch(
+            r#"{"format_version":1,"tables":[
+                {"name":"Enemy","rows":{"1":{"Lv":5,"Hp":10}},"added_rows":[{"$id":9,"Lv":1}]}
+            ]}"#,
+        );
+        let b = patch(
+            r#"{"format_version":1,"tables":[
+                {"name":"Enemy","rows":{"1":{"Lv":5,"Hp":20}}},
+                {"name":"Shop","rows":{"1":{"Lv":5}}}
+            ]}"#,
+        );
+        let hash = Label::Hash(murmur3_str("Enemy"));
+        let c = patch(&format!(
+            r#"{{"format_version":1,"tables":[
+                {{"name":"{hash}","added_rows":[{{"$id":9,"Lv":2}}]}}
+            ]}}"#
+        ));
+        let mut changes = Changes::default();
+        for (i, patch) in [a, b, c].iter().enumerate() {
+            changes.add(i, "file.json", patch);
+        }
+        let mods = ["a", "b", "c"].map(String::from);
+        let conflicts = changes
+            .conflicts(&mods)
+            .into_iter()
+            .map(|c| {
+                (
+                    c.table,
+                    c.row,
+                    c.column,
+                    Value::Object(c.values).to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // Lv in row 1 has the same value in both mods
+        assert!(
+            conflicts
+                == [
+                    (
+                        "Enemy".to_string(),
+                        1,
+                        "Hp".to_string(),
+                        r#"{"a":10,"b":20}"#.to_string()
+                    ),
+                    (
+                        "Enemy".to_string(),
+                        9,
+                        "Lv".to_string(),
+                        r#"{"a":1,"c":2}"#.to_string()
+                    ),
+                ]
+        );
+    }
+}
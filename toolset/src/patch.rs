@@ -0,0 +1,84 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bdat::BdatVersion;
+use bdat_convert::{error::Error, patch::Patch, util::write_atomic};
+use clap::Args;
+
+use crate::{warn_diagnostics, InputData};
+
+#[derive(Args)]
+pub struct PatchArgs {
+    /// The patch file, as written by "diff --emit-patch". Can be given several times to
+    /// merge patches from different mods, which are applied in order.
+    #[arg(short, long, required = true)]
+    patch: Vec<PathBuf>,
+    /// Where to write the patched BDAT file. If absent, the file is modified in place.
+    #[arg(short, long)]
+    out_file: Option<PathBuf>,
+    /// Only check the patches for conflicts and apply them in memory, without writing
+    #[arg(long, conflicts_with = "out_file")]
+    check: bool,
+}
+
+/// Applies one or more patches to the tables of a BDAT file.
+///
+/// Patches are checked for conflicts first (see [`Patch::conflicts`]), and nothing is
+/// written if there are any.
+pub fn apply_patch(input: InputData, args: PatchArgs) -> Result<()> {
+    let [path] = input.files.as_slice() else {
+        return Err(Error::PatchFiles.into());
+    };
+    let patches = args
+        .patch
+        .iter()
+        .map(|path| {
+            let file = File::open(path)
+                .with_context(|| format!("Could not open patch file {}", path.display()))?;
+            let patch: Patch = serde_json::from_reader(BufReader::new(file))
+                .with_context(|| format!("Could not read patch file {}", path.display()))?;
+            Ok((path.display().to_string(), patch))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut bytes = std::fs::read(path)?;
+    let game = input.game_from_bytes(&bytes)?;
+    let version = BdatVersion::from(game);
+    let named = patches
+        .iter()
+        .map(|(name, patch)| (name.as_str(), patch))
+        .collect::<Vec<_>>();
+    let conflicts = Patch::conflicts(&named, version);
+    if !conflicts.is_empty() {
+        return Err(
+            Error::PatchConflicts(conflicts.iter().map(ToString::to_string).collect()).into(),
+        );
+    }
+
+    let mut tables = game
+        .from_bytes(&mut bytes, &mut warn_diagnostics(Path::new(path)))
+        .with_context(|| format!("Could not parse BDAT tables ({path})"))?;
+    let count = patches.len();
+    for (name, patch) in patches {
+        patch
+            .apply(&mut tables, version)
+            .with_context(|| format!("Could not apply patch {name}"))?;
+    }
+    if args.check {
+        println!("No conflicts, {count} patch(es) apply to {path}");
+        return Ok(());
+    }
+
+    let out_path = args.out_file.unwrap_or_else(|| PathBuf::from(path));
+    // Keep the original table order
+    write_atomic(&out_path, |writer| {
+        Ok(game.to_writer(writer, tables, false, false)?)
+    })
+    .context("Could not save BDAT file")?;
+    println!("Patched {}", out_path.display());
+    Ok(())
+}
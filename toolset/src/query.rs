@@ -0,0 +1,594 @@
+//! Runs simple SQL-like queries over BDAT tables.
+//!
+//! Syntax:
+//! ```text
+//! SELECT <* | expr [AS name]>, ...
+//! FROM <table> [[AS] alias]
+//! [JOIN <table> [[AS] alias] ON <expr>]...
+//! [WHERE <expr>]
+//! [ORDER BY <expr> [ASC | DESC], ...]
+//! [LIMIT <n>]
+//! ```
+//! Expressions are parsed by [`bdat_convert::expr`]. Every table also has a `$id` column with
+//! row IDs.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use bdat::{Label, RowRef, Table, TableAccessor};
+use clap::{Args, ValueEnum};
+use serde_json::{Map, Value as JsonValue};
+
+use bdat_convert::{
+    expr::{BinOp, Expr, ExprError, Parser, Scalar, Scope, Token},
+    filter::{Filter, FilterArg},
+};
+
+use crate::{warn_diagnostics, InputData};
+
+const ID_COLUMN: &str = "$id";
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// The query to run, e.g. "SELECT Name, Atk FROM BTL_Enemy WHERE Lv > 50"
+    query: String,
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = QueryFormat::Table)]
+    format: QueryFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum QueryFormat {
+    /// An aligned table
+    Table,
+    Csv,
+    /// An array with an object per row
+    Json,
+}
+
+struct Query {
+    columns: Vec<SelectItem>,
+    sources: Vec<Source>,
+    /// The join condition for each source after the first
+    joins: Vec<Expr>,
+    filter: Option<Expr>,
+    /// Sort keys, and whether they are sorted in descending order
+    order: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+}
+
+enum SelectItem {
+    /// All columns from all tables
+    All,
+    Expr(Expr, Option<String>),
+}
+
+/// A table in the `FROM` or `JOIN` clauses.
+struct Source {
+    name: String,
+    alias: Option<String>,
+}
+
+/// Where the value of a column comes from.
+#[derive(Clone, Copy)]
+struct Binding {
+    source: usize,
+    /// The column index, or [`None`] for row IDs
+    column: Option<usize>,
+}
+
+/// A row from every table in the query, with the columns used by the query.
+struct JoinedRow<'a, 't, 'b> {
+    rows: &'a [RowRef<'t, 'b>],
+    bindings: &'a [((Option<String>, Label), Binding)],
+}
+
+pub fn run_query(input: InputData, args: QueryArgs) -> Result<()> {
+    let query: Query = args.query.parse().context("Could not parse query")?;
+    let hash_table = input.load_hashes()?;
+
+    let mut files = Vec::new();
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let bytes = std::fs::read(&path)?;
+        files.push((path, bytes));
+    }
+    let mut tables: Vec<Option<Table>> = query.sources.iter().map(|_| None).collect();
+    for (path, bytes) in &mut files {
+        let file_tables = input
+            .game_from_bytes(bytes)?
+            .from_bytes(bytes, &mut warn_diagnostics(path))
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
+        for table in file_tables {
+            for (source, slot) in query.sources.iter().zip(&mut tables) {
+                if slot.is_none() && source.is_table(table.name()) {
+                    *slot = Some(table.clone());
+                }
+            }
+        }
+    }
+    let mut tables = query
+        .sources
+        .iter()
+        .zip(tables)
+        .map(|(source, table)| table.with_context(|| format!("Table {} not found", source.name)))
+        .collect::<Result<Vec<_>>>()?;
+    // Tables are matched by hash, so only the tables in the query need their names resolved
+    for table in &mut tables {
+        hash_table.convert_all(table);
+    }
+
+    let (headers, rows) = query.run(&tables)?;
+    match args.format {
+        QueryFormat::Table => print_table(&headers, &rows),
+        QueryFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+            writer.write_record(&headers)?;
+            for row in rows {
+                writer.write_record(row.iter().map(ToString::to_string))?;
+            }
+            writer.flush()?;
+        }
+        QueryFormat::Json => {
+            let rows: Vec<_> = rows
+                .into_iter()
+                .map(|row| {
+                    headers
+                        .iter()
+                        .cloned()
+                        .zip(row.into_iter().map(json_value))
+                        .collect::<Map<_, _>>()
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+    }
+    Ok(())
+}
+
+impl Query {
+    /// Runs the query, returning the column headers and the values of each row.
+    fn run(&self, tables: &[Table]) -> Result<(Vec<String>, Vec<Vec<Scalar>>), ExprError> {
+        let mut bindings = Vec::new();
+        let mut columns = Vec::new();
+        for item in &self.columns {
+            match item {
+                SelectItem::All => {
+                    for (source, table) in tables.iter().enumerate() {
+                        for (column, def) in table.columns().enumerate() {
+                            let binding = Binding {
+                                source,
+                                column: Some(column),
+                            };
+                            columns.push((def.label().to_string(), Selected::Column(binding)));
+                        }
+                    }
+                }
+                SelectItem::Expr(expr, alias) => {
+                    let header = alias.clone().unwrap_or_else(|| match expr {
+                        Expr::Column(l) => l.to_string(),
+                        Expr::Field(_, l) => l.to_string(),
+                        e => e.to_string(),
+                    });
+                    columns.push((header, Selected::Expr(expr)));
+                }
+            }
+        }
+        let exprs = self
+            .columns
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::Expr(e, _) => Some(e),
+                SelectItem::All => None,
+            })
+            .chain(&self.joins)
+            .chain(&self.filter)
+            .chain(self.order.iter().map(|(e, _)| e));
+        for expr in exprs {
+            for (table, label) in expr.fields() {
+                let key = (table.map(str::to_string), label.clone());
+                if !bindings.iter().any(|(k, _)| *k == key) {
+                    let binding = self.bind(tables, table, label)?;
+                    bindings.push((key, binding));
+                }
+            }
+        }
+
+        let mut rows: Vec<Vec<RowRef>> = tables[0].rows().map(|r| vec![r]).collect();
+        for (table, condition) in tables[1..].iter().zip(&self.joins) {
+            let mut joined = Vec::new();
+            for mut partial in rows {
+                for row in table.rows() {
+                    partial.push(row);
+                    let scope = JoinedRow {
+                        rows: &partial,
+                        bindings: &bindings,
+                    };
+                    if condition.eval_in(&scope)?.is_true() {
+                        joined.push(partial.clone());
+                    }
+                    partial.pop();
+                }
+            }
+            rows = joined;
+        }
+
+        let mut results = Vec::new();
+        for row in &rows {
+            let scope = JoinedRow {
+                rows: row,
+                bindings: &bindings,
+            };
+            if let Some(filter) = &self.filter {
+                if !filter.eval_in(&scope)?.is_true() {
+                    continue;
+                }
+            }
+            let keys = self
+                .order
+                .iter()
+                .map(|(e, _)| e.eval_in(&scope))
+                .collect::<Result<Vec<_>, _>>()?;
+            let values = columns
+                .iter()
+                .map(|(_, selected)| match selected {
+                    Selected::Column(binding) => Ok(scope.get(*binding)),
+                    Selected::Expr(e) => e.eval_in(&scope),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            results.push((keys, values));
+        }
+
+        results.sort_by(|(a, _), (b, _)| {
+            a.iter()
+                .zip(b)
+                .zip(&self.order)
+                .map(|((a, b), (_, desc))| match desc {
+                    true => b.compare(a),
+                    false => a.compare(b),
+                })
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+        let limit = self.limit.unwrap_or(usize::MAX);
+        Ok((
+            columns.into_iter().map(|(header, _)| header).collect(),
+            results.into_iter().take(limit).map(|(_, v)| v).collect(),
+        ))
+    }
+
+    /// Finds the table and column a column name refers to. Unqualified names refer to the
+    /// first table that has the column.
+    fn bind(
+        &self,
+        tables: &[Table],
+        table: Option<&str>,
+        label: &Label,
+    ) -> Result<Binding, ExprError> {
+        let label_filter = Filter::from_iter([label.clone()]);
+        let is_id = *label == Label::String(ID_COLUMN.to_string());
+        self.sources
+            .iter()
+            .zip(tables)
+            .enumerate()
+            .filter(|(_, (source, _))| table.is_none_or(|t| source.is_named(t)))
+            .find_map(|(i, (_, t))| {
+                let column = t.columns().position(|c| label_filter.contains(c.label()));
+                match column {
+                    Some(c) => Some(Binding {
+                        source: i,
+                        column: Some(c),
+                    }),
+                    None if is_id => Some(Binding {
+                        source: i,
+                        column: None,
+                    }),
+                    None => None,
+                }
+            })
+            .ok_or_else(|| {
+                ExprError::MissingColumn(match table {
+                    Some(t) => format!("{t}.{label}"),
+                    None => label.to_string(),
+                })
+            })
+    }
+}
+
+/// A column in the query results.
+enum Selected<'a> {
+    Column(Binding),
+    Expr(&'a Expr),
+}
+
+impl Source {
+    /// Returns whether `name` refers to this table in expressions.
+    fn is_named(&self, name: &str) -> bool {
+        match &self.alias {
+            Some(alias) => alias == name,
+            None => self.name == name,
+        }
+    }
+
+    fn is_table(&self, label: &Label) -> bool {
+        Filter::from_iter([FilterArg(self.name.clone())]).contains(label)
+    }
+}
+
+impl JoinedRow<'_, '_, '_> {
+    fn get(&self, binding: Binding) -> Scalar {
+        let row = &self.rows[binding.source];
+        match binding.column {
+            Some(c) => Scalar::from(row.cells().nth(c).unwrap()),
+            None => Scalar::Number(row.id() as f64),
+        }
+    }
+}
+
+impl Scope for JoinedRow<'_, '_, '_> {
+    fn value(&self, table: Option<&str>, column: &Label) -> Result<Scalar, ExprError> {
+        let missing = || {
+            ExprError::MissingColumn(match table {
+                Some(t) => format!("{t}.{column}"),
+                None => column.to_string(),
+            })
+        };
+        let (_, binding) = self
+            .bindings
+            .iter()
+            .find(|((t, l), _)| t.as_deref() == table && l == column)
+            .ok_or_else(missing)?;
+        // Join conditions can't use tables that are joined later
+        if binding.source >= self.rows.len() {
+            return Err(missing());
+        }
+        Ok(self.get(*binding))
+    }
+}
+
+impl FromStr for Query {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s)?;
+        parser.expect_keyword("SELECT")?;
+        let mut columns = Vec::new();
+        loop {
+            if parser.eat(&Token::Op(BinOp::Mul)) {
+                columns.push(SelectItem::All);
+            } else {
+                let expr = parser.expr()?;
+                let alias = match parser.eat_keyword("AS") {
+                    true => Some(name(&mut parser)?),
+                    false => None,
+                };
+                columns.push(SelectItem::Expr(expr, alias));
+            }
+            if !parser.eat(&Token::Comma) {
+                break;
+            }
+        }
+
+        parser.expect_keyword("FROM")?;
+        let mut sources = vec![source(&mut parser)?];
+        let mut joins = Vec::new();
+        while parser.eat_keyword("JOIN") {
+            sources.push(source(&mut parser)?);
+            parser.expect_keyword("ON")?;
+            joins.push(parser.expr()?);
+        }
+
+        let filter = match parser.eat_keyword("WHERE") {
+            true => Some(parser.expr()?),
+            false => None,
+        };
+
+        let mut order = Vec::new();
+        if parser.eat_keyword("ORDER") {
+            parser.expect_keyword("BY")?;
+            loop {
+                let expr = parser.expr()?;
+                let desc = parser.eat_keyword("DESC");
+                if !desc {
+                    parser.eat_keyword("ASC");
+                }
+                order.push((expr, desc));
+                if !parser.eat(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let limit = match parser.eat_keyword("LIMIT") {
+            true => match parser.next_token()? {
+                (_, Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+                (idx, _) => return Err(ExprError::Expected(idx, "number")),
+            },
+            false => None,
+        };
+        parser.end()?;
+
+        Ok(Self {
+            columns,
+            sources,
+            joins,
+            filter,
+            order,
+            limit,
+        })
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "SELECT", "FROM", "JOIN", "ON", "WHERE", "ORDER", "BY", "ASC", "DESC", "LIMIT", "AS", "AND",
+    "OR", "NOT",
+];
+
+fn source(parser: &mut Parser) -> Result<Source, ExprError> {
+    let name = name(parser)?;
+    let alias = match parser.eat_keyword("AS") {
+        true => Some(self::name(parser)?),
+        false => match parser.peek() {
+            Some(Token::Ident(alias)) if !is_keyword(alias) => Some(self::name(parser)?),
+            _ => None,
+        },
+    };
+    Ok(Source { name, alias })
+}
+
+fn name(parser: &mut Parser) -> Result<String, ExprError> {
+    let idx = parser.position();
+    match parser.next_token()? {
+        (_, Token::Ident(name)) if !is_keyword(&name) => Ok(name),
+        _ => Err(ExprError::Expected(idx, "name")),
+    }
+}
+
+fn is_keyword(name: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(name))
+}
+
+fn print_table(headers: &[String], rows: &[Vec<Scalar>]) {
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(ToString::to_string).collect())
+        .collect();
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain([h.chars().count()])
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let print_row = |cells: &[String]| {
+        let line: Vec<_> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{c:<w$}"))
+            .collect();
+        println!("{}", line.join(" | ").trim_end());
+    };
+    print_row(headers);
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .join("-+-")
+    );
+    for row in &rows {
+        print_row(row);
+    }
+}
+
+fn json_value(value: Scalar) -> JsonValue {
+    match value {
+        // Integers are written without a fractional part
+        Scalar::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => (n as i64).into(),
+        Scalar::Number(n) => serde_json::Number::from_f64(n).map_or(JsonValue::Null, Into::into),
+        Scalar::Text(s) => s.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{BdatVersion, Cell, ColumnDef, Row, TableBuilder, Value, ValueType};
+
+    use super::*;
+
+    fn tables() -> Vec<Table<'static>> {
+        let chr = TableBuilder::with_name(Label::String("Chr".into()))
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, "Weapon".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::String("Rex".into())),
+                    Cell::Single(Value::UnsignedShort(2)),
+                ],
+            ))
+            .add_row(Row::new(
+                2,
+                vec![
+                    Cell::Single(Value::String("Nia".into())),
+                    Cell::Single(Value::UnsignedShort(1)),
+                ],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let weapon = TableBuilder::with_name(Label::String("Weapon".into()))
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_column(ColumnDef::new(ValueType::UnsignedShort, "Atk".into()))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::String("Ring".into())),
+                    Cell::Single(Value::UnsignedShort(50)),
+                ],
+            ))
+            .add_row(Row::new(
+                2,
+                vec![
+                    Cell::Single(Value::String("Sword".into())),
+                    Cell::Single(Value::UnsignedShort(80)),
+                ],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        vec![chr, weapon]
+    }
+
+    #[test]
+    fn select_where_order() {
+        let query: Query = "select Name, $id * 10 as Ten from Chr where Weapon > 0 order by Name"
+            .parse()
+            .unwrap();
+        let (headers, rows) = query.run(&tables()[..1]).unwrap();
+        assert_eq!(vec!["Name", "Ten"], headers);
+        assert_eq!(
+            vec![
+                vec![Scalar::Text("Nia".into()), Scalar::Number(20.0)],
+                vec![Scalar::Text("Rex".into()), Scalar::Number(10.0)],
+            ],
+            rows
+        );
+    }
+
+    #[test]
+    fn join() {
+        let query: Query = "SELECT c.Name, w.Name AS WeaponName, Atk FROM Chr c \
+            JOIN Weapon AS w ON c.Weapon = w.$id ORDER BY Atk DESC LIMIT 1"
+            .parse()
+            .unwrap();
+        let (headers, rows) = query.run(&tables()).unwrap();
+        assert_eq!(vec!["Name", "WeaponName", "Atk"], headers);
+        assert_eq!(
+            vec![vec![
+                Scalar::Text("Rex".into()),
+                Scalar::Text("Sword".into()),
+                Scalar::Number(80.0)
+            ]],
+            rows
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(ExprError::Expected(12, "FROM")),
+            "SELECT Name WHERE".parse::<Query>().map(|_| ())
+        );
+        assert_eq!(
+            Err(ExprError::MissingColumn("Missing".to_string())),
+            "SELECT Missing FROM Chr"
+                .parse::<Query>()
+                .unwrap()
+                .run(&tables()[..1])
+                .map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use bdat::BdatVersion;
+use clap::Args;
+
+use crate::InputData;
+
+const MAGIC: &[u8; 4] = b"BDAT";
+const MODERN_FILE_VERSION: u32 = 0x01_00_10_04;
+const MODERN_TABLE_VERSION: u32 = 0x3004;
+/// How far back from a table to look for a legacy file header, in offsets
+const MAX_LEGACY_TABLES: usize = 4096;
+
+#[derive(Args)]
+pub struct ScanArgs {
+    /// Extract the BDAT files that were found to this directory, named after the input file
+    /// and the offset
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+}
+
+/// A BDAT file found inside another file.
+#[derive(Debug, PartialEq)]
+struct Hit {
+    offset: usize,
+    size: usize,
+    version: BdatVersion,
+    table_count: usize,
+}
+
+/// Searches files of any kind for embedded BDAT files, and optionally extracts them.
+///
+/// Modern files are found by their header. Legacy files have no magic of their own, so
+/// they are found by looking back from the first table for a header whose offsets point to
+/// tables. Only headers are checked, the tables themselves are not parsed.
+pub fn scan_files(input: InputData, args: ScanArgs) -> Result<()> {
+    if let Some(out_dir) = &args.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+    }
+    let mut count = 0;
+    for path in input.list_files(None, false)? {
+        let path = path?;
+        if !path.is_file() {
+            continue;
+        }
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("Could not read {}", path.display()))?;
+        let hits = scan(&bytes);
+        if hits.is_empty() {
+            continue;
+        }
+        println!("{}:", path.display());
+        for hit in &hits {
+            println!(
+                "  0x{:08X}: {:?}, {} table(s), {} bytes",
+                hit.offset, hit.version, hit.table_count, hit.size
+            );
+            if let Some(out_dir) = &args.out_dir {
+                let stem = path.file_name().unwrap_or_default().to_string_lossy();
+                let out_path = out_dir.join(format!("{stem}_{:08X}.bdat", hit.offset));
+                std::fs::write(&out_path, &bytes[hit.offset..hit.offset + hit.size])
+                    .with_context(|| format!("Could not write {}", out_path.display()))?;
+            }
+        }
+        count += hits.len();
+    }
+    println!("Found {count} BDAT file(s)");
+    Ok(())
+}
+
+/// Returns the BDAT files in the bytes, in order. Tables inside a file that was found are
+/// not searched again.
+fn scan(bytes: &[u8]) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = find_magic(bytes, pos) {
+        pos = found + 1;
+        if hits.last().is_some_and(|h| found < h.offset + h.size) {
+            continue;
+        }
+        if let Some(hit) = modern_file(bytes, found).or_else(|| legacy_file(bytes, found)) {
+            hits.push(hit);
+        }
+    }
+    hits
+}
+
+fn find_magic(bytes: &[u8], from: usize) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(MAGIC.len())
+        .position(|w| w == MAGIC)
+        .map(|p| p + from)
+}
+
+/// Checks for a modern file header at `offset`.
+fn modern_file(bytes: &[u8], offset: usize) -> Option<Hit> {
+    if read_u32(bytes, offset + 4, false)? != MODERN_FILE_VERSION {
+        return None;
+    }
+    let table_count = read_u32(bytes, offset + 8, false)? as usize;
+    let size = read_u32(bytes, offset + 12, false)? as usize;
+    let file = bytes.get(offset..offset.checked_add(size)?)?;
+    if 16 + table_count.checked_mul(4)? > size {
+        return None;
+    }
+    for i in 0..table_count {
+        let table = read_u32(file, 16 + i * 4, false)? as usize;
+        if file.get(table..table + 4)? != MAGIC
+            || read_u32(file, table + 4, false)? != MODERN_TABLE_VERSION
+        {
+            return None;
+        }
+    }
+    Some(Hit {
+        offset,
+        size,
+        version: BdatVersion::Modern,
+        table_count,
+    })
+}
+
+/// Checks for a legacy file header before a table at `table`, in either byte order.
+fn legacy_file(bytes: &[u8], table: usize) -> Option<Hit> {
+    // The header is the table count, the file size and the table offsets, possibly followed
+    // by padding
+    for distance in (12..=8 + MAX_LEGACY_TABLES * 4).step_by(4) {
+        let Some(offset) = table.checked_sub(distance) else {
+            break;
+        };
+        for big_endian in [false, true] {
+            let read = |pos| read_u32(bytes, offset + pos, big_endian);
+            let (Some(count), Some(size), Some(first)) = (read(0), read(4), read(8)) else {
+                continue;
+            };
+            let (count, size) = (count as usize, size as usize);
+            if count == 0 || first as usize != distance || 8 + count * 4 > distance {
+                continue;
+            }
+            let Some(file) = bytes.get(offset..offset + size) else {
+                continue;
+            };
+            let tables_found = (0..count).all(|i| {
+                read_u32(file, 8 + i * 4, big_endian)
+                    .and_then(|t| file.get(t as usize..t as usize + 4))
+                    .is_some_and(|m| m == MAGIC)
+            });
+            if !tables_found {
+                continue;
+            }
+            let Ok(version) = bdat::detect_bytes_version(file) else {
+                continue;
+            };
+            return Some(Hit {
+                offset,
+                size,
+                version,
+                table_count: count,
+            });
+        }
+    }
+    None
+}
+
+fn read_u32(bytes: &[u8], pos: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = bytes.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+    Some(match big_endian {
+        true => u32::from_be_bytes(bytes),
+        false => u32::from_le_bytes(bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{test_utils::TableFixture, SwitchEndian, Value, ValueType, WiiEndian};
+
+    use super::*;
+
+    fn table(version: BdatVersion) -> bdat::Table<'static> {
+        TableFixture::new("Test")
+            .column("Lv", ValueType::UnsignedInt)
+            .row([Value::UnsignedInt(5)])
+            .build(version)
+    }
+
+    #[test]
+    fn find_embedded_files() {
+        let switch = bdat::legacy::to_vec::<SwitchEndian>(
+            [table(BdatVersion::LegacySwitch).into_legacy()],
+            BdatVersion::LegacySwitch,
+        )
+        .unwrap();
+        let wii = bdat::legacy::to_vec::<WiiEndian>(
+            [
+                table(BdatVersion::LegacyWii).into_legacy(),
+                table(BdatVersion::LegacyWii).into_legacy(),
+            ],
+            BdatVersion::LegacyWii,
+        )
+        .unwrap();
+        let modern =
+            bdat::modern::to_vec::<SwitchEndian>([table(BdatVersion::Modern).into_modern()])
+                .unwrap();
+
+        let mut bytes = b"junk BDAT junk".to_vec();
+        let mut expected = Vec::new();
+        for (file, version, table_count) in [
+            (switch, BdatVersion::LegacySwitch, 1),
+            (wii, BdatVersion::LegacyWii, 2),
+            (modern, BdatVersion::Modern, 1),
+        ] {
+            expected.push(Hit {
+                offset: bytes.len(),
+                size: file.len(),
+                version,
+                table_count,
+            });
+            bytes.extend(file);
+            bytes.extend([0xFF; 3]);
+        }
+        assert_eq!(expected, scan(&bytes));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fs::File, io::BufReader};
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+
+use bdat_convert::{error::Error, schema::FileSchema};
+
+use crate::InputData;
+
+#[derive(Args)]
+pub struct SchemaArgs {
+    #[command(subcommand)]
+    command: SchemaCommand,
+}
+
+#[derive(Subcommand)]
+enum SchemaCommand {
+    /// Migrate .bschema files written by older versions of the toolset to the current format,
+    /// so the extracted tables can be packed again. Files are updated in place.
+    Upgrade,
+    /// Rename a table in .bschema files, e.g. "schema rename out -t ITM_Item --to ITM_ModItem".
+    /// The table keeps its file, and is packed with the new name (and its hash, in XC3).
+    Rename {
+        /// The table to rename
+        #[arg(short, long)]
+        table: String,
+        /// The new name
+        #[arg(long)]
+        to: String,
+    },
+}
+
+pub fn run_schema(input: InputData, args: SchemaArgs) -> Result<()> {
+    match args.command {
+        SchemaCommand::Upgrade => upgrade(input),
+        SchemaCommand::Rename { table, to } => rename(input, &table, &to),
+    }
+}
+
+fn upgrade(input: InputData) -> Result<()> {
+    let mut upgraded = 0;
+    for file in input.list_files("bschema", false)? {
+        let path = file?;
+        let (schema, version) = FileSchema::read_upgrade(BufReader::new(File::open(&path)?))
+            .with_context(|| format!("Could not read schema {}", path.display()))?;
+        if version == schema.format_version {
+            continue;
+        }
+        schema.write_to(&path)?;
+        println!(
+            "Upgraded {} from version {version} to {}",
+            path.display(),
+            schema.format_version
+        );
+        upgraded += 1;
+    }
+    println!("Upgraded {upgraded} schema files");
+    Ok(())
+}
+
+fn rename(input: InputData, table: &str, to: &str) -> Result<()> {
+    let mut renamed = false;
+    for file in input.list_files("bschema", false)? {
+        let path = file?;
+        let mut schema = FileSchema::read(BufReader::new(File::open(&path)?))
+            .with_context(|| format!("Could not read schema {}", path.display()))?;
+        match schema.rename_table(table, to) {
+            Ok(()) => {}
+            Err(Error::TableNotFound(_)) => continue,
+            Err(e) => return Err(e).with_context(|| format!("In {}", path.display())),
+        }
+        schema.write_to(&path)?;
+        println!("Renamed {table} to {to} in {}", path.display());
+        renamed = true;
+    }
+    if !renamed {
+        return Err(Error::TableNotFound(table.to_string()).into());
+    }
+    Ok(())
+}
@@ -1,13 +1,13 @@
-use crate::error::Error;
 use crate::util::{ProgressBarState, RayonPoolJobs};
 use crate::InputData;
 use anyhow::{Context, Result};
 use bdat::legacy::scramble::ScrambleType;
 use bdat::legacy::{FileHeader, TableHeader};
 use bdat::{BdatVersion, SwitchEndian, WiiEndian};
+use bdat_convert::{error::Error, util::write_atomic};
 use clap::Args;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 use std::path::{Path, PathBuf};
 
 #[derive(Args)]
@@ -42,7 +42,7 @@ fn run(
         .into_iter()
         .collect::<walkdir::Result<Vec<_>>>()?;
 
-    let base_path = crate::util::get_common_denominator(&files);
+    let base_path = bdat_convert::util::get_common_denominator(&files);
     let out_dir = args.out_dir.map(PathBuf::from);
 
     let out_file_name = |file: &PathBuf| match out_dir.as_ref() {
@@ -122,7 +122,7 @@ fn unscramble_file(path_in: PathBuf, path_out: PathBuf, progress: &ProgressBarSt
     table_bar.finish();
     progress.remove_child(&table_bar);
 
-    std::fs::write(path_out, bytes)?;
+    write_atomic(&path_out, |writer| Ok(writer.write_all(&bytes)?))?;
     Ok(())
 }
 
@@ -170,6 +170,6 @@ fn scramble_file(path_in: PathBuf, path_out: PathBuf, progress: &ProgressBarStat
     table_bar.finish();
     progress.remove_child(&table_bar);
 
-    std::fs::write(path_out, bytes)?;
+    write_atomic(&path_out, |writer| Ok(writer.write_all(&bytes)?))?;
     Ok(())
 }
@@ -0,0 +1,421 @@
+//! Runs Rhai scripts over the tables of BDAT files.
+//!
+//! The script runs once per BDAT file, with these constants in scope:
+//! * `file`: the path of the BDAT file
+//! * `tables`: an array with the tables in the file
+//!
+//! Tables have `name`, `base_id`, `row_count`, `columns` (an array of column names) and `rows`
+//! (an array of rows) properties, and `row(id)` and `has_column(name)` methods.
+//! Rows have an `id` property, and their cells are read and written by column name, either
+//! with `row["Lv"]` or `row.Lv`. Hashed names can also be written as `row["<9143A12E>"]`.
+//! Rows returned by `row(id)` must be stored in a variable before assigning to them.
+//!
+//! Numbers are written back with the type of the column, rounding floats for integer
+//! columns. List and flag cells are arrays. Raw values can't be modified.
+
+use std::{cell::RefCell, io::Cursor, path::PathBuf, rc::Rc};
+
+use anyhow::{anyhow, Context, Result};
+use bdat::{
+    legacy::float::BdatReal, BdatVersion, Cell, Label, Table, TableAccessor, Utf, Value, ValueType,
+};
+use bdat_convert::{
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+    util::write_atomic,
+};
+use clap::Args;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, Scope, INT};
+
+use crate::{read_tables, InputData};
+
+#[derive(Args)]
+pub struct ScriptArgs {
+    /// The Rhai script to run
+    #[arg(short, long)]
+    script: PathBuf,
+    /// Only pass these tables to the script. If absent, the script gets all tables.
+    #[arg(short, long)]
+    tables: Vec<String>,
+    /// Where to write modified BDAT files. If absent, files are modified in place.
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// A table shared between the handles given to the script.
+type SharedTable = Rc<RefCell<ScriptTable>>;
+
+struct ScriptTable {
+    table: Table<'static>,
+    /// The table name, unhashed if possible
+    name: String,
+    /// Column names, unhashed if possible
+    column_names: Vec<String>,
+    /// Column labels, hashed for modern tables, to look up columns by name
+    column_labels: Vec<Label>,
+    version: BdatVersion,
+    changed: bool,
+}
+
+#[derive(Clone)]
+struct TableHandle(SharedTable);
+
+#[derive(Clone)]
+struct RowHandle {
+    table: SharedTable,
+    id: usize,
+}
+
+/// Runs a script over the tables of every BDAT file. Only files with modified cells are
+/// written.
+pub fn run_script(input: InputData, args: ScriptArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let filter: Filter = args.tables.into_iter().map(FilterArg).collect();
+    let script = std::fs::read_to_string(&args.script).context("Could not read script")?;
+    let engine = engine();
+    let ast = engine
+        .compile(script)
+        .map_err(|e| anyhow!("{e}"))
+        .context("Could not compile script")?;
+
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let bytes = std::fs::read(&path)?;
+        let game = input.game_from_bytes(&bytes)?;
+        let version = BdatVersion::from(game);
+        let tables: Vec<SharedTable> = game
+            .from_reader(Cursor::new(bytes))
+            .and_then(|f| read_tables(f, &path))
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.display()))?
+            .into_iter()
+            .map(|table| Rc::new(RefCell::new(ScriptTable::new(table, version, &hash_table))))
+            .collect();
+
+        let mut scope = Scope::new();
+        scope.push_constant("file", path.display().to_string());
+        scope.push_constant(
+            "tables",
+            tables
+                .iter()
+                .filter(|t| filter.contains(t.borrow().table.name()))
+                .map(|t| Dynamic::from(TableHandle(t.clone())))
+                .collect::<Array>(),
+        );
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("{e}"))
+            .with_context(|| format!("Script failed on {}", path.display()))?;
+        drop(scope);
+
+        if !tables.iter().any(|t| t.borrow().changed) {
+            continue;
+        }
+        let tables = tables.into_iter().map(|t| match Rc::try_unwrap(t) {
+            Ok(t) => t.into_inner().table,
+            Err(t) => t.borrow().table.clone(),
+        });
+        let out_path = match &args.out_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                dir.join(path.file_name().unwrap())
+            }
+            None => path,
+        };
+        // Keep the original table order
+        write_atomic(&out_path, |writer| {
+            Ok(game.to_writer(writer, tables, false, false)?)
+        })
+        .context("Could not save BDAT file")?;
+        println!("Modified {}", out_path.display());
+    }
+    Ok(())
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .register_type_with_name::<TableHandle>("Table")
+        .register_get("name", |t: &mut TableHandle| t.0.borrow().name.clone())
+        .register_get("base_id", |t: &mut TableHandle| {
+            t.0.borrow().table.base_id() as INT
+        })
+        .register_get("row_count", |t: &mut TableHandle| {
+            t.0.borrow().table.row_count() as INT
+        })
+        .register_get("columns", |t: &mut TableHandle| {
+            t.0.borrow()
+                .column_names
+                .iter()
+                .map(|n| Dynamic::from(n.clone()))
+                .collect::<Array>()
+        })
+        .register_get("rows", |t: &mut TableHandle| {
+            let table = t.0.borrow();
+            let base_id = table.table.base_id();
+            (base_id..base_id + table.table.row_count())
+                .map(|id| {
+                    Dynamic::from(RowHandle {
+                        table: t.0.clone(),
+                        id,
+                    })
+                })
+                .collect::<Array>()
+        })
+        .register_fn("row", TableHandle::row)
+        .register_fn("has_column", |t: &mut TableHandle, name: &str| {
+            t.0.borrow().find_column(name).is_some()
+        })
+        .register_fn("to_string", |t: &mut TableHandle| t.0.borrow().name.clone());
+
+    engine
+        .register_type_with_name::<RowHandle>("Row")
+        .register_get("id", |r: &mut RowHandle| r.id as INT)
+        .register_indexer_get(RowHandle::get)
+        .register_indexer_set(RowHandle::set)
+        .register_fn("to_string", |r: &mut RowHandle| {
+            format!("{}#{}", r.table.borrow().name, r.id)
+        });
+    engine
+}
+
+impl ScriptTable {
+    fn new(table: Table<'static>, version: BdatVersion, hash_table: &HashNameTable) -> Self {
+        let name = hash_table.convert_label_cow(table.name()).to_string();
+        let column_names = table
+            .columns()
+            .map(|c| hash_table.convert_label_cow(c.label()).to_string())
+            .collect();
+        let column_labels = table
+            .columns()
+            .map(|c| c.label().clone().into_hash(version))
+            .collect();
+        Self {
+            table,
+            name,
+            column_names,
+            column_labels,
+            version,
+            changed: false,
+        }
+    }
+
+    /// Finds a column by name. Hashed names can be written as plain names or as hashes.
+    fn find_column(&self, name: &str) -> Option<usize> {
+        let label = Label::parse_for_version(name, self.version);
+        self.column_labels.iter().position(|l| *l == label)
+    }
+
+    fn column(&self, name: &str) -> ScriptResult<usize> {
+        self.find_column(name)
+            .ok_or_else(|| format!("Unknown column {name} in table {}", self.name).into())
+    }
+}
+
+impl TableHandle {
+    fn row(&mut self, id: INT) -> ScriptResult<RowHandle> {
+        let table = self.0.borrow();
+        match usize::try_from(id) {
+            Ok(id) if table.table.get_row(id).is_some() => Ok(RowHandle {
+                table: self.0.clone(),
+                id,
+            }),
+            _ => Err(format!("Row {id} not found in table {}", table.name).into()),
+        }
+    }
+}
+
+impl RowHandle {
+    fn get(&mut self, column: ImmutableString) -> ScriptResult<Dynamic> {
+        let table = self.table.borrow();
+        let index = table.column(&column)?;
+        let row = table.table.row(self.id);
+        let cell = row.cells().nth(index).unwrap();
+        Ok(match cell {
+            Cell::Single(v) => value_to_dynamic(v),
+            Cell::List(values) => values
+                .iter()
+                .map(value_to_dynamic)
+                .collect::<Array>()
+                .into(),
+            Cell::Flags(flags) => flags
+                .iter()
+                .map(|&f| Dynamic::from(f as INT))
+                .collect::<Array>()
+                .into(),
+        })
+    }
+
+    fn set(&mut self, column: ImmutableString, value: Dynamic) -> ScriptResult<()> {
+        let mut table = self.table.borrow_mut();
+        let index = table.column(&column)?;
+        let mut row = table.table.row_mut(self.id);
+        let cell = row.cells_mut().nth(index).unwrap();
+        let new = match cell {
+            Cell::Single(old) => Cell::Single(dynamic_to_value(old, value)?),
+            Cell::List(old) => {
+                let values = value
+                    .try_cast::<Array>()
+                    .filter(|a| a.len() == old.len())
+                    .ok_or_else(|| format!("Expected an array of {} values", old.len()))?;
+                Cell::List(
+                    old.iter()
+                        .zip(values)
+                        .map(|(old, v)| dynamic_to_value(old, v))
+                        .collect::<ScriptResult<_>>()?,
+                )
+            }
+            Cell::Flags(old) => {
+                let values = value
+                    .try_cast::<Array>()
+                    .filter(|a| a.len() == old.len())
+                    .ok_or_else(|| format!("Expected an array of {} flags", old.len()))?;
+                Cell::Flags(
+                    values
+                        .into_iter()
+                        .map(|v| to_integer(v, ValueType::UnsignedInt))
+                        .collect::<ScriptResult<_>>()?,
+                )
+            }
+        };
+        if *cell != new {
+            *cell = new;
+            table.changed = true;
+        }
+        Ok(())
+    }
+}
+
+fn value_to_dynamic(value: &Value) -> Dynamic {
+    match value {
+        Value::Unknown => Dynamic::UNIT,
+        Value::UnsignedByte(n) | Value::Percent(n) | Value::Unknown2(n) => (*n as INT).into(),
+        Value::UnsignedShort(n) | Value::MessageId(n) => (*n as INT).into(),
+        Value::UnsignedInt(n) | Value::HashRef(n) => (*n as INT).into(),
+        Value::SignedByte(n) => (*n as INT).into(),
+        Value::SignedShort(n) => (*n as INT).into(),
+        Value::SignedInt(n) => (*n as INT).into(),
+        Value::String(s) | Value::DebugString(s) => s.to_string().into(),
+        // Go through the shortest representation, so e.g. 0.1 isn't 0.10000000149011612
+        Value::Float(f) => f32::from(*f)
+            .to_string()
+            .parse::<f64>()
+            .unwrap_or_default()
+            .into(),
+        Value::Raw(bytes) => Dynamic::from_blob(bytes.to_vec()),
+    }
+}
+
+/// Converts a value from the script to the type of the value it replaces.
+fn dynamic_to_value(old: &Value, new: Dynamic) -> ScriptResult<Value<'static>> {
+    let ty = ValueType::from(old);
+    Ok(match old {
+        Value::UnsignedByte(_) => Value::UnsignedByte(to_integer(new, ty)?),
+        Value::UnsignedShort(_) => Value::UnsignedShort(to_integer(new, ty)?),
+        Value::UnsignedInt(_) => Value::UnsignedInt(to_integer(new, ty)?),
+        Value::SignedByte(_) => Value::SignedByte(to_integer(new, ty)?),
+        Value::SignedShort(_) => Value::SignedShort(to_integer(new, ty)?),
+        Value::SignedInt(_) => Value::SignedInt(to_integer(new, ty)?),
+        Value::HashRef(_) => Value::HashRef(to_integer(new, ty)?),
+        Value::Percent(_) => Value::Percent(to_integer(new, ty)?),
+        Value::Unknown2(_) => Value::Unknown2(to_integer(new, ty)?),
+        Value::MessageId(_) => Value::MessageId(to_integer(new, ty)?),
+        Value::String(_) => Value::String(to_string(new, ty)?),
+        Value::DebugString(_) => Value::DebugString(to_string(new, ty)?),
+        Value::Float(real) => {
+            let f = match new.as_float() {
+                Ok(f) => f as f32,
+                Err(_) => new
+                    .as_int()
+                    .map_err(|t| format!("Expected a number for {ty:?}, found {t}"))?
+                    as f32,
+            };
+            Value::Float(match real {
+                BdatReal::Floating(_) => BdatReal::Floating(f.into()),
+                BdatReal::Fixed(_) => BdatReal::Fixed(f.into()),
+                BdatReal::Unknown(_) => BdatReal::Unknown(f),
+            })
+        }
+        Value::Unknown | Value::Raw(_) => return Err(format!("{ty:?} values are read-only").into()),
+    })
+}
+
+/// Converts a number from the script to an integer type, rounding floats.
+fn to_integer<T: TryFrom<i64>>(value: Dynamic, ty: ValueType) -> ScriptResult<T> {
+    let n = match value.as_float() {
+        Ok(f) => f.round() as i64,
+        Err(_) => value
+            .as_int()
+            .map_err(|t| format!("Expected a number for {ty:?}, found {t}"))?,
+    };
+    T::try_from(n).map_err(|_| format!("{n} is out of range for {ty:?}").into())
+}
+
+fn to_string(value: Dynamic, ty: ValueType) -> ScriptResult<Utf<'static>> {
+    value
+        .into_immutable_string()
+        .map(|s| s.to_string().into())
+        .map_err(|t| format!("Expected a string for {ty:?}, found {t}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{ColumnDef, Row, TableBuilder};
+
+    use super::*;
+
+    #[test]
+    fn modify_cells() {
+        let table = TableBuilder::with_name(Label::from("Enemy"))
+            .add_column(ColumnDef::new(ValueType::UnsignedByte, Label::from("Lv")))
+            .add_column(ColumnDef::new(ValueType::Float, Label::from("Rate")))
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::UnsignedByte(10)),
+                    Cell::Single(Value::Float(BdatReal::Floating(0.5.into()))),
+                ],
+            ))
+            .build(BdatVersion::LegacySwitch);
+        let table = Rc::new(RefCell::new(ScriptTable::new(
+            table,
+            BdatVersion::LegacySwitch,
+            &HashNameTable::empty(),
+        )));
+
+        let engine = engine();
+        let mut scope = Scope::new();
+        scope.push_constant("t", TableHandle(table.clone()));
+        engine
+            .run_with_scope(
+                &mut scope,
+                "for row in t.rows { row.Lv *= 1.15; row[\"Rate\"] += 1 }",
+            )
+            .unwrap();
+        assert!(table.borrow().changed);
+        let row = table
+            .borrow()
+            .table
+            .row(1)
+            .cells()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Cell::Single(Value::UnsignedByte(12)),
+                Cell::Single(Value::Float(BdatReal::Floating(1.5.into()))),
+            ],
+            row
+        );
+
+        for (script, error) in [
+            ("let r = t.row(1); r.Lv = 256", "256 is out of range"),
+            ("let r = t.row(1); r.Missing = 1", "Unknown column Missing"),
+            ("t.row(2)", "Row 2 not found"),
+        ] {
+            let result = engine.run_with_scope(&mut scope, script);
+            assert!(result.unwrap_err().to_string().contains(error), "{script}");
+        }
+    }
+}
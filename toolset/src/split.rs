@@ -0,0 +1,193 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use bdat::{Label, Table, TableAccessor};
+use bdat_convert::{error::Error, hash::HashNameTable};
+use clap::Args;
+
+use crate::{warn_diagnostics, InputData};
+
+#[derive(Args)]
+pub struct SplitArgs {
+    /// The output directory for the split files.
+    #[arg(short, long)]
+    out_dir: String,
+    /// A group of tables, written to "<NAME>.bdat". Patterns are table names, where '*'
+    /// matches any text, e.g. "battle=BTL_*,<1A2B3C4D>". Tables go to the first group they
+    /// match. Syntax: "--group <group1> --group <group2> ...".
+    #[arg(long = "group", value_name = "NAME=PATTERNS")]
+    groups: Vec<TableGroup>,
+    /// A file with a group in each line, in the same format as --group. Blank lines and
+    /// lines starting with '#' are ignored. Groups from the file come after --group.
+    #[arg(long, value_name = "FILE")]
+    groups_file: Option<PathBuf>,
+    /// Writes tables that don't match any group to "<NAME>.bdat". If absent, they are
+    /// left out.
+    #[arg(long, value_name = "NAME")]
+    rest: Option<String>,
+}
+
+/// Tables written to the same file by "split".
+#[derive(Clone)]
+pub struct TableGroup {
+    name: String,
+    patterns: Vec<String>,
+}
+
+/// Writes the tables of a BDAT file to several smaller files, grouped by table name.
+pub fn split_file(input: InputData, args: SplitArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let mut groups = args.groups;
+    if let Some(path) = &args.groups_file {
+        let text = std::fs::read_to_string(path).context("Could not read groups file")?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let group = line
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Line {} of the groups file: {e}", i + 1))?;
+            groups.push(group);
+        }
+    }
+
+    let [path] = input.files.as_slice() else {
+        return Err(Error::SplitFiles.into());
+    };
+    let mut bytes = std::fs::read(path)?;
+    let game = input.game_from_bytes(&bytes)?;
+    let tables = game
+        .from_bytes(&mut bytes, &mut warn_diagnostics(Path::new(path)))
+        .with_context(|| format!("Could not parse BDAT tables ({path})"))?;
+
+    let mut files: Vec<Vec<Table>> = vec![Vec::new(); groups.len() + 1];
+    for table in tables {
+        let index = groups
+            .iter()
+            .position(|g| g.contains(table.name(), &hash_table))
+            .unwrap_or(groups.len());
+        files[index].push(table);
+    }
+    let rest = files.pop().unwrap();
+    let names = groups
+        .iter()
+        .map(|g| Some(&g.name))
+        .chain([args.rest.as_ref()]);
+
+    let out_dir = PathBuf::from(args.out_dir);
+    std::fs::create_dir_all(&out_dir).context("Could not create output directory")?;
+    for (name, tables) in names.zip(files.into_iter().chain([rest])) {
+        let count = tables.len();
+        let Some(name) = name else {
+            if count > 0 {
+                println!("Left out {count} table(s) that didn't match any group");
+            }
+            continue;
+        };
+        if count == 0 {
+            println!("{name}: no tables, skipped");
+            continue;
+        }
+        let out_path = out_dir.join(format!("{name}.bdat"));
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(&out_path)?);
+        // Tables keep their order, which is still sorted for modern files
+        game.to_writer(writer, tables, false, false)
+            .with_context(|| format!("Could not write {}", out_path.display()))?;
+        println!("{name}: {count} table{}", if count == 1 { "" } else { "s" });
+    }
+    Ok(())
+}
+
+impl TableGroup {
+    /// Returns whether a table name matches one of the group's patterns. Hashed names are
+    /// matched both as their hash (e.g. `<1A2B3C4D>`) and as the name in `hash_table`.
+    fn contains(&self, label: &Label, hash_table: &HashNameTable) -> bool {
+        let hash = label.to_string();
+        let name = hash_table.convert_label_cow(label).to_string();
+        self.patterns
+            .iter()
+            .any(|p| matches_pattern(p, &name) || matches_pattern(p, &hash))
+    }
+}
+
+impl FromStr for TableGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, patterns) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=PATTERNS, found '{s}'"))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("missing group name in '{s}'"));
+        }
+        let patterns: Vec<_> = patterns
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(ToString::to_string)
+            .collect();
+        if patterns.is_empty() {
+            return Err(format!("group '{name}' has no patterns"));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            patterns,
+        })
+    }
+}
+
+/// Matches a name against a pattern, where `*` matches any text (including none).
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.peekable();
+    if parts.peek().is_none() {
+        return rest.is_empty();
+    }
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        assert!(matches_pattern("BTL_Enemy", "BTL_Enemy"));
+        assert!(!matches_pattern("BTL_Enemy", "BTL_Enemy2"));
+        assert!(matches_pattern("BTL_*", "BTL_Enemy"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*Enemy*", "BTL_EnemyParty"));
+        assert!(matches_pattern("B*_*y", "BTL_Enemy"));
+        assert!(!matches_pattern("B*_*y", "BTL_Enemies"));
+        assert!(!matches_pattern("*ab*ab", "xab"));
+
+        let group: TableGroup = "battle = BTL_*, <1A2B3C4D>".parse().unwrap();
+        assert_eq!("battle", group.name);
+        assert_eq!(vec!["BTL_*", "<1A2B3C4D>"], group.patterns);
+        assert!("battle".parse::<TableGroup>().is_err());
+        assert!("battle=".parse::<TableGroup>().is_err());
+    }
+}
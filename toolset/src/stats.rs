@@ -0,0 +1,482 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use anyhow::{Context, Result};
+use bdat::{BdatVersion, Cell, ColumnStats, Label, Table, TableAccessor, Value};
+use bdat_convert::{
+    filter::{Filter, FilterArg},
+    hash::HashNameTable,
+    util::BdatGame,
+};
+use clap::Args;
+
+use crate::{info::format_unhashed_label, layout, warn_diagnostics, InputData};
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Only check these tables. If absent, prints stats for all tables.
+    #[arg(short, long)]
+    tables: Vec<String>,
+    /// Only print these columns. If absent, prints all columns.
+    #[arg(short, long)]
+    columns: Vec<String>,
+    /// Also lists the N most frequent values of each column, with how many times they
+    /// appear. 0 lists every value.
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+    /// Instead of printing stats, writes the unique values of this column across all tables
+    /// that have it, with how many times they appear, as CSV. Values are sorted, numbers first.
+    #[arg(long, value_name = "COLUMN", conflicts_with_all = ["columns", "top"])]
+    distinct: Option<String>,
+    /// Instead of printing column stats, reports the size of the string tables in each
+    /// file, how many strings are duplicates, and how much smaller the string tables would
+    /// be after repacking or if tables shared their strings. `--top` sets how many of the
+    /// biggest strings are listed (default 5).
+    #[arg(long, conflicts_with_all = ["columns", "distinct"])]
+    strings: bool,
+}
+
+/// The values found in a column, or in a single flag of a column.
+#[derive(Default)]
+struct ValueStats {
+    stats: ColumnStats,
+    /// Formatted value -> number of occurrences, with hashes resolved
+    counts: HashMap<String, usize>,
+    /// Whether the values are strings, which are quoted when printed
+    strings: bool,
+}
+
+pub fn print_stats(input: InputData, args: StatsArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let table_filter: Filter = args.tables.into_iter().map(FilterArg).collect();
+    let column_filter: Filter = args.columns.into_iter().map(FilterArg).collect();
+    let distinct_filter: Option<Filter> =
+        args.distinct.map(|c| [FilterArg(c)].into_iter().collect());
+    let mut distinct = ValueStats::default();
+    if args.strings {
+        return print_string_stats(&input, &table_filter, args.top.unwrap_or(5));
+    }
+
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let mut file = std::fs::read(&path)?;
+        let tables = input
+            .game_from_bytes(&file)?
+            .from_bytes(&mut file, &mut warn_diagnostics(&path))
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
+        for table in tables {
+            if !table_filter.contains(table.name()) {
+                continue;
+            }
+            if let Some(filter) = &distinct_filter {
+                if let Some(index) = table.columns().position(|c| filter.contains(c.label())) {
+                    distinct.add_column(&table, index, None, &hash_table);
+                }
+                continue;
+            }
+            println!(
+                "Table {} ({} rows)",
+                format_unhashed_label(table.name(), &hash_table),
+                table.row_count()
+            );
+            for (index, col) in table.columns().enumerate() {
+                if !column_filter.contains(col.label()) {
+                    continue;
+                }
+                let label = format_unhashed_label(col.label(), &hash_table);
+                if col.flags().is_empty() {
+                    let stats = ValueStats::from_column(&table, index, None, &hash_table);
+                    let ty = match col.count() {
+                        1 => format!("{:?}", col.value_type()),
+                        n => format!("{:?}[{n}]", col.value_type()),
+                    };
+                    stats.print(&format!("{label}: {ty}"), args.top);
+                }
+                for (i, flag) in col.flags().iter().enumerate() {
+                    let stats = ValueStats::from_column(&table, index, Some(i), &hash_table);
+                    stats.print(&format!("{label}.{}: Flag", flag.label()), args.top);
+                }
+            }
+        }
+    }
+
+    if distinct_filter.is_some() {
+        distinct.write_distinct(std::io::stdout().lock())?;
+    }
+    Ok(())
+}
+
+/// The string values of the tables in a file, and the size of their string tables.
+struct StringStats {
+    /// Bytes used by the string tables in the file, including names and padding
+    stored: usize,
+    /// Bytes used by the string tables after repacking, which stores each string once
+    /// per table
+    repacked: usize,
+    /// Number of string values
+    count: usize,
+    /// Number of strings that are unique in their table
+    unique_in_table: usize,
+    /// Bytes used by the strings that are unique in their table
+    table_bytes: usize,
+    /// String -> (number of occurrences, name of the first table that has it)
+    strings: HashMap<String, (usize, String)>,
+    version: BdatVersion,
+}
+
+fn print_string_stats(input: &InputData, table_filter: &Filter, top: usize) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let mut file = std::fs::read(&path)?;
+        let game = input.game_from_bytes(&file)?;
+        let version = BdatVersion::from(game);
+        // Read before parsing, legacy tables are unscrambled in place
+        let layouts = layout::table_layouts(&file, version)
+            .with_context(|| format!("Could not read table layout ({})", path.to_string_lossy()))?;
+        let tables = game
+            .from_bytes(&mut file, &mut warn_diagnostics(&path))
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.to_string_lossy()))?;
+        let repacked = repacked_layouts(game, &tables)
+            .with_context(|| format!("Could not repack tables ({})", path.to_string_lossy()))?;
+
+        let mut stats = StringStats::new(version);
+        for ((table, stored), repacked) in tables.iter().zip(&layouts).zip(&repacked) {
+            if table_filter.contains(table.name()) {
+                let name = format_unhashed_label(table.name(), &hash_table);
+                stats.add_table(
+                    table,
+                    &name,
+                    string_table_size(stored),
+                    string_table_size(repacked),
+                );
+            }
+        }
+        println!("File {}", path.to_string_lossy());
+        stats.print(top);
+    }
+    Ok(())
+}
+
+/// Writes the tables back to a BDAT file, and returns the layout of the new tables.
+fn repacked_layouts(game: BdatGame, tables: &[Table]) -> Result<Vec<layout::TableLayout>> {
+    let mut out = Vec::new();
+    game.to_writer(&mut out, tables.iter().cloned(), false, false)?;
+    layout::table_layouts(&out, BdatVersion::from(game))
+}
+
+fn string_table_size(layout: &layout::TableLayout) -> usize {
+    let names = ["names", "string table"];
+    layout
+        .sections
+        .iter()
+        .filter(|s| names.contains(&s.name))
+        .map(|s| s.size)
+        .sum()
+}
+
+impl StringStats {
+    fn new(version: BdatVersion) -> Self {
+        Self {
+            stored: 0,
+            repacked: 0,
+            count: 0,
+            unique_in_table: 0,
+            table_bytes: 0,
+            strings: HashMap::new(),
+            version,
+        }
+    }
+
+    fn add_table(&mut self, table: &Table, name: &str, stored: usize, repacked: usize) {
+        self.stored += stored;
+        self.repacked += repacked;
+        let mut in_table = HashSet::new();
+        for row in table.rows() {
+            for cell in row.cells() {
+                let values = match cell {
+                    Cell::Single(value) => std::slice::from_ref(value),
+                    Cell::List(values) => values.as_slice(),
+                    Cell::Flags(_) => &[],
+                };
+                for value in values {
+                    let (Value::String(s) | Value::DebugString(s)) = value else {
+                        continue;
+                    };
+                    self.count += 1;
+                    if in_table.insert(s.to_string()) {
+                        self.unique_in_table += 1;
+                        self.table_bytes += self.encoded_len(s);
+                    }
+                    self.strings
+                        .entry(s.to_string())
+                        .or_insert_with(|| (0, name.to_string()))
+                        .0 += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of bytes a string takes in a string table.
+    fn encoded_len(&self, s: &str) -> usize {
+        match self.version {
+            BdatVersion::Modern => s.len() + 1,
+            // Aligned to 2 bytes
+            _ => (s.len() + 2) & !1,
+        }
+    }
+
+    /// Returns the number of bytes used by the strings if every table shared one string
+    /// table, i.e. if every string were stored once per file.
+    fn file_bytes(&self) -> usize {
+        self.strings.keys().map(|s| self.encoded_len(s)).sum()
+    }
+
+    /// Returns the strings, longest first, with their occurrences and first table. If `n`
+    /// is 0, returns every string.
+    fn biggest(&self, n: usize) -> Vec<(&str, usize, &str)> {
+        let mut strings = self
+            .strings
+            .iter()
+            .map(|(s, (count, table))| (s.as_str(), *count, table.as_str()))
+            .collect::<Vec<_>>();
+        strings.sort_unstable_by(|(s1, ..), (s2, ..)| s2.len().cmp(&s1.len()).then(s1.cmp(s2)));
+        if n != 0 {
+            strings.truncate(n);
+        }
+        strings
+    }
+
+    /// Returns the estimated size of the string tables if every table shared its strings.
+    fn shared_size(&self) -> usize {
+        let saved = self.table_bytes - self.file_bytes();
+        self.repacked.saturating_sub(saved)
+    }
+
+    fn print(&self, top: usize) {
+        let shared = self.shared_size();
+        let change = |bytes: usize| bytes as i64 - self.stored as i64;
+        println!(
+            "  String tables: {} bytes, {} after repacking ({:+}), {} if tables shared strings ({:+})",
+            self.stored,
+            self.repacked,
+            change(self.repacked),
+            shared,
+            change(shared)
+        );
+        if self.count == 0 {
+            return;
+        }
+        let duplicates = (self.count - self.strings.len()) as f64 * 100.0 / self.count as f64;
+        println!(
+            "  Strings: {} values, {} unique in their table, {} unique in the file ({duplicates:.1}% duplicates)",
+            self.count,
+            self.unique_in_table,
+            self.strings.len()
+        );
+        for (s, count, table) in self.biggest(top) {
+            let mut text: String = s.chars().take(60).collect();
+            if text.len() < s.len() {
+                text += "...";
+            }
+            println!(
+                "    {:>6} bytes, {count}x, in {table}: {text:?}",
+                self.encoded_len(s)
+            );
+        }
+    }
+}
+
+impl ValueStats {
+    /// Collects the values of a column. For flag columns, `flag` is the index of the flag.
+    /// Every value of list cells is counted.
+    fn from_column(
+        table: &Table,
+        column: usize,
+        flag: Option<usize>,
+        hash_table: &HashNameTable,
+    ) -> Self {
+        let mut stats = Self::default();
+        stats.add_column(table, column, flag, hash_table);
+        stats
+    }
+
+    /// Adds the values of a column, see [`ValueStats::from_column`].
+    fn add_column(
+        &mut self,
+        table: &Table,
+        column: usize,
+        flag: Option<usize>,
+        hash_table: &HashNameTable,
+    ) {
+        for row in table.rows() {
+            match (row.cells().nth(column).unwrap(), flag) {
+                (Cell::Single(value), _) => self.add(value, hash_table),
+                (Cell::List(values), _) => values.iter().for_each(|v| self.add(v, hash_table)),
+                (Cell::Flags(flags), Some(flag)) => {
+                    self.add(&Value::UnsignedInt(flags[flag]), hash_table)
+                }
+                (Cell::Flags(_), None) => {}
+            }
+        }
+    }
+
+    fn add(&mut self, value: &Value, hash_table: &HashNameTable) {
+        self.stats.add(value);
+        let text = match value {
+            Value::HashRef(hash) => format_unhashed_label(&Label::Hash(*hash), hash_table),
+            Value::String(s) | Value::DebugString(s) => {
+                self.strings = true;
+                s.to_string()
+            }
+            v => v.to_string(),
+        };
+        *self.counts.entry(text).or_default() += 1;
+    }
+
+    /// Returns the `n` most frequent values, most frequent first. Values with the same count
+    /// are sorted by their text. If `n` is 0, returns every value.
+    fn top(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut values = self
+            .counts
+            .iter()
+            .map(|(v, c)| (v.as_str(), *c))
+            .collect::<Vec<_>>();
+        values.sort_unstable_by(|(v1, c1), (v2, c2)| c2.cmp(c1).then_with(|| v1.cmp(v2)));
+        if n != 0 {
+            values.truncate(n);
+        }
+        values
+    }
+
+    /// Returns every value with its count. Numbers come first, sorted by value,
+    /// followed by other values sorted by their text.
+    fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut values = self
+            .counts
+            .iter()
+            .map(|(v, c)| (v.parse::<f64>().ok(), v.as_str(), *c))
+            .collect::<Vec<_>>();
+        values.sort_unstable_by(|(n1, v1, _), (n2, v2, _)| match (n1, n2) {
+            (Some(n1), Some(n2)) => n1.total_cmp(n2),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => v1.cmp(v2),
+        });
+        values.into_iter().map(|(_, v, c)| (v, c)).collect()
+    }
+
+    fn write_distinct(&self, writer: impl Write) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(["value", "count"])?;
+        for (value, count) in self.sorted() {
+            writer.write_record([value, &count.to_string()])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn print(&self, header: &str, top: Option<usize>) {
+        let mut line = format!("  {header}, {} distinct values", self.counts.len());
+        if let (Some((min, max)), Some(mean)) = (self.stats.range(), self.stats.mean()) {
+            line += &format!(", range {min} to {max}, mean {mean:.2}");
+        }
+        if let (Some((min, max)), Some(mean)) =
+            (self.stats.length_range(), self.stats.mean_length())
+        {
+            line += &format!(", length {min} to {max}, mean {mean:.1}");
+        }
+        println!("{line}");
+
+        let Some(n) = top else { return };
+        for (value, count) in self.top(n) {
+            let percent = count as f64 * 100.0 / self.stats.count() as f64;
+            if self.strings {
+                println!("    {count:>6} ({percent:>5.1}%)  {value:?}");
+            } else {
+                println!("    {count:>6} ({percent:>5.1}%)  {value}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{ColumnBuilder, ColumnDef, FlagDef, Row, TableBuilder, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn value_counts() {
+        let table: Table = TableBuilder::with_name(Label::from("Test"))
+            .add_column(ColumnDef::new(ValueType::SignedShort, "Value".into()))
+            .add_column(
+                ColumnBuilder::new(ValueType::UnsignedInt, "Flags".into())
+                    .set_flags(vec![FlagDef::new_bit("A", 0), FlagDef::new_bit("B", 1)])
+                    .build(),
+            )
+            .add_row(Row::new(
+                1,
+                vec![
+                    Cell::Single(Value::SignedShort(-2)),
+                    Cell::Flags(vec![1, 0]),
+                ],
+            ))
+            .add_row(Row::new(
+                2,
+                vec![Cell::Single(Value::SignedShort(5)), Cell::Flags(vec![1, 1])],
+            ))
+            .add_row(Row::new(
+                3,
+                vec![Cell::Single(Value::SignedShort(5)), Cell::Flags(vec![0, 1])],
+            ))
+            .build_legacy()
+            .into();
+        let hashes = HashNameTable::empty();
+
+        let stats = ValueStats::from_column(&table, 0, None, &hashes);
+        assert_eq!(Some((-2.0, 5.0)), stats.stats.range());
+        assert_eq!(vec![("5", 2), ("-2", 1)], stats.top(0));
+        assert_eq!(vec![("5", 2)], stats.top(1));
+
+        let stats = ValueStats::from_column(&table, 1, Some(0), &hashes);
+        assert_eq!(vec![("1", 2), ("0", 1)], stats.top(0));
+        assert_eq!(vec![("0", 1), ("1", 2)], stats.sorted());
+    }
+
+    #[test]
+    fn string_duplicates() {
+        let table = |name: &str, values: &[&'static str]| -> Table<'static> {
+            values
+                .iter()
+                .enumerate()
+                .fold(
+                    TableBuilder::with_name(Label::from(name))
+                        .add_column(ColumnDef::new(ValueType::String, "Name".into())),
+                    |b, (i, v)| {
+                        b.add_row(Row::new(
+                            i + 1,
+                            vec![Cell::Single(Value::String((*v).into()))],
+                        ))
+                    },
+                )
+                .build_legacy()
+                .into()
+        };
+        let mut stats = StringStats::new(BdatVersion::LegacySwitch);
+        stats.add_table(&table("A", &["abc", "abc", "de"]), "A", 20, 12);
+        stats.add_table(&table("B", &["abc", "xyzw"]), "B", 14, 14);
+
+        assert_eq!(
+            (5, 4, 3),
+            (stats.count, stats.unique_in_table, stats.strings.len())
+        );
+        // Strings are null-terminated and padded to 2 bytes, and "abc" is in both tables
+        assert_eq!(4 + 4 + 4 + 6, stats.table_bytes);
+        assert_eq!(22, stats.shared_size());
+        assert_eq!(vec![("xyzw", 1, "B"), ("abc", 3, "A")], stats.biggest(2));
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{Context, Result};
+use bdat::{
+    legacy::float::BdatReal, BdatVersion, Cell, ColumnDef, Row, Table, TableAccessor, TableBuilder,
+    Value, ValueType,
+};
+use bdat_convert::{
+    error::Error,
+    filter::{Filter, FilterArg},
+};
+use clap::{Args, ValueEnum};
+
+use crate::{warn_diagnostics, InputData};
+
+#[derive(Args)]
+pub struct TemplateArgs {
+    /// The table to copy the columns from
+    #[arg(short, long)]
+    table: String,
+    /// The format of the template
+    #[arg(short, long, value_enum, default_value_t = TemplateFormat::Json)]
+    format: TemplateFormat,
+    /// The file to write the template to. If absent, the template is printed.
+    #[arg(short, long)]
+    out: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum TemplateFormat {
+    /// A JSON table with metadata, that can be packed on its own
+    Json,
+    /// A CSV file with typed headers and a "$id" column
+    Csv,
+}
+
+/// Writes an empty copy of a table, with the same columns and a single example row
+/// with default values. The template can be edited and packed as a new table.
+pub fn write_template(input: InputData, args: TemplateArgs) -> Result<()> {
+    let hash_table = input.load_hashes()?;
+    let filter: Filter = [FilterArg(args.table.clone())].into_iter().collect();
+
+    for file in input.list_files("bdat", false)? {
+        let path = file?;
+        let mut bytes = std::fs::read(&path)?;
+        let game = input.game_from_bytes(&bytes)?;
+        let tables = game
+            .from_bytes(&mut bytes, &mut warn_diagnostics(&path))
+            .with_context(|| format!("Could not parse BDAT tables ({})", path.display()))?;
+        let Some(mut table) = tables.into_iter().find(|t| filter.contains(t.name())) else {
+            continue;
+        };
+        hash_table.convert_all(&mut table);
+
+        let version = BdatVersion::from(game);
+        let template = build_template(&table, version);
+        let mut writer: Box<dyn Write> = match &args.out {
+            Some(path) => Box::new(BufWriter::new(
+                File::create(path).context("Could not create template file")?,
+            )),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        match args.format {
+            TemplateFormat::Json => {
+                let opts = bdat::fmt::JsonOptions::new().pretty(true).version(version);
+                bdat::fmt::to_json(&template, &mut writer, &opts)?;
+                writeln!(writer)?;
+            }
+            TemplateFormat::Csv => {
+                let opts = bdat::fmt::CsvOptions::new().row_id(Some("$id"));
+                bdat::fmt::to_csv(&template, &mut writer, &opts)?;
+            }
+        }
+        writer.flush()?;
+        return Ok(());
+    }
+    Err(Error::TableNotFound(args.table).into())
+}
+
+/// Builds a table with the same name, columns and base ID, with a row of default values.
+fn build_template(table: &Table, version: BdatVersion) -> Table<'static> {
+    let cells = table.columns().map(|c| default_cell(c, version)).collect();
+    table
+        .columns()
+        .cloned()
+        .fold(TableBuilder::with_name(table.name().clone()), |b, c| {
+            b.add_column(c)
+        })
+        .add_row(Row::new(table.base_id(), cells))
+        .build(version)
+}
+
+fn default_cell(column: &ColumnDef, version: BdatVersion) -> Cell<'static> {
+    let value = default_value(column, version);
+    if !column.flags().is_empty() {
+        Cell::Flags(vec![0; column.flags().len()])
+    } else if column.count() > 1 {
+        Cell::List(vec![value; column.count()])
+    } else {
+        Cell::Single(value)
+    }
+}
+
+fn default_value(column: &ColumnDef, version: BdatVersion) -> Value<'static> {
+    match column.value_type() {
+        ValueType::Unknown => Value::Unknown,
+        ValueType::UnsignedByte => Value::UnsignedByte(0),
+        ValueType::UnsignedShort => Value::UnsignedShort(0),
+        ValueType::UnsignedInt => Value::UnsignedInt(0),
+        ValueType::SignedByte => Value::SignedByte(0),
+        ValueType::SignedShort => Value::SignedShort(0),
+        ValueType::SignedInt => Value::SignedInt(0),
+        ValueType::String => Value::String("".into()),
+        ValueType::Float => Value::Float(BdatReal::from_bits(0, version)),
+        ValueType::HashRef => Value::HashRef(0),
+        ValueType::Percent => Value::Percent(0),
+        ValueType::DebugString => Value::DebugString("".into()),
+        ValueType::Unknown2 => Value::Unknown2(0),
+        ValueType::MessageId => Value::MessageId(0),
+        ValueType::Raw => Value::Raw(vec![0; column.data_size()].into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdat::{ColumnBuilder, FlagDef, Label, LegacyTable};
+
+    use super::*;
+
+    #[test]
+    fn template_row() {
+        let table: Table = TableBuilder::with_name(Label::from("Test"))
+            .add_column(ColumnDef::new(ValueType::String, "Name".into()))
+            .add_column(
+                ColumnBuilder::new(ValueType::UnsignedShort, "Drops".into())
+                    .set_count(2)
+                    .build(),
+            )
+            .add_column(
+                ColumnBuilder::new(ValueType::UnsignedInt, "Flags".into())
+                    .set_flags(vec![FlagDef::new_bit("A", 0), FlagDef::new_bit("B", 1)])
+                    .build(),
+            )
+            .add_row(Row::new(
+                5,
+                vec![
+                    Cell::Single(Value::String("Test".into())),
+                    Cell::List(vec![Value::UnsignedShort(1), Value::UnsignedShort(2)]),
+                    Cell::Flags(vec![1, 0]),
+                ],
+            ))
+            .build_legacy()
+            .into();
+
+        let template: LegacyTable = build_template(&table, BdatVersion::LegacySwitch).into_legacy();
+        assert_eq!(5, template.base_id());
+        assert_eq!(1, template.row_count());
+        assert_eq!(
+            &[
+                Cell::Single(Value::String("".into())),
+                Cell::List(vec![Value::UnsignedShort(0); 2]),
+                Cell::Flags(vec![0, 0]),
+            ],
+            template
+                .row(5)
+                .cells()
+                .cloned()
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+    }
+}